@@ -0,0 +1,404 @@
+//! Cold storage for old local transcripts (`cc-sessions archive`).
+//!
+//! Archiving gzips a transcript into a directory that mirrors the projects
+//! dir and deletes the original. Sessions from extra local projects dirs
+//! (`[local] projects_dirs`) are mirrored under `roots/`, each in a
+//! directory named after its projects dir the way Claude Code names project
+//! dirs:
+//!
+//! ```text
+//! ~/.local/share/cc-sessions/archive/
+//!   -Users-you-project-a/
+//!     abc12345-1234-1234-1234-123456789abc.jsonl.gz
+//!   roots/
+//!     -mnt-work--claude-projects/
+//!       -Users-you-project-b/
+//!         def12345-1234-1234-1234-123456789abc.jsonl.gz
+//! ```
+//!
+//! The archive holds the only copy of each transcript, so it lives outside
+//! `~/.cache`. Archived sessions are still listed: their scans go through the
+//! metadata cache like any other root, and a miss (cleared cache, version
+//! bump) decompresses the file once to rescan it. Reading goes through
+//! `gzip -dc` rather than a compression crate, the way syncing goes through
+//! rsync.
+
+use anyhow::{Context, Result, bail};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+/// Suffix of an archived transcript, after the session's `.jsonl`.
+const SUFFIX: &str = ".gz";
+
+/// Where the archives of the extra local projects dirs live. Project dir
+/// names start with `-`, so it can't collide with one of the main dir's.
+const ROOTS_DIR: &str = "roots";
+
+pub fn archive_dir() -> Result<PathBuf> {
+    Ok(crate::profile::scoped(crate::paths::DATA.dir()?).join("archive"))
+}
+
+/// Whether `path` is an archived (compressed) transcript.
+pub fn is_archived(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(SUFFIX)
+}
+
+/// The mirror of `roots[index]` in the archive: the archive itself for the
+/// main projects dir, a directory under `roots/` for the others.
+fn root_archive(archive_dir: &Path, index: usize, root: &Path) -> PathBuf {
+    if index == 0 {
+        archive_dir.to_path_buf()
+    } else {
+        archive_dir
+            .join(ROOTS_DIR)
+            .join(crate::claude_code::project_dir_name(root))
+    }
+}
+
+/// Where the transcript at `original` is archived, given the local projects
+/// dirs (the main one first). `None` if it is under none of them.
+pub fn archived_path(archive_dir: &Path, roots: &[PathBuf], original: &Path) -> Option<PathBuf> {
+    let (index, root) = roots
+        .iter()
+        .enumerate()
+        .find(|(_, root)| original.starts_with(root))?;
+    let relative = original.strip_prefix(root).ok()?;
+    let mut path = root_archive(archive_dir, index, root)
+        .join(relative)
+        .into_os_string();
+    path.push(SUFFIX);
+    Some(path.into())
+}
+
+/// Where the archived transcript at `archived` is restored to. `None` if it
+/// isn't in the mirror of any of `roots`, e.g. its projects dir is no longer
+/// configured.
+pub fn original_path(archive_dir: &Path, roots: &[PathBuf], archived: &Path) -> Option<PathBuf> {
+    roots.iter().enumerate().find_map(|(index, root)| {
+        let relative = archived
+            .strip_prefix(root_archive(archive_dir, index, root))
+            .ok()?;
+        // The main dir's mirror contains the others'
+        if index == 0 && relative.starts_with(ROOTS_DIR) {
+            return None;
+        }
+        let relative = relative.to_str()?.strip_suffix(SUFFIX)?;
+        Some(root.join(relative))
+    })
+}
+
+/// Whether `path` sits where an archived transcript would: in a project dir
+/// of the main dir's mirror, or of one under `roots/`.
+pub fn in_transcript_slot(archive_dir: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(archive_dir) else {
+        return false;
+    };
+    let depth = relative.components().count();
+    if relative.starts_with(ROOTS_DIR) {
+        depth == 4
+    } else {
+        depth == 2
+    }
+}
+
+/// Compress `src` into `dest`, keeping `src`. The result is decompressed and
+/// compared with `src` before it takes `dest`'s name, since callers delete
+/// the original next.
+pub fn compress(src: &Path, dest: &Path) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let tmp = with_suffix(dest, ".tmp");
+    let result = (|| {
+        let out =
+            File::create(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+        let status = Command::new("gzip")
+            .arg("-c")
+            .arg(src)
+            .stdout(out)
+            .status()
+            .context("Failed to run gzip")?;
+        if !status.success() {
+            bail!("gzip failed on {} ({})", src.display(), status);
+        }
+        let original =
+            File::open(src).with_context(|| format!("Failed to open {}", src.display()))?;
+        if !same_contents(gunzip(&tmp)?, original)? {
+            bail!("Compressed copy of {} doesn't match it", src.display());
+        }
+        fs::rename(&tmp, dest).with_context(|| format!("Failed to write {}", dest.display()))
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Recompress an archived transcript at gzip's best level, keeping the new
+/// copy only if it is smaller and decompresses to the same bytes. The file
+/// keeps its mtime. Returns the bytes saved.
+pub fn recompress(path: &Path) -> Result<u64> {
+    let old = fs::metadata(path).with_context(|| format!("Failed to stat {}", path.display()))?;
+    let tmp = with_suffix(path, ".tmp");
+    let result = (|| {
+        let out =
+            File::create(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+        let mut source = Command::new("gzip")
+            .arg("-dc")
+            .arg(path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to run gzip")?;
+        let input = source.stdout.take().context("gzip has no stdout")?;
+        let status = Command::new("gzip")
+            .arg("-9c")
+            .stdin(input)
+            .stdout(out)
+            .status()
+            .context("Failed to run gzip")?;
+        let source_status = source.wait().context("Failed to run gzip")?;
+        if !status.success() || !source_status.success() {
+            bail!("gzip failed on {}", path.display());
+        }
+
+        let new_len = fs::metadata(&tmp)
+            .with_context(|| format!("Failed to stat {}", tmp.display()))?
+            .len();
+        if new_len >= old.len() {
+            fs::remove_file(&tmp).with_context(|| format!("Failed to remove {}", tmp.display()))?;
+            return Ok(0);
+        }
+        if !same_contents(gunzip(&tmp)?, gunzip(path)?)? {
+            bail!("Recompressed copy of {} doesn't match it", path.display());
+        }
+        if let Ok(modified) = old.modified() {
+            File::options()
+                .write(true)
+                .open(&tmp)
+                .and_then(|f| f.set_modified(modified))
+                .with_context(|| format!("Failed to set mtime on {}", tmp.display()))?;
+        }
+        fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(old.len() - new_len)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Decompress `src` into `dest`, keeping `src`.
+pub fn decompress(src: &Path, dest: &Path) -> Result<()> {
+    let tmp = with_suffix(dest, ".tmp");
+    let result = (|| {
+        let out =
+            File::create(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+        let status = Command::new("gzip")
+            .arg("-dc")
+            .arg(src)
+            .stdout(out)
+            .status()
+            .context("Failed to run gzip")?;
+        if !status.success() {
+            bail!("gzip failed on {} ({})", src.display(), status);
+        }
+        fs::rename(&tmp, dest).with_context(|| format!("Failed to write {}", dest.display()))
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Read a transcript, decompressing it on the fly if it is archived.
+pub fn open(path: &Path) -> Result<Box<dyn BufRead + Send>> {
+    if !is_archived(path) {
+        let file = File::open(path).context("Could not open session file")?;
+        return Ok(Box::new(BufReader::new(file)));
+    }
+    if !path.is_file() {
+        bail!("Archived session file not found: {}", path.display());
+    }
+    Ok(Box::new(gunzip(path)?))
+}
+
+fn gunzip(path: &Path) -> Result<Gunzip> {
+    let mut child = Command::new("gzip")
+        .arg("-dc")
+        .arg(path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to run gzip")?;
+    let stdout = child.stdout.take().context("gzip has no stdout")?;
+    Ok(Gunzip {
+        child,
+        stdout: BufReader::new(stdout),
+    })
+}
+
+/// Output of a `gzip -dc` child. Dropping it stops and reaps the child, so
+/// readers can stop early (previews read the first 100 messages).
+struct Gunzip {
+    child: Child,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Read for Gunzip {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.stdout.read(buf)
+    }
+}
+
+impl BufRead for Gunzip {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.stdout.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.stdout.consume(amount)
+    }
+}
+
+impl Drop for Gunzip {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn same_contents(mut a: impl Read, mut b: impl Read) -> Result<bool> {
+    let (mut buf_a, mut buf_b) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    loop {
+        let n = read_full(&mut a, &mut buf_a)?;
+        if n != read_full(&mut b, &mut buf_b)? || buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+        if n == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Fill `buf` unless the reader ends first; returns the bytes read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_mirror_the_projects_dir() {
+        let archive = Path::new("/a");
+        let roots = [PathBuf::from("/p"), PathBuf::from("/work/projects")];
+        let original = Path::new("/p/-src-x/1234.jsonl");
+        let archived = archived_path(archive, &roots, original).unwrap();
+        assert_eq!(archived, Path::new("/a/-src-x/1234.jsonl.gz"));
+        assert!(is_archived(&archived) && !is_archived(original));
+        assert!(in_transcript_slot(archive, &archived));
+        assert_eq!(
+            original_path(archive, &roots, &archived).as_deref(),
+            Some(original)
+        );
+        assert_eq!(
+            archived_path(archive, &roots, Path::new("/elsewhere")),
+            None
+        );
+    }
+
+    #[test]
+    fn extra_projects_dirs_are_mirrored_under_roots() {
+        let archive = Path::new("/a");
+        let roots = [PathBuf::from("/p"), PathBuf::from("/work/projects")];
+        let original = Path::new("/work/projects/-src-y/5678.jsonl");
+        let archived = archived_path(archive, &roots, original).unwrap();
+        assert_eq!(
+            archived,
+            Path::new("/a/roots/-work-projects/-src-y/5678.jsonl.gz")
+        );
+        assert!(in_transcript_slot(archive, &archived));
+        assert_eq!(
+            original_path(archive, &roots, &archived).as_deref(),
+            Some(original)
+        );
+        // Without its projects dir configured, there is nowhere to restore it
+        assert_eq!(original_path(archive, &roots[..1], &archived), None);
+    }
+
+    #[test]
+    fn compress_round_trips() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("s.jsonl");
+        let text = "{\"type\":\"user\"}\n".repeat(10_000);
+        fs::write(&src, &text).unwrap();
+
+        let archived = tmp.path().join("archive/p/s.jsonl.gz");
+        compress(&src, &archived).unwrap();
+        assert!(fs::metadata(&archived).unwrap().len() < text.len() as u64 / 10);
+        let mut read = String::new();
+        open(&archived).unwrap().read_to_string(&mut read).unwrap();
+        assert_eq!(read, text);
+
+        let restored = tmp.path().join("restored.jsonl");
+        decompress(&archived, &restored).unwrap();
+        assert_eq!(fs::read_to_string(&restored).unwrap(), text);
+        assert!(compress(&tmp.path().join("missing"), &archived).is_err());
+        assert!(!with_suffix(&archived, ".tmp").exists());
+    }
+
+    #[test]
+    fn recompress_shrinks_and_keeps_contents_and_mtime() {
+        let tmp = tempfile::tempdir().unwrap();
+        let text: String = (0..20_000)
+            .map(|i| format!("{{\"n\":{},\"text\":\"line {}\"}}\n", i, i * 7 % 13))
+            .collect();
+        let src = tmp.path().join("s.jsonl");
+        fs::write(&src, &text).unwrap();
+        let archived = tmp.path().join("s.jsonl.gz");
+        let status = Command::new("gzip")
+            .arg("-1c")
+            .arg(&src)
+            .stdout(File::create(&archived).unwrap())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let mtime = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        File::options()
+            .write(true)
+            .open(&archived)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+        let before = fs::metadata(&archived).unwrap().len();
+
+        let saved = recompress(&archived).unwrap();
+        assert!(saved > 0);
+        let after = fs::metadata(&archived).unwrap();
+        assert_eq!(after.len(), before - saved);
+        assert_eq!(after.modified().unwrap(), mtime);
+        let mut read = String::new();
+        open(&archived).unwrap().read_to_string(&mut read).unwrap();
+        assert_eq!(read, text);
+        assert_eq!(recompress(&archived).unwrap(), 0);
+        assert!(!with_suffix(&archived, ".tmp").exists());
+    }
+}
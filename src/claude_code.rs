@@ -10,6 +10,8 @@
 //! ~/.claude/projects/
 //!   -Users-you-project-a/
 //!     abc12345-1234-1234-1234-123456789abc.jsonl   # Session transcript (UUID filename)
+//!     abc12345-1234-1234-1234-123456789abc/
+//!       subagents/agent-a1b2c3.jsonl               # One subagent (Task tool) run
 //!     def45678-5678-5678-5678-567890123def.jsonl
 //!   -Users-you-project-b/
 //!     ghi78901-9012-9012-9012-901234567890.jsonl
@@ -17,19 +19,28 @@
 //!
 //! Sessions are discovered by scanning for `.jsonl` files with valid UUID filenames.
 //! All metadata is extracted via a single full-file pass per session.
+//! Archived local sessions (see `archive`) are listed from the same scans,
+//! kept for the archive's copies.
 
+use crate::archive;
 use crate::message_classification::{
     counts_as_turn, is_first_prompt_candidate, is_system_content_for_preview,
 };
-use crate::session::{Session, SessionSource};
+use crate::metadata_cache::{Fingerprint, MetadataCache};
+use crate::progress::{self, Progress};
+use crate::session::{
+    Message, OpenTodo, RemoteResume, Role, Session, SessionSource, TokenUsage, ToolCall, ToolResult,
+};
 use anyhow::{Context, Result};
 use memchr::memmem;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::sync::LazyLock;
-use std::time::UNIX_EPOCH;
+use std::sync::{LazyLock, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 
 /// Failure details for a single session discovery source.
@@ -39,11 +50,44 @@ pub struct DiscoveryFailure {
     pub reason: String,
 }
 
+/// How many session files a discovery pass actually parsed versus reused
+/// from the metadata cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScanCounts {
+    pub scanned: usize,
+    pub cached: usize,
+    /// Of `scanned`, files that had only grown and were read from their
+    /// previous end rather than from the start.
+    pub resumed: usize,
+    /// Of `scanned`, files over the size limit that were only read at their
+    /// head and tail.
+    pub partial: usize,
+}
+
+impl std::ops::AddAssign for ScanCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.scanned += other.scanned;
+        self.cached += other.cached;
+        self.resumed += other.resumed;
+        self.partial += other.partial;
+    }
+}
+
+/// How long one source took to discover, for `--timing`.
+#[derive(Debug)]
+pub struct SourceTiming {
+    pub source_name: String,
+    pub elapsed: Duration,
+    pub counts: ScanCounts,
+}
+
 /// Aggregated discovery outcome across local + remote sources.
 #[derive(Debug, Default)]
 pub struct DiscoverySummary {
     pub sessions: Vec<Session>,
     pub failures: Vec<DiscoveryFailure>,
+    pub counts: ScanCounts,
+    pub timings: Vec<SourceTiming>,
 }
 
 impl DiscoverySummary {
@@ -52,49 +96,208 @@ impl DiscoverySummary {
     }
 }
 
+/// Undo a remote clock running ahead: its sessions' times come from that
+/// clock (file mtimes survive rsync, entry timestamps are written there) and
+/// would otherwise show as future (`?`) or too recent.
+fn correct_remote_clock(sessions: &mut [Session], last_transfer: Option<SystemTime>) {
+    let newest = sessions
+        .iter()
+        .flat_map(|s| [Some(s.modified), s.last_activity])
+        .flatten()
+        .max();
+    let skew = last_transfer
+        .zip(newest)
+        .and_then(|(transfer, newest)| crate::remote::observed_clock_skew(transfer, newest));
+    let now = SystemTime::now();
+    for session in sessions {
+        session.correct_clock(skew, now);
+    }
+}
+
 // =============================================================================
 // Path Discovery
 // =============================================================================
 
+static CLAUDE_DIR: OnceLock<PathBuf> = OnceLock::new();
+static LOCAL_ROOTS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+/// Use `dir` instead of ~/.claude for local sessions (`settings.claude_dir`).
+/// Set once at startup, before discovery.
+pub fn set_claude_dir(dir: PathBuf) {
+    let _ = CLAUDE_DIR.set(dir);
+}
+
+/// The Claude Code config dir, if it isn't the default: `settings.claude_dir`,
+/// else `$CLAUDE_CONFIG_DIR` as Claude Code itself reads it.
+pub fn claude_dir_override() -> Option<PathBuf> {
+    CLAUDE_DIR.get().cloned().or_else(|| {
+        std::env::var_os("CLAUDE_CONFIG_DIR")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    })
+}
+
+/// The projects dir of the Claude Code config dir in use, where pulled and
+/// restored sessions go.
 pub fn get_claude_projects_dir() -> Result<PathBuf> {
+    if let Some(dir) = claude_dir_override() {
+        return Ok(dir.join("projects"));
+    }
     let home = dirs::home_dir().context("Could not find home directory")?;
     Ok(home.join(".claude").join("projects"))
 }
 
-/// Check if a source should be included based on the filter.
-fn should_include_source(remote_filter: Option<&str>, source_name: &str) -> bool {
-    match remote_filter {
-        None => true,
-        Some(filter) => source_name == filter,
+/// Set up where local sessions are read from: `settings.claude_dir`, then
+/// the roots. `projects_dirs` (`--projects-dir`) replace the configured
+/// roots when given; otherwise they are the config dir's projects dir and
+/// `[local] projects_dirs`. Set once at startup, before discovery.
+pub fn configure_local_dirs(
+    config: &crate::remote::Config,
+    projects_dirs: &[PathBuf],
+) -> Result<()> {
+    use crate::remote::expand_path;
+
+    if let Some(dir) = &config.settings.claude_dir {
+        set_claude_dir(expand_path(dir)?);
+    }
+    let mut roots = if projects_dirs.is_empty() {
+        let mut roots = vec![get_claude_projects_dir()?];
+        for dir in &config.local.projects_dirs {
+            roots.push(expand_path(dir)?);
+        }
+        roots
+    } else {
+        projects_dirs.to_vec()
+    };
+    // The same dir reached twice (a symlink, a bind mount seen from both sides)
+    let mut seen = std::collections::HashSet::new();
+    roots.retain(|root| seen.insert(fs::canonicalize(root).unwrap_or_else(|_| root.clone())));
+    let _ = LOCAL_ROOTS.set(roots);
+    Ok(())
+}
+
+/// Every projects dir local sessions are listed from, the main one first.
+pub fn local_projects_dirs() -> Result<Vec<PathBuf>> {
+    match LOCAL_ROOTS.get() {
+        Some(roots) => Ok(roots.clone()),
+        None => Ok(vec![get_claude_projects_dir()?]),
+    }
+}
+
+/// How a local root is named in reports: "local" for the first, else
+/// "local (<dir>)".
+pub fn local_root_name(index: usize, root: &Path) -> String {
+    if index == 0 {
+        "local".to_string()
+    } else {
+        format!("local ({})", root.display())
+    }
+}
+
+/// The `CLAUDE_CONFIG_DIR` that lets `claude -r` find the local transcript
+/// at `filepath`: the config dir around its projects dir, unless that is
+/// the default one and nothing overrides it. `None` also for roots not
+/// named `projects`, which aren't inside a config dir.
+pub fn claude_dir_for(filepath: &Path) -> Option<PathBuf> {
+    // With an override in effect, even ~/.claude has to be named
+    let default = match claude_dir_override() {
+        Some(_) => None,
+        None => dirs::home_dir().map(|home| home.join(".claude")),
+    };
+    claude_dir_among(&local_projects_dirs().ok()?, filepath, default.as_deref())
+}
+
+fn claude_dir_among(roots: &[PathBuf], filepath: &Path, default: Option<&Path>) -> Option<PathBuf> {
+    let root = roots.iter().find(|root| filepath.starts_with(root))?;
+    if root.file_name()? != "projects" {
+        return None;
     }
+    let dir = root.parent()?;
+    (Some(dir) != default).then(|| dir.to_path_buf())
 }
 
-/// Find all sessions from local and cached remotes with source-level failures.
+/// MCP servers configured for this machine's Claude Code in `~/.claude.json`
+/// (inside the config dir when it's overridden), user-wide and per project.
+/// Servers only set in a repo's `.mcp.json` (or on a remote) aren't seen.
+pub fn configured_mcp_servers() -> BTreeSet<String> {
+    let path = match claude_dir_override() {
+        Some(dir) => Some(dir.join(".claude.json")),
+        None => dirs::home_dir().map(|home| home.join(".claude.json")),
+    };
+    path.and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .map(|config| mcp_servers_in(&config))
+        .unwrap_or_default()
+}
+
+fn mcp_servers_in(config: &serde_json::Value) -> BTreeSet<String> {
+    let projects = config
+        .get("projects")
+        .and_then(|p| p.as_object())
+        .into_iter()
+        .flat_map(|projects| projects.values());
+    std::iter::once(config)
+        .chain(projects)
+        .filter_map(|scope| scope.get("mcpServers")?.as_object())
+        .flat_map(|servers| servers.keys().cloned())
+        .collect()
+}
+
+/// Find all sessions from local, cached remotes, and teams with source-level failures.
 pub fn find_all_sessions_with_summary(
     config: &crate::remote::Config,
     remote_filter: Option<&str>,
+    deep: bool,
 ) -> Result<DiscoverySummary> {
     use crate::remote;
 
     let mut summary = DiscoverySummary::default();
+    let limit = (!deep).then(|| config.settings.full_scan_limit()).flatten();
 
     // Load local sessions (unsorted — final sort happens once at the end)
-    if should_include_source(remote_filter, "local") {
-        let local_dir = get_claude_projects_dir()?;
-        if local_dir.exists() {
-            summary
-                .sessions
-                .extend(find_sessions_with_source(&local_dir, SessionSource::Local)?);
+    if config.source_matches(remote_filter, "local") {
+        for (i, local_dir) in local_projects_dirs()?.iter().enumerate() {
+            if !local_dir.exists() {
+                continue;
+            }
+            let start = Instant::now();
+            match find_sessions_with_source(local_dir, SessionSource::Local, limit) {
+                Ok((sessions, counts)) => {
+                    summary.sessions.extend(sessions);
+                    summary.counts += counts;
+                    summary.timings.push(SourceTiming {
+                        source_name: local_root_name(i, local_dir),
+                        elapsed: start.elapsed(),
+                        counts,
+                    });
+                }
+                Err(e) => summary.failures.push(DiscoveryFailure {
+                    source_name: local_root_name(i, local_dir),
+                    reason: e.to_string(),
+                }),
+            }
         }
     }
 
+    // Archived local sessions
+    if config.source_matches(remote_filter, "local")
+        && let Ok(archive_dir) = archive::archive_dir()
+        && archive_dir.exists()
+    {
+        let start = Instant::now();
+        let (sessions, counts) = find_archived_sessions(&archive_dir);
+        summary.sessions.extend(sessions);
+        summary.counts += counts;
+        summary.timings.push(SourceTiming {
+            source_name: "archive".to_string(),
+            elapsed: start.elapsed(),
+            counts,
+        });
+    }
+
     // Load cached remote sessions
     for (name, remote_config) in &config.remotes {
-        if !should_include_source(remote_filter, name) {
-            continue;
-        }
-        // "local" filter should not include remotes
-        if remote_filter == Some("local") {
+        if !config.source_matches(remote_filter, name) {
             continue;
         }
 
@@ -107,10 +310,65 @@ pub fn find_all_sessions_with_summary(
             name: name.clone(),
             host: remote_config.host.clone(),
             user: remote_config.user.clone(),
+            label: remote_config.label.clone(),
+            color: remote_config.color,
+            resume: match (&remote_config.resume_command, remote_config.transport) {
+                (Some(command), _) => RemoteResume::Command(command.clone()),
+                (None, remote::Transport::Ssh | remote::Transport::Sftp) => RemoteResume::Ssh,
+                (None, remote::Transport::Rsyncd) => RemoteResume::Unavailable,
+            },
         };
 
-        match find_sessions_with_source(&cache_dir, source) {
-            Ok(sessions) => summary.sessions.extend(sessions),
+        let start = Instant::now();
+        match find_sessions_with_source(&cache_dir, source, limit) {
+            Ok((mut sessions, counts)) => {
+                correct_remote_clock(
+                    &mut sessions,
+                    remote::last_transfer_time(&config.settings, name),
+                );
+                for session in &mut sessions {
+                    session.local_path = remote_config.local_path(&session.project_path);
+                }
+                summary.sessions.extend(sessions);
+                summary.counts += counts;
+                summary.timings.push(SourceTiming {
+                    source_name: name.clone(),
+                    elapsed: start.elapsed(),
+                    counts,
+                });
+            }
+            Err(e) => summary.failures.push(DiscoveryFailure {
+                source_name: name.clone(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    // Sessions teammates published (read in place; nothing to sync)
+    for (name, team) in &config.teams {
+        if !config.source_matches(remote_filter, name) {
+            continue;
+        }
+        let start = Instant::now();
+        match crate::team::find_sessions(name, team) {
+            Ok((sessions, failures)) => {
+                summary.sessions.extend(sessions);
+                summary.timings.push(SourceTiming {
+                    source_name: name.clone(),
+                    elapsed: start.elapsed(),
+                    counts: ScanCounts::default(),
+                });
+                summary
+                    .failures
+                    .extend(
+                        failures
+                            .into_iter()
+                            .map(|(owner, reason)| DiscoveryFailure {
+                                source_name: format!("{} ({})", name, owner),
+                                reason,
+                            }),
+                    );
+            }
             Err(e) => summary.failures.push(DiscoveryFailure {
                 source_name: name.clone(),
                 reason: e.to_string(),
@@ -127,20 +385,48 @@ pub fn find_all_sessions_with_summary(
 // =============================================================================
 
 /// Find all sessions by scanning .jsonl files directly (sorted newest-first).
+/// Bypasses the persistent metadata cache.
 #[cfg(test)]
 pub fn find_sessions(projects_dir: &Path) -> Result<Vec<Session>> {
-    let mut sessions = find_sessions_with_source(projects_dir, SessionSource::Local)?;
+    let mut cache = MetadataCache::default();
+    let (mut sessions, _) = scan_sessions(projects_dir, &SessionSource::Local, &mut cache, None);
     sessions.sort_by(|a, b| b.modified.cmp(&a.modified));
     Ok(sessions)
 }
 
 /// Find sessions with a specific source tag.
 ///
-/// Used by both local discovery and remote cache discovery.
+/// Used by both local discovery and remote cache discovery. Files whose
+/// mtime and size match the persistent metadata cache are not re-read, and
+/// files over `full_scan_limit` bytes are only partially read.
 pub fn find_sessions_with_source(
     projects_dir: &Path,
     source: SessionSource,
-) -> Result<Vec<Session>> {
+    full_scan_limit: Option<u64>,
+) -> Result<(Vec<Session>, ScanCounts)> {
+    // The walk below skips entries it can't read, which would make an
+    // unreadable root look empty.
+    if let Err(e) = fs::read_dir(projects_dir) {
+        anyhow::bail!("can't read {}: {}", projects_dir.display(), e);
+    }
+    let mut cache = MetadataCache::load(projects_dir);
+    let result = scan_sessions(projects_dir, &source, &mut cache, full_scan_limit);
+    if let Err(e) = cache.save() {
+        eprintln!("Warning: Failed to save metadata cache: {}", e);
+    }
+    Ok(result)
+}
+
+/// Scan all session files under `projects_dir`, consulting and refreshing
+/// `cache`. Entries for files that disappeared are pruned. New files larger
+/// than `full_scan_limit` get a head/tail scan; with no limit (`--deep`),
+/// earlier partial scans are redone in full.
+fn scan_sessions(
+    projects_dir: &Path,
+    source: &SessionSource,
+    cache: &mut MetadataCache<SessionScan>,
+    full_scan_limit: Option<u64>,
+) -> (Vec<Session>, ScanCounts) {
     // Find all .jsonl files with valid UUID filenames
     let jsonl_files: Vec<PathBuf> = WalkDir::new(projects_dir)
         .min_depth(2)
@@ -151,17 +437,133 @@ pub fn find_sessions_with_source(
         .map(|e| e.into_path())
         .collect();
 
+    // A warm cache makes this near-instant, so the spinner only shows on cold
+    // or heavy scans.
+    let progress = Progress::new("scanned", jsonl_files.len()).in_scope(source.display_name());
+
     // File sizes are wildly skewed (sessions range from a few KB to hundreds of
     // MB). Force per-item task granularity so rayon can steal individual files;
     // the default recursive-split chunking bundles multiple large files into one
     // unstealable range and stalls other workers.
-    let sessions: Vec<Session> = jsonl_files
-        .into_par_iter()
-        .with_max_len(1)
-        .filter_map(|filepath| extract_session_metadata(filepath, &source))
+    let shared: &MetadataCache<SessionScan> = cache;
+    let scans: Vec<(PathBuf, fs::Metadata, SessionScan, ScanKind)> =
+        progress::run(&progress, || {
+            jsonl_files
+                .into_par_iter()
+                .with_max_len(1)
+                .filter_map(|filepath| {
+                    progress.inc();
+                    let metadata = fs::metadata(&filepath).ok()?;
+                    let oversized = full_scan_limit.is_some_and(|limit| metadata.len() > limit);
+                    if let Some(scan) = shared
+                        .get(&filepath, Fingerprint::of(&metadata))
+                        .filter(|scan| !scan.partial || oversized)
+                    {
+                        return Some((filepath, metadata, scan.clone(), ScanKind::Cached));
+                    }
+                    // Active sessions change constantly but only by appending; pick
+                    // up from the previous end instead of rereading the whole file.
+                    let resumable = shared
+                        .previous(&filepath)
+                        .filter(|prev| can_resume(prev, &filepath, metadata.len()));
+                    let (scan, kind) = match resumable {
+                        Some(prev) => (
+                            scan_session_file_from(&filepath, prev.clone()),
+                            ScanKind::Resumed,
+                        ),
+                        None if oversized => (
+                            scan_session_file_partial(&filepath, metadata.len()),
+                            ScanKind::Partial,
+                        ),
+                        None => (scan_session_file(&filepath), ScanKind::Full),
+                    };
+                    Some((filepath, metadata, scan, kind))
+                })
+                .collect()
+        });
+
+    let mut counts = ScanCounts::default();
+    for (filepath, metadata, scan, kind) in &scans {
+        if *kind == ScanKind::Cached {
+            counts.cached += 1;
+        } else {
+            counts.scanned += 1;
+            counts.resumed += usize::from(*kind == ScanKind::Resumed);
+            counts.partial += usize::from(*kind == ScanKind::Partial);
+            cache.insert(filepath.clone(), Fingerprint::of(metadata), scan.clone());
+        }
+    }
+    cache.retain_paths(&scans.iter().map(|(p, ..)| p.as_path()).collect());
+
+    let mut sessions: Vec<Session> = scans
+        .into_iter()
+        .filter_map(|(filepath, metadata, scan, _)| {
+            build_session(filepath, &metadata, scan, source)
+        })
         .collect();
+    resolve_repo_roots(&mut sessions, matches!(source, SessionSource::Local));
 
-    Ok(sessions)
+    (sessions, counts)
+}
+
+/// Re-read one session after it may have changed (e.g. it was just resumed),
+/// without rescanning the rest of its projects root. Appended lines are read
+/// from the cached resume point, and the cache entry is updated so the next
+/// launch sees the new state. Fields resolved across the whole list (project
+/// name, repo root) are kept from `session`. `None` if the session should no
+/// longer be listed.
+pub fn refresh_session(session: &Session) -> Option<Session> {
+    // Resuming an archived session restored it to the projects dir.
+    let filepath = if session.archived {
+        archive::original_path(
+            &archive::archive_dir().ok()?,
+            &local_projects_dirs().ok()?,
+            &session.filepath,
+        )?
+    } else {
+        session.filepath.clone()
+    };
+    let projects_dir = filepath.parent()?.parent()?;
+    let mut cache = MetadataCache::load(projects_dir);
+    let fresh = rescan_session(session, &filepath, &mut cache);
+    if let Err(e) = cache.save() {
+        eprintln!("Warning: Failed to save metadata cache: {}", e);
+    }
+    fresh
+}
+
+fn rescan_session(
+    session: &Session,
+    filepath: &Path,
+    cache: &mut MetadataCache<SessionScan>,
+) -> Option<Session> {
+    let metadata = fs::metadata(filepath).ok()?;
+    let scan = match cache
+        .previous(filepath)
+        .filter(|prev| can_resume(prev, filepath, metadata.len()))
+    {
+        Some(prev) => scan_session_file_from(filepath, prev.clone()),
+        None if session.partial => scan_session_file_partial(filepath, metadata.len()),
+        None => scan_session_file(filepath),
+    };
+    cache.insert(
+        filepath.to_path_buf(),
+        Fingerprint::of(&metadata),
+        scan.clone(),
+    );
+
+    let mut fresh = build_session(filepath.to_path_buf(), &metadata, scan, &session.source)?;
+    fresh.project = session.project.clone();
+    fresh.repo_root = session.repo_root.clone();
+    Some(fresh)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanKind {
+    Cached,
+    Full,
+    Resumed,
+    Partial,
 }
 
 /// Check if a string is a valid UUID (8-4-4-4-12 format with hex chars)
@@ -192,33 +594,45 @@ fn is_valid_session_file(path: &Path) -> bool {
             .unwrap_or(false)
 }
 
-/// Extract all session metadata from a .jsonl file in a single pass.
-fn extract_session_metadata(filepath: PathBuf, source: &SessionSource) -> Option<Session> {
-    let id = filepath.file_stem()?.to_string_lossy().into_owned();
+/// Assemble a `Session` from a file's scan result and filesystem metadata.
+/// Returns `None` for sessions that should not be listed.
+fn build_session(
+    filepath: PathBuf,
+    metadata: &fs::Metadata,
+    scan: SessionScan,
+    source: &SessionSource,
+) -> Option<Session> {
+    let name = filepath.file_name()?.to_string_lossy();
+    let id = name
+        .strip_suffix(".gz")
+        .unwrap_or(&name)
+        .strip_suffix(".jsonl")?
+        .to_string();
 
-    let metadata = fs::metadata(&filepath).ok()?;
     let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
     // Birthtime is meaningless for rsynced cache copies (it's when the local
     // file was written, not when the remote session began). Fall back to mtime.
     let created = match source {
         SessionSource::Local => metadata.created().unwrap_or(modified),
-        SessionSource::Remote { .. } => modified,
+        SessionSource::Remote { .. } | SessionSource::Team { .. } => modified,
     };
 
-    let scan = scan_session_file(&filepath);
-
     if scan.skip {
         return None;
     }
 
-    // Skip "empty" sessions that have no user content
-    if scan.project_path.is_empty() && scan.first_prompt.is_none() && scan.summary.is_none() {
+    if scan.is_empty() {
         return None;
     }
 
     let parent_dir_name = filepath.parent()?.file_name()?.to_string_lossy();
     let project = extract_project_name(&scan.project_path, &parent_dir_name);
 
+    // Provisional: the cwd itself when it was in a repo. Narrowed to the
+    // actual root by `resolve_repo_roots`.
+    let repo_root =
+        (scan.in_git && !scan.project_path.is_empty()).then(|| scan.project_path.clone());
+
     Some(Session {
         id,
         project,
@@ -230,14 +644,42 @@ fn extract_session_metadata(filepath: PathBuf, source: &SessionSource) -> Option
         summary: scan.summary,
         name: scan.custom_title,
         tag: scan.tag,
+        git_branch: scan.git_branch,
         turn_count: scan.turn_count,
         source: source.clone(),
+        redundant_fork: scan.forked_from.is_some() && scan.unforked_entries == 0,
+        repo_root,
         forked_from: scan.forked_from,
+        tokens: scan
+            .model_usage
+            .values()
+            .fold(TokenUsage::default(), |mut acc, u| {
+                acc += *u;
+                acc
+            }),
+        model_usage: scan.model_usage,
+        size: metadata.len(),
+        first_activity: scan.first_activity_ms.map(from_unix_ms),
+        last_activity: scan.last_activity_ms.map(from_unix_ms),
+        active_hours: scan.active_hours,
+        open_todos: scan.open_todos,
+        plan_offset: scan.plan_offset,
+        mcp_calls: scan.mcp_calls,
+        permission_requests: scan.permission_requests,
+        denied_tools: scan.denied_tools,
+        partial: scan.partial,
+        archived: false,
+        resumed: None,
+        resume_count: 0,
+        relevance: 0.0,
+        clock_skew: None,
+        local_path: None,
     })
 }
 
-/// Output of single-pass scan over a session file.
-#[derive(Default)]
+/// Output of single-pass scan over a session file. Persisted in the metadata
+/// cache — bump `CACHE_VERSION` there when fields change.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 struct SessionScan {
     project_path: String,
     first_prompt: Option<String>,
@@ -246,8 +688,66 @@ struct SessionScan {
     summary: Option<String>,
     custom_title: Option<String>,
     tag: Option<String>,
+    /// Token usage keyed by `message.model` (pricing differs per model).
+    model_usage: BTreeMap<String, TokenUsage>,
+    /// Earliest/latest entry `timestamp`, as Unix milliseconds.
+    first_activity_ms: Option<u64>,
+    last_activity_ms: Option<u64>,
+    /// User/assistant entries per hour, keyed by hours since the Unix epoch.
+    active_hours: BTreeMap<u32, u32>,
+    /// Unfinished items of the latest TodoWrite call (later calls replace it).
+    open_todos: Vec<OpenTodo>,
+    /// Byte offset of the line with the last ExitPlanMode call (plan mode),
+    /// so previews can read the plan without scanning the file again.
+    plan_offset: Option<u64>,
+    /// MCP `tool_use` blocks by tool name.
+    mcp_calls: BTreeMap<String, u32>,
+    /// `tool_use` blocks of tools that ask for permission by default.
+    permission_requests: u32,
+    /// Refused tool calls by tool name (`UNKNOWN_TOOL` if the call wasn't seen).
+    denied_tools: BTreeMap<String, u32>,
+    /// Tool names of `tool_use` blocks still waiting for their result, by
+    /// ID. Carried across a resume: a file often ends at a permission prompt.
+    pending_tool_uses: BTreeMap<String, String>,
+    /// Some entry recorded a `gitBranch`, i.e. the cwd was inside a repo.
+    in_git: bool,
+    /// The `gitBranch` of the latest entry that recorded one.
+    git_branch: Option<String>,
+    /// User/assistant entries not copied from a fork parent.
+    unforked_entries: usize,
     /// Session should be excluded from the picker (sidechain or swarm-teammate).
     skip: bool,
+    /// Resume point: bytes through the last complete line (0 = start over),
+    /// lines read, and a digest of the bytes just before that offset.
+    scanned_bytes: u64,
+    scanned_lines: usize,
+    tail_digest: u64,
+    /// Usage dedupe state, carried across a resume.
+    last_usage_id: Option<String>,
+    /// Only the head and tail were read (see `scan_session_file_partial`).
+    partial: bool,
+}
+
+impl SessionScan {
+    /// No user content at all: no cwd, prompt, or summary. Such sessions are
+    /// left out of the picker.
+    fn is_empty(&self) -> bool {
+        self.project_path.is_empty() && self.first_prompt.is_none() && self.summary.is_none()
+    }
+}
+
+/// Model key for usage entries that don't name their model.
+pub const UNKNOWN_MODEL: &str = "unknown";
+
+/// Read an Anthropic API `usage` object; missing counters are zero.
+fn parse_usage(usage: &serde_json::Value) -> TokenUsage {
+    let field = |name: &str| usage.get(name).and_then(|v| v.as_u64()).unwrap_or(0);
+    TokenUsage {
+        input: field("input_tokens"),
+        output: field("output_tokens"),
+        cache_creation: field("cache_creation_input_tokens"),
+        cache_read: field("cache_read_input_tokens"),
+    }
 }
 
 /// Number of lines to parse fully before the byte-level prefilter engages.
@@ -255,137 +755,554 @@ struct SessionScan {
 /// every entry, so it is reliably present within the first handful of lines.
 const HEADER_SCAN_LINES: usize = 16;
 
+/// Lines longer than this (giant tool results, pasted files) aren't parsed
+/// as a whole; see `oversized_entry`.
+const OVERSIZED_LINE_BYTES: usize = 1024 * 1024;
+
+/// How much of an oversized line is kept from its start and its end.
+const OVERSIZED_HEAD_BYTES: usize = 64 * 1024;
+const OVERSIZED_TAIL_BYTES: usize = 16 * 1024;
+
+/// Entry fields the metadata scan reads. Oversized lines are reduced to
+/// these before parsing.
+const SCAN_FIELDS: &[&[&str]] = &[
+    &["type"],
+    &["cwd"],
+    &["gitBranch"],
+    &["timestamp"],
+    &["isSidechain"],
+    &["teamName"],
+    &["isMeta"],
+    &["isCompactSummary"],
+    &["forkedFrom", "sessionId"],
+    &["summary"],
+    &["customTitle"],
+    &["tag"],
+    &["message", "id"],
+    &["message", "model"],
+    &["message", "content"],
+    &["message", "content", "0", "type"],
+    &["message", "content", "0", "text"],
+    &["message", "usage"],
+];
+
+/// Longest string kept from an oversized line: enough for a first prompt.
+const OVERSIZED_STRING_BYTES: usize = 4096;
+
+/// Reads JSONL lines with bounded memory. Lines up to `OVERSIZED_LINE_BYTES`
+/// are kept whole in `line`; longer ones keep only their first
+/// `OVERSIZED_HEAD_BYTES` there and (roughly) their last
+/// `OVERSIZED_TAIL_BYTES` in `tail`.
+struct LineReader<R> {
+    reader: R,
+    line: Vec<u8>,
+    tail: Vec<u8>,
+    oversized: bool,
+}
+
+impl<R: BufRead> LineReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: Vec::new(),
+            tail: Vec::new(),
+            oversized: false,
+        }
+    }
+
+    /// Read the next line. Returns the bytes consumed (0 at end of file) and
+    /// whether the line ended with a newline.
+    fn next_line(&mut self) -> std::io::Result<(usize, bool)> {
+        self.line.clear();
+        self.tail.clear();
+        self.oversized = false;
+        let mut consumed = 0;
+        loop {
+            let chunk = self.reader.fill_buf()?;
+            if chunk.is_empty() {
+                return Ok((consumed, false));
+            }
+            let (take, done) = match memchr::memchr(b'\n', chunk) {
+                Some(i) => (i + 1, true),
+                None => (chunk.len(), false),
+            };
+            let piece = &chunk[..take];
+            if !self.oversized {
+                self.line.extend_from_slice(piece);
+                if self.line.len() > OVERSIZED_LINE_BYTES {
+                    self.oversized = true;
+                    let tail_start = self.line.len() - OVERSIZED_TAIL_BYTES;
+                    self.tail.extend_from_slice(&self.line[tail_start..]);
+                    self.line.truncate(OVERSIZED_HEAD_BYTES);
+                    self.line.shrink_to(OVERSIZED_LINE_BYTES);
+                }
+            } else {
+                self.tail.extend_from_slice(piece);
+                if self.tail.len() > 2 * OVERSIZED_TAIL_BYTES {
+                    self.tail.drain(..self.tail.len() - OVERSIZED_TAIL_BYTES);
+                }
+            }
+            consumed += take;
+            self.reader.consume(take);
+            if done {
+                return Ok((consumed, true));
+            }
+        }
+    }
+
+    /// Whether the current line mentions a content-bearing entry type.
+    fn mentions_content_type(&self) -> bool {
+        line_mentions_content_type(&self.line)
+            || (self.oversized && line_mentions_content_type(&self.tail))
+    }
+
+    /// The current line as JSON: parsed whole, or reduced to `SCAN_FIELDS`
+    /// if oversized.
+    fn entry(&self) -> Option<serde_json::Value> {
+        if self.oversized {
+            Some(oversized_entry(&self.line, &self.tail))
+        } else {
+            serde_json::from_slice(&self.line).ok()
+        }
+    }
+
+    fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// The `SCAN_FIELDS` of an oversized line, from its head and tail. Fields
+/// Claude Code writes after the message body (`timestamp`, the assistant's
+/// `usage`) are usually only in the tail, which can't be walked from the
+/// root, so they're found by key.
+fn oversized_entry(head: &[u8], tail: &[u8]) -> serde_json::Value {
+    let mut entry = crate::partial_json::extract(head, SCAN_FIELDS, OVERSIZED_STRING_BYTES);
+    if !entry.is_object() {
+        return serde_json::Value::Null;
+    }
+    let value_after = |key: &[u8]| {
+        memmem::rfind(tail, key).map(|pos| {
+            crate::partial_json::extract(&tail[pos + key.len()..], &[&[]], OVERSIZED_STRING_BYTES)
+        })
+    };
+    if entry.get("timestamp").is_none()
+        && let Some(ts @ serde_json::Value::String(_)) = value_after(b"\"timestamp\":")
+    {
+        entry["timestamp"] = ts;
+    }
+    if entry.get("type").and_then(|v| v.as_str()) == Some("assistant")
+        && entry.pointer("/message/usage").is_none()
+        && let Some(usage @ serde_json::Value::Object(_)) = value_after(b"\"usage\":")
+        && let Some(message) = entry.get_mut("message").and_then(|m| m.as_object_mut())
+    {
+        message.insert("usage".to_string(), usage);
+    }
+    entry
+}
+
+/// Bytes read from each end of a file over the full-scan size limit.
+const PARTIAL_SCAN_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Bytes hashed before a resume point to detect rewritten files.
+const TAIL_DIGEST_BYTES: u64 = 64;
+
+/// Digest of the `TAIL_DIGEST_BYTES` bytes ending at `end`.
+fn tail_digest(file: &mut File, end: u64) -> std::io::Result<u64> {
+    use std::hash::Hasher;
+    use std::io::{Read, Seek, SeekFrom};
+
+    let start = end.saturating_sub(TAIL_DIGEST_BYTES);
+    let mut buf = vec![0; (end - start) as usize];
+    file.seek(SeekFrom::Start(start))?;
+    file.read_exact(&mut buf)?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    hasher.write(&buf);
+    Ok(hasher.finish())
+}
+
+/// Whether `path` (now `len` bytes) is `prev`'s file with lines appended,
+/// so scanning can continue from `prev.scanned_bytes`. Transcripts are
+/// append-only; the digest catches the rare rewrite or truncation.
+fn can_resume(prev: &SessionScan, path: &Path, len: u64) -> bool {
+    prev.scanned_bytes > 0
+        && len > prev.scanned_bytes
+        && File::open(path)
+            .and_then(|mut f| tail_digest(&mut f, prev.scanned_bytes))
+            .is_ok_and(|d| d == prev.tail_digest)
+}
+
 /// Scan a session file once to collect all metadata and turn count.
 ///
 /// Single file open, single pass. After the first `HEADER_SCAN_LINES` lines,
 /// a cheap byte-level check skips lines that cannot contribute content (the
 /// bulk of large sessions is `progress` chatter we never read).
 fn scan_session_file(filepath: &Path) -> SessionScan {
-    let mut scan = SessionScan::default();
+    scan_session_file_from(filepath, SessionScan::default())
+}
 
-    let Ok(file) = File::open(filepath) else {
+/// Continue `scan` from its resume point. Every field accumulates (first
+/// seen, last seen, counts, min/max), so reading only the appended lines
+/// gives the same result as a full pass.
+fn scan_session_file_from(filepath: &Path, mut scan: SessionScan) -> SessionScan {
+    use std::io::{Seek, SeekFrom};
+
+    let Ok(mut file) = File::open(filepath) else {
         return scan;
     };
-    let mut reader = BufReader::with_capacity(64 * 1024, file);
-
-    let mut line = String::new();
-    let mut line_no = 0usize;
+    if scan.scanned_bytes > 0 && file.seek(SeekFrom::Start(scan.scanned_bytes)).is_err() {
+        return scan;
+    }
+    let mut lines = LineReader::new(BufReader::with_capacity(64 * 1024, file));
+    let mut cursor = ScanCursor {
+        line_no: scan.scanned_lines,
+        offset: scan.scanned_bytes,
+        complete: true,
+        last_usage_id: scan.last_usage_id.take(),
+    };
+    if !cursor.run(&mut lines, &mut scan) {
+        scan.scanned_bytes = 0;
+        return scan;
+    }
 
-    loop {
-        line.clear();
-        match reader.read_line(&mut line) {
-            Ok(0) => break,
-            Ok(_) => {}
-            Err(_) => break,
+    let mut file = lines.into_inner().into_inner();
+    match tail_digest(&mut file, cursor.offset) {
+        Ok(digest) if cursor.complete && cursor.offset > 0 => {
+            scan.scanned_bytes = cursor.offset;
+            scan.scanned_lines = cursor.line_no;
+            scan.tail_digest = digest;
+            scan.last_usage_id = cursor.last_usage_id;
         }
-        line_no += 1;
+        _ => scan.scanned_bytes = 0,
+    }
+    scan
+}
 
-        // Past the header window, only parse lines that mention a content-bearing
-        // entry type. This skips ~99% of lines in progress-heavy sessions.
-        if line_no > HEADER_SCAN_LINES && !line_mentions_content_type(line.as_bytes()) {
-            continue;
-        }
+/// Scan only the first and last `PARTIAL_SCAN_BYTES` of a file too large to
+/// read in full. Session-level fields (cwd, fork parent, first prompt) come
+/// from the head; title, summary, tag, and latest activity usually from the
+/// tail. Counts cover only those two windows, so they're lower bounds.
+fn scan_session_file_partial(filepath: &Path, len: u64) -> SessionScan {
+    use std::io::{Read, Seek, SeekFrom};
 
-        let entry: serde_json::Value = match serde_json::from_str(&line) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
+    let mut scan = SessionScan {
+        partial: true,
+        ..SessionScan::default()
+    };
+    let Ok(file) = File::open(filepath) else {
+        return scan;
+    };
+    let mut cursor = ScanCursor::default();
+    let head = BufReader::with_capacity(64 * 1024, file.take(PARTIAL_SCAN_BYTES));
+    let mut lines = LineReader::new(head);
+    if !cursor.run(&mut lines, &mut scan) {
+        return scan;
+    }
 
-        // Sidechain (subagent) and teammate (swarm) sessions can both land in
-        // the main project dir as UUID-named files. Bail early — they can be
-        // large and we're discarding them anyway.
-        if entry.get("isSidechain").and_then(|v| v.as_bool()) == Some(true)
-            || entry.get("teamName").and_then(|v| v.as_str()).is_some()
-        {
-            scan.skip = true;
-            return scan;
-        }
+    let mut file = lines.into_inner().into_inner().into_inner();
+    let tail_start = len
+        .saturating_sub(PARTIAL_SCAN_BYTES)
+        .max(PARTIAL_SCAN_BYTES);
+    if file.seek(SeekFrom::Start(tail_start)).is_err() {
+        return scan;
+    }
+    let mut lines = LineReader::new(BufReader::with_capacity(64 * 1024, file));
+    // The window starts mid-line; drop the fragment. Offsets from here on
+    // are real file positions again.
+    if let Ok((n, _)) = lines.next_line() {
+        cursor.offset = tail_start + n as u64;
+        cursor.run(&mut lines, &mut scan);
+    }
+    scan
+}
 
-        let entry_type = entry.get("type").and_then(|v| v.as_str());
+/// Position and carry-over state of a scan through one file.
+#[derive(Default)]
+struct ScanCursor {
+    line_no: usize,
+    /// Bytes consumed through the last complete (newline-terminated) line.
+    offset: u64,
+    /// A final line without its newline may still be being written; don't
+    /// resume past it.
+    complete: bool,
+    /// Multi-block responses are written as one entry per block, each
+    /// repeating the message's usage; count each message ID once.
+    last_usage_id: Option<String>,
+}
 
-        match entry_type {
-            Some("summary") => {
-                if let Some(s) = entry.get("summary").and_then(|v| v.as_str()) {
-                    scan.summary = Some(s.to_owned());
+impl ScanCursor {
+    /// Feed the remaining lines of `lines` into `scan`. Returns false if the
+    /// file turned out to be a sidechain or teammate session (`scan.skip`).
+    fn run<R: BufRead>(&mut self, lines: &mut LineReader<R>, scan: &mut SessionScan) -> bool {
+        self.complete = true;
+        loop {
+            let line_start = self.offset;
+            match lines.next_line() {
+                Ok((0, _)) => break,
+                Ok((n, true)) => self.offset += n as u64,
+                Ok(_) => self.complete = false,
+                Err(_) => {
+                    self.complete = false;
+                    break;
                 }
+            }
+            self.line_no += 1;
+
+            // Past the header window, only parse lines that mention a content-bearing
+            // entry type. This skips ~99% of lines in progress-heavy sessions.
+            if self.line_no > HEADER_SCAN_LINES && !lines.mentions_content_type() {
                 continue;
             }
-            Some("custom-title") => {
-                if let Some(t) = entry.get("customTitle").and_then(|v| v.as_str()) {
-                    scan.custom_title = Some(t.to_owned());
-                }
+
+            let Some(entry) = lines.entry() else {
                 continue;
+            };
+
+            // Sidechain (subagent) and teammate (swarm) sessions can both land in
+            // the main project dir as UUID-named files. Bail early — they can be
+            // large and we're discarding them anyway.
+            if entry.get("isSidechain").and_then(|v| v.as_bool()) == Some(true)
+                || entry.get("teamName").and_then(|v| v.as_str()).is_some()
+            {
+                scan.skip = true;
+                return false;
             }
-            Some("tag") => {
-                // Empty string = explicit removal. Missing field = malformed,
-                // preserve existing (matches summary/custom-title semantics).
-                if let Some(t) = entry.get("tag").and_then(|v| v.as_str()) {
-                    scan.tag = (!t.is_empty()).then(|| t.to_owned());
+
+            let timestamp_ms = entry
+                .get("timestamp")
+                .and_then(|v| v.as_str())
+                .and_then(crate::dates::parse_timestamp_ms);
+            if let Some(ms) = timestamp_ms {
+                scan.first_activity_ms = Some(scan.first_activity_ms.map_or(ms, |f| f.min(ms)));
+                scan.last_activity_ms = Some(scan.last_activity_ms.map_or(ms, |l| l.max(ms)));
+            }
+
+            let entry_type = entry.get("type").and_then(|v| v.as_str());
+            if let Some(ms) = timestamp_ms
+                && matches!(entry_type, Some("user" | "assistant"))
+            {
+                *scan
+                    .active_hours
+                    .entry((ms / 3_600_000) as u32)
+                    .or_default() += 1;
+            }
+
+            match entry_type {
+                Some("summary") => {
+                    if let Some(s) = entry.get("summary").and_then(|v| v.as_str()) {
+                        scan.summary = Some(s.to_owned());
+                    }
+                    continue;
                 }
-                continue;
+                Some("custom-title") => {
+                    if let Some(t) = entry.get("customTitle").and_then(|v| v.as_str()) {
+                        scan.custom_title = Some(t.to_owned());
+                    }
+                    continue;
+                }
+                Some("tag") => {
+                    // Empty string = explicit removal. Missing field = malformed,
+                    // preserve existing (matches summary/custom-title semantics).
+                    if let Some(t) = entry.get("tag").and_then(|v| v.as_str()) {
+                        scan.tag = (!t.is_empty()).then(|| t.to_owned());
+                    }
+                    continue;
+                }
+                _ => {}
             }
-            _ => {}
-        }
 
-        if scan.project_path.is_empty()
-            && let Some(cwd) = entry.get("cwd").and_then(|v| v.as_str())
-        {
-            scan.project_path = cwd.to_owned();
-        }
+            if scan.project_path.is_empty()
+                && let Some(cwd) = entry.get("cwd").and_then(|v| v.as_str())
+            {
+                scan.project_path = cwd.to_owned();
+            }
 
-        if scan.forked_from.is_none()
-            && let Some(parent_id) = entry
-                .get("forkedFrom")
-                .and_then(|f| f.get("sessionId"))
+            if let Some(branch) = entry
+                .get("gitBranch")
                 .and_then(|v| v.as_str())
-        {
-            scan.forked_from = Some(parent_id.to_owned());
-        }
+                .filter(|b| !b.is_empty())
+            {
+                scan.in_git = true;
+                // The latest one: a session can switch branches midway
+                if scan.git_branch.as_deref() != Some(branch) {
+                    scan.git_branch = Some(branch.to_owned());
+                }
+            }
 
-        // isMeta/isCompactSummary mark synthetic user messages (attachment
-        // context, post-compaction summaries). They carry cwd/forkedFrom like
-        // any entry, but their content is never real user input.
-        if entry.get("isMeta").and_then(|v| v.as_bool()) == Some(true)
-            || entry.get("isCompactSummary").and_then(|v| v.as_bool()) == Some(true)
-        {
-            continue;
-        }
+            if scan.forked_from.is_none()
+                && let Some(parent_id) = entry
+                    .get("forkedFrom")
+                    .and_then(|f| f.get("sessionId"))
+                    .and_then(|v| v.as_str())
+            {
+                scan.forked_from = Some(parent_id.to_owned());
+            }
 
-        if entry_type == Some("user")
-            && let Some(content) = entry.get("message").and_then(|m| m.get("content"))
-            && let Some(first) = iter_text_blocks(content).next()
-        {
-            if scan.first_prompt.is_none() && is_first_prompt_candidate(first) {
-                scan.first_prompt = Some(crate::normalize_summary(first, 120));
+            // Forks copy the parent's conversation with `forkedFrom` stamped on
+            // each copied entry; entries without it were written after forking.
+            if matches!(entry_type, Some("user" | "assistant")) && entry.get("forkedFrom").is_none()
+            {
+                scan.unforked_entries += 1;
             }
-            if counts_as_turn(first) {
-                scan.turn_count += 1;
+
+            // isMeta/isCompactSummary mark synthetic user messages (attachment
+            // context, post-compaction summaries). They carry cwd/forkedFrom like
+            // any entry, but their content is never real user input.
+            if entry.get("isMeta").and_then(|v| v.as_bool()) == Some(true)
+                || entry.get("isCompactSummary").and_then(|v| v.as_bool()) == Some(true)
+            {
+                continue;
+            }
+
+            if entry_type == Some("assistant")
+                && let Some(content) = entry.get("message").and_then(|m| m.get("content"))
+            {
+                if let Some(todos) = last_todo_list(content) {
+                    scan.open_todos = todos;
+                }
+                if exit_plan_mode_plan(content).is_some() {
+                    scan.plan_offset = Some(line_start);
+                }
+                for name in iter_tool_use_names(content) {
+                    if crate::session::mcp_tool(name).is_some() {
+                        *scan.mcp_calls.entry(name.to_owned()).or_default() += 1;
+                    }
+                    if asks_permission(name) {
+                        scan.permission_requests += 1;
+                    }
+                }
+                for (id, name) in iter_tool_uses(content) {
+                    scan.pending_tool_uses
+                        .insert(id.to_owned(), name.to_owned());
+                }
+            }
+
+            if entry_type == Some("user")
+                && let Some(content) = entry.get("message").and_then(|m| m.get("content"))
+            {
+                for result in iter_blocks_of_type(content, "tool_result") {
+                    let name = result
+                        .get("tool_use_id")
+                        .and_then(|v| v.as_str())
+                        .and_then(|id| scan.pending_tool_uses.remove(id));
+                    if is_permission_denial(result) {
+                        let name = name.unwrap_or_else(|| UNKNOWN_TOOL.to_owned());
+                        *scan.denied_tools.entry(name).or_default() += 1;
+                    }
+                }
+            }
+
+            if entry_type == Some("assistant")
+                && let Some(message) = entry.get("message")
+                && let Some(usage) = message.get("usage")
+            {
+                let id = message.get("id").and_then(|v| v.as_str());
+                if id.is_none() || id != self.last_usage_id.as_deref() {
+                    let model = message
+                        .get("model")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(UNKNOWN_MODEL);
+                    *scan.model_usage.entry(model.to_owned()).or_default() += parse_usage(usage);
+                    self.last_usage_id = id.map(str::to_owned);
+                }
+                continue;
+            }
+
+            if entry_type == Some("user")
+                && let Some(content) = entry.get("message").and_then(|m| m.get("content"))
+                && let Some(first) = iter_text_blocks(content).next()
+            {
+                if scan.first_prompt.is_none() && is_first_prompt_candidate(first) {
+                    scan.first_prompt = Some(crate::normalize_summary(first, 120));
+                }
+                if counts_as_turn(first) {
+                    scan.turn_count += 1;
+                }
             }
         }
+        true
     }
-
-    scan
 }
 
 // =============================================================================
-// Search Index (built lazily, off the discovery hot path)
+// Search Index (extracted on first search, refreshed per file)
 // =============================================================================
 
 /// Lowercase transcript text keyed by session ID, for Ctrl+S filtering.
-pub type SearchIndex = std::collections::HashMap<String, String>;
+///
+/// Nothing is extracted until a search actually runs — most invocations never
+/// search, and the lowercased copies cost both CPU and memory. Each entry
+/// remembers the (mtime, size) of the file it came from, so later searches
+/// only re-read transcripts that changed in the meantime.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    texts: HashMap<String, String>,
+    fingerprints: HashMap<String, Fingerprint>,
+}
 
-/// Build the transcript search index for the given sessions in parallel.
-/// Intended to run on a background thread after the picker has rendered.
-pub fn build_search_index(targets: Vec<(String, PathBuf)>) -> SearchIndex {
-    targets
-        .into_par_iter()
-        .with_max_len(1)
-        .map(|(id, path)| (id, scan_search_text(&path)))
-        .collect()
+/// A session whose index entry is missing or out of date.
+pub struct StaleEntry {
+    id: String,
+    path: PathBuf,
+    fingerprint: Fingerprint,
+}
+
+impl SearchIndex {
+    /// Sessions among `targets` (ID, file) that need (re-)extracting.
+    /// Files that can no longer be read are skipped, keeping any old text.
+    pub fn stale(&self, targets: &[(&str, &Path)]) -> Vec<StaleEntry> {
+        targets
+            .iter()
+            .filter_map(|&(id, path)| {
+                let fingerprint = Fingerprint::of(&fs::metadata(path).ok()?);
+                (self.fingerprints.get(id) != Some(&fingerprint)).then(|| StaleEntry {
+                    id: id.to_string(),
+                    path: path.to_path_buf(),
+                    fingerprint,
+                })
+            })
+            .collect()
+    }
+
+    /// Extract text for `stale` in parallel, bumping `progress` once per
+    /// file. `include_tools` adds `tool_result` output, which can dwarf the
+    /// conversation itself, so it is opt-in.
+    pub fn update(&mut self, stale: Vec<StaleEntry>, include_tools: bool, progress: &Progress) {
+        let extracted: Vec<(StaleEntry, String)> = stale
+            .into_par_iter()
+            .with_max_len(1)
+            .map(|entry| {
+                let text = scan_search_text(&entry.path, include_tools);
+                progress.inc();
+                (entry, text)
+            })
+            .collect();
+        for (entry, text) in extracted {
+            self.fingerprints
+                .insert(entry.id.clone(), entry.fingerprint);
+            self.texts.insert(entry.id, text);
+        }
+    }
+
+    /// Indexed text by session ID.
+    pub fn texts(&self) -> &HashMap<String, String> {
+        &self.texts
+    }
 }
 
 /// Extract lowercase transcript text from a single session file.
-fn scan_search_text(filepath: &Path) -> String {
+pub fn scan_search_text(filepath: &Path, include_tools: bool) -> String {
+    scan_transcript_text(filepath, true, include_tools)
+}
+
+/// Extract original-case transcript text from a single session file. The
+/// index is ASCII-lowercased, so case-sensitive searches use it only as a
+/// prefilter and confirm candidates against this.
+pub fn transcript_text(filepath: &Path, include_tools: bool) -> String {
+    scan_transcript_text(filepath, false, include_tools)
+}
+
+/// Collect the user/assistant text that search covers, newline-separated.
+fn scan_transcript_text(filepath: &Path, lowercase: bool, include_tools: bool) -> String {
     let Ok(file) = File::open(filepath) else {
         return String::new();
     };
@@ -421,6 +1338,13 @@ fn scan_search_text(filepath: &Path) -> String {
         };
         let Some(content) = content else { continue };
 
+        // Tool results ride on user entries with no text blocks of their own.
+        if is_user && include_tools {
+            for text in iter_tool_result_texts(content) {
+                append_text(&mut out, text, lowercase);
+            }
+        }
+
         let mut blocks = iter_text_blocks(content);
         let Some(first) = blocks.next() else { continue };
 
@@ -430,9 +1354,9 @@ fn scan_search_text(filepath: &Path) -> String {
             continue;
         }
 
-        append_lowercase(&mut out, first);
+        append_text(&mut out, first, lowercase);
         for text in blocks {
-            append_lowercase(&mut out, text);
+            append_text(&mut out, text, lowercase);
         }
     }
 
@@ -463,54 +1387,887 @@ pub fn line_mentions_content_type(line: &[u8]) -> bool {
         if is_content {
             return true;
         }
-        haystack = after;
+        haystack = after;
+    }
+    false
+}
+
+/// Append `text` to `buf` (optionally lowercased), separated by a newline.
+/// Avoids the intermediate `Vec<String>` + `join` + `to_lowercase`
+/// triple-allocation the previous implementation performed per file.
+fn append_text(buf: &mut String, text: &str, lowercase: bool) {
+    if text.is_empty() {
+        return;
+    }
+    if !buf.is_empty() {
+        buf.push('\n');
+    }
+    let start = buf.len();
+    buf.push_str(text);
+    if !lowercase {
+        return;
+    }
+    // SAFETY: make_ascii_lowercase only flips bit 0x20 on uppercase ASCII and
+    // leaves all other bytes (including UTF-8 continuation bytes) untouched,
+    // so the buffer remains valid UTF-8. Non-ASCII uppercase is left as-is —
+    // a reasonable trade since Ctrl+S search compares against the user's own
+    // transcript text and the quadratic cost of full Unicode case-folding
+    // dominates on multi-GB corpora.
+    unsafe { buf.as_bytes_mut()[start..].make_ascii_lowercase() };
+}
+
+/// Iterate over all text blocks in a message content value (string or array
+/// of content blocks), borrowing from the underlying JSON.
+fn iter_text_blocks(content: &serde_json::Value) -> impl Iterator<Item = &str> {
+    let single = content.as_str();
+    let blocks = content
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|c| c.get("type").and_then(|v| v.as_str()) == Some("text"))
+        .filter_map(|c| c.get("text").and_then(|v| v.as_str()));
+    single.into_iter().chain(blocks)
+}
+
+/// Unfinished items of the last TodoWrite `tool_use` block in message
+/// content; `None` without one. Each call carries the whole list, so the
+/// last one is the current state.
+fn last_todo_list(content: &serde_json::Value) -> Option<Vec<OpenTodo>> {
+    let todos = content
+        .as_array()?
+        .iter()
+        .rfind(|c| {
+            c.get("type").and_then(|v| v.as_str()) == Some("tool_use")
+                && c.get("name").and_then(|v| v.as_str()) == Some("TodoWrite")
+        })?
+        .get("input")?
+        .get("todos")?
+        .as_array()?;
+    Some(
+        todos
+            .iter()
+            .filter_map(|todo| {
+                let status = todo.get("status").and_then(|v| v.as_str());
+                if status == Some("completed") {
+                    return None;
+                }
+                Some(OpenTodo {
+                    content: todo.get("content")?.as_str()?.to_owned(),
+                    in_progress: status == Some("in_progress"),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Tool names of the `tool_use` blocks in message content.
+fn iter_tool_use_names(content: &serde_json::Value) -> impl Iterator<Item = &str> {
+    content
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|c| c.get("type").and_then(|v| v.as_str()) == Some("tool_use"))
+        .filter_map(|c| c.get("name").and_then(|v| v.as_str()))
+}
+
+/// ID and name of each `tool_use` block in message content.
+fn iter_tool_uses(content: &serde_json::Value) -> impl Iterator<Item = (&str, &str)> {
+    iter_blocks_of_type(content, "tool_use").filter_map(|c| {
+        Some((
+            c.get("id").and_then(|v| v.as_str())?,
+            c.get("name").and_then(|v| v.as_str())?,
+        ))
+    })
+}
+
+fn iter_blocks_of_type<'a>(
+    content: &'a serde_json::Value,
+    block_type: &'a str,
+) -> impl Iterator<Item = &'a serde_json::Value> {
+    content
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(move |c| c.get("type").and_then(|v| v.as_str()) == Some(block_type))
+}
+
+/// Tool name for refused calls whose `tool_use` block wasn't seen.
+pub const UNKNOWN_TOOL: &str = "unknown";
+
+/// Built-in tools that ask before running unless allowed by a rule or a
+/// permission mode. Every MCP tool asks too.
+const PERMISSION_TOOLS: &[&str] = &[
+    "Bash",
+    "Edit",
+    "ExitPlanMode",
+    "MultiEdit",
+    "NotebookEdit",
+    "WebFetch",
+    "WebSearch",
+    "Write",
+];
+
+/// Whether calling `tool` asks for permission by default. Transcripts record
+/// refusals but not approvals, so these calls stand in for the prompts shown.
+fn asks_permission(tool: &str) -> bool {
+    PERMISSION_TOOLS.contains(&tool) || crate::session::mcp_tool(tool).is_some()
+}
+
+/// Whether a `tool_result` block is Claude Code refusing the call: the user
+/// rejected it at the prompt, a deny rule matched, or a non-interactive run
+/// had no permission to grant.
+fn is_permission_denial(result: &serde_json::Value) -> bool {
+    if result.get("is_error").and_then(|v| v.as_bool()) != Some(true) {
+        return false;
+    }
+    let Some(text) = result.get("content").and_then(first_text_block) else {
+        return false;
+    };
+    text.starts_with("The user doesn't want to proceed with this tool use.")
+        || text.starts_with("Claude requested permissions to ")
+        || (text.starts_with("Permission to use ") && text.contains("has been denied"))
+}
+
+/// The plan text of the last ExitPlanMode `tool_use` block in message
+/// content (what the user approved when leaving plan mode).
+fn exit_plan_mode_plan(content: &serde_json::Value) -> Option<&str> {
+    content
+        .as_array()?
+        .iter()
+        .rfind(|c| {
+            c.get("type").and_then(|v| v.as_str()) == Some("tool_use")
+                && c.get("name").and_then(|v| v.as_str()) == Some("ExitPlanMode")
+        })?
+        .get("input")?
+        .get("plan")?
+        .as_str()
+}
+
+/// The plan on the line at `offset` (a session's `plan_offset`). `None` if
+/// that line no longer holds one, e.g. the file was rewritten since.
+pub fn read_plan(filepath: &Path, offset: u64) -> Option<String> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut reader: Box<dyn BufRead> = if archive::is_archived(filepath) {
+        let mut reader = archive::open(filepath).ok()?;
+        std::io::copy(&mut reader.by_ref().take(offset), &mut std::io::sink()).ok()?;
+        reader
+    } else {
+        let mut file = File::open(filepath).ok()?;
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        Box::new(BufReader::new(file))
+    };
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let entry: serde_json::Value = serde_json::from_str(&line).ok()?;
+    exit_plan_mode_plan(entry.get("message")?.get("content")?).map(str::to_owned)
+}
+
+/// Iterate the text of `tool_result` blocks in message content. A result's
+/// `content` is either a plain string or an array of text blocks.
+pub fn iter_tool_result_texts(content: &serde_json::Value) -> impl Iterator<Item = &str> {
+    content
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|c| c.get("type").and_then(|v| v.as_str()) == Some("tool_result"))
+        .filter_map(|c| c.get("content"))
+        .flat_map(iter_text_blocks)
+}
+
+/// Extract the first text block from message content, borrowing from the JSON.
+pub fn first_text_block(content: &serde_json::Value) -> Option<&str> {
+    iter_text_blocks(content).next()
+}
+
+// =============================================================================
+// Archive (compressed copies of old local sessions)
+// =============================================================================
+
+/// Sessions in the archive, built from the archive's own metadata cache.
+/// Files missing from it are decompressed once to be scanned.
+fn find_archived_sessions(archive_dir: &Path) -> (Vec<Session>, ScanCounts) {
+    let mut cache = MetadataCache::load(archive_dir);
+    let files: Vec<PathBuf> = WalkDir::new(archive_dir)
+        .min_depth(2)
+        .max_depth(4)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| archive::in_transcript_slot(archive_dir, e.path()))
+        .filter(|e| {
+            e.path()
+                .to_str()
+                .and_then(|p| p.strip_suffix(".gz"))
+                .is_some_and(|p| is_valid_session_file(Path::new(p)))
+        })
+        .map(|e| e.into_path())
+        .collect();
+
+    let shared: &MetadataCache<SessionScan> = &cache;
+    let scans: Vec<(PathBuf, fs::Metadata, SessionScan, ScanKind)> = files
+        .into_par_iter()
+        .with_max_len(1)
+        .filter_map(|filepath| {
+            let metadata = fs::metadata(&filepath).ok()?;
+            if let Some(scan) = shared.get(&filepath, Fingerprint::of(&metadata)) {
+                return Some((filepath, metadata, scan.clone(), ScanKind::Cached));
+            }
+            let scan = scan_archived_file(&filepath);
+            Some((filepath, metadata, scan, ScanKind::Full))
+        })
+        .collect();
+
+    let mut counts = ScanCounts::default();
+    for (filepath, metadata, scan, kind) in &scans {
+        if *kind == ScanKind::Cached {
+            counts.cached += 1;
+        } else {
+            counts.scanned += 1;
+            cache.insert(filepath.clone(), Fingerprint::of(metadata), scan.clone());
+        }
+    }
+    cache.retain_paths(&scans.iter().map(|(p, ..)| p.as_path()).collect());
+    if let Err(e) = cache.save() {
+        eprintln!("Warning: Failed to save metadata cache: {}", e);
+    }
+
+    let mut sessions: Vec<Session> = scans
+        .into_iter()
+        .filter_map(|(filepath, metadata, scan, _)| {
+            let mut session = build_session(filepath, &metadata, scan, &SessionSource::Local)?;
+            // The archive file's birthtime is when it was archived.
+            session.created = session.first_activity.unwrap_or(session.modified);
+            session.archived = true;
+            Some(session)
+        })
+        .collect();
+    resolve_repo_roots(&mut sessions, true);
+    (sessions, counts)
+}
+
+/// Scan an archived transcript through a temporary decompressed copy.
+fn scan_archived_file(archived: &Path) -> SessionScan {
+    let mut tmp = archived.as_os_str().to_owned();
+    tmp.push(".scan");
+    let tmp = PathBuf::from(tmp);
+    let scan = match archive::decompress(archived, &tmp) {
+        Ok(()) => scan_session_file(&tmp),
+        Err(e) => {
+            eprintln!("Warning: Failed to read {}: {:#}", archived.display(), e);
+            SessionScan::default()
+        }
+    };
+    let _ = fs::remove_file(&tmp);
+    scan
+}
+
+/// Move local sessions into the archive under `archive_dir`, in parallel.
+/// `roots` are the local projects dirs, the main one first; each session is
+/// archived into the mirror of the one it is under. Each transcript is
+/// compressed (and checked) before the original is deleted, and its scan is
+/// carried over so listing it reads nothing. Returns the archived size of
+/// each session, or why it was left alone.
+pub fn archive_sessions<'a>(
+    sessions: &[&'a Session],
+    roots: &[PathBuf],
+    archive_dir: &Path,
+) -> Vec<(&'a Session, Result<u64>)> {
+    let local_caches: Vec<MetadataCache<SessionScan>> =
+        roots.iter().map(|root| MetadataCache::load(root)).collect();
+    let results: Vec<_> = sessions
+        .par_iter()
+        .with_max_len(1)
+        .map(|&session| {
+            let result = (|| {
+                if session.archived || !matches!(session.source, SessionSource::Local) {
+                    anyhow::bail!("Only local sessions can be archived");
+                }
+                let original = &session.filepath;
+                let dest =
+                    archive::archived_path(archive_dir, roots, original).with_context(|| {
+                        format!("{} is not under a local projects dir", original.display())
+                    })?;
+                let metadata = fs::metadata(original)
+                    .with_context(|| format!("Failed to read {}", original.display()))?;
+                let scan = roots
+                    .iter()
+                    .position(|root| original.starts_with(root))
+                    .and_then(|i| local_caches[i].get(original, Fingerprint::of(&metadata)))
+                    .cloned()
+                    .unwrap_or_else(|| scan_session_file(original));
+                archive::compress(original, &dest)?;
+                // Keep the original mtime: it places the session in the list.
+                if let Ok(modified) = metadata.modified() {
+                    File::options()
+                        .write(true)
+                        .open(&dest)
+                        .and_then(|f| f.set_modified(modified))
+                        .with_context(|| format!("Failed to set mtime of {}", dest.display()))?;
+                }
+                fs::remove_file(original)
+                    .with_context(|| format!("Failed to delete {}", original.display()))?;
+                let archived = fs::metadata(&dest)
+                    .with_context(|| format!("Failed to read {}", dest.display()))?;
+                anyhow::Ok((dest, archived, scan))
+            })();
+            (session, result)
+        })
+        .collect();
+
+    let mut cache = MetadataCache::load(archive_dir);
+    let results = results
+        .into_iter()
+        .map(|(session, result)| {
+            let result = result.map(|(dest, metadata, scan)| {
+                let size = metadata.len();
+                cache.insert(dest, Fingerprint::of(&metadata), scan);
+                size
+            });
+            (session, result)
+        })
+        .collect();
+    if let Err(e) = cache.save() {
+        eprintln!("Warning: Failed to save metadata cache: {}", e);
+    }
+    results
+}
+
+/// Put an archived session's transcript back where Claude Code expects it
+/// and delete the archived copy. Returns the restored path.
+pub fn unarchive_session(session: &Session) -> Result<PathBuf> {
+    restore_archived(
+        &session.filepath,
+        &archive::archive_dir()?,
+        &local_projects_dirs()?,
+    )
+}
+
+/// Decompress `archived` back to its place under the one of `roots` it was
+/// archived from and delete it; fails rather than overwrite a transcript
+/// that is already there.
+pub fn restore_archived(archived: &Path, archive_dir: &Path, roots: &[PathBuf]) -> Result<PathBuf> {
+    let original = archive::original_path(archive_dir, roots, archived).with_context(|| {
+        format!(
+            "{} is not in the archive of a local projects dir",
+            archived.display()
+        )
+    })?;
+    if original.exists() {
+        anyhow::bail!(
+            "Can't restore {}: {} already exists",
+            archived.display(),
+            original.display()
+        );
+    }
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    archive::decompress(archived, &original)?;
+    if let Ok(modified) = fs::metadata(archived).and_then(|m| m.modified()) {
+        let _ = File::options()
+            .write(true)
+            .open(&original)
+            .and_then(|f| f.set_modified(modified));
+    }
+    fs::remove_file(archived)
+        .with_context(|| format!("Failed to delete {}", archived.display()))?;
+    Ok(original)
+}
+
+// =============================================================================
+// Pulling Remote Sessions (`--pull`)
+// =============================================================================
+
+/// The directory name Claude Code keeps a project's transcripts under: its
+/// path with every character other than a letter or digit replaced by `-`.
+pub fn project_dir_name(project: &Path) -> String {
+    project
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Copy the remote transcript `cached` into `projects_dir` as a session of
+/// `local_dir`, so `claude -r` finds it there. Recorded `cwd`s in or under
+/// `remote_dir` are rewritten to the same place under `local_dir`. Returns
+/// the copy's path; a copy that already exists is only reused unchanged,
+/// never overwritten, since it may have been continued here.
+pub fn pull_transcript(
+    cached: &Path,
+    remote_dir: &str,
+    local_dir: &Path,
+    projects_dir: &Path,
+) -> Result<PathBuf> {
+    let file_name = cached
+        .file_name()
+        .with_context(|| format!("{} is not a transcript", cached.display()))?;
+    let dest = projects_dir
+        .join(project_dir_name(local_dir))
+        .join(file_name);
+    let text = fs::read_to_string(cached)
+        .with_context(|| format!("Failed to read {}", cached.display()))?;
+    let json = |s: &str| serde_json::to_string(s).unwrap_or_default();
+    let (from, to) = (json(remote_dir), json(&local_dir.to_string_lossy()));
+    // Without the closing quote, so paths below the directory match too
+    let (from, to) = (&from[..from.len() - 1], &to[..to.len() - 1]);
+    let pulled = [format!("\"cwd\":{}\"", from), format!("\"cwd\":{}/", from)]
+        .iter()
+        .zip([format!("\"cwd\":{}\"", to), format!("\"cwd\":{}/", to)])
+        .fold(text, |text, (from, to)| text.replace(from, &to));
+
+    if dest.exists() {
+        if fs::read_to_string(&dest).is_ok_and(|existing| existing == pulled) {
+            return Ok(dest);
+        }
+        anyhow::bail!(
+            "{} was pulled before and differs from the remote copy now; resume that local copy, or move it aside to pull again",
+            dest.display()
+        );
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(&dest, pulled).with_context(|| format!("Failed to write {}", dest.display()))?;
+    // Keep the remote's time, so the copy sorts and ages like the original
+    if let Ok(modified) = fs::metadata(cached).and_then(|m| m.modified()) {
+        let _ = File::options()
+            .write(true)
+            .open(&dest)
+            .and_then(|f| f.set_modified(modified));
+    }
+    Ok(dest)
+}
+
+// =============================================================================
+// Tagging (picker batch actions)
+// =============================================================================
+
+/// Tag the session stored at `filepath` by appending the `tag` entry Claude
+/// Code writes itself; an empty `tag` clears it. Only for local transcripts:
+/// a remote's cache is overwritten by the next sync.
+pub fn append_tag(filepath: &Path, session_id: &str, tag: &str) -> Result<()> {
+    let entry = serde_json::json!({"type": "tag", "tag": tag, "sessionId": session_id});
+    let mut file = File::options()
+        .read(true)
+        .append(true)
+        .open(filepath)
+        .with_context(|| format!("Failed to open {}", filepath.display()))?;
+    // A transcript cut off mid-line keeps its partial line to itself
+    let mut line = String::new();
+    if file.metadata()?.len() > 0 {
+        let mut last = [0u8];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            line.push('\n');
+        }
+    }
+    line.push_str(&entry.to_string());
+    line.push('\n');
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("Failed to write {}", filepath.display()))
+}
+
+// =============================================================================
+// Transcript Export
+// =============================================================================
+
+/// The transcript at `filepath` as normalized messages. Synthetic entries
+/// (`isMeta`) are dropped, and a response Claude Code wrote as one entry per
+/// content block is merged back into one message.
+pub fn read_messages(filepath: &Path) -> Result<Vec<Message>> {
+    let reader = archive::open(filepath)?;
+    let mut messages: Vec<Message> = Vec::new();
+    let mut last_response_id: Option<String> = None;
+    for line in reader.lines() {
+        let line = line.context("Failed to read session file")?;
+        let Ok(entry) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        let Some((normalized, response_id)) = entry_message(&entry) else {
+            continue;
+        };
+
+        let continues = normalized.role == Role::Assistant
+            && response_id.is_some()
+            && response_id == last_response_id.as_deref()
+            && messages.last().is_some_and(|m| m.role == Role::Assistant);
+        last_response_id = response_id.map(str::to_owned);
+        if continues && let Some(last) = messages.last_mut() {
+            last.text.extend(normalized.text);
+            last.tool_calls.extend(normalized.tool_calls);
+            last.tool_results.extend(normalized.tool_results);
+            // Each block repeats the response's usage; later ones are final.
+            last.usage = normalized.usage.or(last.usage);
+            continue;
+        }
+        messages.push(normalized);
+    }
+    Ok(messages)
+}
+
+/// One transcript entry as a message, with the API response id it belongs
+/// to; `None` for entries that aren't user/assistant messages or are
+/// synthetic (`isMeta`). Consecutive entries of one response aren't merged.
+pub fn entry_message(entry: &serde_json::Value) -> Option<(Message, Option<&str>)> {
+    let role = match entry.get("type").and_then(|v| v.as_str()) {
+        Some("user") => Role::User,
+        Some("assistant") => Role::Assistant,
+        _ => return None,
+    };
+    if entry.get("isMeta").and_then(|v| v.as_bool()) == Some(true) {
+        return None;
+    }
+    let message = entry.get("message")?;
+    let content = message.get("content").unwrap_or(&serde_json::Value::Null);
+    let response_id = message.get("id").and_then(|v| v.as_str());
+    let usage = message.get("usage").map(parse_usage);
+
+    let mut normalized = Message {
+        role,
+        timestamp: entry
+            .get("timestamp")
+            .and_then(|v| v.as_str())
+            .map(str::to_owned),
+        model: message
+            .get("model")
+            .and_then(|v| v.as_str())
+            .map(str::to_owned),
+        text: Vec::new(),
+        tool_calls: Vec::new(),
+        tool_results: Vec::new(),
+        usage: usage.filter(|_| role == Role::Assistant),
+        compact_summary: entry.get("isCompactSummary").and_then(|v| v.as_bool()) == Some(true),
+    };
+    append_blocks(&mut normalized, content);
+    Some((normalized, response_id))
+}
+
+/// Add the text, `tool_use`, and `tool_result` blocks of `content` to
+/// `message`. Thinking and image blocks are skipped.
+fn append_blocks(message: &mut Message, content: &serde_json::Value) {
+    let text = |v: Option<&serde_json::Value>| v.and_then(|v| v.as_str()).map(str::to_owned);
+    message
+        .text
+        .extend(iter_text_blocks(content).map(str::to_owned));
+    for block in content.as_array().into_iter().flatten() {
+        match block.get("type").and_then(|v| v.as_str()) {
+            Some("tool_use") => message.tool_calls.push(ToolCall {
+                id: text(block.get("id")).unwrap_or_default(),
+                name: text(block.get("name")).unwrap_or_default(),
+                input: block.get("input").cloned().unwrap_or_default(),
+            }),
+            Some("tool_result") => message.tool_results.push(ToolResult {
+                tool_use_id: text(block.get("tool_use_id")).unwrap_or_default(),
+                is_error: block.get("is_error").and_then(|v| v.as_bool()) == Some(true),
+                text: block
+                    .get("content")
+                    .map(|c| iter_text_blocks(c).collect::<Vec<_>>().join("\n"))
+                    .unwrap_or_default(),
+            }),
+            _ => {}
+        }
+    }
+}
+
+// =============================================================================
+// Subagent Runs
+// =============================================================================
+
+/// One subagent run of a session, from `{session}/subagents/agent-*.jsonl`.
+#[derive(Debug, Clone)]
+pub struct SubagentRun {
+    pub filepath: PathBuf,
+    /// File stem without the `agent-` prefix.
+    pub agent_id: String,
+    /// The task the parent handed the agent.
+    pub first_prompt: Option<String>,
+    pub turn_count: usize,
+    pub modified: SystemTime,
+}
+
+/// Subagent transcripts recorded for the session stored at `session_file`,
+/// newest first. Empty if the session never ran an agent.
+pub fn find_subagent_runs(session_file: &Path) -> Vec<SubagentRun> {
+    let dir = session_file.with_extension("").join("subagents");
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut runs: Vec<SubagentRun> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let path = e.path();
+            let name = path.file_name()?.to_str()?;
+            let agent_id = name.strip_prefix("agent-")?.strip_suffix(".jsonl")?;
+            let modified = e.metadata().ok()?.modified().unwrap_or(UNIX_EPOCH);
+            let (first_prompt, turn_count) = scan_subagent_file(&path);
+            Some(SubagentRun {
+                agent_id: agent_id.to_string(),
+                filepath: path,
+                first_prompt,
+                turn_count,
+                modified,
+            })
+        })
+        .collect();
+    runs.sort_by_key(|r| std::cmp::Reverse(r.modified));
+    runs
+}
+
+/// First prompt and turn count of a subagent transcript. The session scan
+/// can't be reused: it stops at the first sidechain entry, and every entry of
+/// a subagent transcript is one.
+fn scan_subagent_file(path: &Path) -> (Option<String>, usize) {
+    let Ok(file) = File::open(path) else {
+        return (None, 0);
+    };
+    let mut first_prompt = None;
+    let mut turns = 0;
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        if !line_mentions_content_type(line.as_bytes()) {
+            continue;
+        }
+        let Ok(entry) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        if entry.get("type").and_then(|v| v.as_str()) != Some("user") {
+            continue;
+        }
+        let Some(text) = entry
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(first_text_block)
+        else {
+            continue;
+        };
+        if first_prompt.is_none() && is_first_prompt_candidate(text) {
+            first_prompt = Some(crate::normalize_summary(text, 120));
+        }
+        if counts_as_turn(text) {
+            turns += 1;
+        }
+    }
+    (first_prompt, turns)
+}
+
+// =============================================================================
+// Project Identity
+// =============================================================================
+
+/// Replace each session's cwd-based identity with its git repository root,
+/// so sessions started in sub-folders of one repo share a project.
+///
+/// Local sessions look for `.git` on disk. Remote sessions (and local ones
+/// whose directory is gone) can't, so the root is inferred: among cwds that
+/// recorded a `gitBranch`, the outermost one enclosing this session's cwd.
+fn resolve_repo_roots(sessions: &mut [Session], local: bool) {
+    let mut on_disk: HashMap<String, Option<Option<PathBuf>>> = HashMap::new();
+    for session in sessions.iter_mut() {
+        if !local || session.project_path.is_empty() {
+            continue;
+        }
+        let found = on_disk
+            .entry(session.project_path.clone())
+            .or_insert_with(|| {
+                let dir = Path::new(&session.project_path);
+                dir.is_dir().then(|| crate::repo::repo_root(dir))
+            });
+        // Directory still exists: the filesystem answer is authoritative.
+        if let Some(root) = found {
+            session.repo_root = root.as_ref().map(|r| r.to_string_lossy().into_owned());
+        }
+    }
+
+    let git_cwds: Vec<String> = sessions
+        .iter()
+        .filter_map(|s| s.repo_root.clone())
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    for session in sessions.iter_mut() {
+        let settled = matches!(on_disk.get(&session.project_path), Some(Some(_)));
+        // BTreeSet order puts ancestors before descendants, so the first
+        // enclosing cwd is the outermost.
+        if !settled
+            && let Some(root) = &session.repo_root
+            && let Some(outer) = git_cwds
+                .iter()
+                .find(|c| crate::repo::is_within(Path::new(root), Path::new(c.as_str())))
+        {
+            session.repo_root = Some(outer.clone());
+        }
+        if let Some(root) = &session.repo_root {
+            session.project = extract_project_name(root, "");
+        }
+    }
+}
+
+// =============================================================================
+// Health Checks (used by `fsck`; full parse, never on the discovery path)
+// =============================================================================
+
+/// Everything `fsck` needs to know about one `.jsonl` file at session depth.
+#[derive(Debug, Default)]
+pub struct FileHealth {
+    /// I/O error opening or reading the file; other fields are partial.
+    pub read_error: Option<String>,
+    pub bytes: u64,
+    /// Non-blank lines.
+    pub lines: usize,
+    /// Non-blank lines that aren't valid JSON.
+    pub bad_lines: usize,
+    /// The only bad line is the last one — an append cut off mid-write.
+    pub truncated_tail: bool,
+    /// First `sessionId` recorded inside the transcript.
+    pub session_id: Option<String>,
+    /// Some entry is marked `isSidechain`: a subagent transcript, which
+    /// carries its parent's `sessionId`.
+    pub sidechain: bool,
+    pub forked_from: Option<String>,
+    /// Parses, but has nothing discovery would list (see `build_session`).
+    /// Sidechain and teammate transcripts are hidden on purpose, not empty.
+    pub empty: bool,
+}
+
+/// Every `.jsonl` file at session depth under `projects_dir`, UUID-named or
+/// not, plus paths the walk couldn't enter.
+pub fn session_candidate_files(projects_dir: &Path) -> (Vec<PathBuf>, Vec<(PathBuf, String)>) {
+    let mut files = Vec::new();
+    let mut unreadable = Vec::new();
+    for entry in WalkDir::new(projects_dir).min_depth(1).max_depth(2) {
+        match entry {
+            Ok(e) if e.depth() == 2 && e.path().extension() == Some("jsonl".as_ref()) => {
+                files.push(e.into_path())
+            }
+            Ok(_) => {}
+            Err(err) => {
+                let path = err.path().unwrap_or(projects_dir).to_path_buf();
+                unreadable.push((path, err.to_string()));
+            }
+        }
+    }
+    files.sort();
+    (files, unreadable)
+}
+
+/// Whether discovery would pick this file up by name.
+pub fn is_session_file_name(path: &Path) -> bool {
+    is_valid_session_file(path)
+}
+
+/// Parse every line of a session file, unlike the discovery scan which
+/// skips lines it doesn't need and silently drops unparseable ones.
+pub fn inspect_session_file(filepath: &Path) -> FileHealth {
+    let mut health = FileHealth::default();
+    let file = match File::open(filepath) {
+        Ok(f) => f,
+        Err(e) => {
+            health.read_error = Some(e.to_string());
+            return health;
+        }
+    };
+    health.bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut reader = BufReader::with_capacity(64 * 1024, file);
+
+    let mut line = Vec::new();
+    let mut last_line_bad = false;
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                health.read_error = Some(e.to_string());
+                return health;
+            }
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        health.lines += 1;
+        match serde_json::from_slice::<serde_json::Value>(&line) {
+            Ok(entry) => {
+                last_line_bad = false;
+                health.sidechain |=
+                    entry.get("isSidechain").and_then(|v| v.as_bool()) == Some(true);
+                if health.session_id.is_none() {
+                    health.session_id = entry
+                        .get("sessionId")
+                        .and_then(|v| v.as_str())
+                        .map(str::to_owned);
+                }
+            }
+            Err(_) => {
+                health.bad_lines += 1;
+                last_line_bad = true;
+            }
+        }
     }
-    false
+    health.truncated_tail = health.bad_lines == 1 && last_line_bad;
+
+    let scan = scan_session_file(filepath);
+    health.empty = !scan.skip && scan.is_empty();
+    health.forked_from = scan.forked_from;
+    health
 }
 
-/// Append `text` to `buf` lowercased, separated by a newline. Avoids the
-/// intermediate `Vec<String>` + `join` + `to_lowercase` triple-allocation
-/// the previous implementation performed per file.
-fn append_lowercase(buf: &mut String, text: &str) {
-    if text.is_empty() {
-        return;
+/// Whether a fork provably adds nothing to its parent: every conversation
+/// entry is a `forkedFrom` copy whose source message still exists in the
+/// parent's file. Anything unparseable makes the answer "no".
+pub fn fork_is_redundant(fork: &Path, parent: &Path) -> Result<bool> {
+    let mut copied = std::collections::HashSet::new();
+    for line in BufReader::new(File::open(fork)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Ok(entry) = serde_json::from_str::<serde_json::Value>(&line) else {
+            return Ok(false);
+        };
+        if !matches!(
+            entry.get("type").and_then(|v| v.as_str()),
+            Some("user" | "assistant")
+        ) {
+            continue;
+        }
+        match entry
+            .get("forkedFrom")
+            .and_then(|f| f.get("messageUuid"))
+            .and_then(|v| v.as_str())
+        {
+            Some(uuid) => copied.insert(uuid.to_owned()),
+            None => return Ok(false),
+        };
     }
-    if !buf.is_empty() {
-        buf.push('\n');
+    if copied.is_empty() {
+        return Ok(false);
     }
-    let start = buf.len();
-    buf.push_str(text);
-    // SAFETY: make_ascii_lowercase only flips bit 0x20 on uppercase ASCII and
-    // leaves all other bytes (including UTF-8 continuation bytes) untouched,
-    // so the buffer remains valid UTF-8. Non-ASCII uppercase is left as-is —
-    // a reasonable trade since Ctrl+S search compares against the user's own
-    // transcript text and the quadratic cost of full Unicode case-folding
-    // dominates on multi-GB corpora.
-    unsafe { buf.as_bytes_mut()[start..].make_ascii_lowercase() };
-}
 
-/// Iterate over all text blocks in a message content value (string or array
-/// of content blocks), borrowing from the underlying JSON.
-fn iter_text_blocks(content: &serde_json::Value) -> impl Iterator<Item = &str> {
-    let single = content.as_str();
-    let blocks = content
-        .as_array()
-        .into_iter()
-        .flatten()
-        .filter(|c| c.get("type").and_then(|v| v.as_str()) == Some("text"))
-        .filter_map(|c| c.get("text").and_then(|v| v.as_str()));
-    single.into_iter().chain(blocks)
-}
-
-/// Extract the first text block from message content, borrowing from the JSON.
-pub fn first_text_block(content: &serde_json::Value) -> Option<&str> {
-    iter_text_blocks(content).next()
+    for line in BufReader::new(File::open(parent)?).lines() {
+        let line = line?;
+        if let Ok(entry) = serde_json::from_str::<serde_json::Value>(&line)
+            && let Some(uuid) = entry.get("uuid").and_then(|v| v.as_str())
+        {
+            copied.remove(uuid);
+            if copied.is_empty() {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
 }
 
 // =============================================================================
 // Helper Functions
 // =============================================================================
 
+fn from_unix_ms(ms: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(ms)
+}
+
 /// Extract project name from path or directory name fallback
 ///
 /// Claude Code uses directory names like `-Users-alice-Documents-repos-foo`
@@ -580,6 +2337,161 @@ mod tests {
         (tmp, root)
     }
 
+    // =========================================================================
+    // Archive
+    // =========================================================================
+
+    #[test]
+    fn archived_sessions_stay_listed_and_restore() {
+        let uuid = test_uuid(7);
+        let content = "{\"type\":\"user\",\"message\":{\"role\":\"user\",\"content\":\"Old idea\"},\"cwd\":\"/src/old\"}\n";
+        let (_tmp, projects) = project_fixture("-src-old", &uuid, content);
+        let archive_dir = projects.join("../archive");
+        let session = find_sessions(&projects).unwrap().remove(0);
+
+        let roots = [projects.clone()];
+        let results = archive_sessions(&[&session], &roots, &archive_dir);
+        assert!(results[0].1.is_ok());
+        assert!(!session.filepath.exists());
+        assert!(find_sessions(&projects).unwrap().is_empty());
+
+        let (archived, counts) = find_archived_sessions(&archive_dir);
+        assert_eq!(counts.cached, 1, "scan carried over from archiving");
+        let archived = &archived[0];
+        assert_eq!(archived.id, uuid);
+        assert!(archived.archived);
+        assert_eq!(archived.first_message.as_deref(), Some("Old idea"));
+        assert_eq!(archived.modified, session.modified);
+
+        let restored = restore_archived(&archived.filepath, &archive_dir, &roots).unwrap();
+        assert_eq!(restored, session.filepath);
+        assert_eq!(fs::read_to_string(&restored).unwrap(), content);
+        assert!(!archived.filepath.exists());
+    }
+
+    #[test]
+    fn sessions_from_every_local_root_archive_and_restore() {
+        let content = "{\"type\":\"user\",\"message\":{\"role\":\"user\",\"content\":\"Hi\"},\"cwd\":\"/src/x\"}\n";
+        let (_main, main_root) = project_fixture("-src-x", &test_uuid(1), content);
+        let (_extra, extra_root) = project_fixture("-src-x", &test_uuid(2), content);
+        let archive_tmp = tempfile::tempdir().unwrap();
+        let archive_dir = archive_tmp.path().join("archive");
+        let roots = [main_root.clone(), extra_root.clone()];
+        let sessions: Vec<Session> = roots
+            .iter()
+            .flat_map(|root| find_sessions(root).unwrap())
+            .collect();
+        let sessions: Vec<&Session> = sessions.iter().collect();
+
+        let results = archive_sessions(&sessions, &roots, &archive_dir);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        assert!(find_sessions(&main_root).unwrap().is_empty());
+        assert!(find_sessions(&extra_root).unwrap().is_empty());
+
+        let (archived, counts) = find_archived_sessions(&archive_dir);
+        assert_eq!(archived.len(), 2);
+        assert_eq!(counts.cached, 2, "scans carried over from both roots");
+        for session in &archived {
+            let restored = restore_archived(&session.filepath, &archive_dir, &roots).unwrap();
+            let root = if session.id == test_uuid(1) {
+                &main_root
+            } else {
+                &extra_root
+            };
+            assert!(restored.starts_with(root), "{}", restored.display());
+        }
+        assert_eq!(find_sessions(&main_root).unwrap().len(), 1);
+        assert_eq!(find_sessions(&extra_root).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn pulled_transcript_gets_local_cwds_and_is_never_overwritten() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cached = tmp.path().join("cache/-home-ian-api/abc.jsonl");
+        fs::create_dir_all(cached.parent().unwrap()).unwrap();
+        fs::write(
+            &cached,
+            "{\"type\":\"user\",\"cwd\":\"/home/ian/api\"}\n\
+             {\"type\":\"user\",\"cwd\":\"/home/ian/api/src\"}\n\
+             {\"type\":\"user\",\"cwd\":\"/home/ian/api-old\"}\n",
+        )
+        .unwrap();
+        let projects = tmp.path().join("projects");
+        let local = Path::new("/Users/ian/src/api");
+
+        let dest = pull_transcript(&cached, "/home/ian/api", local, &projects).unwrap();
+        assert_eq!(dest, projects.join("-Users-ian-src-api/abc.jsonl"));
+        assert_eq!(
+            fs::read_to_string(&dest).unwrap(),
+            "{\"type\":\"user\",\"cwd\":\"/Users/ian/src/api\"}\n\
+             {\"type\":\"user\",\"cwd\":\"/Users/ian/src/api/src\"}\n\
+             {\"type\":\"user\",\"cwd\":\"/home/ian/api-old\"}\n"
+        );
+        // Pulling again unchanged reuses the copy; a continued copy stays
+        assert_eq!(
+            pull_transcript(&cached, "/home/ian/api", local, &projects).unwrap(),
+            dest
+        );
+        fs::write(&dest, "continued here\n").unwrap();
+        assert!(pull_transcript(&cached, "/home/ian/api", local, &projects).is_err());
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "continued here\n");
+    }
+
+    #[test]
+    fn git_branch_is_the_latest_recorded_one() {
+        let uuid = test_uuid(8);
+        let content = concat!(
+            r#"{"type":"user","cwd":"/src/api","gitBranch":"main","message":{"content":"start"}}"#,
+            "\n",
+            r#"{"type":"user","cwd":"/src/api","gitBranch":"feature/auth","message":{"content":"switched"}}"#,
+            "\n",
+            r#"{"type":"user","cwd":"/src/api","gitBranch":"","message":{"content":"no branch"}}"#,
+            "\n",
+        );
+        let (_tmp, projects) = project_fixture("-src-api", &uuid, content);
+        let session = find_sessions(&projects).unwrap().remove(0);
+        assert_eq!(session.git_branch.as_deref(), Some("feature/auth"));
+        assert_eq!(session.repo_root.as_deref(), Some("/src/api"));
+    }
+
+    // =========================================================================
+    // Subagent runs
+    // =========================================================================
+
+    #[test]
+    fn subagent_runs_are_read_from_the_session_dir() {
+        let uuid = "12345678-1234-1234-1234-123456789abc";
+        let (_tmp, root) = project_fixture(
+            "-proj",
+            uuid,
+            r#"{"type":"user","cwd":"/proj","message":{"content":"run the tests"}}"#,
+        );
+        let session_file = root.join("-proj").join(format!("{}.jsonl", uuid));
+        assert!(find_subagent_runs(&session_file).is_empty());
+
+        let agents = root.join("-proj").join(uuid).join("subagents");
+        fs::create_dir_all(&agents).unwrap();
+        fs::write(
+            agents.join("agent-a1b2.jsonl"),
+            concat!(
+                r#"{"type":"user","isSidechain":true,"message":{"content":"Find failing tests"}}"#,
+                "\n",
+                r#"{"type":"assistant","isSidechain":true,"message":{"content":[{"type":"text","text":"Found 2"}]}}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+        fs::write(agents.join("notes.txt"), "not a transcript").unwrap();
+
+        let runs = find_subagent_runs(&session_file);
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].agent_id, "a1b2");
+        assert_eq!(runs[0].first_prompt.as_deref(), Some("Find failing tests"));
+        assert_eq!(runs[0].turn_count, 1);
+        // Discovery still ignores them.
+        assert_eq!(find_sessions(&root).unwrap().len(), 1);
+    }
+
     // =========================================================================
     // UUID validation - Critical for filtering non-session files
     // =========================================================================
@@ -758,6 +2670,391 @@ mod tests {
         assert_eq!(sessions[0].name, Some("Important Session".to_string()));
     }
 
+    #[test]
+    fn scan_sessions_reuses_cache_for_unchanged_files() {
+        let (_tmp, root) = project_fixture(
+            "-Users-lancelot-castle",
+            &test_uuid(3),
+            r#"{"type":"user","message":{"role":"user","content":"Run away"},"cwd":"/Users/lancelot/castle"}"#,
+        );
+        let mut cache = MetadataCache::default();
+
+        let (sessions, counts) = scan_sessions(&root, &SessionSource::Local, &mut cache, None);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(
+            counts,
+            ScanCounts {
+                scanned: 1,
+                cached: 0,
+                resumed: 0,
+                partial: 0,
+            }
+        );
+
+        let (sessions, counts) = scan_sessions(&root, &SessionSource::Local, &mut cache, None);
+        assert_eq!(sessions[0].first_message, Some("Run away".to_string()));
+        assert_eq!(
+            counts,
+            ScanCounts {
+                scanned: 0,
+                cached: 1,
+                resumed: 0,
+                partial: 0,
+            }
+        );
+
+        // Appending changes size, so the file is rescanned.
+        let path = root
+            .join("-Users-lancelot-castle")
+            .join(format!("{}.jsonl", test_uuid(3)));
+        let mut content = fs::read_to_string(&path).unwrap();
+        content.push_str("\n{\"type\":\"summary\",\"summary\":\"Retreat\"}");
+        fs::write(&path, content).unwrap();
+        let (sessions, counts) = scan_sessions(&root, &SessionSource::Local, &mut cache, None);
+        assert_eq!(sessions[0].summary, Some("Retreat".to_string()));
+        assert_eq!(
+            counts,
+            ScanCounts {
+                scanned: 1,
+                cached: 0,
+                resumed: 0,
+                partial: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn last_todo_write_decides_open_todos() {
+        let todo_write = |todos: &str| {
+            format!(
+                "{{\"type\":\"assistant\",\"message\":{{\"content\":[{{\"type\":\"tool_use\",\"name\":\"TodoWrite\",\"input\":{{\"todos\":{todos}}}}}]}}}}\n"
+            )
+        };
+        let plan = todo_write(
+            r#"[{"content":"Add tests","status":"completed"},{"content":"Fix parser","status":"in_progress"},{"content":"Update docs","status":"pending"}]"#,
+        );
+        let content = format!(
+            "{{\"type\":\"user\",\"cwd\":\"/src/a\",\"message\":{{\"role\":\"user\",\"content\":\"Plan it\"}}}}\n{plan}"
+        );
+        let (_tmp, path) = scan_fixture(&content);
+        let open = scan(&path).open_todos;
+        assert_eq!(
+            open,
+            [
+                OpenTodo {
+                    content: "Fix parser".to_string(),
+                    in_progress: true
+                },
+                OpenTodo {
+                    content: "Update docs".to_string(),
+                    in_progress: false
+                },
+            ]
+        );
+
+        let done = todo_write(r#"[{"content":"Fix parser","status":"completed"}]"#);
+        let (_tmp, path) = scan_fixture(&format!("{content}{done}"));
+        assert!(scan(&path).open_todos.is_empty());
+    }
+
+    #[test]
+    fn mcp_tool_calls_are_counted_by_name() {
+        let call = |name: &str| {
+            format!(
+                "{{\"type\":\"assistant\",\"message\":{{\"content\":[{{\"type\":\"tool_use\",\"name\":\"{name}\",\"input\":{{}}}}]}}}}\n"
+            )
+        };
+        let content = format!(
+            "{{\"type\":\"user\",\"cwd\":\"/src/a\",\"message\":{{\"role\":\"user\",\"content\":\"Go\"}}}}\n{}{}{}",
+            call("mcp__github__create_issue"),
+            call("Bash"),
+            call("mcp__github__create_issue"),
+        );
+        let (_tmp, path) = scan_fixture(&content);
+        assert_eq!(
+            scan(&path).mcp_calls,
+            BTreeMap::from([("mcp__github__create_issue".to_string(), 2)])
+        );
+
+        let config = serde_json::json!({
+            "mcpServers": {"github": {}},
+            "projects": {"/src/a": {"mcpServers": {"sentry": {}}}, "/src/b": {}}
+        });
+        assert_eq!(
+            mcp_servers_in(&config).into_iter().collect::<Vec<_>>(),
+            ["github", "sentry"]
+        );
+    }
+
+    #[test]
+    fn permission_requests_and_denials_are_counted() {
+        let call = |id: &str, name: &str| {
+            format!(
+                "{{\"type\":\"assistant\",\"message\":{{\"content\":[{{\"type\":\"tool_use\",\"id\":\"{id}\",\"name\":\"{name}\",\"input\":{{}}}}]}}}}\n"
+            )
+        };
+        let result = |id: &str, is_error: bool, text: &str| {
+            format!(
+                "{{\"type\":\"user\",\"message\":{{\"role\":\"user\",\"content\":[{{\"type\":\"tool_result\",\"tool_use_id\":\"{id}\",\"is_error\":{is_error},\"content\":\"{text}\"}}]}}}}\n"
+            )
+        };
+        let rejected =
+            "The user doesn't want to proceed with this tool use. The tool use was rejected.";
+        let head = format!(
+            "{{\"type\":\"user\",\"cwd\":\"/src/a\",\"message\":{{\"role\":\"user\",\"content\":\"Go\"}}}}\n{}{}{}{}{}",
+            call("t1", "Read"),
+            result("t1", false, "fn main() {}"),
+            call("t2", "Bash"),
+            result("t2", true, rejected),
+            call("t3", "Bash"),
+        );
+        let tail = format!(
+            "{}{}{}{}",
+            result(
+                "t3",
+                true,
+                "Permission to use Bash with command rm -rf / has been denied."
+            ),
+            call("t4", "mcp__github__create_issue"),
+            result("t4", true, "Error: 404 Not Found"),
+            result("t9", true, rejected),
+        );
+        let (_tmp, path) = scan_fixture(&head);
+        let first = scan(&path);
+        assert_eq!(first.permission_requests, 2);
+        assert_eq!(first.pending_tool_uses.len(), 1);
+
+        // The denial of t3 lands after a resume; its name carries over.
+        fs::write(&path, format!("{head}{tail}")).unwrap();
+        let resumed = scan_session_file_from(&path, first);
+        assert_eq!(resumed.permission_requests, 3);
+        assert_eq!(
+            resumed.denied_tools,
+            BTreeMap::from([("Bash".to_string(), 2), (UNKNOWN_TOOL.to_string(), 1)])
+        );
+        assert!(resumed.pending_tool_uses.is_empty());
+    }
+
+    #[test]
+    fn remote_clock_running_ahead_is_corrected() {
+        let hour = Duration::from_secs(3600);
+        let transfer = SystemTime::now() - 10 * hour;
+        let hour_of =
+            |t: SystemTime| (t.duration_since(UNIX_EPOCH).unwrap().as_secs() / 3600) as u32;
+        let mut newest = Session::fixture("newest");
+        newest.modified = transfer + 2 * hour;
+        newest.last_activity = Some(transfer + 2 * hour);
+        newest.active_hours = [(hour_of(transfer + 2 * hour), 4)].into();
+        let mut older = Session::fixture("older");
+        older.modified = transfer - hour;
+
+        let mut sessions = [newest, older];
+        correct_remote_clock(&mut sessions, Some(transfer));
+        assert_eq!(sessions[0].clock_skew, Some(2 * hour));
+        assert_eq!(sessions[0].modified, transfer);
+        assert_eq!(sessions[0].last_activity, Some(transfer));
+        assert_eq!(sessions[0].active_hours, [(hour_of(transfer), 4)].into());
+        assert_eq!(sessions[1].modified, transfer - 3 * hour);
+
+        // Within tolerance nothing moves, but the future is still clamped.
+        let mut sessions = [Session::fixture("a")];
+        sessions[0].modified = SystemTime::now() + Duration::from_secs(60);
+        correct_remote_clock(&mut sessions, Some(SystemTime::now()));
+        assert_eq!(sessions[0].clock_skew, None);
+        assert!(sessions[0].modified <= SystemTime::now());
+    }
+
+    #[test]
+    fn read_messages_normalizes_entries() {
+        let (_tmp, path) = scan_fixture(
+            r#"{"type":"user","timestamp":"2025-01-15T10:00:00Z","message":{"role":"user","content":"Run the tests"}}
+{"type":"user","isMeta":true,"message":{"role":"user","content":"<command-name>/clear</command-name>"}}
+{"type":"assistant","timestamp":"2025-01-15T10:00:01Z","message":{"id":"m1","model":"claude-x","content":[{"type":"thinking","thinking":"hmm"}],"usage":{"input_tokens":10,"output_tokens":1}}}
+{"type":"assistant","message":{"id":"m1","model":"claude-x","content":[{"type":"text","text":"Running them."}],"usage":{"input_tokens":10,"output_tokens":5}}}
+{"type":"assistant","message":{"id":"m1","model":"claude-x","content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo test"}}],"usage":{"input_tokens":10,"output_tokens":9}}}
+{"type":"progress","data":{}}
+{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","is_error":true,"content":[{"type":"text","text":"1 failed"},{"type":"image"}]}]}}
+{"type":"summary","summary":"Tests"}
+"#,
+        );
+        let messages = read_messages(&path).unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].text, ["Run the tests"]);
+        assert_eq!(messages[0].usage, None);
+
+        let response = &messages[1];
+        assert_eq!(response.role, Role::Assistant);
+        assert_eq!(response.timestamp.as_deref(), Some("2025-01-15T10:00:01Z"));
+        assert_eq!(response.text, ["Running them."]);
+        assert_eq!(response.tool_calls[0].name, "Bash");
+        assert_eq!(response.tool_calls[0].input["command"], "cargo test");
+        assert_eq!(response.usage.map(|u| u.output), Some(9));
+
+        let result = &messages[2].tool_results[0];
+        assert_eq!(
+            (
+                result.tool_use_id.as_str(),
+                result.is_error,
+                result.text.as_str()
+            ),
+            ("t1", true, "1 failed")
+        );
+    }
+
+    #[test]
+    fn plan_offset_points_at_the_last_approved_plan() {
+        let exit_plan = |plan: &str| {
+            format!(
+                "{{\"type\":\"assistant\",\"message\":{{\"content\":[{{\"type\":\"tool_use\",\"name\":\"ExitPlanMode\",\"input\":{{\"plan\":\"{plan}\"}}}}]}}}}\n"
+            )
+        };
+        let user = "{\"type\":\"user\",\"cwd\":\"/src/a\",\"message\":{\"role\":\"user\",\"content\":\"Plan it\"}}\n";
+        let (_tmp, path) = scan_fixture(user);
+        assert_eq!(scan(&path).plan_offset, None);
+
+        let content = format!(
+            "{user}{}{}",
+            exit_plan("# Draft"),
+            exit_plan("# Fix parser\\n1. Add tests")
+        );
+        let (_tmp, path) = scan_fixture(&content);
+        let offset = scan(&path).plan_offset.unwrap();
+        assert_eq!(
+            read_plan(&path, offset).as_deref(),
+            Some("# Fix parser\n1. Add tests")
+        );
+        assert_eq!(read_plan(&path, 0), None);
+    }
+
+    #[test]
+    fn oversized_lines_keep_metadata_from_head_and_tail() {
+        let paste = "x".repeat(2 * OVERSIZED_LINE_BYTES);
+        let content = format!(
+            "{{\"type\":\"user\",\"cwd\":\"/src/big\",\"message\":{{\"role\":\"user\",\"content\":\"Look at this {paste}\"}},\"timestamp\":\"2025-01-15T10:00:00.000Z\"}}\n\
+             {{\"type\":\"assistant\",\"message\":{{\"id\":\"m1\",\"model\":\"claude-opus-4-1\",\"content\":[{{\"type\":\"text\",\"text\":\"{paste}\"}}],\"usage\":{{\"input_tokens\":7,\"output_tokens\":9}}}},\"timestamp\":\"2025-01-15T10:05:00.000Z\"}}\n"
+        );
+        let (_tmp, path) = scan_fixture(&content);
+        let scan = scan(&path);
+        assert_eq!(scan.project_path, "/src/big");
+        assert!(scan.first_prompt.unwrap().starts_with("Look at this xxx"));
+        assert_eq!(scan.turn_count, 1);
+        assert_eq!(scan.model_usage["claude-opus-4-1"].output, 9);
+        let span = scan.last_activity_ms.unwrap() - scan.first_activity_ms.unwrap();
+        assert_eq!(span, 5 * 60 * 1000);
+        assert_eq!(scan.scanned_bytes, content.len() as u64);
+    }
+
+    #[test]
+    fn files_over_the_limit_are_scanned_at_head_and_tail_only() {
+        let user = |text: &str| {
+            format!(
+                "{{\"type\":\"user\",\"message\":{{\"role\":\"user\",\"content\":\"{}\"}},\"cwd\":\"/Users/gawain/keep\"}}\n",
+                text
+            )
+        };
+        let filler = format!(
+            "{{\"type\":\"progress\",\"data\":\"{}\"}}\n",
+            "p".repeat(1000)
+        );
+        let half = filler.repeat(PARTIAL_SCAN_BYTES as usize / 1000 + 1);
+        let content = format!(
+            "{}{half}{}{half}{}{{\"type\":\"custom-title\",\"customTitle\":\"Green Knight\"}}\n",
+            user("Start here"),
+            user("lost in the middle"),
+            user("near the end"),
+        );
+        let (_tmp, root) = project_fixture("-Users-gawain-keep", &test_uuid(5), &content);
+        let limit = Some(PARTIAL_SCAN_BYTES);
+        let mut cache = MetadataCache::default();
+
+        let (sessions, counts) = scan_sessions(&root, &SessionSource::Local, &mut cache, limit);
+        assert_eq!(counts.partial, 1);
+        assert!(sessions[0].partial);
+        assert_eq!(sessions[0].turn_count, 2);
+        assert_eq!(sessions[0].first_message, Some("Start here".to_string()));
+        assert_eq!(sessions[0].name, Some("Green Knight".to_string()));
+
+        // --deep redoes partial scans in full; the full result is then reused.
+        let (sessions, counts) = scan_sessions(&root, &SessionSource::Local, &mut cache, None);
+        assert_eq!((counts.scanned, counts.partial), (1, 0));
+        assert!(!sessions[0].partial);
+        assert_eq!(sessions[0].turn_count, 3);
+        let (_, counts) = scan_sessions(&root, &SessionSource::Local, &mut cache, limit);
+        assert_eq!(counts.cached, 1);
+    }
+
+    #[test]
+    fn rescan_session_picks_up_appended_turns() {
+        let user = |text: &str| {
+            format!(
+                "{{\"type\":\"user\",\"message\":{{\"role\":\"user\",\"content\":\"{}\"}},\"cwd\":\"/Users/kay/hall\"}}\n",
+                text
+            )
+        };
+        let (_tmp, root) = project_fixture("-Users-kay-hall", &test_uuid(6), &user("First"));
+        let mut cache = MetadataCache::default();
+        let (mut sessions, _) = scan_sessions(&root, &SessionSource::Local, &mut cache, None);
+        let mut session = sessions.remove(0);
+        session.project = "renamed".to_string();
+        assert_eq!(session.turn_count, 1);
+
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(&session.filepath)
+            .unwrap();
+        std::io::Write::write_all(&mut file, user("Second").as_bytes()).unwrap();
+
+        let fresh = rescan_session(&session, &session.filepath, &mut cache).unwrap();
+        assert_eq!(fresh.turn_count, 2);
+        assert_eq!(fresh.project, "renamed");
+        let (_, counts) = scan_sessions(&root, &SessionSource::Local, &mut cache, None);
+        assert_eq!(counts.cached, 1);
+    }
+
+    #[test]
+    fn appended_files_are_scanned_from_the_previous_end() {
+        let user = |text: &str| {
+            format!(
+                "{{\"type\":\"user\",\"message\":{{\"role\":\"user\",\"content\":\"{}\"}},\"cwd\":\"/Users/robin/tower\"}}\n",
+                text
+            )
+        };
+        let (_tmp, root) = project_fixture("-Users-robin-tower", &test_uuid(4), &user("Bravely"));
+        let path = root
+            .join("-Users-robin-tower")
+            .join(format!("{}.jsonl", test_uuid(4)));
+        let mut cache = MetadataCache::default();
+        scan_sessions(&root, &SessionSource::Local, &mut cache, None);
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, user("ran away").as_bytes()).unwrap();
+        std::io::Write::write_all(
+            &mut file,
+            b"{\"type\":\"summary\",\"summary\":\"Retreat\"}\n",
+        )
+        .unwrap();
+        let (sessions, counts) = scan_sessions(&root, &SessionSource::Local, &mut cache, None);
+        assert_eq!(counts.resumed, 1);
+        assert_eq!(sessions[0].turn_count, 2);
+        assert_eq!(sessions[0].first_message, Some("Bravely".to_string()));
+        assert_eq!(sessions[0].summary, Some("Retreat".to_string()));
+        assert_eq!(
+            cache.previous(&path).unwrap().scanned_bytes,
+            fs::metadata(&path).unwrap().len()
+        );
+
+        // A rewritten prefix forces a full rescan
+        fs::write(
+            &path,
+            format!("{}{}{}", user("Boldly"), user("x"), user("y")),
+        )
+        .unwrap();
+        let (sessions, counts) = scan_sessions(&root, &SessionSource::Local, &mut cache, None);
+        assert_eq!(counts.resumed, 0);
+        assert_eq!(sessions[0].first_message, Some("Boldly".to_string()));
+        assert_eq!(sessions[0].turn_count, 3);
+    }
+
     #[test]
     fn find_sessions_handles_empty_sessions() {
         let (_tmp, root) = project_fixture("-Users-spam-eggs", &test_uuid(7), r#"{"type":"init"}"#);
@@ -859,6 +3156,80 @@ mod tests {
         assert_eq!(scan.first_prompt, Some("hello".to_string()));
     }
 
+    #[test]
+    fn fork_with_only_copied_entries_is_redundant() {
+        let tmp = tempfile::tempdir().unwrap();
+        let parent = tmp.path().join("parent.jsonl");
+        let fork = tmp.path().join("fork.jsonl");
+        fs::write(
+            &parent,
+            r#"{"type":"user","uuid":"m1","message":{"role":"user","content":"hello"}}
+{"type":"assistant","uuid":"m2","message":"hi"}
+{"type":"user","uuid":"m3","message":{"role":"user","content":"parent kept going"}}"#,
+        )
+        .unwrap();
+        let copy = r#"{"type":"user","uuid":"c1","message":{"role":"user","content":"hello"},"forkedFrom":{"sessionId":"p","messageUuid":"m1"}}
+{"type":"assistant","uuid":"c2","message":"hi","forkedFrom":{"sessionId":"p","messageUuid":"m2"}}"#;
+        fs::write(&fork, copy).unwrap();
+        assert_eq!(scan(&fork).unforked_entries, 0);
+        assert!(fork_is_redundant(&fork, &parent).unwrap());
+
+        // Anything written after forking makes it worth keeping
+        fs::write(
+            &fork,
+            format!(
+                "{}\n{}",
+                copy,
+                r#"{"type":"user","uuid":"n1","message":{"role":"user","content":"new idea"}}"#
+            ),
+        )
+        .unwrap();
+        assert_eq!(scan(&fork).unforked_entries, 1);
+        assert!(!fork_is_redundant(&fork, &parent).unwrap());
+
+        // So does a parent that no longer holds the copied messages
+        fs::write(&fork, copy).unwrap();
+        fs::write(&parent, r#"{"type":"user","uuid":"m1","message":"hello"}"#).unwrap();
+        assert!(!fork_is_redundant(&fork, &parent).unwrap());
+    }
+
+    #[test]
+    fn repo_roots_group_subfolder_sessions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("grail");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("src")).unwrap();
+
+        let session_in = |id: &str, cwd: &Path, in_git: bool| {
+            let mut s = Session::fixture(id);
+            s.project_path = cwd.display().to_string();
+            s.project = extract_project_name(&s.project_path, "");
+            s.repo_root = in_git.then(|| s.project_path.clone());
+            s
+        };
+
+        // Local: the filesystem decides
+        let mut local = vec![
+            session_in("a", &repo.join("src"), true),
+            session_in("b", tmp.path(), false),
+        ];
+        resolve_repo_roots(&mut local, true);
+        assert_eq!(local[0].project, "grail");
+        assert_eq!(local[0].repo_root, Some(repo.display().to_string()));
+        assert_eq!(local[1].repo_root, None);
+
+        // Remote: the outermost enclosing cwd that was in a repo
+        let mut remote = vec![
+            session_in("c", Path::new("/r/holy/src/deep"), true),
+            session_in("d", Path::new("/r/holy"), true),
+            session_in("e", Path::new("/r/holy-v2"), true),
+            session_in("f", Path::new("/r/notes"), false),
+        ];
+        resolve_repo_roots(&mut remote, false);
+        let projects: Vec<&str> = remote.iter().map(|s| s.project.as_str()).collect();
+        assert_eq!(projects, ["holy", "holy", "holy-v2", "notes"]);
+    }
+
     // =========================================================================
     // Turn counting - only real user messages, not system content
     // =========================================================================
@@ -922,11 +3293,11 @@ mod tests {
     #[test]
     fn discovery_summary_tracks_source_failures() {
         let summary = DiscoverySummary {
-            sessions: Vec::new(),
             failures: vec![DiscoveryFailure {
                 source_name: "devbox".to_string(),
                 reason: "cache unreadable".to_string(),
             }],
+            ..Default::default()
         };
         assert_eq!(summary.failure_count(), 1);
         assert_eq!(summary.failures.len(), 1);
@@ -1012,7 +3383,7 @@ mod tests {
         assert_eq!(scan.project_path, "/tmp");
         assert_eq!(scan.first_prompt, Some("real user prompt".to_string()));
         assert_eq!(scan.turn_count, 1);
-        assert!(!scan_search_text(&path).contains("synthetic"));
+        assert!(!scan_search_text(&path, false).contains("synthetic"));
     }
 
     #[test]
@@ -1063,11 +3434,99 @@ mod tests {
 {"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Service healthy"}]}}
 {"type":"summary","summary":"ignored summary"}"#,
         );
-        let text = scan_search_text(&path);
+        let text = scan_search_text(&path, false);
         assert!(text.contains("api status"));
         assert!(text.contains("service healthy"));
     }
 
+    #[test]
+    fn search_index_re_extracts_only_changed_files() {
+        let (_tmp, path) =
+            scan_fixture(r#"{"type":"user","message":{"role":"user","content":"First question"}}"#);
+        let targets = [("s1", path.as_path())];
+        let mut index = SearchIndex::default();
+        let stale = index.stale(&targets);
+        assert_eq!(stale.len(), 1);
+        index.update(stale, false, &Progress::new("indexed", 1));
+        assert_eq!(index.texts()["s1"], "first question");
+        assert!(index.stale(&targets).is_empty());
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(
+            &mut file,
+            b"\n{\"type\":\"user\",\"message\":{\"role\":\"user\",\"content\":\"Follow-up\"}}\n",
+        )
+        .unwrap();
+        let stale = index.stale(&targets);
+        assert_eq!(stale.len(), 1);
+        index.update(stale, false, &Progress::new("indexed", 1));
+        assert!(index.texts()["s1"].contains("follow-up"));
+    }
+
+    #[test]
+    fn transcript_text_preserves_case() {
+        let (_tmp, path) = scan_fixture(
+            r#"{"type":"user","message":{"role":"user","content":"Wrap it in an Arc"}}
+{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Arc<Mutex<T>> works"}]}}"#,
+        );
+        let text = transcript_text(&path, false);
+        assert!(text.contains("Wrap it in an Arc"));
+        assert!(text.contains("Arc<Mutex<T>>"));
+        assert_eq!(text.to_ascii_lowercase(), scan_search_text(&path, false));
+    }
+
+    #[test]
+    fn scan_sums_token_usage_once_per_message() {
+        let (_tmp, path) = scan_fixture(
+            r#"{"type":"user","message":{"role":"user","content":"hello"},"cwd":"/tmp"}
+{"type":"assistant","message":{"id":"msg_1","model":"claude-opus-4-1","role":"assistant","content":[{"type":"thinking","thinking":"..."}],"usage":{"input_tokens":10,"output_tokens":5,"cache_read_input_tokens":100}}}
+{"type":"assistant","message":{"id":"msg_1","model":"claude-opus-4-1","role":"assistant","content":[{"type":"text","text":"hi"}],"usage":{"input_tokens":10,"output_tokens":5,"cache_read_input_tokens":100}}}
+{"type":"assistant","message":{"id":"msg_2","model":"claude-sonnet-4-5","role":"assistant","content":[{"type":"text","text":"bye"}],"usage":{"input_tokens":3,"output_tokens":7,"cache_creation_input_tokens":20}}}
+{"type":"assistant","message":{"id":"msg_3","role":"assistant","content":[{"type":"text","text":"?"}],"usage":{"output_tokens":1}}}"#,
+        );
+        let usage = scan(&path).model_usage;
+        assert_eq!(
+            usage["claude-opus-4-1"],
+            TokenUsage {
+                input: 10,
+                output: 5,
+                cache_creation: 0,
+                cache_read: 100,
+            }
+        );
+        assert_eq!(usage["claude-sonnet-4-5"].total(), 30);
+        assert_eq!(usage[UNKNOWN_MODEL].output, 1);
+    }
+
+    #[test]
+    fn scan_tracks_activity_span() {
+        let (_tmp, path) = scan_fixture(
+            r#"{"type":"user","message":{"role":"user","content":"hi"},"cwd":"/tmp","timestamp":"2025-01-15T10:00:00.000Z"}
+{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"hello"}]},"timestamp":"2025-01-15T12:30:00.000Z"}
+{"type":"summary","summary":"no timestamp here"}"#,
+        );
+        let scan = scan(&path);
+        let span = scan.last_activity_ms.unwrap() - scan.first_activity_ms.unwrap();
+        assert_eq!(span, 150 * 60 * 1000);
+    }
+
+    #[test]
+    fn search_text_tool_output_is_opt_in() {
+        let (_tmp, path) = scan_fixture(
+            r#"{"type":"user","message":{"role":"user","content":"run the tests"}}
+{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"test result: FAILED"}]}}
+{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t2","content":[{"type":"text","text":"Segmentation fault"}]}]}}"#,
+        );
+        let without = scan_search_text(&path, false);
+        assert!(without.contains("run the tests"));
+        assert!(!without.contains("failed"));
+
+        let with = scan_search_text(&path, true);
+        assert!(with.contains("test result: failed"));
+        assert!(with.contains("segmentation fault"));
+        assert!(transcript_text(&path, true).contains("Segmentation fault"));
+    }
+
     #[test]
     fn scan_tag_empty_string_clears_previous() {
         let (_tmp, path) = scan_fixture(
@@ -1094,7 +3553,7 @@ mod tests {
 {"type":"user","message":{"role":"user","content":"real question about API"}}
 {"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"answer"}]}}"#,
         );
-        let text = scan_search_text(&path);
+        let text = scan_search_text(&path, false);
         assert!(!text.contains("deploy"));
         assert!(text.contains("api"));
         assert!(text.contains("answer"));
@@ -1161,6 +3620,37 @@ mod tests {
         assert_eq!(scan.turn_count, 1);
     }
 
+    #[test]
+    fn claude_dir_for_names_non_default_config_dirs() {
+        let roots = [
+            PathBuf::from("/home/me/.claude/projects"),
+            PathBuf::from("/home/me/dc/.claude/projects"),
+            PathBuf::from("/mnt/sessions"),
+        ];
+        let default = Some(Path::new("/home/me/.claude"));
+        let dir = |file: &str, default| claude_dir_among(&roots, Path::new(file), default);
+        assert_eq!(dir("/home/me/.claude/projects/-a/x.jsonl", default), None);
+        assert_eq!(
+            dir("/home/me/.claude/projects/-a/x.jsonl", None),
+            Some(PathBuf::from("/home/me/.claude"))
+        );
+        assert_eq!(
+            dir("/home/me/dc/.claude/projects/-a/x.jsonl", default),
+            Some(PathBuf::from("/home/me/dc/.claude"))
+        );
+        assert_eq!(dir("/mnt/sessions/-a/x.jsonl", default), None);
+    }
+
+    #[test]
+    fn append_tag_is_read_back_and_empty_clears() {
+        let (_tmp, path) = scan_fixture(r#"{"type":"user","message":{"content":"hi"}}"#);
+        append_tag(&path, "x", "review").unwrap();
+        assert_eq!(scan(&path).tag, Some("review".to_string()));
+        append_tag(&path, "x", "").unwrap();
+        assert_eq!(scan(&path).tag, None);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+    }
+
     #[test]
     fn scan_tag_takes_last_non_empty() {
         let (_tmp, path) = scan_fixture(
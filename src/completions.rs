@@ -0,0 +1,73 @@
+//! Values for dynamic shell completion (`cc-sessions completions`).
+//!
+//! The registered completion function calls the binary back with
+//! `COMPLETE=<shell>` on every Tab, so these run in a fresh process each
+//! time. They read the config and the scan caches as `--no-sync` would,
+//! draw no progress, and return nothing rather than fail: a broken config
+//! shouldn't make Tab print errors into the command line.
+
+use crate::remote::{self, Config};
+use clap_complete::engine::CompletionCandidate;
+use std::collections::BTreeMap;
+
+/// The config for this run's profile (`$CC_SESSIONS_PROFILE`; a `--profile`
+/// on the line being completed isn't parsed yet).
+fn config() -> Option<Config> {
+    crate::profile::activate(None).ok()?;
+    crate::progress::hide();
+    let config = remote::load_config().ok()?;
+    crate::claude_code::configure_local_dirs(&config, &[]).ok()?;
+    Some(config)
+}
+
+/// `--project`: every project with a discovered session, with its count.
+pub fn projects() -> Vec<CompletionCandidate> {
+    let Some(config) = config() else {
+        return Vec::new();
+    };
+    let Ok(discovery) = crate::claude_code::find_all_sessions_with_summary(&config, None, false)
+    else {
+        return Vec::new();
+    };
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for session in &discovery.sessions {
+        *counts.entry(session.project.as_str()).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(project, _)| !project.is_empty())
+        .map(|(project, count)| {
+            let plural = if count == 1 { "" } else { "s" };
+            CompletionCandidate::new(project).help(Some(format!("{count} session{plural}").into()))
+        })
+        .collect()
+}
+
+/// `--remote`: `local`, each remote, each `@group`, and each team.
+pub fn remotes() -> Vec<CompletionCandidate> {
+    let config = config().unwrap_or_default();
+    let mut candidates = vec![CompletionCandidate::new("local").help(Some("this machine".into()))];
+    let mut remotes: Vec<(&String, &remote::RemoteConfig)> = config.remotes.iter().collect();
+    remotes.sort_by_key(|(name, _)| *name);
+    let mut groups: BTreeMap<&str, usize> = BTreeMap::new();
+    for (name, remote) in remotes {
+        candidates.push(
+            CompletionCandidate::new(name.as_str()).help(Some(remote::ssh_target(remote).into())),
+        );
+        if let Some(group) = &remote.group {
+            *groups.entry(group).or_default() += 1;
+        }
+    }
+    for (group, count) in groups {
+        candidates.push(
+            CompletionCandidate::new(format!("@{group}"))
+                .help(Some(format!("group of {count}").into())),
+        );
+    }
+    let mut teams: Vec<&String> = config.teams.keys().collect();
+    teams.sort();
+    for team in teams {
+        candidates.push(CompletionCandidate::new(team.as_str()).help(Some("team".into())));
+    }
+    candidates
+}
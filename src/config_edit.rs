@@ -0,0 +1,265 @@
+//! Text-level edits to `remotes.toml` for the `remote add/remove/rename`
+//! commands.
+//!
+//! Going through the TOML serializer would drop every comment in the file,
+//! so edits work on lines instead: a new remote is appended as its own
+//! `[remotes.<name>]` table, and removing one cuts that table's lines. The
+//! rest of the file is kept byte for byte. Remotes written another way
+//! (inline tables, dotted keys under `[remotes]`) can't be edited like that;
+//! callers parse the result and refuse to write it if the edit didn't take.
+
+use anyhow::{Result, bail};
+
+/// Whether `name` can be a remote name: a bare TOML key, which is also a
+/// safe cache directory name.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Append a `[remotes.<name>]` table with `fields` (key, value) to `content`.
+pub fn add_remote(content: &str, name: &str, fields: &[(&str, &str)]) -> String {
+    let mut out = content.to_string();
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    if !out.trim().is_empty() {
+        out.push('\n');
+    }
+    out.push_str(&format!("[remotes.{}]\n", name));
+    for (key, value) in fields {
+        out.push_str(&format!("{} = {}\n", key, toml::Value::from(*value)));
+    }
+    out
+}
+
+/// Remove the `[remotes.<name>]` table (and any `[remotes.<name>.*]`
+/// subtables), along with comment lines directly above its header. Comment
+/// and blank lines just above the next table stay with that table. Errors if
+/// there is no such table header.
+pub fn remove_remote(content: &str, name: &str) -> Result<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let Some(start) = lines
+        .iter()
+        .position(|line| header_names_remote(line, name))
+    else {
+        bail!("No [remotes.{}] table found", name);
+    };
+    let mut end = start + 1;
+    while end < lines.len()
+        && (table_header(lines[end]).is_none() || header_names_remote(lines[end], name))
+    {
+        end += 1;
+    }
+    // Leave trailing comments/blank lines for whatever follows.
+    if end < lines.len() {
+        while end > start + 1 && is_trivia(lines[end - 1]) {
+            end -= 1;
+        }
+    }
+    // Its own leading comment, then the blank lines separating it from
+    // the table before.
+    let mut start = start;
+    while start > 0 && lines[start - 1].trim_start().starts_with('#') {
+        start -= 1;
+    }
+    while start > 0 && lines[start - 1].trim().is_empty() {
+        start -= 1;
+    }
+
+    let mut out: Vec<&str> = lines[..start].to_vec();
+    let rest = &lines[end..];
+    if !out.is_empty() && !rest.is_empty() && !rest[0].trim().is_empty() {
+        out.push("");
+    }
+    out.extend_from_slice(rest);
+    let mut text = out.join("\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Rename the `[remotes.<old>]` table (and subtables) to `new`, and a
+/// `[defaults]` `remote = "<old>"` along with it. Everything else, including
+/// comments on the header lines, is kept.
+pub fn rename_remote(content: &str, old: &str, new: &str) -> Result<String> {
+    let mut renamed = false;
+    let mut table: Vec<String> = Vec::new();
+    let mut out = String::with_capacity(content.len() + new.len());
+    for line in content.lines() {
+        if let Some((keys, rest)) = table_header(line) {
+            table = keys;
+            if table.len() >= 2 && table[0] == "remotes" && table[1] == old {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                let mut keys = table.clone();
+                keys[1] = new.to_string();
+                let keys: Vec<String> = keys.iter().map(|k| quote_key(k)).collect();
+                out.push_str(&format!("{}[{}]{}\n", indent, keys.join("."), rest));
+                renamed = true;
+                continue;
+            }
+        } else if table == ["defaults"] && is_default_remote(line, old) {
+            out.push_str(&replace_string_value(line, old, new));
+            out.push('\n');
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    if !renamed {
+        bail!("No [remotes.{}] table found", old);
+    }
+    Ok(out)
+}
+
+/// Whether `line` is `remote = "<name>"` (the `[defaults]` key).
+fn is_default_remote(line: &str, name: &str) -> bool {
+    toml::from_str::<toml::Table>(line)
+        .ok()
+        .and_then(|t| t.get("remote").and_then(|v| v.as_str().map(|s| s == name)))
+        .unwrap_or(false)
+}
+
+fn replace_string_value(line: &str, old: &str, new: &str) -> String {
+    for quote in ['"', '\''] {
+        let quoted = format!("{quote}{old}{quote}");
+        if line.contains(&quoted) {
+            return line.replacen(&quoted, &format!("{quote}{new}{quote}"), 1);
+        }
+    }
+    line.to_string()
+}
+
+fn quote_key(key: &str) -> String {
+    if is_valid_name(key) {
+        key.to_string()
+    } else {
+        toml::Value::from(key).to_string()
+    }
+}
+
+fn is_trivia(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+/// Whether `line` is `[remotes.<name>]` or a subtable of it.
+fn header_names_remote(line: &str, name: &str) -> bool {
+    table_header(line)
+        .is_some_and(|(keys, _)| keys.len() >= 2 && keys[0] == "remotes" && keys[1] == name)
+}
+
+/// The dotted key of a `[table]` header line, unquoted, and whatever follows
+/// the closing bracket (e.g. a comment). `None` for other lines, including
+/// array-of-table headers.
+fn table_header(line: &str) -> Option<(Vec<String>, &str)> {
+    let inner = line.trim().strip_prefix('[')?;
+    if inner.starts_with('[') {
+        return None;
+    }
+    let mut keys = Vec::new();
+    let mut current = String::new();
+    let mut chars = inner.char_indices();
+    let mut quote = None;
+    let rest = loop {
+        let (i, c) = chars.next()?;
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '.') => keys.push(std::mem::take(&mut current).trim().to_string()),
+            (None, ']') => break &inner[i + 1..],
+            (None, c) => current.push(c),
+        }
+    };
+    keys.push(current.trim().to_string());
+    Some((keys, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+version = 1
+
+# Work machines
+[remotes.devbox]
+host = \"devbox\" # ssh alias
+group = \"work\"
+
+# The lab box
+[remotes.\"homelab\"]
+host = \"10.0.0.2\"
+
+[settings]
+stale_threshold = 600
+";
+
+    #[test]
+    fn add_appends_a_table_and_keeps_comments() {
+        let out = add_remote(
+            CONFIG,
+            "gpu-1",
+            &[("host", "gpu.example.com"), ("user", "o'neil")],
+        );
+        assert!(out.starts_with(CONFIG));
+        assert!(out.ends_with(
+            "stale_threshold = 600\n\n[remotes.gpu-1]\nhost = \"gpu.example.com\"\nuser = \"o'neil\"\n"
+        ));
+        assert_eq!(
+            add_remote("", "a", &[("host", "a")]),
+            "[remotes.a]\nhost = \"a\"\n"
+        );
+    }
+
+    #[test]
+    fn remove_cuts_only_that_table() {
+        let out = remove_remote(CONFIG, "devbox").unwrap();
+        assert_eq!(
+            out,
+            "\
+version = 1
+
+# The lab box
+[remotes.\"homelab\"]
+host = \"10.0.0.2\"
+
+[settings]
+stale_threshold = 600
+"
+        );
+        let out = remove_remote(CONFIG, "homelab").unwrap();
+        assert!(!out.contains("10.0.0.2"));
+        assert!(out.contains("# Work machines\n[remotes.devbox]\nhost = \"devbox\" # ssh alias\ngroup = \"work\"\n\n[settings]"));
+        assert!(remove_remote(CONFIG, "nope").is_err());
+    }
+
+    #[test]
+    fn rename_rewrites_headers_and_the_default_remote() {
+        let config =
+            format!("{CONFIG}\n[defaults]\nremote = \"homelab\" # usual\nproject = \"homelab\"\n");
+        let out = rename_remote(&config, "homelab", "lab").unwrap();
+        assert!(out.contains("# The lab box\n[remotes.lab]\nhost = \"10.0.0.2\""));
+        assert!(out.contains("[defaults]\nremote = \"lab\" # usual\nproject = \"homelab\"\n"));
+        assert_eq!(
+            rename_remote(&config, "devbox", "dev")
+                .unwrap()
+                .lines()
+                .count(),
+            config.lines().count()
+        );
+        assert!(rename_remote(&config, "nope", "x").is_err());
+    }
+
+    #[test]
+    fn names_must_be_bare_keys() {
+        assert!(is_valid_name("dev-box_2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("dev box"));
+        assert!(!is_valid_name("../x"));
+    }
+}
@@ -0,0 +1,373 @@
+//! Config file schema versioning.
+//!
+//! `remotes.toml` carries a top-level `version`. Files written before
+//! versioning existed have none and count as version 0. On load, an older
+//! file is upgraded in place by running each migration from its version up
+//! to `CONFIG_VERSION`; the original is kept alongside as
+//! `remotes.toml.v<old>.bak` and a summary of what changed is printed.
+//!
+//! Migrations operate on the raw TOML table, so they can rename keys or
+//! reshape sections that the typed `Config` would no longer accept. When a
+//! migration only stamps the version, the file's text (and comments) is kept
+//! as-is; structural rewrites go through the TOML serializer, which drops
+//! comments, so the backup is the reference for those.
+//!
+//! New optional keys only need adding to `KNOWN_KEYS`. To rename or reshape
+//! existing ones: bump `CONFIG_VERSION`, append a `Migration` whose `from`
+//! is the previous version, and update `KNOWN_KEYS`.
+
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Schema version written by this build.
+pub const CONFIG_VERSION: i64 = 1;
+
+/// One schema step: upgrades a table at version `from` to `from + 1`,
+/// returning human-readable notes about what it changed.
+pub struct Migration {
+    pub from: i64,
+    pub apply: fn(&mut Table) -> Vec<String>,
+}
+
+/// All migrations, in order.
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    apply: |_| Vec::new(),
+}];
+
+/// Keys each section accepts. Anything else is reported instead of being
+/// silently ignored. `*` matches any name (remote and model names).
+const KNOWN_KEYS: &[(&str, &[&str])] = &[
+    (
+        "",
+        &[
+            "version", "remotes", "settings", "defaults", "layout", "pricing", "ranking", "share",
+            "teams", "local",
+        ],
+    ),
+    ("local", &["projects_dirs"]),
+    ("ranking", &["repo", "recency", "frecency"]),
+    (
+        "share",
+        &["backend", "public", "url", "headers", "url_field", "redact"],
+    ),
+    ("layout", &["list", "picker"]),
+    ("layout.list", &["columns", "widths"]),
+    ("layout.picker", &["columns", "widths"]),
+    ("layout.list.widths.*", &["min", "max"]),
+    ("layout.picker.widths.*", &["min", "max"]),
+    (
+        "remotes.*",
+        &[
+            "host",
+            "user",
+            "projects_dir",
+            "label",
+            "color",
+            "group",
+            "sync_interval",
+            "path_map",
+            "transport",
+            "module",
+            "password_file",
+            "resume_command",
+        ],
+    ),
+    ("teams.*", &["path", "label", "color", "owner"]),
+    (
+        "settings",
+        &[
+            "cache_dir",
+            "stale_threshold",
+            "sync_cooldown",
+            "search_tool_output",
+            "picker",
+            "threads",
+            "low_priority",
+            "full_scan_limit_mb",
+            "max_concurrent_syncs",
+            "editor",
+            "sync_state",
+            "state_sync_dir",
+            "claude_dir",
+            "live_preview",
+        ],
+    ),
+    (
+        "defaults",
+        &[
+            "count",
+            "min_turns",
+            "sort",
+            "project",
+            "remote",
+            "no_sync",
+            "include_forks",
+            "return_to_picker",
+            "flat_forks",
+            "named_first",
+            "plain",
+        ],
+    ),
+    (
+        "pricing.*",
+        &["input", "output", "cache_write", "cache_read"],
+    ),
+];
+
+/// Result of upgrading a table.
+#[derive(Debug, PartialEq)]
+pub struct Upgrade {
+    pub from: i64,
+    pub notes: Vec<String>,
+    /// Whether anything besides `version` changed.
+    pub structural: bool,
+}
+
+/// Version recorded in `table` (0 when absent).
+pub fn version_of(table: &Table) -> Result<i64> {
+    match table.get("version") {
+        None => Ok(0),
+        Some(Value::Integer(v)) if *v >= 0 => Ok(*v),
+        Some(other) => bail!("Invalid config version: {}", other),
+    }
+}
+
+/// Bring `table` up to `CONFIG_VERSION`. Returns `None` if it already is;
+/// fails if it's from a newer cc-sessions.
+pub fn upgrade(table: &mut Table, migrations: &[Migration]) -> Result<Option<Upgrade>> {
+    let from = version_of(table)?;
+    let target = migrations.last().map_or(0, |m| m.from + 1);
+    if from > target {
+        bail!(
+            "Config version {} is newer than this cc-sessions supports ({}); please upgrade",
+            from,
+            target
+        );
+    }
+    if from == target {
+        return Ok(None);
+    }
+
+    let before = without_version(table);
+    let mut notes = Vec::new();
+    for migration in migrations.iter().filter(|m| m.from >= from) {
+        notes.extend((migration.apply)(table));
+    }
+    table.insert("version".to_string(), Value::Integer(target));
+    notes.push(format!("Set version = {}", target));
+    Ok(Some(Upgrade {
+        from,
+        notes,
+        structural: without_version(table) != before,
+    }))
+}
+
+fn without_version(table: &Table) -> Table {
+    let mut table = table.clone();
+    table.remove("version");
+    table
+}
+
+/// Dotted paths of keys not in `KNOWN_KEYS`.
+pub fn unknown_keys(table: &Table) -> Vec<String> {
+    let mut unknown = Vec::new();
+    collect_unknown(table, "", "", &mut unknown);
+    unknown
+}
+
+fn collect_unknown(table: &Table, pattern: &str, path: &str, out: &mut Vec<String>) {
+    let Some((_, known)) = KNOWN_KEYS.iter().find(|(p, _)| *p == pattern) else {
+        return;
+    };
+    for (key, value) in table {
+        let full = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        if !known.contains(&key.as_str()) {
+            out.push(full);
+            continue;
+        }
+        if let Value::Table(child) = value {
+            // Named sections (`[remotes.devbox]`) hold one level of arbitrary
+            // names before their fields.
+            let child_pattern = if pattern.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", pattern, key)
+            };
+            if KNOWN_KEYS.iter().any(|(p, _)| *p == child_pattern) {
+                collect_unknown(child, &child_pattern, &full, out);
+            } else {
+                let named = format!("{}.*", child_pattern);
+                for (name, entry) in child {
+                    if let Value::Table(entry) = entry {
+                        collect_unknown(entry, &named, &format!("{}.{}", full, name), out);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Prepend `version = N` to the file text, replacing an existing top-level
+/// `version` line. Used when nothing else changed, to keep comments.
+pub fn stamp_version(content: &str, version: i64) -> String {
+    let mut out = format!("version = {}\n", version);
+    let mut in_root = true;
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        in_root &= !trimmed.starts_with('[');
+        let is_version = trimmed
+            .strip_prefix("version")
+            .is_some_and(|rest| rest.trim_start().starts_with('='));
+        if in_root && is_version {
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Backup path for a config file at `version`.
+fn backup_path(path: &Path, version: i64) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    path.with_file_name(name)
+}
+
+/// Parse `content`, upgrading the file at `path` in place if it's from an
+/// older schema. Returns the (possibly migrated) table.
+pub fn load_and_migrate(path: &Path, content: &str) -> Result<Table> {
+    let mut table: Table = toml::from_str(content)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+    let upgrade = upgrade(&mut table, MIGRATIONS)
+        .with_context(|| format!("Failed to migrate config file: {}", path.display()))?;
+
+    if let Some(upgrade) = upgrade {
+        let new_content = if upgrade.structural {
+            toml::to_string(&table).context("Failed to serialize migrated config")?
+        } else {
+            stamp_version(content, CONFIG_VERSION)
+        };
+        let backup = backup_path(path, upgrade.from);
+        let written = fs::copy(path, &backup)
+            .with_context(|| format!("Failed to back up config to {}", backup.display()))
+            .and_then(|_| {
+                fs::write(path, new_content)
+                    .with_context(|| format!("Failed to write config file: {}", path.display()))
+            });
+        match written {
+            Ok(()) => {
+                eprintln!(
+                    "Migrated {} from version {} to {} (backup: {})",
+                    path.display(),
+                    upgrade.from,
+                    CONFIG_VERSION,
+                    backup.display()
+                );
+                for note in &upgrade.notes {
+                    eprintln!("  - {}", note);
+                }
+                if upgrade.structural {
+                    eprintln!("  Comments were not preserved; see the backup.");
+                }
+            }
+            // Still usable in memory; try again next run.
+            Err(e) => eprintln!("Warning: {:#}", e),
+        }
+    }
+
+    for key in unknown_keys(&table) {
+        eprintln!(
+            "Warning: Unknown config key '{}' in {} (ignored)",
+            key,
+            path.display()
+        );
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Table {
+        toml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn unversioned_file_is_stamped_without_structural_change() {
+        let mut table = parse("[remotes.devbox]\nhost = \"devbox\"\n");
+        let upgrade = upgrade(&mut table, MIGRATIONS).unwrap().unwrap();
+        assert_eq!(upgrade.from, 0);
+        assert!(!upgrade.structural);
+        assert_eq!(version_of(&table).unwrap(), CONFIG_VERSION);
+        assert_eq!(super::upgrade(&mut table, MIGRATIONS).unwrap(), None);
+    }
+
+    #[test]
+    fn migrations_run_in_order_and_report_renames() {
+        const STEPS: &[Migration] = &[
+            Migration {
+                from: 0,
+                apply: |_| Vec::new(),
+            },
+            Migration {
+                from: 1,
+                apply: |t| {
+                    let Some(Value::Table(settings)) = t.get_mut("settings") else {
+                        return Vec::new();
+                    };
+                    let Some(v) = settings.remove("cache") else {
+                        return Vec::new();
+                    };
+                    settings.insert("cache_dir".into(), v);
+                    vec!["Renamed settings.cache to settings.cache_dir".into()]
+                },
+            },
+        ];
+        let mut table = parse("version = 1\n[settings]\ncache = \"/c\"\n");
+        let upgrade = upgrade(&mut table, STEPS).unwrap().unwrap();
+        assert!(upgrade.structural);
+        assert_eq!(
+            upgrade.notes,
+            [
+                "Renamed settings.cache to settings.cache_dir",
+                "Set version = 2"
+            ]
+        );
+        assert_eq!(table["settings"]["cache_dir"].as_str(), Some("/c"));
+
+        let mut newer = parse("version = 3\n");
+        assert!(super::upgrade(&mut newer, STEPS).is_err());
+    }
+
+    #[test]
+    fn reports_unknown_keys_at_every_level() {
+        let table = parse(
+            "colour = true\n\
+             [settings]\npicker = \"fzf\"\nstale = 10\n\
+             [remotes.devbox]\nhost = \"d\"\nport = 22\n\
+             [pricing.opus]\ninput = 1.0\noutput = 2.0\n",
+        );
+        assert_eq!(
+            unknown_keys(&table),
+            ["colour", "remotes.devbox.port", "settings.stale"]
+        );
+    }
+
+    #[test]
+    fn stamp_version_keeps_comments_and_replaces_old_version() {
+        let content = "# my remotes\nversion = 0\n[remotes.a]\nhost = \"a\" # box\nversion = 9\n";
+        assert_eq!(
+            stamp_version(content, 1),
+            "version = 1\n# my remotes\n[remotes.a]\nhost = \"a\" # box\nversion = 9\n"
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Calendar date helpers (UTC only).
+//!
+//! Transcript timestamps are RFC 3339 UTC strings and CLI periods are plain
+//! dates, so a few lines of civil-calendar arithmetic (Howard Hinnant's
+//! `days_from_civil` / `civil_from_days`) cover everything without pulling in
+//! a date-time crate.
+
+use anyhow::{Context, Result, bail};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const SECS_PER_DAY: u64 = 86_400;
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a valid proleptic Gregorian date on or after
+/// the epoch. Impossible dates (`2025-02-30`) are rejected rather than
+/// rolled into the next month; four-digit years keep the arithmetic in range.
+fn days_from_civil(year: i64, month: i64, day: i64) -> Option<u64> {
+    if !(0..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+    {
+        return None;
+    }
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    u64::try_from(era * 146_097 + doe - 719_468).ok()
+}
+
+/// Parse `YYYY-MM-DD` into days since the epoch.
+fn parse_day(date: &str) -> Option<u64> {
+    let mut parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    days_from_civil(year, month, day)
+}
+
+/// Format days since the epoch as `YYYY-MM-DD`.
+pub fn format_day(days: u64) -> String {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Format as an RFC 3339 UTC timestamp with second precision
+/// (`2025-01-15T10:30:00Z`). Pre-epoch times clamp to the epoch.
+pub fn format_timestamp(t: SystemTime) -> String {
+    let secs = t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let rem = secs % SECS_PER_DAY;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        format_day(secs / SECS_PER_DAY),
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Days since the epoch of the UTC day containing `t` (0 for pre-epoch).
+pub fn day_of(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / SECS_PER_DAY)
+}
+
+/// Start of the given day as a `SystemTime`.
+pub fn start_of_day(days: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(days * SECS_PER_DAY)
+}
+
+/// Parse an RFC 3339 UTC timestamp (`2025-01-15T10:30:00.123Z`) into Unix
+/// milliseconds. Claude Code always writes UTC with a `Z` suffix; anything
+/// else is rejected rather than guessed at.
+pub fn parse_timestamp_ms(s: &str) -> Option<u64> {
+    let s = s.strip_suffix('Z')?;
+    let (date, time) = s.split_once('T')?;
+    let days = parse_day(date)?;
+    let (hms, frac) = time.split_once('.').unwrap_or((time, ""));
+    let mut parts = hms.splitn(3, ':').map(|p| p.parse::<u64>().ok());
+    let (hour, minute, second) = (parts.next()??, parts.next()??, parts.next()??);
+    if hour > 23 || minute > 59 {
+        return None;
+    }
+    let millis = frac
+        .chars()
+        .chain(std::iter::repeat('0'))
+        .take(3)
+        .collect::<String>()
+        .parse::<u64>()
+        .ok()?;
+
+    Some(((days * 24 + hour) * 60 + minute) * 60_000 + second * 1000 + millis)
+}
+
+/// Day of the week of days since the epoch, Monday = 0 (1970-01-01 was a
+/// Thursday).
+fn weekday(days: u64) -> u64 {
+    (days + 3) % 7
+}
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// The most recent day (today included) named by `arg`: `today`,
+/// `yesterday`, or a weekday, spelled out or as its first three letters.
+fn named_day(arg: &str, today: u64) -> Option<u64> {
+    let arg = arg.to_lowercase();
+    match arg.as_str() {
+        "today" => return Some(today),
+        "yesterday" => return today.checked_sub(1),
+        _ => {}
+    }
+    let target = WEEKDAYS
+        .iter()
+        .position(|day| arg.len() >= 3 && day.starts_with(&arg))? as u64;
+    today.checked_sub((weekday(today) + 7 - target) % 7)
+}
+
+/// Parse a CLI period bound: an absolute `YYYY-MM-DD` (start of that UTC
+/// day), a relative `Nd` / `Nw` counted back from the start of today, or a
+/// named day (`today`, `yesterday`, `monday`, ...).
+pub fn parse_period_start(arg: &str, now: SystemTime) -> Result<SystemTime> {
+    let arg = arg.trim();
+    let relative = |suffix: char, unit_days: u64| {
+        let n = arg.strip_suffix(suffix)?.parse::<u64>().ok()?;
+        Some(
+            n.checked_mul(unit_days)
+                .map(|back| day_of(now).saturating_sub(back))
+                .with_context(|| format!("Period '{}' is out of range", arg)),
+        )
+    };
+    if let Some(days) = relative('d', 1).or_else(|| relative('w', 7)) {
+        return Ok(start_of_day(days?));
+    }
+    match parse_day(arg).or_else(|| named_day(arg, day_of(now))) {
+        Some(days) => Ok(start_of_day(days)),
+        None => bail!(
+            "Invalid date '{}': expected YYYY-MM-DD, Nd (days ago), Nw (weeks ago), or a day name like monday",
+            arg
+        ),
+    }
+}
+
+/// The exclusive end of an inclusive period ending on `arg` (same formats
+/// as `parse_period_start`): the start of the following day.
+pub fn parse_period_end(arg: &str, now: SystemTime) -> Result<SystemTime> {
+    Ok(parse_period_start(arg, now)? + std::time::Duration::from_secs(SECS_PER_DAY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_timestamp_handles_claude_format() {
+        assert_eq!(parse_timestamp_ms("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(
+            parse_timestamp_ms("2025-01-15T10:30:00.123Z"),
+            Some(1_736_937_000_123)
+        );
+        assert_eq!(
+            parse_timestamp_ms("2024-02-29T23:59:59.5Z"),
+            Some(1_709_251_199_500)
+        );
+        assert_eq!(parse_timestamp_ms("2025-01-15T10:30:00+02:00"), None);
+        assert_eq!(parse_timestamp_ms("2025-13-01T00:00:00Z"), None);
+        assert_eq!(parse_timestamp_ms("garbage"), None);
+    }
+
+    #[test]
+    fn format_day_round_trips() {
+        assert_eq!(format_day(0), "1970-01-01");
+        assert_eq!(format_day(20_103), "2025-01-15");
+        assert_eq!(format_day(11_016), "2000-02-29");
+        assert_eq!(parse_day("2025-01-15"), Some(20_103));
+    }
+
+    #[test]
+    fn parse_day_rejects_impossible_dates() {
+        assert_eq!(parse_day("2025-02-30"), None);
+        assert_eq!(parse_day("2025-04-31"), None);
+        assert_eq!(parse_day("2025-02-29"), None);
+        assert_eq!(parse_day("1900-02-29"), None);
+        assert_eq!(parse_day("2024-02-29"), Some(19_782));
+        assert_eq!(parse_day("2000-02-29"), Some(11_016));
+        assert_eq!(parse_day("2025-04-30"), Some(20_208));
+        assert_eq!(parse_day("99999999999999-01-01"), None);
+    }
+
+    #[test]
+    fn format_timestamp_round_trips_with_parse() {
+        let t = UNIX_EPOCH + Duration::from_millis(1_736_937_000_123);
+        assert_eq!(format_timestamp(t), "2025-01-15T10:30:00Z");
+        assert_eq!(
+            parse_timestamp_ms(&format_timestamp(t)),
+            Some(1_736_937_000_000)
+        );
+    }
+
+    #[test]
+    fn parse_period_start_accepts_dates_and_relative() {
+        let now = start_of_day(20_103) + Duration::from_secs(3600);
+        assert_eq!(
+            parse_period_start("2025-01-01", now).unwrap(),
+            start_of_day(20_089)
+        );
+        assert_eq!(parse_period_start("7d", now).unwrap(), start_of_day(20_096));
+        assert_eq!(parse_period_start("2w", now).unwrap(), start_of_day(20_089));
+        assert!(parse_period_start("last tuesday", now).is_err());
+        assert_eq!(parse_period_start("99999d", now).unwrap(), UNIX_EPOCH);
+        let err = parse_period_start("3000000000000000000w", now).unwrap_err();
+        assert!(err.to_string().contains("out of range"), "{err}");
+        assert_eq!(
+            parse_period_end("2025-01-01", now).unwrap(),
+            start_of_day(20_090)
+        );
+    }
+
+    #[test]
+    fn parse_period_start_accepts_day_names() {
+        // 2025-01-15 was a Wednesday.
+        let now = start_of_day(20_103) + Duration::from_secs(3600);
+        let day = |arg| day_of(parse_period_start(arg, now).unwrap());
+        assert_eq!(day("today"), 20_103);
+        assert_eq!(day("Yesterday"), 20_102);
+        assert_eq!(day("monday"), 20_101);
+        assert_eq!(day("wed"), 20_103);
+        assert_eq!(day("thursday"), 20_097);
+        assert!(parse_period_start("mo", now).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! `cc-sessions digest`: what was worked on in a period, as a bullet list
+//! ready to paste into a standup or a weekly note.
+//!
+//! A session belongs to the period if any of its messages fall in it
+//! (`Session::active_hours`), so a session picked up again today shows up in
+//! today's digest even though it started last week. Sessions are grouped by
+//! project, busiest project first, and each is listed by its name, summary,
+//! or first prompt, with its approved plan and unfinished todos as outcomes.
+
+use crate::session::Session;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Open todos listed per session before the rest are counted instead.
+const MAX_TODOS: usize = 3;
+
+/// Longest title or plan line kept in a bullet.
+const LINE_CHARS: usize = 100;
+
+/// One session's part of the period.
+pub struct DigestSession<'a> {
+    pub session: &'a Session,
+    /// User/assistant messages written during the period.
+    pub messages: u32,
+}
+
+/// The sessions of one project, in the order they were worked on.
+pub struct ProjectDigest<'a> {
+    pub project: String,
+    pub messages: u32,
+    pub sessions: Vec<DigestSession<'a>>,
+}
+
+/// Sessions with activity in `[since, until)`, grouped by project. Forks
+/// with nothing written after forking repeat their parent and are left out.
+pub fn collect<'a>(
+    sessions: &[&'a Session],
+    since: SystemTime,
+    until: Option<SystemTime>,
+) -> Vec<ProjectDigest<'a>> {
+    let hour = |t: SystemTime| {
+        t.duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() / 3600)
+    };
+    let (first, end) = (hour(since), until.map_or(u64::MAX, hour));
+    let mut projects: BTreeMap<&str, Vec<DigestSession<'a>>> = BTreeMap::new();
+    for &session in sessions {
+        if session.redundant_fork {
+            continue;
+        }
+        let messages = session
+            .active_hours
+            .range(u32::try_from(first).unwrap_or(u32::MAX)..)
+            .take_while(|(h, _)| u64::from(**h) < end)
+            .map(|(_, n)| n)
+            .sum();
+        // Sessions without timestamps only have their file times to go by.
+        let active = session.last_activity.unwrap_or(session.modified);
+        let in_period = if session.active_hours.is_empty() {
+            active >= since && until.is_none_or(|t| active < t)
+        } else {
+            messages > 0
+        };
+        if in_period {
+            projects
+                .entry(session.project.as_str())
+                .or_default()
+                .push(DigestSession { session, messages });
+        }
+    }
+
+    let mut rows: Vec<ProjectDigest> = projects
+        .into_iter()
+        .map(|(project, mut sessions)| {
+            sessions.sort_by_key(|s| s.session.last_activity.unwrap_or(s.session.modified));
+            ProjectDigest {
+                project: project.to_string(),
+                messages: sessions.iter().map(|s| s.messages).sum(),
+                sessions,
+            }
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.messages
+            .cmp(&a.messages)
+            .then_with(|| a.project.cmp(&b.project))
+    });
+    rows
+}
+
+/// The digest as a Markdown bullet list under a one-line summary of the
+/// period. `plan_of` reads a session's approved plan (it's not part of the
+/// scan); its first line stands for the plan.
+pub fn render(
+    projects: &[ProjectDigest],
+    period: &str,
+    plan_of: impl Fn(&Session) -> Option<String>,
+) -> String {
+    let mut out = String::new();
+    if projects.is_empty() {
+        let _ = writeln!(out, "No sessions active {}", period);
+        return out;
+    }
+    let sessions: usize = projects.iter().map(|p| p.sessions.len()).sum();
+    let messages: u32 = projects.iter().map(|p| p.messages).sum();
+    let _ = writeln!(
+        out,
+        "{}: {} in {}, {} messages\n",
+        capitalize(period),
+        plural(sessions, "session"),
+        plural(projects.len(), "project"),
+        messages
+    );
+    for project in projects {
+        let _ = writeln!(
+            out,
+            "- **{}** ({})",
+            project.project,
+            plural(project.sessions.len(), "session")
+        );
+        for entry in &project.sessions {
+            let session = entry.session;
+            let _ = writeln!(out, "  - {}", title(session));
+            if let Some(plan) = plan_of(session).as_deref().and_then(first_line) {
+                let _ = writeln!(out, "    - Plan: {}", plan);
+            }
+            for todo in session.open_todos.iter().take(MAX_TODOS) {
+                let state = if todo.in_progress {
+                    "In progress"
+                } else {
+                    "Open"
+                };
+                let content = crate::normalize_summary(&todo.content, LINE_CHARS);
+                let _ = writeln!(out, "    - {}: {}", state, content);
+            }
+            if session.open_todos.len() > MAX_TODOS {
+                let _ = writeln!(
+                    out,
+                    "    - …and {} more open",
+                    session.open_todos.len() - MAX_TODOS
+                );
+            }
+        }
+    }
+    out
+}
+
+fn title(session: &Session) -> String {
+    let text = session
+        .name
+        .as_deref()
+        .or(session.summary.as_deref())
+        .or(session.first_message.as_deref())
+        .unwrap_or("(untitled session)");
+    crate::normalize_summary(text, LINE_CHARS)
+}
+
+/// First non-empty line of `text`, without Markdown heading marks.
+fn first_line(text: &str) -> Option<String> {
+    let line = text.lines().map(str::trim).find(|l| !l.is_empty())?;
+    Some(crate::normalize_summary(line, LINE_CHARS))
+}
+
+fn plural(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or_else(String::new, |c| {
+        c.to_uppercase().chain(chars).collect::<String>()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::OpenTodo;
+    use std::time::Duration;
+
+    const HOUR: u32 = 480_000; // 2024-10-04T00:00Z, in hours since the epoch
+
+    fn at(hour: u32) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(u64::from(hour) * 3600)
+    }
+
+    fn session(id: &str, project: &str, hours: &[(u32, u32)]) -> Session {
+        let mut s = Session::fixture(id);
+        s.project = project.to_string();
+        s.summary = Some(format!("Work on {id}"));
+        s.active_hours = hours.iter().copied().collect();
+        s.last_activity = hours.last().map(|&(h, _)| at(h));
+        s
+    }
+
+    #[test]
+    fn collects_sessions_active_in_the_period_by_project() {
+        let sessions = [
+            session("old", "grail", &[(HOUR - 30, 5)]),
+            session("spans", "grail", &[(HOUR - 30, 5), (HOUR + 2, 3)]),
+            session("today", "grail", &[(HOUR + 25, 9)]),
+            session("busy", "shrubbery", &[(HOUR + 1, 7), (HOUR + 20, 4)]),
+        ];
+        let refs: Vec<&Session> = sessions.iter().collect();
+        let digest = collect(&refs, at(HOUR), Some(at(HOUR + 24)));
+
+        let shape: Vec<(&str, u32, Vec<&str>)> = digest
+            .iter()
+            .map(|p| {
+                let ids = p.sessions.iter().map(|s| s.session.id.as_str()).collect();
+                (p.project.as_str(), p.messages, ids)
+            })
+            .collect();
+        assert_eq!(
+            shape,
+            [("shrubbery", 11, vec!["busy"]), ("grail", 3, vec!["spans"])]
+        );
+    }
+
+    #[test]
+    fn renders_a_standup_list_with_plans_and_open_todos() {
+        let mut planned = session("a", "grail", &[(HOUR, 4)]);
+        planned.name = Some("Auth refactor".to_string());
+        planned.plan_offset = Some(0);
+        planned.open_todos = (1..=5)
+            .map(|i| OpenTodo {
+                content: format!("Step {i}"),
+                in_progress: i == 1,
+            })
+            .collect();
+        let plain = session("b", "grail", &[(HOUR + 1, 2)]);
+        let refs = [&planned, &plain];
+        let digest = collect(&refs, at(HOUR), None);
+        let out = render(&digest, "yesterday (2024-10-04)", |s| {
+            s.plan_offset
+                .map(|_| "\n# Move token refresh into middleware\n\nDetails".to_string())
+        });
+        assert_eq!(
+            out,
+            "Yesterday (2024-10-04): 2 sessions in 1 project, 6 messages
+
+- **grail** (2 sessions)
+  - Auth refactor
+    - Plan: Move token refresh into middleware
+    - In progress: Step 1
+    - Open: Step 2
+    - Open: Step 3
+    - …and 2 more open
+  - Work on b
+"
+        );
+        assert_eq!(render(&[], "today", |_| None), "No sessions active today\n");
+    }
+}
@@ -0,0 +1,333 @@
+//! `cc-sessions export`: a session's transcript in a documented shape for
+//! other tools.
+//!
+//! Claude Code's JSONL is an implementation detail: one response can span
+//! several entries, tool results arrive as user messages, and synthetic
+//! entries sit between real ones. `claude_code::read_messages` undoes that;
+//! this module wraps the messages with the session's metadata. The JSON
+//! carries a `schema_version`, bumped on incompatible changes (adding fields
+//! isn't one). Markdown is for people: it is what `share` uploads, and
+//! makes no stability promise.
+//!
+//! `--list --json` uses the same session shape, with everything discovery
+//! knows about each session, as a plain array.
+
+use crate::dates;
+use crate::session::{Message, Role, Session, TokenUsage};
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Output format of `export --format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// `{schema_version, session, messages}` as pretty JSON
+    #[default]
+    Json,
+    /// A readable transcript with numbered messages and tool calls folded away
+    #[value(alias = "md")]
+    Markdown,
+}
+
+/// Tool results longer than this many lines are cut in Markdown.
+const MAX_RESULT_LINES: usize = 30;
+
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    schema_version: u32,
+    session: JsonSession<'a>,
+    messages: &'a [Message],
+}
+
+#[derive(Serialize)]
+struct JsonSession<'a> {
+    id: &'a str,
+    project: &'a str,
+    project_path: &'a str,
+    source: &'a str,
+    name: Option<&'a str>,
+    summary: Option<&'a str>,
+    forked_from: Option<&'a str>,
+    created: String,
+    modified: String,
+}
+
+/// `session` and its `messages` as pretty JSON.
+pub fn render_json(session: &Session, messages: &[Message]) -> Result<String> {
+    let export = JsonExport {
+        schema_version: JSON_SCHEMA_VERSION,
+        session: JsonSession {
+            id: &session.id,
+            project: &session.project,
+            project_path: &session.project_path,
+            source: session.source.display_name(),
+            name: session.name.as_deref(),
+            summary: session.summary.as_deref(),
+            forked_from: session.forked_from.as_deref(),
+            created: dates::format_timestamp(session.created),
+            modified: dates::format_timestamp(session.modified),
+        },
+        messages,
+    };
+    serde_json::to_string_pretty(&export).context("Failed to serialize transcript")
+}
+
+/// One session of `--list --json`: `JsonSession` plus discovery's metadata.
+#[derive(Serialize)]
+struct JsonListedSession<'a> {
+    id: &'a str,
+    project: &'a str,
+    project_path: &'a str,
+    source: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<&'a str>,
+    file: String,
+    name: Option<&'a str>,
+    summary: Option<&'a str>,
+    first_message: Option<&'a str>,
+    tag: Option<&'a str>,
+    git_branch: Option<&'a str>,
+    forked_from: Option<&'a str>,
+    created: String,
+    modified: String,
+    first_activity: Option<String>,
+    last_activity: Option<String>,
+    resumed: Option<String>,
+    turn_count: usize,
+    size_bytes: u64,
+    tokens: TokenUsage,
+    archived: bool,
+}
+
+/// `sessions` as a pretty JSON array, for `--list --json`.
+pub fn render_session_list(sessions: &[&Session]) -> Result<String> {
+    let listed: Vec<JsonListedSession> = sessions
+        .iter()
+        .map(|s| JsonListedSession {
+            id: &s.id,
+            project: &s.project,
+            project_path: &s.project_path,
+            source: s.source.display_name(),
+            owner: s.source.owner(),
+            file: s.filepath.display().to_string(),
+            name: s.name.as_deref(),
+            summary: s.summary.as_deref(),
+            first_message: s.first_message.as_deref(),
+            tag: s.tag.as_deref(),
+            git_branch: s.git_branch.as_deref(),
+            forked_from: s.forked_from.as_deref(),
+            created: dates::format_timestamp(s.created),
+            modified: dates::format_timestamp(s.modified),
+            first_activity: s.first_activity.map(dates::format_timestamp),
+            last_activity: s.last_activity.map(dates::format_timestamp),
+            resumed: s.resumed.map(dates::format_timestamp),
+            turn_count: s.turn_count,
+            size_bytes: s.size,
+            tokens: s.tokens,
+            archived: s.archived,
+        })
+        .collect();
+    serde_json::to_string_pretty(&listed).context("Failed to serialize sessions")
+}
+
+/// `messages` as Markdown, numbered from `first` (1-based) out of `total`
+/// in the session, so an excerpt keeps the numbers of the full transcript.
+pub fn render_markdown(
+    session: &Session,
+    messages: &[Message],
+    first: usize,
+    total: usize,
+) -> String {
+    use std::fmt::Write as _;
+
+    let title = session
+        .name
+        .as_deref()
+        .or(session.summary.as_deref())
+        .or(session.first_message.as_deref())
+        .unwrap_or("Claude Code session");
+    let mut out = format!("# {}\n\n", title.lines().next().unwrap_or(title));
+    let _ = writeln!(
+        out,
+        "- Session `{}` in `{}` ({})",
+        session.id,
+        session.project,
+        session.source.display_name()
+    );
+    let _ = writeln!(
+        out,
+        "- Created {}, last modified {}",
+        dates::format_timestamp(session.created),
+        dates::format_timestamp(session.modified)
+    );
+    let _ = writeln!(out, "- {} turn(s)", session.turn_count);
+    if messages.len() < total {
+        let _ = writeln!(
+            out,
+            "- Messages {}-{} of {}",
+            first,
+            first + messages.len().saturating_sub(1),
+            total
+        );
+    }
+    out.push('\n');
+
+    for (i, message) in messages.iter().enumerate() {
+        let role = match message.role {
+            Role::User if message.text.is_empty() && !message.tool_results.is_empty() => {
+                "Tool results"
+            }
+            Role::User => "User",
+            Role::Assistant => "Assistant",
+        };
+        let _ = write!(out, "## {}. {}", first + i, role);
+        if let Some(timestamp) = &message.timestamp {
+            let _ = write!(out, " ({})", timestamp);
+        }
+        out.push_str("\n\n");
+        if message.compact_summary {
+            out.push_str("*(Earlier conversation compacted into this summary.)*\n\n");
+        }
+        for text in &message.text {
+            let _ = writeln!(out, "{}\n", text.trim_end());
+        }
+        for call in &message.tool_calls {
+            let input = serde_json::to_string_pretty(&call.input).unwrap_or_default();
+            out.push_str(&collapsed(
+                &format!("Tool call: {}", call.name),
+                &fenced(&input, "json"),
+            ));
+        }
+        for result in &message.tool_results {
+            let lines: Vec<&str> = result.text.lines().collect();
+            let mut body = lines[..lines.len().min(MAX_RESULT_LINES)].join("\n");
+            if lines.len() > MAX_RESULT_LINES {
+                let _ = write!(
+                    body,
+                    "\n... ({} more lines)",
+                    lines.len() - MAX_RESULT_LINES
+                );
+            }
+            let label = if result.is_error {
+                "Tool error"
+            } else {
+                "Tool result"
+            };
+            out.push_str(&collapsed(label, &fenced(&body, "")));
+        }
+    }
+    out
+}
+
+/// `body` folded under `summary` (GitHub and most renderers support
+/// `<details>`), so tool traffic doesn't drown the conversation.
+fn collapsed(summary: &str, body: &str) -> String {
+    format!(
+        "<details><summary>{}</summary>\n\n{}\n</details>\n\n",
+        summary, body
+    )
+}
+
+/// A code block whose fence is longer than any backtick run inside it.
+fn fenced(body: &str, lang: &str) -> String {
+    let longest = body.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{lang}\n{}\n{fence}\n", body.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_list_is_an_array_with_rfc3339_times() {
+        let mut session = Session::fixture("abc");
+        session.created = std::time::UNIX_EPOCH;
+        session.forked_from = Some("parent".to_string());
+        let json: serde_json::Value =
+            serde_json::from_str(&render_session_list(&[&session]).unwrap()).unwrap();
+        let listed = &json.as_array().unwrap()[0];
+        assert_eq!(listed["id"], "abc");
+        assert_eq!(listed["source"], "local");
+        assert_eq!(listed["created"], "1970-01-01T00:00:00Z");
+        assert_eq!(listed["forked_from"], "parent");
+        assert_eq!(listed["turn_count"], 1);
+        assert!(listed.get("owner").is_none());
+    }
+    use crate::session::{Role, TokenUsage, ToolCall};
+
+    #[test]
+    fn json_wraps_messages_with_session_metadata() {
+        let mut session = Session::fixture("abc");
+        session.name = Some("Auth refactor".to_string());
+        let messages = [Message {
+            role: Role::Assistant,
+            timestamp: Some("2025-01-15T10:00:00Z".to_string()),
+            model: None,
+            text: vec!["Done.".to_string()],
+            tool_calls: vec![ToolCall {
+                id: "t1".to_string(),
+                name: "Bash".to_string(),
+                input: serde_json::json!({"command": "ls"}),
+            }],
+            tool_results: Vec::new(),
+            usage: Some(TokenUsage {
+                output: 3,
+                ..Default::default()
+            }),
+            compact_summary: false,
+        }];
+        let json: serde_json::Value =
+            serde_json::from_str(&render_json(&session, &messages).unwrap()).unwrap();
+        assert_eq!(json["schema_version"], JSON_SCHEMA_VERSION);
+        assert_eq!(json["session"]["id"], "abc");
+        assert_eq!(json["session"]["name"], "Auth refactor");
+        let message = &json["messages"][0];
+        assert_eq!(message["role"], "assistant");
+        assert_eq!(message["tool_calls"][0]["input"]["command"], "ls");
+        assert_eq!(message["usage"]["output"], 3);
+        assert!(message.get("model").is_none() && message.get("compact_summary").is_none());
+    }
+
+    #[test]
+    fn markdown_numbers_an_excerpt_and_collapses_tool_output() {
+        let mut session = Session::fixture("abc");
+        session.name = Some("Auth refactor".to_string());
+        let result = crate::session::ToolResult {
+            tool_use_id: "t1".to_string(),
+            is_error: false,
+            text: "```\nok".to_string(),
+        };
+        let messages = [
+            Message {
+                role: Role::User,
+                timestamp: None,
+                model: None,
+                text: vec!["Run the tests".to_string()],
+                tool_calls: Vec::new(),
+                tool_results: Vec::new(),
+                usage: None,
+                compact_summary: false,
+            },
+            Message {
+                role: Role::User,
+                timestamp: Some("2025-01-15T10:00:00Z".to_string()),
+                model: None,
+                text: Vec::new(),
+                tool_calls: Vec::new(),
+                tool_results: vec![result],
+                usage: None,
+                compact_summary: false,
+            },
+        ];
+        let markdown = render_markdown(&session, &messages, 10, 40);
+        assert!(markdown.starts_with("# Auth refactor\n\n- Session `abc`"));
+        assert!(markdown.contains("- 1 turn(s)\n"));
+        assert!(markdown.contains("- Messages 10-11 of 40\n"));
+        assert!(markdown.contains("\n## 10. User\n\nRun the tests\n"));
+        assert!(markdown.contains(
+            "\n## 11. Tool results (2025-01-15T10:00:00Z)\n\n<details><summary>Tool result</summary>\n\n````\n```\nok\n````\n\n</details>\n"
+        ));
+    }
+}
@@ -0,0 +1,401 @@
+//! `cc-sessions follow` (and ctrl-f in the picker): print a session's
+//! messages as Claude Code writes them.
+//!
+//! A local transcript is polled for growth. A remote one is streamed with
+//! `tail -F` over ssh, since the cached copy only changes on sync. Entries
+//! are shown as they arrive, so a response written as several entries shows
+//! up piece by piece. When stdin is a terminal, q / esc / ctrl-c stop
+//! following (the terminal is in raw mode meanwhile, hence the `\r\n`).
+
+use crate::claude_code;
+use crate::colors;
+use crate::session::{Message, Role};
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+
+/// How often a local file is checked for growth, and keys for a quit.
+const POLL: Duration = Duration::from_millis(250);
+
+/// Tool call and result lines are cut to this many characters.
+const MAX_DETAIL_CHARS: usize = 120;
+
+/// Width of the `HH:MM:SS U ` prefix; continuation lines are indented by it.
+const PREFIX_WIDTH: usize = 11;
+
+/// Where the transcript is read from.
+pub enum Source {
+    /// A local file, polled for growth
+    File(PathBuf),
+    /// `tail -F` of `path` on `target` over ssh
+    Ssh { target: String, path: String },
+}
+
+/// Print the messages in the last `backlog` transcript entries, then new
+/// ones as they are written, until a quit key or the stream ends.
+pub fn follow(source: &Source, backlog: usize) -> Result<()> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+    let mut child = match source {
+        Source::File(path) => {
+            let (path, stop) = (path.clone(), Arc::clone(&stop));
+            std::thread::spawn(move || tail_file(&path, backlog, &tx, &stop));
+            None
+        }
+        Source::Ssh { target, path } => Some(spawn_ssh_tail(target, path, backlog, tx)?),
+    };
+
+    let keys = Keys::open();
+    let newline = if keys.is_some() { "\r\n" } else { "\n" };
+    let mut out = std::io::stdout().lock();
+    let ended = loop {
+        match rx.recv_timeout(POLL) {
+            Ok(Ok(line)) => {
+                for rendered in render_line(&line) {
+                    write!(out, "{}{}", rendered, newline)?;
+                }
+                out.flush()?;
+            }
+            Ok(Err(e)) => break Some(e),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break None,
+        }
+        if keys.as_ref().is_some_and(Keys::quit_pressed) {
+            break None;
+        }
+    };
+    drop(keys);
+
+    stop.store(true, Ordering::Relaxed);
+    if let Some(child) = &mut child {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    match ended {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Raw-mode key polling, restored on drop. `None` without a terminal.
+pub struct Keys;
+
+impl Keys {
+    pub fn open() -> Option<Self> {
+        let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+        (interactive && crossterm::terminal::enable_raw_mode().is_ok()).then_some(Keys)
+    }
+
+    pub fn quit_pressed(&self) -> bool {
+        use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            if let Ok(Event::Key(key)) = event::read() {
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return true,
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        return true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        false
+    }
+}
+
+impl Drop for Keys {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
+
+type Lines = mpsc::Sender<Result<String>>;
+
+/// Send the last `backlog` lines of `path`, then every complete line
+/// appended to it, until `stop`. A file that shrinks was rewritten and is
+/// read again from the start.
+fn tail_file(path: &Path, backlog: usize, tx: &Lines, stop: &AtomicBool) {
+    let result = (|| {
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut recent = VecDeque::with_capacity(backlog);
+        let mut reader = BufReader::new(&mut file);
+        let mut offset = 0u64;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 && line.ends_with('\n') {
+            offset += line.len() as u64;
+            if recent.len() == backlog {
+                recent.pop_front();
+            }
+            if backlog > 0 {
+                recent.push_back(std::mem::take(&mut line));
+            }
+            line.clear();
+        }
+        for line in recent {
+            if tx.send(Ok(line)).is_err() {
+                return Ok(());
+            }
+        }
+
+        let mut pending = Vec::new();
+        while !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(POLL);
+            let len = file.metadata()?.len();
+            if len < offset {
+                offset = 0;
+                pending.clear();
+            }
+            if len == offset {
+                continue;
+            }
+            file.seek(SeekFrom::Start(offset))?;
+            let read = (&mut file).take(len - offset).read_to_end(&mut pending)?;
+            offset += read as u64;
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                if tx
+                    .send(Ok(String::from_utf8_lossy(&line).into_owned()))
+                    .is_err()
+                {
+                    return Ok(());
+                }
+            }
+        }
+        anyhow::Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = tx.send(Err(e));
+    }
+}
+
+/// Start `tail -F` on the remote and forward its lines from a thread. When
+/// ssh exits, its error output is sent as the reason.
+fn spawn_ssh_tail(target: &str, path: &str, backlog: usize, tx: Lines) -> Result<Child> {
+    let mut child = Command::new("ssh")
+        .args([
+            target,
+            &format!("tail -n {} -F {}", backlog, crate::remote::shell_path(path)),
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute ssh")?;
+    let stdout = child.stdout.take().context("ssh has no stdout")?;
+    let stderr = child.stderr.take();
+    let target = target.to_string();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            if tx.send(Ok(line)).is_err() {
+                return;
+            }
+        }
+        let mut reason = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut reason);
+        }
+        let _ = tx.send(Err(match reason.trim() {
+            "" => anyhow::anyhow!("Lost the connection to {}", target),
+            reason => anyhow::anyhow!("Following on {} failed: {}", target, reason),
+        }));
+    });
+    Ok(child)
+}
+
+/// The display lines for one transcript line; none for entries that aren't
+/// messages or have nothing to show.
+pub fn render_line(line: &str) -> Vec<String> {
+    serde_json::from_str::<serde_json::Value>(line)
+        .ok()
+        .and_then(|entry| Some(render(&claude_code::entry_message(&entry)?.0)))
+        .unwrap_or_default()
+}
+
+/// `HH:MM:SS U text` (UTC time from the entry), then tool calls and results
+/// as one indented line each.
+pub fn render(message: &Message) -> Vec<String> {
+    let mut body = Vec::new();
+    if message.compact_summary {
+        body.push(format!(
+            "{}(conversation compacted){}",
+            colors::DIM,
+            colors::RESET
+        ));
+    } else {
+        body.extend(
+            message
+                .text
+                .iter()
+                .flat_map(|text| text.lines())
+                .filter(|line| !line.trim().is_empty())
+                .map(str::to_owned),
+        );
+    }
+    for call in &message.tool_calls {
+        body.push(format!(
+            "{}→ {} {}{}",
+            colors::DIM,
+            call.name,
+            clip(&tool_input_summary(&call.input)),
+            colors::RESET
+        ));
+    }
+    for result in &message.tool_results {
+        let first = result.text.lines().find(|l| !l.trim().is_empty());
+        let (color, mark) = if result.is_error {
+            (colors::RED, "✗")
+        } else {
+            (colors::DIM, "←")
+        };
+        body.push(format!(
+            "{}{} {}{}",
+            color,
+            mark,
+            clip(first.unwrap_or("")),
+            colors::RESET
+        ));
+    }
+    if body.is_empty() {
+        return body;
+    }
+
+    let time = message
+        .timestamp
+        .as_deref()
+        .and_then(|t| t.get(11..19))
+        .unwrap_or("--:--:--");
+    let (glyph, color) = match message.role {
+        Role::User => ('U', colors::CYAN),
+        Role::Assistant => ('A', colors::YELLOW),
+    };
+    let indent = " ".repeat(PREFIX_WIDTH);
+    body.iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 {
+                format!(
+                    "{}{}{} {}{}{} {}",
+                    colors::DIM,
+                    time,
+                    colors::RESET,
+                    color,
+                    glyph,
+                    colors::RESET,
+                    line
+                )
+            } else {
+                format!("{}{}", indent, line)
+            }
+        })
+        .collect()
+}
+
+/// The input field that says what a tool call does: a command, path, or
+/// pattern, else the first string field.
+fn tool_input_summary(input: &serde_json::Value) -> String {
+    const KEYS: [&str; 6] = [
+        "command",
+        "file_path",
+        "pattern",
+        "path",
+        "url",
+        "description",
+    ];
+    let field = KEYS
+        .iter()
+        .find_map(|key| input.get(key).and_then(|v| v.as_str()))
+        .or_else(|| input.as_object()?.values().find_map(|v| v.as_str()));
+    field
+        .and_then(|s| s.lines().next())
+        .unwrap_or("")
+        .to_string()
+}
+
+fn clip(text: &str) -> String {
+    if text.chars().count() <= MAX_DETAIL_CHARS {
+        return text.to_string();
+    }
+    let mut clipped: String = text.chars().take(MAX_DETAIL_CHARS - 1).collect();
+    clipped.push('…');
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(lines: Vec<String>) -> Vec<String> {
+        lines
+            .into_iter()
+            .map(|l| {
+                let mut out = String::new();
+                let mut chars = l.chars();
+                while let Some(c) = chars.next() {
+                    if c == '\x1b' {
+                        chars.by_ref().find(|&c| c == 'm');
+                    } else {
+                        out.push(c);
+                    }
+                }
+                out
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renders_text_tool_calls_and_results() {
+        let assistant = r#"{"type":"assistant","timestamp":"2025-01-15T10:00:05.123Z","message":{"id":"r1","content":[{"type":"text","text":"Running tests.\nThen fixing."},{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo test\necho done","timeout":5}}]}}"#;
+        assert_eq!(
+            plain(render_line(assistant)),
+            [
+                "10:00:05 A Running tests.",
+                "           Then fixing.",
+                "           → Bash cargo test",
+            ]
+        );
+
+        let result = r#"{"type":"user","timestamp":"2025-01-15T10:00:09Z","message":{"content":[{"type":"tool_result","tool_use_id":"t1","is_error":true,"content":"\nerror: 2 tests failed\nmore"}]}}"#;
+        assert_eq!(
+            plain(render_line(result)),
+            ["10:00:09 U ✗ error: 2 tests failed"]
+        );
+
+        assert!(render_line(r#"{"type":"summary","summary":"x"}"#).is_empty());
+        assert!(render_line("not json").is_empty());
+    }
+
+    #[test]
+    fn tail_file_sends_backlog_then_appended_lines() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("s.jsonl");
+        std::fs::write(&path, "a\nb\nc\npart").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let (path, stop) = (path.clone(), Arc::clone(&stop));
+            std::thread::spawn(move || tail_file(&path, 2, &tx, &stop))
+        };
+        let next = || rx.recv_timeout(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!((next(), next()), ("b\n".to_string(), "c\n".to_string()));
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"ial\nd\n").unwrap();
+        assert_eq!(
+            (next(), next()),
+            ("partial\n".to_string(), "d\n".to_string())
+        );
+        stop.store(true, Ordering::Relaxed);
+        thread.join().unwrap();
+    }
+}
@@ -0,0 +1,483 @@
+//! `cc-sessions fsck`: find session files that discovery silently skips or
+//! half-reads, and say how to repair each one.
+//!
+//! Discovery is deliberately forgiving — unparseable lines are dropped,
+//! non-UUID names and empty transcripts are ignored — so damage never shows
+//! up in the picker. This module does the strict pass: every line of every
+//! `.jsonl` at session depth, in every source.
+//!
+//! `--fix` only applies repairs that can't lose data, and only to local
+//! files: remote caches are mirrors and get overwritten by the next sync.
+
+use crate::claude_code::{self, FileHealth};
+use crate::undo;
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One kind of damage, with enough detail to explain it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The file or directory can't be opened or read.
+    Unreadable { error: String },
+    /// Zero bytes: the session was created but nothing was ever written.
+    ZeroBytes,
+    /// Some lines aren't valid JSON and are skipped on load.
+    CorruptLines {
+        bad: usize,
+        total: usize,
+        truncated_tail: bool,
+    },
+    /// Valid JSON but no prompt, summary, or cwd, so it never lists.
+    NoContent,
+    /// Contains a session but isn't named `<uuid>.jsonl`, so discovery
+    /// ignores it. `taken` when a file already has that name.
+    Misnamed { session_id: String, taken: bool },
+    /// A fork whose parent transcript doesn't exist in the same source.
+    MissingParent { parent: String },
+}
+
+impl Problem {
+    fn label(&self) -> &'static str {
+        match self {
+            Problem::Unreadable { .. } => "unreadable",
+            Problem::ZeroBytes => "empty",
+            Problem::CorruptLines { .. } => "corrupt",
+            Problem::NoContent => "no content",
+            Problem::Misnamed { .. } => "misnamed",
+            Problem::MissingParent { .. } => "orphan fork",
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            Problem::Unreadable { error } => error.clone(),
+            Problem::ZeroBytes => "0 bytes".to_string(),
+            Problem::CorruptLines {
+                bad,
+                total,
+                truncated_tail: true,
+                ..
+            } => format!("last of {} lines is cut off ({} bad)", total, bad),
+            Problem::CorruptLines { bad, total, .. } => {
+                format!("{} of {} lines are not valid JSON", bad, total)
+            }
+            Problem::NoContent => "no prompts, summary, or working directory".to_string(),
+            Problem::Misnamed { session_id, .. } => format!("holds session {}", session_id),
+            Problem::MissingParent { parent } => format!("parent {} not found", parent),
+        }
+    }
+
+    fn hint(&self) -> String {
+        match self {
+            Problem::Unreadable { .. } => "check ownership and permissions".to_string(),
+            Problem::ZeroBytes => "safe to delete".to_string(),
+            Problem::CorruptLines {
+                truncated_tail: true,
+                ..
+            } => "interrupted write; the rest loads normally".to_string(),
+            Problem::CorruptLines { .. } => {
+                "bad lines are skipped; restore from backup if content is missing".to_string()
+            }
+            Problem::NoContent => "hidden from the picker; delete if unwanted".to_string(),
+            Problem::Misnamed {
+                session_id,
+                taken: true,
+            } => format!(
+                "{}.jsonl already exists; compare the two before replacing either",
+                session_id
+            ),
+            Problem::Misnamed { session_id, .. } => format!("rename to {}.jsonl", session_id),
+            Problem::MissingParent { .. } => "shown as a root in the fork tree".to_string(),
+        }
+    }
+}
+
+/// A problem found in one file of one source.
+#[derive(Debug)]
+pub struct Finding {
+    pub path: PathBuf,
+    pub problem: Problem,
+    /// `--fix` knows a lossless repair for this finding.
+    pub fixable: bool,
+}
+
+/// Results for one source: how much was checked and what was wrong.
+#[derive(Debug)]
+pub struct SourceReport {
+    pub source: String,
+    pub files: usize,
+    pub findings: Vec<Finding>,
+}
+
+/// Check every session-depth `.jsonl` under one projects root.
+pub fn check_root(root: &Path, source: &str) -> SourceReport {
+    let (files, unreadable_dirs) = claude_code::session_candidate_files(root);
+    let local = source == "local";
+
+    let inspected: Vec<(PathBuf, FileHealth)> = files
+        .into_par_iter()
+        .with_max_len(1)
+        .map(|path| {
+            let health = claude_code::inspect_session_file(&path);
+            (path, health)
+        })
+        .collect();
+
+    // Fork parents are looked up among files that exist under any name that
+    // discovery would load.
+    let known_ids: HashSet<&str> = inspected
+        .iter()
+        .filter(|(path, _)| claude_code::is_session_file_name(path))
+        .filter_map(|(path, _)| path.file_stem()?.to_str())
+        .collect();
+
+    let mut findings: Vec<Finding> = unreadable_dirs
+        .into_iter()
+        .map(|(path, error)| Finding {
+            path,
+            problem: Problem::Unreadable { error },
+            fixable: false,
+        })
+        .collect();
+
+    for (path, health) in &inspected {
+        for problem in file_problems(path, health, &known_ids) {
+            let fixable = local && is_fixable(path, &problem);
+            findings.push(Finding {
+                path: path.clone(),
+                problem,
+                fixable,
+            });
+        }
+    }
+
+    SourceReport {
+        source: source.to_string(),
+        files: inspected.len(),
+        findings,
+    }
+}
+
+fn file_problems(path: &Path, health: &FileHealth, known_ids: &HashSet<&str>) -> Vec<Problem> {
+    if let Some(error) = &health.read_error {
+        return vec![Problem::Unreadable {
+            error: error.clone(),
+        }];
+    }
+    if !claude_code::is_session_file_name(path) {
+        // Stray non-session JSONL (notes, exports) is none of our business,
+        // and subagent transcripts hold their parent's id: renaming one would
+        // clobber or impersonate the parent session.
+        if is_subagent_file(path) || health.sidechain {
+            return Vec::new();
+        }
+        return health
+            .session_id
+            .iter()
+            .map(|id| Problem::Misnamed {
+                session_id: id.clone(),
+                taken: path.with_file_name(format!("{}.jsonl", id)).exists(),
+            })
+            .collect();
+    }
+    if health.bytes == 0 {
+        return vec![Problem::ZeroBytes];
+    }
+
+    let mut problems = Vec::new();
+    if health.bad_lines > 0 {
+        problems.push(Problem::CorruptLines {
+            bad: health.bad_lines,
+            total: health.lines,
+            truncated_tail: health.truncated_tail,
+        });
+    }
+    if health.empty {
+        problems.push(Problem::NoContent);
+    }
+    if let Some(parent) = &health.forked_from
+        && !known_ids.contains(parent.as_str())
+    {
+        problems.push(Problem::MissingParent {
+            parent: parent.clone(),
+        });
+    }
+    problems
+}
+
+/// Legacy subagent transcripts sit beside sessions as `agent-<id>.jsonl`.
+fn is_subagent_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with("agent-"))
+}
+
+/// Where a misnamed file should live, if that name is free.
+fn rename_target(path: &Path, problem: &Problem) -> Option<PathBuf> {
+    let Problem::Misnamed { session_id, .. } = problem else {
+        return None;
+    };
+    if is_subagent_file(path) {
+        return None;
+    }
+    let target = path.with_file_name(format!("{}.jsonl", session_id));
+    (claude_code::is_session_file_name(&target) && !target.exists()).then_some(target)
+}
+
+fn is_fixable(path: &Path, problem: &Problem) -> bool {
+    match problem {
+        Problem::ZeroBytes => true,
+        Problem::Misnamed { .. } => rename_target(path, problem).is_some(),
+        _ => false,
+    }
+}
+
+/// Apply the lossless repair for a fixable finding, recording it in `batch`;
+/// returns what was done.
+pub fn fix(finding: &Finding, batch: &mut undo::Batch) -> Result<String> {
+    let path = &finding.path;
+    match &finding.problem {
+        Problem::ZeroBytes => {
+            // Re-check: the session may have started writing since the scan.
+            let len = fs::metadata(path)
+                .with_context(|| format!("Failed to stat {}", path.display()))?
+                .len();
+            anyhow::ensure!(len == 0, "{} is no longer empty", path.display());
+            batch.trash(path)?;
+            Ok(format!("Deleted {}", path.display()))
+        }
+        problem @ Problem::Misnamed { .. } => {
+            let target = rename_target(path, problem)
+                .with_context(|| format!("No free session name for {}", path.display()))?;
+            fs::rename(path, &target)
+                .with_context(|| format!("Failed to rename {}", path.display()))?;
+            batch.record(undo::Action::Moved {
+                from: path.clone(),
+                to: target.clone(),
+            })?;
+            Ok(format!(
+                "Renamed {} -> {}",
+                path.display(),
+                target.display()
+            ))
+        }
+        _ => anyhow::bail!("No automatic fix for {}", path.display()),
+    }
+}
+
+/// Render the repair-oriented report: one block per source, one line per
+/// finding with its hint, then a summary line.
+pub fn render_report(reports: &[SourceReport]) -> String {
+    let mut out = String::new();
+    for report in reports {
+        out.push_str(&format!(
+            "{}: {} file(s) checked, {} problem(s)\n",
+            report.source,
+            report.files,
+            report.findings.len()
+        ));
+        for f in &report.findings {
+            out.push_str(&format!(
+                "  {:<11} {}\n              {}; {}{}\n",
+                f.problem.label(),
+                f.path.display(),
+                f.problem.detail(),
+                f.problem.hint(),
+                if f.fixable { " [fixable]" } else { "" }
+            ));
+        }
+    }
+
+    let total: usize = reports.iter().map(|r| r.findings.len()).sum();
+    let fixable = reports
+        .iter()
+        .flat_map(|r| &r.findings)
+        .filter(|f| f.fixable)
+        .count();
+    if total == 0 {
+        out.push_str("No problems found\n");
+    } else if fixable > 0 {
+        out.push_str(&format!(
+            "{} problem(s), {} fixable with --fix\n",
+            total, fixable
+        ));
+    } else {
+        out.push_str(&format!("{} problem(s)\n", total));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARENT: &str = "aaaaaaaa-0000-0000-0000-000000000001";
+    const CHILD: &str = "aaaaaaaa-0000-0000-0000-000000000002";
+    const PROMPT: &str =
+        r#"{"type":"user","sessionId":"S","cwd":"/p","message":{"role":"user","content":"hello"}}"#;
+
+    fn write(root: &Path, name: &str, content: &str) -> PathBuf {
+        let dir = root.join("-p");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn problems(report: &SourceReport) -> Vec<(&str, &Problem)> {
+        report
+            .findings
+            .iter()
+            .map(|f| (f.path.file_name().unwrap().to_str().unwrap(), &f.problem))
+            .collect()
+    }
+
+    #[test]
+    fn healthy_sessions_report_nothing() {
+        let tmp = tempfile::tempdir().unwrap();
+        write(tmp.path(), &format!("{}.jsonl", PARENT), PROMPT);
+        let fork = PROMPT.replace(
+            r#""cwd""#,
+            &format!(r#""forkedFrom":{{"sessionId":"{}"}},"cwd""#, PARENT),
+        );
+        write(tmp.path(), &format!("{}.jsonl", CHILD), &fork);
+
+        let report = check_root(tmp.path(), "local");
+        assert_eq!(report.files, 2);
+        assert!(report.findings.is_empty(), "{:?}", report.findings);
+    }
+
+    #[test]
+    fn detects_each_kind_of_damage() {
+        let tmp = tempfile::tempdir().unwrap();
+        write(tmp.path(), &format!("{}.jsonl", PARENT), "");
+        let fork = PROMPT.replace(
+            r#""cwd""#,
+            r#""forkedFrom":{"sessionId":"bbbbbbbb-0000-0000-0000-000000000000"},"cwd""#,
+        );
+        write(
+            tmp.path(),
+            &format!("{}.jsonl", CHILD),
+            &format!("{}\n{{\"type\":\"assist", fork),
+        );
+        write(
+            tmp.path(),
+            "cccccccc-0000-0000-0000-000000000003.jsonl",
+            r#"{"type":"progress"}"#,
+        );
+        let stray = PROMPT.replace(
+            "\"S\"",
+            &format!("\"{}\"", "dddddddd-0000-0000-0000-000000000004"),
+        );
+        write(tmp.path(), "backup copy.jsonl", &stray);
+        write(tmp.path(), "notes.jsonl", r#"{"todo":1}"#);
+
+        let report = check_root(tmp.path(), "local");
+        let found = problems(&report);
+        assert_eq!(found.len(), 5, "{:?}", found);
+        assert!(found.contains(&(&format!("{}.jsonl", PARENT), &Problem::ZeroBytes)));
+        assert!(found.iter().any(|(_, p)| matches!(
+            p,
+            Problem::CorruptLines {
+                bad: 1,
+                total: 2,
+                truncated_tail: true
+            }
+        )));
+        assert!(found.iter().any(|(_, p)| matches!(
+            p,
+            Problem::MissingParent { parent } if parent.starts_with("bbbbbbbb")
+        )));
+        assert!(found.iter().any(|(_, p)| *p == &Problem::NoContent));
+        assert!(
+            found
+                .iter()
+                .any(|(name, p)| *name == "backup copy.jsonl"
+                    && matches!(p, Problem::Misnamed { .. }))
+        );
+    }
+
+    #[test]
+    fn fix_deletes_empty_and_renames_misnamed_locals_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let empty = write(tmp.path(), &format!("{}.jsonl", PARENT), "");
+        let stray = write(
+            tmp.path(),
+            "old.jsonl",
+            &PROMPT.replace("\"S\"", &format!("\"{}\"", CHILD)),
+        );
+
+        let remote = check_root(tmp.path(), "devbox");
+        assert!(remote.findings.iter().all(|f| !f.fixable));
+
+        let local = check_root(tmp.path(), "local");
+        assert!(local.findings.iter().all(|f| f.fixable));
+        let undo_root = tempfile::tempdir().unwrap();
+        let mut batch = undo::Batch::begin_in(undo_root.path(), "fsck --fix");
+        for finding in &local.findings {
+            fix(finding, &mut batch).unwrap();
+        }
+        assert!(!empty.exists());
+        assert!(!stray.exists());
+        assert!(stray.with_file_name(format!("{}.jsonl", CHILD)).exists());
+        assert!(check_root(tmp.path(), "local").findings.is_empty());
+    }
+
+    #[test]
+    fn misnamed_copy_of_an_existing_session_is_a_conflict() {
+        let tmp = tempfile::tempdir().unwrap();
+        let content = PROMPT.replace("\"S\"", &format!("\"{}\"", PARENT));
+        let original = write(tmp.path(), &format!("{}.jsonl", PARENT), &content);
+        write(tmp.path(), "copy.jsonl", &content);
+
+        let report = check_root(tmp.path(), "local");
+        let [finding] = &report.findings[..] else {
+            panic!("{:?}", report.findings);
+        };
+        assert_eq!(
+            finding.problem,
+            Problem::Misnamed {
+                session_id: PARENT.to_string(),
+                taken: true
+            }
+        );
+        assert!(!finding.fixable);
+        assert!(!finding.problem.hint().contains("rename"));
+        let undo_root = tempfile::tempdir().unwrap();
+        let mut batch = undo::Batch::begin_in(undo_root.path(), "fsck --fix");
+        assert!(fix(finding, &mut batch).is_err());
+        assert_eq!(fs::read_to_string(original).unwrap(), content);
+    }
+
+    #[test]
+    fn subagent_transcripts_are_not_misnamed() {
+        let tmp = tempfile::tempdir().unwrap();
+        write(tmp.path(), &format!("{}.jsonl", PARENT), PROMPT);
+        let holds_parent = PROMPT.replace("\"S\"", &format!("\"{}\"", PARENT));
+        let legacy = write(tmp.path(), "agent-1a2b3c4d.jsonl", &holds_parent);
+        let sidechain = write(
+            tmp.path(),
+            "side.jsonl",
+            &holds_parent.replace(r#""cwd""#, r#""isSidechain":true,"cwd""#),
+        );
+
+        let report = check_root(tmp.path(), "local");
+        assert!(report.findings.is_empty(), "{:?}", report.findings);
+        let forced = Finding {
+            path: legacy.clone(),
+            problem: Problem::Misnamed {
+                session_id: CHILD.to_string(),
+                taken: false,
+            },
+            fixable: true,
+        };
+        let undo_root = tempfile::tempdir().unwrap();
+        let mut batch = undo::Batch::begin_in(undo_root.path(), "fsck --fix");
+        assert!(fix(&forced, &mut batch).is_err());
+        assert!(legacy.exists() && sidechain.exists());
+        assert!(!legacy.with_file_name(format!("{}.jsonl", CHILD)).exists());
+    }
+}
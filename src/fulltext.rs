@@ -0,0 +1,549 @@
+//! Persistent full-text index behind `cc-sessions search`.
+//!
+//! Ctrl+S and `--grep` read every transcript on every search, which is fine
+//! for hundreds of sessions and slow for thousands. This index maps each word
+//! of each transcript to the files containing it, so a search only reads the
+//! few transcripts that contain all of its words, to confirm the match:
+//!
+//! ```text
+//! ~/.cache/cc-sessions/search.idx
+//! ```
+//!
+//! Words are maximal runs of alphanumeric characters of the ASCII-lowercased
+//! text Ctrl+S searches. A term's inner words must be indexed words; its
+//! outer ones may be cut off (`oken ref` finds "token refresh"), so any
+//! substring search can be narrowed. Narrowing only ever over-approximates;
+//! `Query::matches_file` has the final say.
+//!
+//! Updates are incremental: files whose fingerprint changed are re-read and
+//! appended as new documents, their old documents and those of deleted files
+//! become tombstones, and the postings are compacted once tombstones
+//! outnumber live documents. `search` refreshes the files it is about to
+//! search; `index` refreshes everything once the index exists.
+//!
+//! The format is a compact binary one (postings are delta-encoded varints),
+//! as JSON would be several times the size of the transcripts' vocabulary.
+//! Bump `VERSION` on any change; unreadable files are rebuilt from scratch.
+
+use crate::claude_code;
+use crate::metadata_cache::{self, Fingerprint};
+use crate::progress::Progress;
+use crate::search::{SearchOptions, SearchQuery};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"CCFT";
+const VERSION: u32 = 1;
+
+/// Longer words (base64 blobs, minified code) aren't indexed; query words
+/// this long don't narrow the search.
+const MAX_WORD_LEN: usize = 64;
+
+pub fn index_path() -> Result<PathBuf> {
+    Ok(crate::profile::scoped(crate::paths::CACHE.dir()?).join("search.idx"))
+}
+
+// =============================================================================
+// Queries
+// =============================================================================
+
+/// A `search` query: words and `"quoted phrases"`, all of which must occur
+/// in a transcript. Leading `cs:` / `w:` / `in:tools` / `-in:tools`
+/// modifiers apply to every term, as in Ctrl+S.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub terms: Vec<SearchQuery>,
+}
+
+impl Query {
+    pub fn parse(raw: &str, defaults: SearchOptions) -> Self {
+        let parsed = SearchQuery::parse(raw, defaults);
+        let mut terms = Vec::new();
+        let mut rest = parsed.pattern.as_str();
+        loop {
+            rest = rest.trim_start();
+            let term = if let Some(quoted) = rest.strip_prefix('"') {
+                // An unterminated quote runs to the end
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                rest = quoted.get(end + 1..).unwrap_or("");
+                &quoted[..end]
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let word = &rest[..end];
+                rest = &rest[end..];
+                word
+            };
+            if !term.trim().is_empty() {
+                terms.push(SearchQuery {
+                    pattern: term.to_string(),
+                    options: parsed.options,
+                });
+            }
+            if rest.is_empty() {
+                break;
+            }
+        }
+        Self { terms }
+    }
+
+    fn options(&self) -> SearchOptions {
+        self.terms.first().map(|t| t.options).unwrap_or_default()
+    }
+
+    /// Read the transcript at `path` and check every term against it.
+    pub fn matches_file(&self, path: &Path) -> bool {
+        let include_tools = self.options().include_tools;
+        if self.terms.iter().all(SearchQuery::index_only) {
+            let text = claude_code::scan_search_text(path, include_tools);
+            self.terms.iter().all(|t| t.matches_lowercased(&text))
+        } else {
+            self.matches_text(&claude_code::transcript_text(path, include_tools))
+        }
+    }
+
+    pub fn matches_text(&self, text: &str) -> bool {
+        self.terms.iter().all(|t| t.matches(text))
+    }
+}
+
+// =============================================================================
+// Index
+// =============================================================================
+
+#[derive(Debug, Default)]
+pub struct FullTextIndex {
+    path: Option<PathBuf>,
+    include_tools: bool,
+    /// Document number → file; `None` for tombstones.
+    docs: Vec<Option<Doc>>,
+    /// Word → ascending document numbers.
+    words: HashMap<String, Vec<u32>>,
+    dirty: bool,
+}
+
+#[derive(Debug)]
+struct Doc {
+    path: PathBuf,
+    fingerprint: Fingerprint,
+}
+
+/// A file whose index entry is missing or out of date.
+pub struct StaleFile {
+    path: PathBuf,
+    fingerprint: Fingerprint,
+}
+
+impl FullTextIndex {
+    /// Whether `search` has ever built the index.
+    pub fn exists() -> bool {
+        index_path().is_ok_and(|path| path.exists())
+    }
+
+    /// Load the index, or start an empty one if it is missing, unreadable,
+    /// built with a different `include_tools`, or `--reindex` was given.
+    pub fn load(include_tools: bool) -> Self {
+        let Ok(path) = index_path() else {
+            return Self::default();
+        };
+        let stored = if metadata_cache::reindexing() {
+            None
+        } else {
+            fs::read(&path)
+                .ok()
+                .and_then(|bytes| decode(&bytes))
+                .filter(|index| index.include_tools == include_tools)
+        };
+        match stored {
+            Some(index) => Self {
+                path: Some(path),
+                ..index
+            },
+            None => Self {
+                path: Some(path),
+                include_tools,
+                dirty: true,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// Files among `targets` that are new or changed since they were
+    /// indexed. Files that can no longer be read are skipped.
+    pub fn stale(&self, targets: &[&Path]) -> Vec<StaleFile> {
+        let indexed: HashMap<&Path, Fingerprint> = self
+            .docs
+            .iter()
+            .flatten()
+            .map(|doc| (doc.path.as_path(), doc.fingerprint))
+            .collect();
+        targets
+            .iter()
+            .filter_map(|&path| {
+                let fingerprint = Fingerprint::of(&fs::metadata(path).ok()?);
+                (indexed.get(path) != Some(&fingerprint)).then(|| StaleFile {
+                    path: path.to_path_buf(),
+                    fingerprint,
+                })
+            })
+            .collect()
+    }
+
+    /// Index `stale` in parallel, bumping `progress` once per file, and drop
+    /// the documents of files that were replaced or deleted.
+    pub fn update(&mut self, stale: Vec<StaleFile>, progress: &Progress) {
+        let replaced: HashSet<&Path> = stale.iter().map(|f| f.path.as_path()).collect();
+        for slot in &mut self.docs {
+            if slot
+                .as_ref()
+                .is_some_and(|doc| replaced.contains(doc.path.as_path()) || !doc.path.exists())
+            {
+                *slot = None;
+                self.dirty = true;
+            }
+        }
+
+        let include_tools = self.include_tools;
+        let extracted: Vec<(StaleFile, Vec<String>)> = stale
+            .into_par_iter()
+            .with_max_len(1)
+            .map(|file| {
+                let text = claude_code::scan_search_text(&file.path, include_tools);
+                let words = words_of(&text);
+                progress.inc();
+                (file, words)
+            })
+            .collect();
+        for (file, words) in extracted {
+            let number = self.docs.len() as u32;
+            self.docs.push(Some(Doc {
+                path: file.path,
+                fingerprint: file.fingerprint,
+            }));
+            for word in words {
+                self.words.entry(word).or_default().push(number);
+            }
+            self.dirty = true;
+        }
+
+        let live = self.docs.iter().flatten().count();
+        if self.docs.len() - live > live {
+            self.compact();
+        }
+    }
+
+    /// Renumber live documents densely and drop tombstones from postings.
+    fn compact(&mut self) {
+        let mut renumbered = vec![None; self.docs.len()];
+        let mut live = Vec::new();
+        for (old, doc) in std::mem::take(&mut self.docs).into_iter().enumerate() {
+            if let Some(doc) = doc {
+                renumbered[old] = Some(live.len() as u32);
+                live.push(Some(doc));
+            }
+        }
+        self.docs = live;
+        self.words.retain(|_, postings| {
+            *postings = postings
+                .iter()
+                .filter_map(|&doc| renumbered[doc as usize])
+                .collect();
+            !postings.is_empty()
+        });
+        self.dirty = true;
+    }
+
+    /// Indexed files that may match `query`: a superset of the matches among
+    /// them. `None` when the index can't narrow it, e.g. an `in:tools` query
+    /// against an index without tool output.
+    pub fn candidates(&self, query: &Query) -> Option<HashSet<&Path>> {
+        if !query
+            .terms
+            .iter()
+            .all(|t| t.covered_by_index(self.include_tools))
+        {
+            return None;
+        }
+        let mut docs: Option<HashSet<u32>> = None;
+        for term in &query.terms {
+            for found in self.docs_with_term(&term.pattern.to_ascii_lowercase()) {
+                docs = Some(match docs {
+                    Some(docs) => docs.intersection(&found).copied().collect(),
+                    None => found,
+                });
+            }
+        }
+        let paths = |numbers: &mut dyn Iterator<Item = u32>| {
+            numbers
+                .filter_map(|n| self.docs[n as usize].as_ref())
+                .map(|doc| doc.path.as_path())
+                .collect()
+        };
+        Some(match docs {
+            Some(docs) => paths(&mut docs.into_iter()),
+            // No word long enough to look up: every file is a candidate
+            None => paths(&mut (0..self.docs.len() as u32)),
+        })
+    }
+
+    /// For each word of `term`, the documents containing a word it may be
+    /// part of. A word at the edge of the term may continue past it.
+    fn docs_with_term(&self, term: &str) -> Vec<HashSet<u32>> {
+        let words: Vec<(usize, &str)> = word_spans(term).collect();
+        words
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, word))| word.len() <= MAX_WORD_LEN)
+            .map(|(i, &(start, word))| {
+                let open_start = i == 0 && start == 0;
+                let open_end = i == words.len() - 1 && start + word.len() == term.len();
+                let postings: Vec<&Vec<u32>> = match (open_start, open_end) {
+                    (false, false) => self.words.get(word).into_iter().collect(),
+                    (start, end) => self
+                        .words
+                        .iter()
+                        .filter(|(indexed, _)| match (start, end) {
+                            (true, true) => indexed.contains(word),
+                            (true, false) => indexed.ends_with(word),
+                            _ => indexed.starts_with(word),
+                        })
+                        .map(|(_, postings)| postings)
+                        .collect(),
+                };
+                postings.into_iter().flatten().copied().collect()
+            })
+            .collect()
+    }
+
+    /// Persist if anything changed since load.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create cache dir: {}", parent.display()))?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, encode(self))
+            .with_context(|| format!("Failed to write search index: {}", tmp.display()))?;
+        fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to replace search index: {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Byte offset and text of each word of `text`.
+fn word_spans(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+/// The distinct indexable words of `text`.
+fn words_of(text: &str) -> Vec<String> {
+    let words: HashSet<&str> = word_spans(text)
+        .map(|(_, word)| word)
+        .filter(|word| word.len() <= MAX_WORD_LEN)
+        .collect();
+    words.into_iter().map(str::to_owned).collect()
+}
+
+// =============================================================================
+// On-disk format
+// =============================================================================
+//
+// MAGIC, VERSION (u32 LE), include_tools (u8), then
+//   doc count, per doc: live (u8) [, path, mtime_ns (u128 LE), size (u64 LE)]
+//   word count, per word: word, posting count, varint deltas
+// Strings are a u32 LE length and UTF-8 bytes; counts are u32 LE.
+
+fn encode(index: &FullTextIndex) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.push(index.include_tools as u8);
+    let put_str = |out: &mut Vec<u8>, s: &str| {
+        out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        out.extend_from_slice(s.as_bytes());
+    };
+
+    out.extend_from_slice(&(index.docs.len() as u32).to_le_bytes());
+    for doc in &index.docs {
+        let Some(doc) = doc else {
+            out.push(0);
+            continue;
+        };
+        out.push(1);
+        put_str(&mut out, &doc.path.to_string_lossy());
+        let (mtime_ns, size) = doc.fingerprint.parts();
+        out.extend_from_slice(&mtime_ns.to_le_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+    }
+
+    out.extend_from_slice(&(index.words.len() as u32).to_le_bytes());
+    for (word, postings) in &index.words {
+        put_str(&mut out, word);
+        out.extend_from_slice(&(postings.len() as u32).to_le_bytes());
+        let mut previous = 0;
+        for &doc in postings {
+            let mut delta = doc - previous;
+            previous = doc;
+            while delta >= 0x80 {
+                out.push((delta as u8 & 0x7f) | 0x80);
+                delta >>= 7;
+            }
+            out.push(delta as u8);
+        }
+    }
+    out
+}
+
+fn decode(bytes: &[u8]) -> Option<FullTextIndex> {
+    let mut r = Reader(bytes);
+    if r.take(MAGIC.len())? != MAGIC || r.u32()? != VERSION {
+        return None;
+    }
+    let include_tools = r.take(1)?[0] == 1;
+
+    let doc_count = r.u32()?;
+    let mut docs = Vec::new();
+    for _ in 0..doc_count {
+        if r.take(1)?[0] == 0 {
+            docs.push(None);
+            continue;
+        }
+        let path = PathBuf::from(r.string()?);
+        let mtime_ns = u128::from_le_bytes(r.take(16)?.try_into().ok()?);
+        let size = u64::from_le_bytes(r.take(8)?.try_into().ok()?);
+        docs.push(Some(Doc {
+            path,
+            fingerprint: Fingerprint::from_parts(mtime_ns, size),
+        }));
+    }
+
+    let word_count = r.u32()?;
+    let mut words = HashMap::with_capacity(word_count as usize);
+    for _ in 0..word_count {
+        let word = r.string()?;
+        let len = r.u32()?;
+        let mut postings = Vec::with_capacity(len as usize);
+        let mut doc = 0u32;
+        for _ in 0..len {
+            doc = doc.checked_add(r.varint()?)?;
+            if doc >= doc_count {
+                return None;
+            }
+            postings.push(doc);
+        }
+        words.insert(word, postings);
+    }
+    Some(FullTextIndex {
+        path: None,
+        include_tools,
+        docs,
+        words,
+        dirty: false,
+    })
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let (head, tail) = self.0.split_at_checked(n)?;
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn varint(&mut self) -> Option<u32> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u32::from(byte & 0x7f).checked_shl(shift)?;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_session(dir: &Path, name: &str, text: &str) -> PathBuf {
+        let path = dir.join(name);
+        let entry = serde_json::json!({
+            "type": "user",
+            "message": {"role": "user", "content": text},
+        });
+        fs::write(&path, format!("{}\n", entry)).unwrap();
+        path
+    }
+
+    fn matching(index: &FullTextIndex, raw: &str) -> Vec<PathBuf> {
+        let query = Query::parse(raw, SearchOptions::default());
+        let mut found: Vec<PathBuf> = index
+            .candidates(&query)
+            .unwrap()
+            .into_iter()
+            .filter(|path| query.matches_file(path))
+            .map(Path::to_path_buf)
+            .collect();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn query_splits_words_and_quoted_phrases() {
+        let query = Query::parse(
+            r#"cs:auth "token refresh"  "open"#,
+            SearchOptions::default(),
+        );
+        let patterns: Vec<&str> = query.terms.iter().map(|t| t.pattern.as_str()).collect();
+        assert_eq!(patterns, ["auth", "token refresh", "open"]);
+        assert!(query.terms.iter().all(|t| t.options.case_sensitive));
+    }
+
+    #[test]
+    fn finds_words_phrases_and_fragments_and_survives_a_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = write_session(dir.path(), "a.jsonl", "Fix the token refresh in auth.rs");
+        let b = write_session(dir.path(), "b.jsonl", "Refresh the token cache");
+        let mut index = FullTextIndex::default();
+        let stale = index.stale(&[&a, &b]);
+        index.update(stale, &Progress::new("indexed", 2));
+
+        assert_eq!(matching(&index, "token refresh"), [a.clone(), b.clone()]);
+        assert_eq!(matching(&index, r#""token refresh""#), vec![a.clone()]);
+        assert_eq!(matching(&index, r#""oken refr""#), vec![a.clone()]);
+        assert_eq!(matching(&index, "auth.rs cache"), Vec::<PathBuf>::new());
+
+        let mut index = decode(&encode(&index)).unwrap();
+        assert_eq!(matching(&index, "cache"), vec![b.clone()]);
+
+        // A changed file is re-read; its old words stop matching
+        write_session(dir.path(), "b.jsonl", "Rename the queue");
+        let stale = index.stale(&[&a, &b]);
+        assert_eq!(stale.len(), 1);
+        index.update(stale, &Progress::new("indexed", 1));
+        assert_eq!(matching(&index, "cache"), Vec::<PathBuf>::new());
+        assert_eq!(matching(&index, "queue"), [b]);
+    }
+}
@@ -0,0 +1,121 @@
+//! OSC 8 terminal hyperlinks for list output.
+//!
+//! `ESC ] 8 ; ; URL ESC \ text ESC ] 8 ; ; ESC \` renders `text` as a link in
+//! terminals that understand it. Terminals that don't may print the escape
+//! bytes literally, so links are only emitted when the terminal is known to
+//! cope (or when forced with `FORCE_HYPERLINK=1`).
+
+use std::io::IsTerminal;
+use std::path::Path;
+
+/// Whether stdout is a terminal that renders OSC 8 links.
+pub fn enabled() -> bool {
+    std::io::stdout().is_terminal() && supported_by_env(|k| std::env::var(k).ok())
+}
+
+/// Environment-based detection, split out for testing. `FORCE_HYPERLINK`
+/// wins either way; otherwise look for terminals known to support OSC 8.
+fn supported_by_env(var: impl Fn(&str) -> Option<String>) -> bool {
+    if let Some(force) = var("FORCE_HYPERLINK") {
+        return force != "0";
+    }
+    if var("TERM").as_deref() == Some("dumb") {
+        return false;
+    }
+    if let Some(program) = var("TERM_PROGRAM")
+        && matches!(
+            program.as_str(),
+            "iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper"
+        )
+    {
+        return true;
+    }
+    // VTE (GNOME Terminal, Tilix, ...) gained OSC 8 in 0.50.
+    if var("VTE_VERSION").and_then(|v| v.parse::<u32>().ok()) >= Some(5000) {
+        return true;
+    }
+    [
+        "WT_SESSION",
+        "KITTY_WINDOW_ID",
+        "KONSOLE_VERSION",
+        "DOMTERM",
+    ]
+    .iter()
+    .any(|k| var(k).is_some())
+}
+
+/// `file://` URL for an absolute path, percent-encoding anything outside the
+/// unreserved set (spaces, `#`, `%`, non-ASCII bytes).
+pub fn file_url(path: &Path) -> String {
+    let mut url = String::from("file://");
+    for &b in path.to_string_lossy().as_bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~".contains(&b) {
+            url.push(b as char);
+        } else {
+            url.push_str(&format!("%{:02X}", b));
+        }
+    }
+    url
+}
+
+/// Wrap `text` in an OSC 8 link to `url`.
+pub fn link(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// `text` left-aligned in `width` columns, linked when `url` is given. The
+/// padding stays outside the link so it isn't underlined.
+pub fn padded(url: Option<&str>, text: &str, width: usize) -> String {
+    let pad = " ".repeat(width.saturating_sub(text.chars().count()));
+    match url {
+        Some(url) => format!("{}{}", link(url, text), pad),
+        None => format!("{}{}", text, pad),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let map: HashMap<String, String> = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |k| map.get(k).cloned()
+    }
+
+    #[test]
+    fn detects_known_terminals_and_honors_force() {
+        assert!(supported_by_env(env(&[("TERM_PROGRAM", "iTerm.app")])));
+        assert!(supported_by_env(env(&[("VTE_VERSION", "6003")])));
+        assert!(!supported_by_env(env(&[("VTE_VERSION", "4200")])));
+        assert!(!supported_by_env(env(&[(
+            "TERM_PROGRAM",
+            "Apple_Terminal"
+        )])));
+        assert!(supported_by_env(env(&[("FORCE_HYPERLINK", "1")])));
+        assert!(!supported_by_env(env(&[
+            ("FORCE_HYPERLINK", "0"),
+            ("WT_SESSION", "x")
+        ])));
+    }
+
+    #[test]
+    fn file_url_percent_encodes() {
+        assert_eq!(
+            file_url(Path::new("/Users/me/my proj/#1")),
+            "file:///Users/me/my%20proj/%231"
+        );
+    }
+
+    #[test]
+    fn padding_sits_outside_the_link() {
+        assert_eq!(padded(None, "api", 6), "api   ");
+        assert_eq!(
+            padded(Some("file:///x"), "api", 6),
+            "\x1b]8;;file:///x\x1b\\api\x1b]8;;\x1b\\   "
+        );
+    }
+}
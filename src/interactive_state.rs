@@ -5,6 +5,12 @@ pub struct InteractiveState {
     search_pattern: Option<String>,
     search_results: Option<HashSet<String>>,
     focus_stack: Vec<String>,
+    /// Forks shown inline under their parents instead of by drill-down.
+    flat: bool,
+    /// Only sessions from this source (`SessionSource::display_name`).
+    source_filter: Option<String>,
+    /// Only sessions of this project (`Session::project`).
+    project_filter: Option<String>,
 }
 
 #[derive(Debug)]
@@ -25,6 +31,16 @@ pub enum Action {
     Enter {
         selected_id: Option<String>,
     },
+    ToggleFlat,
+    /// Step the source filter: all → each of `sources` in order → all.
+    CycleSource {
+        sources: Vec<String>,
+    },
+    /// Show only `project` (the highlighted session's), or everything again
+    /// if a project filter is already set.
+    ToggleProject {
+        project: Option<String>,
+    },
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -36,6 +52,17 @@ pub enum Effect {
 }
 
 impl InteractiveState {
+    pub fn new(flat: bool) -> Self {
+        Self {
+            flat,
+            ..Self::default()
+        }
+    }
+
+    pub fn flat(&self) -> bool {
+        self.flat
+    }
+
     pub fn search_pattern(&self) -> Option<&String> {
         self.search_pattern.as_ref()
     }
@@ -44,6 +71,14 @@ impl InteractiveState {
         self.search_results.as_ref()
     }
 
+    pub fn source_filter(&self) -> Option<&String> {
+        self.source_filter.as_ref()
+    }
+
+    pub fn project_filter(&self) -> Option<&String> {
+        self.project_filter.as_ref()
+    }
+
     pub fn focus(&self) -> Option<&String> {
         self.focus_stack.last()
     }
@@ -62,11 +97,17 @@ impl InteractiveState {
                     return Effect::Continue;
                 }
 
-                if !self.focus_stack.is_empty() {
+                if !self.flat && !self.focus_stack.is_empty() {
                     self.focus_stack.clear();
                     return Effect::Continue;
                 }
 
+                if self.source_filter.is_some() || self.project_filter.is_some() {
+                    self.source_filter = None;
+                    self.project_filter = None;
+                    return Effect::Continue;
+                }
+
                 Effect::Exit
             }
             Action::CtrlS { query } => {
@@ -90,7 +131,7 @@ impl InteractiveState {
                 selected_id,
                 has_children,
             } => {
-                if self.search_results.is_some() {
+                if self.search_results.is_some() || self.flat {
                     return Effect::Continue;
                 }
                 let Some(selected_id) = selected_id else {
@@ -108,7 +149,7 @@ impl InteractiveState {
                 Effect::Continue
             }
             Action::Left => {
-                if self.search_results.is_some() {
+                if self.search_results.is_some() || self.flat {
                     return Effect::Continue;
                 }
                 self.focus_stack.pop();
@@ -120,6 +161,34 @@ impl InteractiveState {
                 };
                 Effect::Select { session_id }
             }
+            // The focus stack is kept, so toggling back returns to the same
+            // subtree.
+            Action::ToggleFlat => {
+                self.flat = !self.flat;
+                Effect::Continue
+            }
+            // A filter change can hide the focused subtree, so it starts
+            // over from the root view.
+            Action::CycleSource { sources } => {
+                let next = match &self.source_filter {
+                    None => 0,
+                    Some(current) => sources
+                        .iter()
+                        .position(|s| s == current)
+                        .map_or(sources.len(), |i| i + 1),
+                };
+                self.source_filter = sources.into_iter().nth(next);
+                self.focus_stack.clear();
+                Effect::Continue
+            }
+            Action::ToggleProject { project } => {
+                self.project_filter = match self.project_filter {
+                    Some(_) => None,
+                    None => project,
+                };
+                self.focus_stack.clear();
+                Effect::Continue
+            }
         }
     }
 }
@@ -219,4 +288,61 @@ mod tests {
         state.apply(Action::Left);
         assert!(state.focus().is_none());
     }
+
+    #[test]
+    fn source_filter_cycles_through_sources_and_esc_clears_filters() {
+        let mut state = InteractiveState::default();
+        let sources = || vec!["local".to_string(), "devbox".to_string()];
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            state.apply(Action::CycleSource { sources: sources() });
+            seen.push(state.source_filter().cloned());
+        }
+        assert_eq!(
+            seen,
+            [Some("local".to_string()), Some("devbox".to_string()), None]
+        );
+
+        state.push_focus_for_test("root");
+        state.apply(Action::CycleSource { sources: sources() });
+        assert!(state.focus().is_none());
+        state.apply(Action::ToggleProject {
+            project: Some("api".to_string()),
+        });
+        assert_eq!(state.project_filter().map(String::as_str), Some("api"));
+
+        assert_eq!(state.apply(Action::Esc), Effect::Continue);
+        assert!(state.source_filter().is_none() && state.project_filter().is_none());
+        assert_eq!(state.apply(Action::Esc), Effect::Exit);
+
+        state.apply(Action::ToggleProject {
+            project: Some("api".to_string()),
+        });
+        state.apply(Action::ToggleProject {
+            project: Some("web".to_string()),
+        });
+        assert!(state.project_filter().is_none());
+    }
+
+    #[test]
+    fn flat_view_disables_drill_down_and_keeps_focus_for_later() {
+        let mut state = InteractiveState::new(false);
+        state.push_focus_for_test("root");
+        state.apply(Action::ToggleFlat);
+        assert!(state.flat());
+
+        state.apply(Action::Right {
+            selected_id: Some("child".to_string()),
+            has_children: true,
+        });
+        state.apply(Action::Left);
+        assert_eq!(state.focus().map(String::as_str), Some("root"));
+        // Nothing to back out of in the flat view.
+        assert_eq!(state.apply(Action::Esc), Effect::Exit);
+
+        state.apply(Action::ToggleFlat);
+        assert!(!state.flat());
+        assert_eq!(state.apply(Action::Esc), Effect::Continue);
+        assert!(state.focus().is_none());
+    }
 }
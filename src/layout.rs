@@ -0,0 +1,462 @@
+//! Column layout for `--list` and picker rows.
+//!
+//! Each view (list table, picker) has an ordered set of columns, each with a
+//! min/max width. Fixed columns size to their widest cell within those
+//! bounds; SUMMARY comes last and takes whatever the terminal has left.
+//! Both are configurable:
+//!
+//! ```toml
+//! [layout.list]
+//! columns = ["modified", "turns", "project", "summary"]
+//!
+//! [layout.list.widths]
+//! project = { max = 30 }
+//! summary = { max = 100 }
+//!
+//! [layout.picker.widths]
+//! project = { min = 16 }
+//! ```
+//!
+//! `--debug` adds the ID (and, in the list, FORK) column when the configured
+//! set doesn't already include it.
+
+use crate::session::Session;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Id,
+    Created,
+    Modified,
+    Resumed,
+    Turns,
+    Activity,
+    Permissions,
+    Fork,
+    Source,
+    Owner,
+    Project,
+    Branch,
+    Summary,
+}
+
+impl Column {
+    fn header(self, view: View) -> &'static str {
+        match (self, view) {
+            (Column::Id, _) => "ID",
+            (Column::Created, View::List) => "CREAT",
+            (Column::Created, View::Picker) => "CRE",
+            (Column::Modified, _) => "MOD",
+            (Column::Resumed, View::List) => "RESUM",
+            (Column::Resumed, View::Picker) => "RES",
+            (Column::Turns, _) => "MSG",
+            (Column::Activity, _) => "ACTIVITY",
+            (Column::Permissions, _) => "PERM",
+            (Column::Fork, _) => "FORK",
+            (Column::Source, _) => "SOURCE",
+            (Column::Owner, _) => "OWNER",
+            (Column::Project, _) => "PROJECT",
+            (Column::Branch, _) => "BRANCH",
+            (Column::Summary, _) => "SUMMARY",
+        }
+    }
+
+    /// Names keep their distinctive head and tail when cut; everything else
+    /// is simply truncated.
+    fn elides_middle(self) -> bool {
+        matches!(
+            self,
+            Column::Project | Column::Source | Column::Owner | Column::Branch
+        )
+    }
+}
+
+/// Width bounds for one column. Either end may be left open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Width {
+    pub min: Option<usize>,
+    pub max: Option<usize>,
+}
+
+/// `[layout.list]` / `[layout.picker]`: which columns appear and how wide.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ViewConfig {
+    pub columns: Option<Vec<Column>>,
+    #[serde(default)]
+    pub widths: HashMap<Column, Width>,
+}
+
+impl ViewConfig {
+    /// This config with `column` shown after `after` (or first) if it isn't
+    /// already, e.g. so `--sort resumed` shows what it sorts by.
+    pub fn including(&self, view: View, column: Column, after: Column) -> ViewConfig {
+        let mut columns = self
+            .columns
+            .clone()
+            .unwrap_or_else(|| builtin(view).iter().map(|&(c, ..)| c).collect());
+        if !columns.contains(&column) {
+            let at = columns
+                .iter()
+                .position(|&c| c == after)
+                .map_or(0, |i| i + 1);
+            columns.insert(at, column);
+        }
+        ViewConfig {
+            columns: Some(columns),
+            widths: self.widths.clone(),
+        }
+    }
+}
+
+/// `[layout]` config section.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutConfig {
+    #[serde(default)]
+    pub list: ViewConfig,
+    #[serde(default)]
+    pub picker: ViewConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    List,
+    Picker,
+}
+
+/// Built-in columns and bounds per view, matching the historical output.
+fn builtin(view: View) -> &'static [(Column, usize, Option<usize>)] {
+    match view {
+        View::List => &[
+            (Column::Created, 6, Some(6)),
+            (Column::Modified, 6, Some(6)),
+            (Column::Source, 8, Some(16)),
+            (Column::Project, 16, Some(24)),
+            (Column::Summary, 20, None),
+        ],
+        View::Picker => &[
+            (Column::Created, 4, Some(4)),
+            (Column::Modified, 4, Some(4)),
+            (Column::Turns, 3, Some(5)),
+            (Column::Source, 6, Some(12)),
+            (Column::Project, 12, Some(12)),
+            (Column::Summary, 20, None),
+        ],
+    }
+}
+
+fn builtin_bounds(view: View, column: Column) -> (usize, Option<usize>) {
+    builtin(view)
+        .iter()
+        .find(|(c, ..)| *c == column)
+        .map(|&(_, min, max)| (min, max))
+        .unwrap_or(match column {
+            Column::Id if view == View::List => (36, Some(36)),
+            Column::Id => (5, Some(5)),
+            Column::Fork => (4, Some(4)),
+            Column::Activity => (8, Some(8)),
+            Column::Permissions => (4, Some(9)),
+            Column::Resumed if view == View::List => (6, Some(6)),
+            Column::Resumed => (4, Some(4)),
+            Column::Turns => (3, Some(5)),
+            Column::Owner => (5, Some(12)),
+            Column::Branch if view == View::List => (6, Some(20)),
+            Column::Branch => (6, Some(12)),
+            _ => (4, None),
+        })
+}
+
+/// One column with its resolved width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    pub column: Column,
+    pub width: usize,
+    min: usize,
+    max: Option<usize>,
+}
+
+/// Resolved columns for one view.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    view: View,
+    slots: Vec<Slot>,
+    /// Columns taken before the first slot (the picker's tree glyph).
+    lead: usize,
+}
+
+impl Layout {
+    pub fn new(view: View, config: &ViewConfig, debug: bool) -> Self {
+        let mut columns: Vec<Column> = config
+            .columns
+            .clone()
+            .unwrap_or_else(|| builtin(view).iter().map(|&(c, ..)| c).collect());
+        // SUMMARY always closes the row so it can take the remaining width.
+        let mut seen = std::collections::HashSet::new();
+        columns.retain(|&c| c != Column::Summary && seen.insert(c));
+        if debug {
+            let after_modified = columns
+                .iter()
+                .position(|&c| c == Column::Modified)
+                .map_or(usize::MAX, |i| i + 1);
+            let mut add = |column, at: usize| {
+                if !columns.contains(&column) {
+                    columns.insert(at.min(columns.len()), column);
+                }
+            };
+            match view {
+                View::List => {
+                    add(Column::Fork, after_modified);
+                    add(Column::Permissions, usize::MAX);
+                    add(Column::Id, usize::MAX);
+                }
+                View::Picker => add(Column::Id, 0),
+            }
+        }
+        columns.push(Column::Summary);
+
+        let slots = columns
+            .into_iter()
+            .map(|column| {
+                let (mut min, mut max) = builtin_bounds(view, column);
+                if let Some(width) = config.widths.get(&column) {
+                    max = width.max.or(max);
+                    // A configured max below the built-in min lowers the min
+                    min = width.min.unwrap_or(min.min(width.max.unwrap_or(min)));
+                }
+                let max = max.map(|m| m.max(min));
+                Slot {
+                    column,
+                    width: min,
+                    min,
+                    max,
+                }
+            })
+            .collect();
+        Self {
+            view,
+            slots,
+            lead: if view == View::Picker { 2 } else { 0 },
+        }
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    pub fn has(&self, column: Column) -> bool {
+        self.slots.iter().any(|s| s.column == column)
+    }
+
+    /// Widen fixed columns to their widest cell across `sessions`, within
+    /// each column's bounds.
+    pub fn fit<'a>(
+        &mut self,
+        sessions: impl IntoIterator<Item = &'a Session>,
+        cell: impl Fn(&Session, Column) -> String,
+    ) {
+        for session in sessions {
+            for slot in self
+                .slots
+                .iter_mut()
+                .filter(|s| s.column != Column::Summary)
+            {
+                let len = cell(session, slot.column).chars().count();
+                slot.width = slot.width.max(len.min(slot.max.unwrap_or(usize::MAX)));
+            }
+        }
+    }
+
+    /// Width used by everything before SUMMARY, including separators.
+    fn fixed_width(&self) -> usize {
+        self.lead
+            + self
+                .slots
+                .iter()
+                .filter(|s| s.column != Column::Summary)
+                .map(|s| s.width + 1)
+                .sum::<usize>()
+    }
+
+    /// Size SUMMARY to fill `total` columns (clamped to its bounds), or to
+    /// `fallback` when the total width isn't known.
+    pub fn fit_summary(&mut self, total: Option<usize>, fallback: usize) {
+        let fixed = self.fixed_width();
+        if let Some(slot) = self.slots.iter_mut().find(|s| s.column == Column::Summary) {
+            let want = total.map_or(fallback, |t| t.saturating_sub(fixed));
+            slot.width = want.min(slot.max.unwrap_or(usize::MAX)).max(slot.min);
+        }
+    }
+
+    /// Width of SUMMARY after `fit_summary`.
+    pub fn summary_width(&self) -> usize {
+        self.slots
+            .iter()
+            .find(|s| s.column == Column::Summary)
+            .map_or(0, |s| s.width)
+    }
+
+    /// Character positions `column` occupies in a rendered row (including
+    /// the picker's lead), without the trailing separator.
+    pub fn char_range(&self, column: Column) -> Option<std::ops::Range<usize>> {
+        let mut start = self.lead;
+        for slot in &self.slots {
+            if slot.column == column {
+                return Some(start..start + slot.width);
+            }
+            start += slot.width + 1;
+        }
+        None
+    }
+
+    /// Full row width, for rulers.
+    pub fn total_width(&self) -> usize {
+        self.fixed_width() + self.summary_width()
+    }
+
+    /// Header row (without the picker's lead).
+    pub fn header(&self) -> String {
+        let mut out = String::new();
+        for slot in &self.slots {
+            if slot.column == Column::Summary {
+                out.push_str(slot.column.header(self.view));
+            } else {
+                out.push_str(&slot.pad(slot.column.header(self.view)));
+                out.push(' ');
+            }
+        }
+        out
+    }
+}
+
+impl Slot {
+    /// `text` cut to this column's width (without padding).
+    pub fn clip<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.column.elides_middle() {
+            elide_middle(text, self.width)
+        } else if text.chars().count() > self.width {
+            Cow::Owned(text.chars().take(self.width).collect())
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /// `text` clipped and padded to exactly this column's width. Turn counts
+    /// are right-aligned.
+    pub fn pad(&self, text: &str) -> String {
+        let text = self.clip(text);
+        if self.column == Column::Turns {
+            format!("{:>w$}", text, w = self.width)
+        } else {
+            format!("{:<w$}", text, w = self.width)
+        }
+    }
+}
+
+/// Middle-elide a string to at most `max` chars. Keeps roughly equal head and
+/// tail, inserts `…` between them. Returns a `Cow` to avoid allocating when
+/// the input already fits.
+pub fn elide_middle(s: &str, max: usize) -> Cow<'_, str> {
+    let chars: Vec<char> = s.chars().collect();
+    if chars.len() <= max {
+        return Cow::Borrowed(s);
+    }
+    if max == 0 {
+        return Cow::Borrowed("");
+    }
+    let head = (max - 1) / 2;
+    let tail = max - 1 - head;
+    let mut out = String::with_capacity(max);
+    out.extend(&chars[..head]);
+    out.push('…');
+    out.extend(&chars[chars.len() - tail..]);
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elide_middle_passthrough_when_fits() {
+        assert_eq!(elide_middle("short", 12), "short");
+        assert_eq!(elide_middle("exactly-12ch", 12), "exactly-12ch");
+    }
+
+    #[test]
+    fn elide_middle_shortens_long_names() {
+        let out = elide_middle("claude-cli-internal", 12);
+        assert_eq!(out.chars().count(), 12);
+        assert!(out.contains('…'));
+        // Keeps head and tail readable
+        assert!(out.starts_with("claud"));
+        assert!(out.ends_with("ternal"));
+    }
+
+    #[test]
+    fn configured_columns_and_bounds_replace_builtins() {
+        let config: ViewConfig = toml::from_str(
+            "columns = [\"summary\", \"modified\", \"project\"]\n\
+             widths = { project = { min = 4, max = 8 }, summary = { max = 30 } }",
+        )
+        .unwrap();
+        let mut layout = Layout::new(View::List, &config, true);
+        let columns: Vec<Column> = layout.slots().iter().map(|s| s.column).collect();
+        // --debug adds FORK, PERM, and ID; SUMMARY is always last
+        assert_eq!(
+            columns,
+            [
+                Column::Modified,
+                Column::Fork,
+                Column::Project,
+                Column::Permissions,
+                Column::Id,
+                Column::Summary
+            ]
+        );
+
+        let mut long = Session::fixture("a");
+        long.project = "a-very-long-project".to_string();
+        let short = Session::fixture("b");
+        layout.fit([&long, &short], |s, c| match c {
+            Column::Project => s.project.clone(),
+            _ => String::new(),
+        });
+        assert_eq!(layout.slots()[2].width, 8);
+        assert_eq!(layout.slots()[2].pad(&long.project), "a-v…ject");
+
+        layout.fit_summary(Some(500), 50);
+        assert_eq!(layout.summary_width(), 30);
+    }
+
+    #[test]
+    fn summary_takes_remaining_width_with_a_floor() {
+        let mut layout = Layout::new(View::Picker, &ViewConfig::default(), false);
+        // lead 2 + CRE 5 + MOD 5 + MSG 4 + SOURCE 7 + PROJECT 13
+        layout.fit_summary(Some(200), 80);
+        assert_eq!(layout.summary_width(), 164);
+        layout.fit_summary(Some(40), 80);
+        assert_eq!(layout.summary_width(), 20);
+        layout.fit_summary(None, 80);
+        assert_eq!(layout.summary_width(), 80);
+
+        let resumed =
+            ViewConfig::default().including(View::Picker, Column::Resumed, Column::Modified);
+        assert_eq!(
+            Layout::new(View::Picker, &resumed, false).header(),
+            "CRE  MOD  RES  MSG SOURCE PROJECT      SUMMARY"
+        );
+
+        let mut debug = Layout::new(View::Picker, &ViewConfig::default(), true);
+        debug.fit_summary(Some(200), 80);
+        assert_eq!(debug.summary_width(), 158);
+        assert_eq!(
+            debug.header(),
+            "ID    CRE  MOD  MSG SOURCE PROJECT      SUMMARY"
+        );
+    }
+}
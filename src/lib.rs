@@ -0,0 +1,214 @@
+//! Discover, search, and sync Claude Code sessions.
+//!
+//! The `cc-sessions` binary is a CLI over this library; other tools (a
+//! statusline widget, an editor plugin) can use the same discovery without
+//! shelling out and parsing tables:
+//!
+//! ```no_run
+//! let config = cc_sessions::remote::load_config()?;
+//! let discovery = cc_sessions::claude_code::find_all_sessions_with_summary(&config, None, false)?;
+//! for session in &discovery.sessions {
+//!     println!("{} {}", session.id, cc_sessions::format_session_desc(session, 60));
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The documented modules are the supported surface:
+//!
+//! - [`session`]: `Session`, `SessionSource`, transcript `Message`s
+//! - [`claude_code`]: discovery (`find_all_sessions_with_summary`), transcript
+//!   reading (`read_messages`, `transcript_text`)
+//! - [`search`] and [`fulltext`]: query parsing, matching, the persistent index
+//! - [`remote`]: config loading and remote sync (`sync_all`, `sync_if_stale`)
+//! - [`export`]: the JSON shapes of `export` and `--list --json`
+//!
+//! Everything else exists for the CLI and is hidden from the docs; it may
+//! change in any release. Discovery reads and writes the same caches as the
+//! CLI, so both stay warm. Process-wide settings the CLI applies from flags
+//! and config (`profile::activate`, `claude_code::set_claude_dir`,
+//! `plain::enable`) default to the CLI's defaults when left alone.
+
+pub mod claude_code;
+pub mod export;
+pub mod fulltext;
+pub mod remote;
+pub mod search;
+pub mod session;
+
+#[doc(hidden)]
+pub mod archive;
+#[doc(hidden)]
+pub mod completions;
+#[doc(hidden)]
+pub mod config_edit;
+#[doc(hidden)]
+pub mod config_migration;
+#[doc(hidden)]
+pub mod dates;
+#[doc(hidden)]
+pub mod digest;
+#[doc(hidden)]
+pub mod follow;
+#[doc(hidden)]
+pub mod fsck;
+#[doc(hidden)]
+pub mod hyperlink;
+#[doc(hidden)]
+pub mod interactive_state;
+#[doc(hidden)]
+pub mod layout;
+#[doc(hidden)]
+pub mod live_preview;
+#[doc(hidden)]
+pub mod message_classification;
+#[doc(hidden)]
+pub mod metadata_cache;
+#[doc(hidden)]
+pub mod partial_json;
+#[doc(hidden)]
+pub mod paths;
+#[doc(hidden)]
+pub mod picker;
+#[doc(hidden)]
+pub mod plain;
+#[doc(hidden)]
+pub mod preview_cache;
+#[doc(hidden)]
+pub mod pricing;
+#[doc(hidden)]
+pub mod profile;
+#[doc(hidden)]
+pub mod progress;
+#[doc(hidden)]
+pub mod ranking;
+#[doc(hidden)]
+pub mod redact;
+#[doc(hidden)]
+pub mod repo;
+#[doc(hidden)]
+pub mod resume;
+#[doc(hidden)]
+pub mod share;
+#[doc(hidden)]
+pub mod state;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod team;
+#[doc(hidden)]
+pub mod timing;
+#[doc(hidden)]
+pub mod top;
+#[doc(hidden)]
+pub mod undo;
+
+use session::Session;
+
+// =============================================================================
+// Display helpers (shared by the CLI and several modules)
+// =============================================================================
+
+/// Format session description: name (★) > tag (#) > summary > first_message,
+/// after a ⧖ for archived sessions. Plain mode spells the marks out.
+pub fn format_session_desc(session: &Session, max_chars: usize) -> String {
+    if session.archived {
+        let mark = if plain::enabled() {
+            "[archived]"
+        } else {
+            "⧖"
+        };
+        let width = max_chars.saturating_sub(mark.chars().count() + 1);
+        return format!("{} {}", mark, format_session_desc_unmarked(session, width));
+    }
+    format_session_desc_unmarked(session, max_chars)
+}
+
+fn format_session_desc_unmarked(session: &Session, max_chars: usize) -> String {
+    let star = if plain::enabled() { "[named]" } else { "★" };
+    let label = match (&session.name, &session.tag) {
+        (Some(name), Some(tag)) => Some(format!("{} {} #{}", star, name, tag)),
+        (Some(name), None) => Some(format!("{} {}", star, name)),
+        (None, Some(tag)) => Some(format!("#{}", tag)),
+        (None, None) => None,
+    };
+
+    if let Some(label) = label {
+        let label_len = label.chars().count();
+        if label_len >= max_chars {
+            return label.chars().take(max_chars).collect();
+        }
+        // Append summary if there's room for " - " + at least 10 chars
+        if let Some(summary) = &session.summary
+            && max_chars > label_len + 13
+        {
+            let remaining = max_chars - label_len - 3;
+            return format!(
+                "{} - {}",
+                label,
+                summary.chars().take(remaining).collect::<String>()
+            );
+        }
+        return label;
+    }
+
+    session
+        .summary
+        .as_deref()
+        .or(session.first_message.as_deref())
+        .map(|s| s.chars().take(max_chars).collect())
+        .unwrap_or_default()
+}
+
+/// Normalize text for display: collapse whitespace, strip markdown, truncate gracefully
+pub fn normalize_summary(text: &str, max_chars: usize) -> String {
+    // Collapse whitespace and build directly into the output buffer — stop
+    // collecting once we're past max_chars (summary inputs can be very long).
+    let mut normalized = String::with_capacity(max_chars.min(text.len()) + 4);
+    let mut words = text.split_whitespace();
+    if let Some(first) = words.next() {
+        normalized.push_str(first);
+        for w in words {
+            normalized.push(' ');
+            normalized.push_str(w);
+            if normalized.len() > max_chars * 4 {
+                break;
+            }
+        }
+    }
+
+    let stripped = normalized.trim_start_matches(['#', '*']).trim_start();
+
+    if stripped.chars().count() <= max_chars {
+        return stripped.to_owned();
+    }
+
+    let truncated: String = stripped.chars().take(max_chars).collect();
+    let break_point = truncated
+        .rfind(' ')
+        .filter(|&i| i > max_chars / 2)
+        .unwrap_or(truncated.len());
+
+    format!("{}...", &truncated[..break_point])
+}
+
+/// Escape a string for safe inclusion in single-quoted shell argument.
+/// Handles single quotes by ending the quote, adding escaped quote, reopening.
+/// Only used for remote SSH commands where shell invocation is unavoidable.
+pub fn shell_escape(s: &str) -> String {
+    s.replace("'", "'\\''")
+}
+
+// =============================================================================
+// ANSI Colors (shared across preview functions)
+// =============================================================================
+
+pub mod colors {
+    pub const CYAN: &str = "\x1b[36m";
+    pub const YELLOW: &str = "\x1b[33m";
+    pub const RED: &str = "\x1b[31m";
+    pub const GREEN: &str = "\x1b[32m";
+    pub const DIM: &str = "\x1b[2m";
+    pub const BOLD: &str = "\x1b[1m";
+    pub const BOLD_INVERSE: &str = "\x1b[1;7m";
+    pub const RESET: &str = "\x1b[0m";
+}
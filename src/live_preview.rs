@@ -0,0 +1,151 @@
+//! Fresh previews of remote sessions whose cache is stale
+//! (`settings.live_preview`).
+//!
+//! A remote's cached transcripts are only as new as its last sync. When that
+//! is older than `stale_threshold`, the picker fetches just the bytes written
+//! after the cached copy ends (`tail -c +N` over SSH) and shows their
+//! messages above the cached preview. Skim renders previews on its UI
+//! thread, so a fetch gives up after `TIMEOUT`, and a remote that failed is
+//! left alone for `REFRESH` instead of stalling every row it owns.
+
+use crate::remote::{self, RemoteConfig, Settings};
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long the picker waits for a remote before showing the cache alone.
+pub const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a fetch (or a failure) is reused before asking again.
+const REFRESH: Duration = Duration::from_secs(30);
+
+/// At most this much new data is fetched; older new messages are dropped.
+const MAX_BYTES: u64 = 512 * 1024;
+
+/// What a stale remote had beyond its cached copy of a transcript.
+#[derive(Debug)]
+pub struct Fetched {
+    /// The remote's last successful sync, if it ever synced
+    pub synced: Option<SystemTime>,
+    /// JSONL written since the cached copy, possibly starting mid-line
+    pub result: std::result::Result<Vec<u8>, String>,
+}
+
+pub struct LivePreviews {
+    settings: Settings,
+    remotes: HashMap<String, RemoteConfig>,
+    fetched: Mutex<HashMap<PathBuf, (Instant, Arc<Fetched>)>>,
+    failed: Mutex<HashMap<String, (Instant, String)>>,
+}
+
+impl LivePreviews {
+    pub fn new(config: &remote::Config) -> Self {
+        Self {
+            settings: config.settings.clone(),
+            remotes: config.remotes.clone(),
+            fetched: Mutex::default(),
+            failed: Mutex::default(),
+        }
+    }
+
+    /// The newer part of `cached`, a transcript in `remote`'s cache, or
+    /// `None` when the cache is fresh enough to show as is (or the remote
+    /// is an rsync daemon, which can't be asked for part of a file).
+    pub fn get(&self, remote: &str, cached: &Path) -> Option<Arc<Fetched>> {
+        let config = self
+            .remotes
+            .get(remote)
+            .filter(|config| config.transport.has_ssh())?;
+        let synced = remote::last_sync_time(&self.settings, remote);
+        let threshold = Duration::from_secs(self.settings.stale_threshold);
+        if synced.is_some_and(|t| t.elapsed().unwrap_or_default() <= threshold) {
+            return None;
+        }
+        if let Some((at, fetched)) = self.fetched.lock().unwrap().get(cached)
+            && at.elapsed() < REFRESH
+        {
+            return Some(Arc::clone(fetched));
+        }
+        if let Some((at, reason)) = self.failed.lock().unwrap().get(remote)
+            && at.elapsed() < REFRESH
+        {
+            return Some(Arc::new(Fetched {
+                synced,
+                result: Err(reason.clone()),
+            }));
+        }
+
+        let result = fetch(&self.settings, remote, config, cached).map_err(|e| format!("{:#}", e));
+        if let Err(reason) = &result {
+            self.failed
+                .lock()
+                .unwrap()
+                .insert(remote.to_string(), (Instant::now(), reason.clone()));
+        }
+        let fetched = Arc::new(Fetched { synced, result });
+        self.fetched
+            .lock()
+            .unwrap()
+            .insert(cached.to_path_buf(), (Instant::now(), Arc::clone(&fetched)));
+        Some(fetched)
+    }
+}
+
+fn fetch(settings: &Settings, name: &str, remote: &RemoteConfig, cached: &Path) -> Result<Vec<u8>> {
+    let offset = fs::metadata(cached)
+        .with_context(|| format!("Failed to read {}", cached.display()))?
+        .len();
+    let path = remote::shell_path(&remote::remote_file_path(settings, name, remote, cached)?);
+    let script = format!(
+        "[ -f {path} ] || {{ echo 'no longer exists' >&2; exit 1; }}; tail -c +{} {path} | tail -c {}",
+        offset + 1,
+        MAX_BYTES
+    );
+    let mut child = Command::new("ssh")
+        .args([
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            &format!("ConnectTimeout={}", TIMEOUT.as_secs()),
+            &remote::ssh_target(remote),
+            &script,
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute ssh")?;
+
+    // Read on a thread so the wait below can time out
+    let (mut stdout, mut stderr) = (child.stdout.take(), child.stderr.take());
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let (mut out, mut err) = (Vec::new(), String::new());
+        if let Some(stdout) = stdout.as_mut() {
+            let _ = stdout.read_to_end(&mut out);
+        }
+        if let Some(stderr) = stderr.as_mut() {
+            let _ = stderr.read_to_string(&mut err);
+        }
+        let _ = tx.send((out, err));
+    });
+    let Ok((out, err)) = rx.recv_timeout(TIMEOUT) else {
+        let _ = child.kill();
+        let _ = child.wait();
+        bail!("{} didn't answer within {}s", name, TIMEOUT.as_secs());
+    };
+    let status = child.wait().context("Failed to execute ssh")?;
+    if !status.success() {
+        match err.trim() {
+            "" => bail!("ssh failed ({})", status),
+            reason => bail!("{}", reason),
+        }
+    }
+    Ok(out)
+}
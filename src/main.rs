@@ -1,16 +1,22 @@
-mod claude_code;
-mod interactive_state;
-mod message_classification;
-mod remote;
-mod session;
+use cc_sessions::{
+    archive, claude_code, config_edit, config_migration, dates, digest, export, follow, fsck,
+    fulltext, hyperlink, interactive_state, layout, live_preview, message_classification,
+    metadata_cache, paths, picker, plain, preview_cache, pricing, profile, progress, ranking,
+    redact, remote, repo, resume, search, session, share, state, stats, team, timing, top, undo,
+};
+use cc_sessions::{colors, format_session_desc, shell_escape};
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{CommandFactory, Parser, Subcommand};
 use interactive_state::{Action as StateAction, Effect as StateEffect, InteractiveState};
-use session::{Session, SessionSource};
+use layout::{Column, Layout, View};
+use search::{SearchOptions, SearchQuery};
+use session::{Session, SessionSource, SortOrder};
 use skim::prelude::*;
 use std::borrow::Cow;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::Ordering;
 use std::time::SystemTime;
 
 // =============================================================================
@@ -24,6 +30,25 @@ use std::time::SystemTime;
     about = "List and resume Claude Code sessions across projects and machines"
 )]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// `-` resumes the session you resumed before the last one, like `cd -`
+    #[arg(value_name = "-", value_parser = ["-"], conflicts_with_all = ["list", "print0"])]
+    previous: Option<String>,
+
+    /// Arguments after `--` are passed to `claude` when resuming or forking, e.g. `-- --model opus`
+    #[arg(last = true, value_name = "CLAUDE_ARGS")]
+    claude_args: Vec<String>,
+
+    /// Use a named profile: its own config, remotes, caches, and state [env: CC_SESSIONS_PROFILE]
+    #[arg(long, value_name = "NAME", global = true)]
+    profile: Option<String>,
+
+    /// Read local sessions from this Claude Code projects dir instead of the configured ones (repeatable)
+    #[arg(long, value_name = "DIR", global = true)]
+    projects_dir: Vec<PathBuf>,
+
     // -------------------------------------------------------------------------
     // Mode
     // -------------------------------------------------------------------------
@@ -31,9 +56,26 @@ struct Args {
     #[arg(long, help_heading = "Mode")]
     list: bool,
 
-    /// Number of sessions to show [default: 15]. List only (ignored in interactive mode)
-    #[arg(long, default_value = "15", help_heading = "Mode")]
-    count: usize,
+    /// Resume the most recently modified session started in the current directory, without the picker
+    #[arg(
+        short = 'l',
+        long,
+        help_heading = "Mode",
+        conflicts_with_all = ["list", "print0", "previous"]
+    )]
+    last: bool,
+
+    /// Number of sessions to show [default: 15, or all with --tsv/--json]. List only (ignored in interactive mode)
+    #[arg(long, help_heading = "Mode")]
+    count: Option<usize>,
+
+    /// Order sessions by this key, newest/largest first [default: relevance inside a git repo, else modified]
+    #[arg(long, value_enum, help_heading = "Mode")]
+    sort: Option<session::SortKey>,
+
+    /// List named (★) sessions before the rest, each in --sort order
+    #[arg(long, help_heading = "Mode")]
+    named_first: bool,
 
     // -------------------------------------------------------------------------
     // Interactive-only (ignored with --list)
@@ -42,13 +84,84 @@ struct Args {
     #[arg(long, help_heading = "Interactive only")]
     fork: bool,
 
+    /// Print the selected session's project directory instead of resuming (see `shell-init`)
+    #[arg(long, conflicts_with = "fork", help_heading = "Interactive only")]
+    cd: bool,
+
+    /// Continue a remote session on this machine: copy its transcript into the local projects dir, in the directory its `path_map` gives
+    #[arg(long, conflicts_with = "cd", help_heading = "Interactive only")]
+    pull: bool,
+
+    /// Come back to the picker when claude exits, reusing the loaded session list
+    #[arg(long, conflicts_with = "cd", help_heading = "Interactive only")]
+    return_to_picker: bool,
+
+    /// Start the picker with forks shown inline under their parents (ctrl+t toggles)
+    #[arg(long, help_heading = "Interactive only")]
+    flat_forks: bool,
+
+    /// Make ctrl+s transcript search case-sensitive (per query: prefix with `cs:`)
+    #[arg(long, help_heading = "Interactive only")]
+    case_sensitive: bool,
+
+    /// Make ctrl+s transcript search match whole words only (per query: prefix with `w:`)
+    #[arg(long, help_heading = "Interactive only")]
+    whole_word: bool,
+
+    /// Use an external picker instead of the built-in one (e.g. "fzf --height=60%").
+    /// Defaults to `settings.picker` from the config; "builtin" forces the built-in UI
+    #[arg(long, value_name = "CMD", help_heading = "Interactive only")]
+    picker: Option<String>,
+
     /// Show session ID prefixes and extra stats
     #[arg(long, help_heading = "Mode")]
     debug: bool,
 
+    /// Fully scan transcripts over the `full_scan_limit_mb` setting instead of reading only their head and tail
+    #[arg(long, global = true, help_heading = "Mode")]
+    deep: bool,
+
+    /// Rescan every transcript instead of trusting the metadata cache, and rebuild it
+    #[arg(long, global = true, help_heading = "Mode")]
+    reindex: bool,
+
+    /// Report how long each phase took (config, sync, discovery per source, filtering, search, picker) on stderr
+    #[arg(long, global = true, help_heading = "Mode")]
+    timing: bool,
+
+    /// No colors, box drawing, or glyphs in the list and previews; markers are spelled out ("[named]", "[fork of …]") for screen readers and logs
+    #[arg(long, global = true, help_heading = "Mode")]
+    plain: bool,
+
     // -------------------------------------------------------------------------
     // List-only
     // -------------------------------------------------------------------------
+    /// Print tab-separated records (fixed columns, no truncation or color) for scripts. List only
+    #[arg(long, requires = "list", help_heading = "List only")]
+    tsv: bool,
+
+    /// Print the sessions as a JSON array (every field, RFC 3339 times) for scripts and jq. List only
+    #[arg(
+        long,
+        requires = "list",
+        conflicts_with = "tsv",
+        help_heading = "List only"
+    )]
+    json: bool,
+
+    /// Print session file paths (or IDs with `--print0 id`) separated by NUL, for `xargs -0`. Implies --list
+    #[arg(
+        short = '0',
+        long,
+        value_enum,
+        value_name = "WHAT",
+        num_args = 0..=1,
+        default_missing_value = "path",
+        conflicts_with_all = ["tsv", "json"],
+        help_heading = "List only"
+    )]
+    print0: Option<Print0Field>,
+
     /// Include forked sessions in the table. List only (interactive mode shows forks via → navigation)
     #[arg(long, help_heading = "List only")]
     include_forks: bool,
@@ -57,15 +170,57 @@ struct Args {
     // Filtering (both modes)
     // -------------------------------------------------------------------------
     /// Filter by project name (substring match, case-insensitive)
-    #[arg(long, help_heading = "Filtering")]
+    #[arg(long, global = true, help_heading = "Filtering",
+          add = clap_complete::ArgValueCandidates::new(cc_sessions::completions::projects))]
     project: Option<String>,
 
+    /// Filter by the git branch the session was on (substring match, case-insensitive)
+    #[arg(long, value_name = "NAME", global = true, help_heading = "Filtering")]
+    branch: Option<String>,
+
     /// Minimum number of conversation turns (filters out one-shot sessions)
-    #[arg(long, help_heading = "Filtering")]
+    #[arg(long, global = true, help_heading = "Filtering")]
     min_turns: Option<usize>,
 
-    /// Filter to sessions from a specific remote (e.g. devbox) or "local"
-    #[arg(long, value_name = "NAME", help_heading = "Filtering")]
+    /// Only sessions saved in this workspace (see `ws save`); skips the current-repo scoping
+    #[arg(long, global = true, value_name = "NAME", help_heading = "Filtering")]
+    workspace: Option<String>,
+
+    /// Only sessions whose last todo list (TodoWrite) has unfinished items
+    #[arg(long, global = true, help_heading = "Filtering")]
+    has_open_todos: bool,
+
+    /// Only sessions where a tool call was refused permission (rejected at the prompt or denied by a rule)
+    #[arg(long, global = true, help_heading = "Filtering")]
+    has_denials: bool,
+
+    /// Only sessions that appeared or grew since they were last shown in the picker or --list table
+    #[arg(long, global = true, help_heading = "Filtering")]
+    new_only: bool,
+
+    /// Only sessions active on or after this date (YYYY-MM-DD, monday, or e.g. 3d / 1w ago)
+    #[arg(long, global = true, value_name = "DATE", help_heading = "Filtering")]
+    since: Option<String>,
+
+    /// Only sessions active on or before this date (same formats as --since)
+    #[arg(long, global = true, value_name = "DATE", help_heading = "Filtering")]
+    until: Option<String>,
+
+    /// Only sessions started on or after this date (same formats as --since)
+    #[arg(long, global = true, value_name = "DATE", help_heading = "Filtering")]
+    created_since: Option<String>,
+
+    /// Show sessions from every project. By default, inside a git repo only that repo's sessions are shown
+    #[arg(long, help_heading = "Filtering")]
+    global: bool,
+
+    /// Only sessions whose transcript matches QUERY (same syntax as ctrl+s: `cs:`, `w:`, `in:tools`, `-in:tools`)
+    #[arg(long, value_name = "QUERY", help_heading = "Filtering")]
+    grep: Option<String>,
+
+    /// Filter to sessions from a specific remote (e.g. devbox), a group (@work), a team, or "local"
+    #[arg(long, value_name = "NAME", global = true, help_heading = "Filtering",
+          add = clap_complete::ArgValueCandidates::new(cc_sessions::completions::remotes))]
     remote: Option<String>,
 
     // -------------------------------------------------------------------------
@@ -76,17 +231,25 @@ struct Args {
     sync: bool,
 
     /// Skip auto-sync (use cached remote data only)
-    #[arg(long, help_heading = "Remote sync")]
+    #[arg(long, global = true, help_heading = "Remote sync")]
     no_sync: bool,
 
-    /// Sync all remotes and exit; no listing or picker (e.g. for cron). Other flags ignored
+    /// Sync remotes that are due (per-remote `sync_interval`) and exit; no listing or picker (e.g. for cron)
     #[arg(long, help_heading = "Remote sync")]
     sync_only: bool,
 
+    /// Only sync remotes in this group (`group = "..."` in the config)
+    #[arg(long, value_name = "GROUP", help_heading = "Remote sync")]
+    group: Option<String>,
+
     /// Treat any remote sync/discovery source failure as fatal
-    #[arg(long, help_heading = "Remote sync")]
+    #[arg(long, global = true, help_heading = "Remote sync")]
     strict: bool,
 
+    /// Re-sync only the remotes whose last sync failed (flagged with ⚠), then continue
+    #[arg(long, conflicts_with = "sync", help_heading = "Remote sync")]
+    retry_failed: bool,
+
     // -------------------------------------------------------------------------
     // Internal (hidden from --help)
     // -------------------------------------------------------------------------
@@ -95,32 +258,468 @@ struct Args {
     preview: Option<PathBuf>,
 }
 
+/// Shells `shell-init` and `completions` support.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// What happens to the session picked interactively.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OnSelect {
+    Resume,
+    Fork,
+    /// Print the project directory (`--cd`) for a shell wrapper to `cd` into.
+    PrintDir,
+}
+
+/// What the picker ended with, short of quitting.
+enum Picked {
+    /// A session to open
+    Open(String),
+    /// Sessions a batch action changed or deleted, to re-read before the
+    /// picker comes back
+    Changed(Vec<String>),
+}
+
+/// What `--print0` emits per session.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Print0Field {
+    Path,
+    Id,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Refresh the metadata cache for all sources without launching the picker (e.g. for cron)
+    Index,
+    /// Aggregate sessions, turns, and tokens per project (honors the filtering flags)
+    Stats(StatsArgs),
+    /// Standup-ready list of what was worked on in a period, grouped by project (honors the filtering flags)
+    Digest(DigestArgs),
+    /// Resume the session whose name, summary, or first message matches QUERY; picks among several matches
+    Resume {
+        /// Text to look for, e.g. "auth refactor" (fuzzy)
+        query: String,
+        /// Arguments after `--` are passed to `claude`
+        #[arg(last = true, value_name = "CLAUDE_ARGS")]
+        claude_args: Vec<String>,
+    },
+    /// List sessions whose transcript contains every word of QUERY, using a persistent index; quoted phrases match as written
+    Search {
+        /// Words and "quoted phrases" (arguments with spaces count as phrases); `cs:`, `w:`, `in:tools`, and `-in:tools` work as in ctrl+s
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    /// Print a session's transcript as normalized messages for other tools
+    Export {
+        /// Session ID (or unique prefix)
+        id: String,
+        #[arg(long, value_enum, default_value_t)]
+        format: export::ExportFormat,
+        /// Write to FILE instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Print a session's whole conversation in color (e.g. `| less -R`), without the preview's cut-offs
+    Show {
+        /// Session ID (or unique prefix)
+        id: String,
+    },
+    /// Upload a session's messages as Markdown to a gist or paste service and print the link; secrets are redacted
+    Share {
+        /// Session ID (or unique prefix)
+        id: String,
+        /// Messages to share, numbered from 1, e.g. 10-30, 10- or -30 (default: all)
+        #[arg(long, value_name = "FROM-TO")]
+        range: Option<share::Range>,
+        /// Upload without hiding secrets, emails, and home directories
+        #[arg(long)]
+        no_redact: bool,
+        /// Print the Markdown that would be uploaded instead
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print a session's messages as they are written (q to stop); remote sessions are followed over ssh
+    Follow {
+        /// Session ID (or unique prefix)
+        id: String,
+        /// Transcript entries to show before following
+        #[arg(short = 'n', long, default_value_t = FOLLOW_BACKLOG)]
+        lines: usize,
+    },
+    /// Live view of the sessions being written to right now, on this machine and every SSH remote (q to quit)
+    Top {
+        /// Seconds between polls of each source
+        #[arg(long, value_name = "SECS", default_value_t = 2)]
+        interval: u64,
+        /// Sessions written to within this long count as active (e.g. 90s, 10m, 1h)
+        #[arg(long, value_name = "AGE", default_value = "10m")]
+        window: String,
+    },
+    /// Maintain ~/.cache/cc-sessions
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    /// Inspect and clean up forked sessions
+    Forks {
+        #[command(subcommand)]
+        action: ForksCommand,
+    },
+    /// Publish your sessions to a team location configured under [teams]; teammates' sessions are listed with their owner
+    Team {
+        #[command(subcommand)]
+        action: TeamCommand,
+    },
+    /// Print a `ccd` shell function that jumps to a picked session's project
+    ShellInit {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print a tab-completion script, with `--project` and `--remote` values completed from your sessions and config
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Compare a remote's cache with the remote by checksum and report drift
+    Verify {
+        /// Configured remote name
+        #[arg(value_name = "REMOTE")]
+        name: String,
+    },
+    /// Inspect configured remotes (honors --remote)
+    Remote {
+        #[command(subcommand)]
+        action: RemoteCommand,
+    },
+    /// Save named sets of sessions to reopen together with --workspace NAME
+    Ws {
+        #[command(subcommand)]
+        action: WsCommand,
+    },
+    /// Compress local sessions not modified for a while into the archive; they stay listed and are restored on resume
+    Archive {
+        /// Archive sessions last modified before this (e.g. 90d, 12w, or YYYY-MM-DD)
+        #[arg(long, value_name = "AGE")]
+        older_than: String,
+        /// List what would be archived without touching anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check all sources for damaged, misnamed, or orphaned session files
+    Fsck {
+        /// Apply safe repairs to local files (delete empty files, rename misnamed ones)
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Restore what the last `forks prune`, `fsck --fix`, `archive`, `cache vacuum`, or picker delete deleted or moved
+    Undo {
+        /// List what would be restored without touching anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Delete caches for remotes no longer in the config and stale index files
+    Vacuum {
+        /// Report what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Remove unconfigured remotes' caches without asking
+        #[arg(long, short)]
+        yes: bool,
+        /// Also recompress archived transcripts at gzip's best level
+        #[arg(long)]
+        recompress: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum RemoteCommand {
+    /// Disk used by each remote's projects dir, its largest projects and sessions, and the local cache
+    Du {
+        /// How many of the largest projects and sessions to list
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+    },
+    /// Check SSH, rsync, the projects dir, and claude on each remote
+    Check,
+    /// Add a remote to remotes.toml
+    Add(AddRemoteArgs),
+    /// Remove a remote from remotes.toml (its cache is left for `cache vacuum`)
+    Remove {
+        /// Configured remote name
+        name: String,
+    },
+    /// Rename a remote in remotes.toml and move its cached sessions along
+    Rename {
+        /// Current remote name
+        old: String,
+        /// New remote name
+        new: String,
+    },
+    /// List configured remotes
+    List,
+}
+
+#[derive(clap::Args)]
+struct AddRemoteArgs {
+    /// Name used in --remote, the SOURCE column, and the cache dir
+    name: String,
+    /// SSH host (alias from ~/.ssh/config or hostname/IP)
+    host: String,
+    /// SSH user, for raw hosts
+    #[arg(long)]
+    user: Option<String>,
+    /// Projects directory on the remote [default: ~/.claude/projects]
+    #[arg(long)]
+    projects_dir: Option<String>,
+    /// Short label for the SOURCE column
+    #[arg(long)]
+    label: Option<String>,
+    /// Group for --group / --remote @group
+    #[arg(long)]
+    group: Option<String>,
+    /// Sync from this module of an rsync daemon on the host instead of over
+    /// SSH (--projects-dir is then the path inside the module)
+    #[arg(long, value_name = "MODULE")]
+    rsyncd_module: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum TeamCommand {
+    /// Publish your local sessions' metadata, replacing your last publish (honors --project and --min-turns)
+    Publish {
+        /// Team from [teams] (may be left out with a single team)
+        team: Option<String>,
+        /// Include transcripts, so teammates can preview and search them
+        #[arg(long)]
+        transcripts: bool,
+        /// Publish without hiding secrets, emails, and home directories
+        #[arg(long)]
+        no_redact: bool,
+        /// Report what would be published without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Remove everything you published to a team
+    Unpublish {
+        /// Team from [teams] (may be left out with a single team)
+        team: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ForksCommand {
+    /// Delete local forks that provably add nothing to their parent
+    Prune {
+        /// Report what would be deleted without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List forks whose parent session no longer exists
+    Orphans,
+    /// Nest a fork under a different parent (recorded in local state)
+    Reparent {
+        /// Fork session ID (or unique prefix)
+        fork: String,
+        /// New parent session ID (or unique prefix)
+        parent: String,
+    },
+    /// Show a fork as a root session (recorded in local state)
+    Flatten {
+        /// Fork session ID (or unique prefix)
+        fork: String,
+    },
+    /// Forget a reparent/flatten decision and use the transcript's parent again
+    Reset {
+        /// Fork session ID (or unique prefix)
+        fork: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum WsCommand {
+    /// Add sessions to a workspace, creating it if needed
+    Save {
+        /// Workspace name, e.g. infra-sprint
+        name: String,
+        /// Session IDs (or unique prefixes)
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// List workspaces and their sessions
+    List,
+    /// Delete a workspace, or just the given sessions from it
+    Remove {
+        name: String,
+        /// Session IDs (or prefixes) to drop; all of them if omitted
+        ids: Vec<String>,
+    },
+}
+
+#[derive(clap::Args)]
+struct StatsArgs {
+    /// Split each project's totals by source (local vs each remote)
+    #[arg(long, conflicts_with_all = ["top", "trend", "costs", "mcp"])]
+    by_source: bool,
+
+    /// Emit JSON instead of a table (schema versioned via `schema_version`)
+    #[arg(long)]
+    json: bool,
+
+    /// List the N biggest individual sessions instead of per-project totals
+    #[arg(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// Metric for --top
+    #[arg(long, value_enum, default_value_t, requires = "top")]
+    by: stats::RankBy,
+
+    /// Chart weekly input/output token totals instead of per-project totals
+    #[arg(long, conflicts_with = "top")]
+    trend: bool,
+
+    /// Number of weeks shown by --trend
+    #[arg(long, value_name = "N", default_value = "12", requires = "trend")]
+    weeks: usize,
+
+    /// Estimated spend per project and source, from token usage and model prices
+    #[arg(long, conflicts_with_all = ["top", "trend"])]
+    costs: bool,
+
+    /// MCP tool calls per server, including configured servers that were never called
+    #[arg(long, conflicts_with_all = ["top", "trend", "costs"])]
+    mcp: bool,
+
+    /// Emit --costs as CSV (one row per project × source)
+    #[arg(long, requires = "costs", conflicts_with = "json")]
+    csv: bool,
+}
+
+#[derive(clap::Args)]
+struct DigestArgs {
+    /// Cover yesterday (UTC) instead of today; --since / --until cover another period
+    #[arg(long, conflicts_with_all = ["since", "until"])]
+    yesterday: bool,
+}
+
 // =============================================================================
 // Main Entry Point
 // =============================================================================
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    // Answers the completion script's `COMPLETE=<shell> cc-sessions ...` calls
+    clap_complete::CompleteEnv::with_factory(Args::command).complete();
+
+    let mut args = Args::parse();
 
     // Preview mode: output formatted transcript for a session file
     if let Some(ref filepath) = args.preview {
+        plain::enable(args.plain);
         print_session_preview(filepath)?;
         return Ok(());
     }
 
+    let mut timing = timing::Timing::new(args.timing);
+
+    // Pick the profile before anything reads a config, cache, or state path
+    profile::activate(args.profile.as_deref())?;
+    paths::migrate_and_report(&[&paths::CONFIG], None);
+    check_profile_exists(&args)?;
+
     // Load remote config
     let config = remote::load_config()?;
+    // After loading, so an explicit cache_dir in the old location stays put
+    let cache_dir = remote::expand_path(&config.settings.cache_dir).ok();
+    paths::migrate_and_report(
+        &[&paths::CACHE, &paths::STATE, &paths::DATA],
+        cache_dir.as_deref(),
+    );
+    apply_config_defaults(&mut args, &config.defaults);
+    plain::enable(args.plain);
+    metadata_cache::set_reindex(args.reindex);
+    let claude_args = match &mut args.command {
+        Some(Command::Resume { claude_args, .. }) if !claude_args.is_empty() => claude_args,
+        _ => &mut args.claude_args,
+    };
+    let _ = CLAUDE_ARGS.set(std::mem::take(claude_args));
+    let _ = PULL.set(args.pull);
+    apply_resource_settings(&config.settings, is_background_run(&args, &config.settings));
+    sync_state_dir(&config.settings);
+    claude_code::configure_local_dirs(&config, &args.projects_dir)?;
+    config.check_remote_filter(args.remote.as_deref())?;
+    timing.lap("config load");
+
+    match args.command {
+        Some(Command::Index) => return run_index(&config, &args, &mut timing),
+        Some(Command::Cache {
+            action:
+                CacheCommand::Vacuum {
+                    dry_run,
+                    yes,
+                    recompress,
+                },
+        }) => return run_cache_vacuum(&config, dry_run, yes, recompress),
+        Some(Command::Forks {
+            action: ForksCommand::Prune { dry_run },
+        }) => return run_forks_prune(&config, dry_run),
+        Some(Command::Forks { ref action }) => return run_forks(&config, &args, action),
+        Some(Command::Team { ref action }) => return run_team(&config, &args, action),
+        Some(Command::Ws { ref action }) => return run_ws(&config, &args, action),
+        Some(Command::Export {
+            ref id,
+            format,
+            ref output,
+        }) => return run_export(&config, &args, id, format, output.as_deref()),
+        Some(Command::Show { ref id }) => return run_show(&config, &args, id),
+        Some(Command::Share {
+            ref id,
+            range,
+            no_redact,
+            dry_run,
+        }) => return run_share(&config, &args, id, range, !no_redact, dry_run),
+        Some(Command::Follow { ref id, lines }) => return run_follow(&config, &args, id, lines),
+        Some(Command::Top {
+            interval,
+            ref window,
+        }) => {
+            let options = top::Options {
+                interval: std::time::Duration::from_secs(interval.max(1)),
+                window: remote::parse_interval(window)?,
+            };
+            return top::run(&config, args.remote.as_deref(), &options);
+        }
+        Some(Command::Fsck { fix }) => return run_fsck(&config, &args, fix),
+        Some(Command::Undo { dry_run }) => return run_undo(dry_run),
+        Some(Command::Archive {
+            ref older_than,
+            dry_run,
+        }) => return run_archive(&config, &args, older_than, dry_run),
+        Some(Command::Remote { ref action }) => return run_remote(&config, &args, action),
+        Some(Command::ShellInit { shell }) => {
+            print!("{}", shell_init(shell));
+            return Ok(());
+        }
+        Some(Command::Completions { shell }) => {
+            print!("{}", completion_script(shell)?);
+            return Ok(());
+        }
+        Some(Command::Verify { ref name }) => return run_verify(&config, name, args.strict),
+        _ => {}
+    }
 
     // Handle sync operations
     if args.sync_only {
-        // Sync all remotes and exit
-        let summary = remote::sync_all(&config)?;
+        // Sync remotes that are due and exit
+        let summary = remote::sync_scheduled(&config, args.group.as_deref())?;
         for result in &summary.successes {
-            println!(
-                "Synced '{}' in {:.1}s",
-                result.remote_name,
-                result.duration.as_secs_f64()
-            );
+            println!("{}", format_sync_result("Synced", result));
         }
         for failure in &summary.failures {
             eprintln!(
@@ -128,8 +727,8 @@ fn main() -> Result<()> {
                 failure.remote_name, failure.reason
             );
         }
-        if summary.successes.is_empty() {
-            println!("No remotes configured. Add remotes to ~/.config/cc-sessions/remotes.toml");
+        if config.remotes.is_empty() {
+            println!("{}", no_remotes_message());
         }
         enforce_strict_mode(args.strict, summary.failure_count(), 0)?;
         return Ok(());
@@ -138,294 +737,2552 @@ fn main() -> Result<()> {
     let mut sync_failures = 0;
 
     if args.sync {
-        // Force sync all remotes
-        let summary = remote::sync_all(&config)?;
+        // Force sync all remotes, or just the filtered project's directories
+        let project = args.project.as_deref().filter(|p| !p.is_empty());
+        let summary = remote::sync_all(&config, args.group.as_deref(), project)?;
         for result in &summary.successes {
-            eprintln!(
-                "Synced '{}' in {:.1}s",
-                result.remote_name,
-                result.duration.as_secs_f64()
-            );
+            eprintln!("{}", format_sync_result("Synced", result));
+        }
+        sync_failures = summary.failure_count();
+        report_sync_timing(&mut timing, &summary);
+    } else if args.retry_failed {
+        let summary = remote::sync_failed(&config, args.group.as_deref())?;
+        for result in &summary.successes {
+            eprintln!("{}", format_sync_result("Synced", result));
+        }
+        if summary.successes.is_empty() && summary.failures.is_empty() {
+            eprintln!("No failed remotes to retry");
         }
         sync_failures = summary.failure_count();
+        report_sync_timing(&mut timing, &summary);
     } else if !args.no_sync && !config.remotes.is_empty() {
         // Auto-sync stale remotes
-        let summary = remote::sync_if_stale(&config)?;
+        let summary = remote::sync_if_stale(&config, args.group.as_deref())?;
         for result in &summary.successes {
-            eprintln!(
-                "Auto-synced '{}' in {:.1}s",
-                result.remote_name,
-                result.duration.as_secs_f64()
-            );
+            eprintln!("{}", format_sync_result("Auto-synced", result));
         }
         sync_failures = summary.failure_count();
+        report_sync_timing(&mut timing, &summary);
     }
 
     // Find sessions from all sources (local + remotes)
-    let discovery = claude_code::find_all_sessions_with_summary(&config, args.remote.as_deref())?;
+    let discovery =
+        claude_code::find_all_sessions_with_summary(&config, args.remote.as_deref(), args.deep)?;
     for failure in &discovery.failures {
         eprintln!(
             "Warning: Failed to load sessions from '{}': {}",
             failure.source_name, failure.reason
         );
     }
+    report_discovery_timing(&mut timing, &discovery);
     enforce_strict_mode(args.strict, sync_failures, discovery.failure_count())?;
     let mut sessions = discovery.sessions;
+    state::ForkOverrides::load().apply(&mut sessions);
+    state::ResumeHistory::load().apply(&mut sessions);
+    let repo_root = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| repo::repo_root(&cwd));
+    let ranking = ranking::Ranking::new(&config.ranking, repo_root.as_deref(), SystemTime::now());
+    ranking.apply(&mut sessions);
+    let mut seen = state::SeenSessions::load();
+    seen.start(&sessions);
+    let change_notices = change_notices(&sessions, &seen, !args.new_only);
+
+    if args.previous.is_some() {
+        let session = previous_session(&sessions)?;
+        eprintln!("Back to [{}]", format_session_desc(session, 60));
+        timing.total();
+        return open_session(session, on_select_for(&args));
+    }
+
+    if args.last {
+        let cwd = std::env::current_dir().context("Failed to read the current directory")?;
+        let session = last_session_in(&sessions, &cwd)?;
+        eprintln!("Resuming [{}]", format_session_desc(session, 60));
+        timing.total();
+        return open_session(session, on_select_for(&args));
+    }
 
     // Filter by project name if specified
     if let Some(ref filter) = args.project {
         let filter_lower = filter.to_lowercase();
         sessions.retain(|s| s.project.to_lowercase().contains(&filter_lower));
     }
+    if let Some(ref filter) = args.branch {
+        let filter_lower = filter.to_lowercase();
+        sessions.retain(|s| {
+            s.git_branch
+                .as_deref()
+                .is_some_and(|b| b.to_lowercase().contains(&filter_lower))
+        });
+    }
+
+    if let Some(ref name) = args.workspace {
+        filter_to_workspace(&mut sessions, name)?;
+    }
+
+    // Inside a git repo, the picker and list default to that repo's sessions
+    if args.command.is_none()
+        && !args.global
+        && args.project.is_none()
+        && args.workspace.is_none()
+        && let Some(root) = &repo_root
+    {
+        let quiet = args.tsv || args.json || args.print0.is_some();
+        if filter_to_repo(&mut sessions, root) {
+            if !quiet {
+                eprintln!("Sessions in {} (--global for all)", root.display());
+            }
+        } else if !quiet {
+            eprintln!("No sessions in {}; showing all projects", root.display());
+        }
+    }
 
     // Filter by minimum turns (excludes one-shot sessions)
     if let Some(min) = args.min_turns {
         sessions.retain(|s| s.turn_count >= min);
     }
+    if args.has_open_todos {
+        sessions.retain(|s| !s.open_todos.is_empty());
+    }
+    if args.has_denials {
+        sessions.retain(|s| !s.denied_tools.is_empty());
+    }
+    if args.new_only {
+        sessions.retain(|s| seen.change(s).is_some());
+    }
+    // `digest` applies the period itself, by the hours each session was
+    // active, and defaults to today.
+    let in_period = !matches!(args.command, Some(Command::Digest(_)));
+    let now = SystemTime::now();
+    if let Some(since) = args.since.as_deref().filter(|_| in_period) {
+        let since = dates::parse_period_start(since, now)?;
+        sessions.retain(|s| s.last_activity.unwrap_or(s.modified) >= since);
+    }
+    if let Some(until) = args.until.as_deref().filter(|_| in_period) {
+        let until = dates::parse_period_end(until, now)?;
+        sessions.retain(|s| s.last_activity.unwrap_or(s.modified) < until);
+    }
+    if let Some(since) = args.created_since.as_deref() {
+        let since = dates::parse_period_start(since, now)?;
+        sessions.retain(|s| s.first_activity.unwrap_or(s.created) >= since);
+    }
+    sort_order(&args, repo_root.is_some()).sort(&mut sessions);
+    timing.lap("filtering");
+
+    let search_defaults = SearchOptions {
+        case_sensitive: args.case_sensitive,
+        whole_word: args.whole_word,
+        include_tools: config.settings.search_tool_output,
+    };
+
+    if let Some(ref pattern) = args.grep {
+        let query = SearchQuery::parse(pattern, search_defaults);
+        let matching = grep_sessions(&sessions, &query);
+        sessions.retain(|s| matching.contains(&s.filepath));
+        if sessions.is_empty() {
+            anyhow::bail!("No sessions match '{}'", pattern);
+        }
+        timing.lap("search (--grep)");
+    }
+
+    if let Some(Command::Search { ref query }) = args.command {
+        let raw = search_query_from_args(query);
+        let query = fulltext::Query::parse(&raw, search_defaults);
+        let matching = search_sessions(&sessions, &query, search_defaults.include_tools);
+        sessions.retain(|s| matching.contains(&s.filepath));
+        if sessions.is_empty() {
+            anyhow::bail!("No sessions match '{}'", raw);
+        }
+        timing.lap("search (index)");
+    }
 
     if sessions.is_empty() {
+        if args.new_only {
+            anyhow::bail!("No new or updated sessions since the last listing");
+        }
         if args.project.is_some() {
             anyhow::bail!("No sessions found matching project filter");
         }
+        if let Some(ref branch) = args.branch {
+            anyhow::bail!("No sessions found on a branch matching '{}'", branch);
+        }
+        if args.since.is_some() || args.until.is_some() || args.created_since.is_some() {
+            anyhow::bail!("No sessions found in that period");
+        }
         if let Some(ref remote_name) = args.remote {
             anyhow::bail!("No sessions found for remote '{}'", remote_name);
         }
         anyhow::bail!("No sessions found");
     }
 
-    if args.list {
-        let list_sessions = filter_forks_for_list(&sessions, args.include_forks);
-        print_sessions(&list_sessions, args.count, args.debug);
-    } else {
-        interactive_mode(&sessions, args.fork, args.debug)?;
-    }
-
-    Ok(())
-}
-
-fn enforce_strict_mode(
-    strict: bool,
-    sync_failures: usize,
-    discovery_failures: usize,
-) -> Result<()> {
-    if !strict {
-        return Ok(());
-    }
-
-    if sync_failures > 0 {
-        anyhow::bail!("Strict mode: {} sync source(s) failed", sync_failures);
+    if let Some(Command::Resume { ref query, .. }) = args.command {
+        let ranked = resume::rank(&sessions, query);
+        if ranked.is_empty() {
+            anyhow::bail!("No session matches '{}'", query);
+        }
+        if let Some(index) = resume::confident(&ranked) {
+            let session = &sessions[index];
+            eprintln!("Matched [{}]", format_session_desc(session, 60));
+            timing.total();
+            return open_session(session, on_select_for(&args));
+        }
+        // Several candidates: the picker shows just those, best match first.
+        let mut position = vec![usize::MAX; sessions.len()];
+        for (rank, m) in ranked.iter().enumerate() {
+            position[m.index] = rank;
+        }
+        let mut matched: Vec<(usize, Session)> = sessions
+            .drain(..)
+            .enumerate()
+            .filter(|(i, _)| position[*i] != usize::MAX)
+            .map(|(i, s)| (position[i], s))
+            .collect();
+        matched.sort_by_key(|(rank, _)| *rank);
+        sessions = matched.into_iter().map(|(_, s)| s).collect();
+        eprintln!("{} sessions match '{}'", sessions.len(), query);
     }
 
-    if discovery_failures > 0 {
-        anyhow::bail!(
-            "Strict mode: {} discovery source(s) failed",
-            discovery_failures
-        );
+    // Teammates' sessions would skew your own numbers; `--remote <team>`
+    // asks for them explicitly.
+    if matches!(args.command, Some(Command::Stats(_) | Command::Digest(_))) && args.remote.is_none()
+    {
+        sessions.retain(|s| s.source.owner().is_none());
     }
 
-    Ok(())
-}
-
-// =============================================================================
-// Display Functions
-// =============================================================================
-
-fn print_sessions(sessions: &[&Session], count: usize, debug: bool) {
-    if debug {
-        println!(
-            "{:<6} {:<6} {:<4} {:<8} {:<16} {:<40} SUMMARY",
-            "CREAT", "MOD", "FORK", "SOURCE", "PROJECT", "ID"
-        );
-        println!("{}", "─".repeat(130));
-
-        for session in sessions.iter().take(count) {
-            let created = format_time_relative(session.created);
-            let modified = format_time_relative(session.modified);
-            let source = session.source.display_name();
-            let fork_indicator = if session.forked_from.is_some() {
-                "↳"
-            } else {
-                ""
-            };
-            let id_short = if session.id.len() > 36 {
-                &session.id[..36]
-            } else {
-                &session.id
-            };
-            let desc = format_session_desc(session, 30);
-            let desc = if session.name.is_some() {
-                format!("{}{}{}", colors::YELLOW, desc, colors::RESET)
-            } else {
-                desc
-            };
-
-            println!(
-                "{:<6} {:<6} {:<4} {:<8} {:<16} {:<40} {}",
-                created, modified, fork_indicator, source, session.project, id_short, desc
+    if let Some(Command::Stats(ref stats_args)) = args.command {
+        run_stats(&sessions, stats_args, &config)?;
+        timing.lap("output");
+        timing.total();
+    } else if let Some(Command::Digest(ref digest_args)) = args.command {
+        run_digest(&sessions, digest_args, &args)?;
+        timing.lap("output");
+        timing.total();
+    } else if args.list
+        || args.print0.is_some()
+        || matches!(args.command, Some(Command::Search { .. }))
+        || !can_show_picker(&args, &config.settings)
+    {
+        let fallback = !args.list
+            && args.print0.is_none()
+            && !matches!(args.command, Some(Command::Search { .. }));
+        if fallback {
+            eprintln!(
+                "Not an interactive terminal: listing sessions instead of opening the picker (run in a terminal to pick one; --list skips this note)"
             );
         }
-
-        println!("{}", "─".repeat(130));
-        println!("Total: {} sessions", sessions.len());
+        let mut notices = source_notices(&config, args.remote.as_deref(), &discovery.failures);
+        notices.extend(change_notices);
+        let list_sessions = filter_forks_for_list(&sessions, args.include_forks);
+        if let Some(field) = args.print0 {
+            print_sessions_nul(&list_sessions, field, args.count.unwrap_or(usize::MAX))?;
+        } else if args.tsv {
+            print_sessions_tsv(&list_sessions, args.count.unwrap_or(usize::MAX));
+        } else if args.json {
+            let count = args.count.unwrap_or(usize::MAX).min(list_sessions.len());
+            println!("{}", export::render_session_list(&list_sessions[..count])?);
+        } else {
+            let count = args.count.unwrap_or(15);
+            print_sessions(
+                &list_sessions,
+                count,
+                &layout_config(&config.layout.list, View::List, &args, &sessions),
+                args.debug,
+                &notices,
+                !fallback,
+            );
+            seen.record(list_sessions.iter().copied().take(count));
+            save_seen(&seen);
+        }
+        timing.lap("output");
+        timing.total();
     } else {
-        println!(
-            "{:<6} {:<6} {:<8} {:<16} SUMMARY",
-            "CREAT", "MOD", "SOURCE", "PROJECT"
-        );
-        println!("{}", "─".repeat(100));
-
-        for session in sessions.iter().take(count) {
-            let created = format_time_relative(session.created);
-            let modified = format_time_relative(session.modified);
-            let source = session.source.display_name();
-            let desc = format_session_desc(session, 50);
-            let desc = if session.forked_from.is_some() {
-                format!("↳ {}", desc)
-            } else {
-                desc
+        let picker_cmd = picker_command(&args, &config.settings);
+        let on_select = on_select_for(&args);
+        let sort = sort_order(&args, repo_root.is_some());
+        let picker_config = &layout_config(&config.layout.picker, View::Picker, &args, &sessions);
+        let mut notices = source_notices(&config, args.remote.as_deref(), &discovery.failures);
+        notices.extend(change_notices);
+        seen.record(&sessions);
+        save_seen(&seen);
+        if picker::is_builtin(picker_cmd) {
+            let mut memory = PickerMemory {
+                state: InteractiveState::new(args.flat_forks),
+                ..PickerMemory::default()
             };
-            let desc = if session.name.is_some() {
-                format!("{}{}{}", colors::YELLOW, desc, colors::RESET)
-            } else {
-                desc
+            let options = PickerOptions {
+                on_select,
+                search_defaults,
+                notices: &notices,
+                editor: config.settings.editor.as_deref(),
+                config: &config,
             };
-
-            println!(
-                "{:<6} {:<6} {:<8} {:<16} {}",
-                created, modified, source, session.project, desc
-            );
+            run_picker(
+                &mut sessions,
+                on_select,
+                args.return_to_picker,
+                sort,
+                &ranking,
+                |sessions| {
+                    let layout = picker_layout(sessions, picker_config, args.debug);
+                    interactive_mode(sessions, layout, &options, &mut timing, &mut memory)
+                },
+            )?;
+        } else {
+            for notice in &notices {
+                eprintln!("{}", plain::render(notice));
+            }
+            run_picker(
+                &mut sessions,
+                on_select,
+                args.return_to_picker,
+                sort,
+                &ranking,
+                |sessions| {
+                    let layout = picker_layout(sessions, picker_config, args.debug);
+                    external_picker_mode(picker_cmd, sessions, layout, &mut timing)
+                        .map(|id| id.map(Picked::Open))
+                },
+            )?;
         }
-
-        println!("{}", "─".repeat(100));
-        println!("Run without --list for interactive picker; use --fork to fork when resuming");
     }
+
+    Ok(())
 }
 
-fn format_time_relative(time: SystemTime) -> String {
-    let now = SystemTime::now();
+/// The picker to run: `--picker`, else `settings.picker`, else the built-in.
+fn picker_command<'a>(args: &'a Args, settings: &'a remote::Settings) -> &'a str {
+    args.picker
+        .as_deref()
+        .or(settings.picker.as_deref())
+        .unwrap_or("builtin")
+}
 
-    // Handle future timestamps (clock skew, filesystem issues)
-    let secs = match now.duration_since(time) {
-        Ok(d) => d.as_secs(),
-        Err(_) => return "?".to_string(), // Future timestamp
-    };
+/// Whether the built-in picker can draw here. It needs a real terminal on
+/// stdout, except with `--cd`, whose stdout is meant to be captured. External
+/// pickers open the terminal themselves and are always tried.
+fn can_show_picker(args: &Args, settings: &remote::Settings) -> bool {
+    use std::io::IsTerminal;
 
-    if secs < 60 {
-        "now".to_string()
-    } else if secs < 3600 {
-        format!("{}m", secs / 60)
-    } else if secs < 86400 {
-        format!("{}h", secs / 3600)
-    } else if secs < 604800 {
-        format!("{}d", secs / 86400)
-    } else {
-        format!("{}w", secs / 604800)
+    if !picker::is_builtin(picker_command(args, settings)) {
+        return true;
     }
+    let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+    !dumb && (args.cd || std::io::stdout().is_terminal())
 }
 
-/// Format session description: name (★) > tag (#) > summary > first_message
-fn format_session_desc(session: &Session, max_chars: usize) -> String {
-    let label = match (&session.name, &session.tag) {
-        (Some(name), Some(tag)) => Some(format!("★ {} #{}", name, tag)),
-        (Some(name), None) => Some(format!("★ {}", name)),
-        (None, Some(tag)) => Some(format!("#{}", tag)),
-        (None, None) => None,
-    };
+/// What to do with the chosen session, from `--cd` / `--fork`.
+fn on_select_for(args: &Args) -> OnSelect {
+    if args.cd {
+        OnSelect::PrintDir
+    } else if args.fork {
+        OnSelect::Fork
+    } else {
+        OnSelect::Resume
+    }
+}
 
-    if let Some(label) = label {
-        let label_len = label.chars().count();
-        if label_len >= max_chars {
-            return label.chars().take(max_chars).collect();
-        }
-        // Append summary if there's room for " - " + at least 10 chars
-        if let Some(summary) = &session.summary
-            && max_chars > label_len + 13
-        {
-            let remaining = max_chars - label_len - 3;
-            return format!(
-                "{} - {}",
-                label,
-                summary.chars().take(remaining).collect::<String>()
-            );
+/// Whether this run only reports or maintains (cron, shell hooks, scripts)
+/// and never opens the picker, a live view, or `claude`.
+fn is_background_run(args: &Args, settings: &remote::Settings) -> bool {
+    if args.previous.is_some() || args.last {
+        return false;
+    }
+    match &args.command {
+        Some(Command::Resume { .. } | Command::Follow { .. } | Command::Top { .. }) => false,
+        Some(_) => true,
+        None => {
+            args.sync_only || args.list || args.print0.is_some() || !can_show_picker(args, settings)
         }
-        return label;
     }
-
-    session
-        .summary
-        .as_deref()
-        .or(session.first_message.as_deref())
-        .map(|s| s.chars().take(max_chars).collect())
-        .unwrap_or_default()
 }
 
-fn filter_forks_for_list(sessions: &[Session], include_forks: bool) -> Vec<&Session> {
-    if include_forks {
-        return sessions.iter().collect();
+/// Apply `[settings]` limits on how hard cc-sessions works the machine.
+/// Must run before anything uses the rayon pool, and before worker threads
+/// or rsync children are spawned so they inherit the lower priority.
+/// `low_priority` only applies to `background` runs: niceness can't be
+/// raised back, and the picker, and the `claude` it execs, would keep it.
+fn apply_resource_settings(settings: &remote::Settings, background: bool) {
+    if settings.low_priority && background {
+        lower_priority();
+    }
+    if let Some(threads) = settings.threads.filter(|&n| n > 0)
+        && let Err(e) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+    {
+        eprintln!(
+            "Warning: Failed to limit worker threads to {}: {}",
+            threads, e
+        );
     }
-
-    sessions
-        .iter()
-        .filter(|s| s.forked_from.is_none())
-        .collect()
 }
 
-/// Normalize text for display: collapse whitespace, strip markdown, truncate gracefully
-pub fn normalize_summary(text: &str, max_chars: usize) -> String {
-    // Collapse whitespace and build directly into the output buffer — stop
-    // collecting once we're past max_chars (summary inputs can be very long).
-    let mut normalized = String::with_capacity(max_chars.min(text.len()) + 4);
-    let mut words = text.split_whitespace();
-    if let Some(first) = words.next() {
-        normalized.push_str(first);
-        for w in words {
-            normalized.push(' ');
-            normalized.push_str(w);
-            if normalized.len() > max_chars * 4 {
-                break;
-            }
-        }
+/// Drop to nice level +10, so background runs (cron, shell hooks) yield the
+/// CPU to builds. Threads and child processes started afterwards inherit it.
+/// An unprivileged process can't undo this.
+#[cfg(unix)]
+fn lower_priority() {
+    unsafe extern "C" {
+        fn nice(inc: std::ffi::c_int) -> std::ffi::c_int;
     }
-
-    let stripped = normalized.trim_start_matches(['#', '*']).trim_start();
-
-    if stripped.chars().count() <= max_chars {
-        return stripped.to_owned();
+    // SAFETY: nice(2) only adjusts the caller's scheduling priority. Its -1
+    // return is ambiguous without errno; failing to lower priority is harmless.
+    unsafe {
+        nice(10);
     }
-
-    let truncated: String = stripped.chars().take(max_chars).collect();
-    let break_point = truncated
-        .rfind(' ')
-        .filter(|&i| i > max_chars / 2)
-        .unwrap_or(truncated.len());
-
-    format!("{}...", &truncated[..break_point])
 }
 
-// =============================================================================
-// ANSI Colors (shared across preview functions)
-// =============================================================================
+#[cfg(not(unix))]
+fn lower_priority() {}
 
-mod colors {
-    pub const CYAN: &str = "\x1b[36m";
-    pub const YELLOW: &str = "\x1b[33m";
-    pub const GREEN: &str = "\x1b[32m";
-    pub const DIM: &str = "\x1b[2m";
-    pub const BOLD: &str = "\x1b[1m";
-    pub const BOLD_INVERSE: &str = "\x1b[1;7m";
-    pub const RESET: &str = "\x1b[0m";
+/// "Synced 'devbox' in 1.2s: 4 new sessions, 7 updated, 1 deleted".
+fn format_sync_result(verb: &str, result: &remote::SyncResult) -> String {
+    format!(
+        "{} '{}' in {:.1}s: {}",
+        verb,
+        result.remote_name,
+        result.duration.as_secs_f64(),
+        result.changes.describe()
+    )
 }
 
-// =============================================================================
-// Preview Mode (internal, replaces jaq dependency)
-// =============================================================================
+/// `--timing` lines for a sync pass: the whole pass, then each remote.
+fn report_sync_timing(timing: &mut timing::Timing, summary: &remote::SyncSummary) {
+    timing.lap("sync");
+    for result in &summary.successes {
+        timing.detail(&result.remote_name, result.duration, None);
+    }
+}
 
-/// Print formatted transcript preview for a session file.
-/// Used internally by skim's preview command.
-fn print_session_preview(filepath: &PathBuf) -> Result<()> {
-    let content = generate_preview_content(filepath)?;
-    print!("{}", content);
-    Ok(())
+/// `--timing` lines for discovery: the whole pass, then each source with how
+/// many files it had to parse, so slow scans can be told from slow disks.
+fn report_discovery_timing(timing: &mut timing::Timing, discovery: &claude_code::DiscoverySummary) {
+    timing.lap("discovery");
+    for source in &discovery.timings {
+        let counts = source.counts;
+        let note = format!(
+            "{} parsed, {} appended to, {} partially, {} cached",
+            progress::format_count(counts.scanned),
+            progress::format_count(counts.resumed),
+            progress::format_count(counts.partial),
+            progress::format_count(counts.cached)
+        );
+        timing.detail(&source.source_name, source.elapsed, Some(&note));
+    }
+}
+
+/// `cc-sessions index`: run discovery purely for its cache side effects.
+fn run_index(config: &remote::Config, args: &Args, timing: &mut timing::Timing) -> Result<()> {
+    let discovery =
+        claude_code::find_all_sessions_with_summary(config, args.remote.as_deref(), args.deep)?;
+    report_discovery_timing(timing, &discovery);
+    for failure in &discovery.failures {
+        eprintln!(
+            "Warning: Failed to index sessions from '{}': {}",
+            failure.source_name, failure.reason
+        );
+    }
+    let counts = discovery.counts;
+    println!(
+        "Indexed {} file(s) ({} appended to, {} partially), {} unchanged ({} sessions)",
+        progress::format_count(counts.scanned),
+        progress::format_count(counts.resumed),
+        progress::format_count(counts.partial),
+        progress::format_count(counts.cached),
+        progress::format_count(discovery.sessions.len())
+    );
+    // Built by the first `search`; kept fresh here so searches stay instant
+    if fulltext::FullTextIndex::exists() {
+        let mut index = fulltext::FullTextIndex::load(config.settings.search_tool_output);
+        let targets: Vec<&Path> = discovery
+            .sessions
+            .iter()
+            .filter(|s| !s.archived)
+            .map(|s| s.filepath.as_path())
+            .collect();
+        let updated = refresh_fulltext_index(&mut index, &targets);
+        println!(
+            "Search index: {} file(s) updated",
+            progress::format_count(updated)
+        );
+    }
+    enforce_strict_mode(args.strict, 0, discovery.failure_count())
+}
+
+/// `cc-sessions cache vacuum`: remove cache data nothing will read again.
+fn run_cache_vacuum(
+    config: &remote::Config,
+    dry_run: bool,
+    yes: bool,
+    recompress: bool,
+) -> Result<()> {
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    let mut reclaimed = 0u64;
+    let mut batch = undo::Batch::begin("cache vacuum")?;
+
+    // A removed remote's cache may be the last copy of its sessions (or the
+    // remote is only commented out for now), so ask first, and send it to
+    // the undo trash; index files are rebuilt from the transcripts.
+    let mut orphans = remote::find_orphaned_caches(config)?;
+    if !orphans.is_empty() && !dry_run && !yes {
+        for orphan in &orphans {
+            println!(
+                "Cache for unconfigured remote '{}' ({}): {}",
+                orphan.name,
+                stats::format_size(orphan.bytes),
+                orphan.path.display()
+            );
+        }
+        if !confirm(&format!("Remove {} cache dir(s)?", orphans.len()))? {
+            println!("Kept the remote caches");
+            orphans.clear();
+        }
+    }
+    for orphan in orphans {
+        if !dry_run {
+            batch.trash(&orphan.path).with_context(|| {
+                format!("Failed to remove cache dir: {}", orphan.path.display())
+            })?;
+        }
+        println!(
+            "{} cache for unconfigured remote '{}' ({})",
+            verb,
+            orphan.name,
+            stats::format_size(orphan.bytes)
+        );
+        reclaimed += orphan.bytes;
+    }
+
+    // Index files are named after the roots discovery scans; anything else
+    // belongs to a root that no longer exists.
+    let mut live_roots = claude_code::local_projects_dirs()?;
+    live_roots.push(claude_code::get_claude_projects_dir()?);
+    live_roots.push(archive::archive_dir()?);
+    for name in config.remotes.keys() {
+        live_roots.push(remote::get_remote_cache_dir(&config.settings, name)?);
+    }
+    let stale = metadata_cache::stale_cache_files(&live_roots)?;
+    if !stale.is_empty() {
+        let bytes: u64 = stale.iter().map(|(_, b)| b).sum();
+        if !dry_run {
+            for (path, _) in &stale {
+                std::fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        println!(
+            "{} {} stale index file(s) ({})",
+            verb,
+            stale.len(),
+            stats::format_size(bytes)
+        );
+        reclaimed += bytes;
+    }
+
+    if recompress {
+        reclaimed += recompress_archive(dry_run)?;
+    }
+
+    if reclaimed == 0 {
+        if !(dry_run && recompress) {
+            println!("Cache is already tidy");
+        }
+    } else if dry_run {
+        println!("Would reclaim {}", stats::format_size(reclaimed));
+    } else {
+        println!("Reclaimed {}", stats::format_size(reclaimed));
+    }
+    print_undo_hint(&batch);
+    Ok(())
+}
+
+/// Ask a yes/no question on the terminal; without one, refuse and point at
+/// `--yes`.
+fn confirm(question: &str) -> Result<bool> {
+    use std::io::{BufRead, IsTerminal, Write};
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("{} Pass --yes to confirm without a terminal", question);
+    }
+    eprint!("{} [y/N] ", question);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// `cache vacuum --recompress`: rewrite archived transcripts at gzip's best
+/// level, keeping their scan index entries valid so discovery doesn't
+/// decompress them again. Returns the bytes saved.
+fn recompress_archive(dry_run: bool) -> Result<u64> {
+    let archive_dir = archive::archive_dir()?;
+    let files: Vec<PathBuf> = walkdir::WalkDir::new(&archive_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && archive::is_archived(e.path()))
+        .map(|e| e.into_path())
+        .collect();
+    if dry_run {
+        if !files.is_empty() {
+            println!("Would recompress {} archived transcript(s)", files.len());
+        }
+        return Ok(0);
+    }
+
+    let mut cache: metadata_cache::MetadataCache<serde_json::Value> =
+        metadata_cache::MetadataCache::load(&archive_dir);
+    let (mut shrunk, mut saved) = (0usize, 0u64);
+    for path in &files {
+        match archive::recompress(path) {
+            Ok(0) => {}
+            Ok(bytes) => {
+                shrunk += 1;
+                saved += bytes;
+                if let (Some(scan), Ok(meta)) = (cache.previous(path).cloned(), path.metadata()) {
+                    cache.insert(path.clone(), metadata_cache::Fingerprint::of(&meta), scan);
+                }
+            }
+            Err(e) => eprintln!("Warning: {:#}", e),
+        }
+    }
+    if let Err(e) = cache.save() {
+        eprintln!(
+            "Warning: could not update the archive's scan index: {:#}",
+            e
+        );
+    }
+    if shrunk > 0 {
+        println!(
+            "Recompressed {} archived transcript(s) ({} smaller)",
+            shrunk,
+            stats::format_size(saved)
+        );
+    }
+    Ok(saved)
+}
+
+/// After a command that recorded an undo batch.
+fn print_undo_hint(batch: &undo::Batch) {
+    if !batch.is_empty() {
+        println!(
+            "Undo with `cc-sessions undo` (within {} days)",
+            undo::KEEP_DAYS
+        );
+    }
+}
+
+/// `cc-sessions team publish|unpublish`.
+fn run_team(config: &remote::Config, args: &Args, action: &TeamCommand) -> Result<()> {
+    let (TeamCommand::Publish { team, .. } | TeamCommand::Unpublish { team }) = action;
+    let (name, team) = match team {
+        Some(name) => config
+            .teams
+            .get_key_value(name)
+            .with_context(|| format!("Unknown team '{}'", name))?,
+        None => {
+            let mut teams = config.teams.iter();
+            match (teams.next(), teams.next()) {
+                (Some(only), None) => only,
+                (None, _) => anyhow::bail!("No teams configured; add a [teams.<name>] section"),
+                (Some(_), Some(_)) => anyhow::bail!("Several teams are configured; name one"),
+            }
+        }
+    };
+
+    let TeamCommand::Publish {
+        transcripts,
+        no_redact,
+        dry_run,
+        ..
+    } = *action
+    else {
+        if team::unpublish(team)? {
+            println!("Removed your sessions from team '{}'", name);
+        } else {
+            println!("Nothing of yours is published to team '{}'", name);
+        }
+        return Ok(());
+    };
+
+    let discovery = claude_code::find_all_sessions_with_summary(config, Some("local"), false)?;
+    for failure in &discovery.failures {
+        eprintln!(
+            "Warning: Failed to load sessions from '{}': {}",
+            failure.source_name, failure.reason
+        );
+    }
+    let project = args.project.as_deref().map(str::to_lowercase);
+    let sessions: Vec<&Session> = discovery
+        .sessions
+        .iter()
+        .filter(|s| s.source.is_local())
+        .filter(|s| {
+            project
+                .as_deref()
+                .is_none_or(|p| s.project.to_lowercase().contains(p))
+        })
+        .filter(|s| args.min_turns.is_none_or(|min| s.turn_count >= min))
+        .collect();
+    let redactor = redact::Redactor::new(&config.share.redact);
+    let published = team::publish(
+        team,
+        &sessions,
+        &team::PublishOptions {
+            transcripts,
+            redactor: (!no_redact).then_some(&redactor),
+            dry_run,
+        },
+    )?;
+
+    let verb = if dry_run {
+        "Would publish"
+    } else {
+        "Published"
+    };
+    let transcripts = if transcripts {
+        format!(" with {} transcript(s)", published.transcripts)
+    } else {
+        String::new()
+    };
+    println!(
+        "{} {} session(s){} to team '{}' as {}",
+        verb, published.sessions, transcripts, name, published.owner
+    );
+    if published.redacted > 0 {
+        println!("Redacted {} value(s)", published.redacted);
+    }
+    if published.withdrawn > 0 {
+        let verb = if dry_run {
+            "Would withdraw"
+        } else {
+            "Withdrew"
+        };
+        println!(
+            "{} {} previously published transcript(s)",
+            verb, published.withdrawn
+        );
+    }
+    Ok(())
+}
+
+/// `cc-sessions archive`: move old local transcripts into the archive.
+/// Honors `--project` and `--min-turns` like the listing does.
+fn run_archive(
+    config: &remote::Config,
+    args: &Args,
+    older_than: &str,
+    dry_run: bool,
+) -> Result<()> {
+    let cutoff = dates::parse_period_start(older_than, SystemTime::now())?;
+    let discovery = claude_code::find_all_sessions_with_summary(config, Some("local"), false)?;
+    let project = args.project.as_deref().map(str::to_lowercase);
+    let candidates: Vec<&Session> = discovery
+        .sessions
+        .iter()
+        .filter(|s| !s.archived && s.modified < cutoff)
+        .filter(|s| {
+            project
+                .as_deref()
+                .is_none_or(|p| s.project.to_lowercase().contains(p))
+        })
+        .filter(|s| args.min_turns.is_none_or(|min| s.turn_count >= min))
+        .collect();
+    if candidates.is_empty() {
+        println!("No local sessions older than {}", older_than);
+        return Ok(());
+    }
+
+    let before: u64 = candidates.iter().map(|s| s.size).sum();
+    if dry_run {
+        for session in &candidates {
+            println!(
+                "Would archive {} ({}, {})",
+                session.id,
+                session.project,
+                stats::format_size(session.size)
+            );
+        }
+        println!(
+            "Would archive {} session(s) ({})",
+            candidates.len(),
+            stats::format_size(before)
+        );
+        return Ok(());
+    }
+
+    let roots = claude_code::local_projects_dirs()?;
+    let archive_dir = archive::archive_dir()?;
+    let mut batch = undo::Batch::begin("archive")?;
+    let results = claude_code::archive_sessions(&candidates, &roots, &archive_dir);
+    let (mut archived, mut original, mut compressed) = (0usize, 0u64, 0u64);
+    for (session, result) in results {
+        match result {
+            Ok(size) => {
+                archived += 1;
+                original += session.size;
+                compressed += size;
+                if let Some(dest) = archive::archived_path(&archive_dir, &roots, &session.filepath)
+                    && let Err(e) = batch.record(undo::Action::Archived {
+                        original: session.filepath.clone(),
+                        archived: dest,
+                    })
+                {
+                    eprintln!("Warning: {:#}", e);
+                }
+            }
+            Err(e) => eprintln!("Warning: Failed to archive {}: {:#}", session.id, e),
+        }
+    }
+    println!(
+        "Archived {} session(s): {} → {}",
+        archived,
+        stats::format_size(original),
+        stats::format_size(compressed)
+    );
+    print_undo_hint(&batch);
+    if archived < candidates.len() {
+        anyhow::bail!(
+            "{} session(s) could not be archived",
+            candidates.len() - archived
+        );
+    }
+    Ok(())
+}
+
+/// `cc-sessions forks prune`: delete local forks nothing was added to.
+///
+/// Discovery only flags candidates (no entry written after forking); each is
+/// re-proven against its parent's file before deletion. Tagged forks and
+/// forks renamed by the user are kept — someone cared enough to label them.
+/// The "(Fork)" title Claude Code inherits from the parent doesn't count.
+/// Forks `forks prune` may delete, with their parents, before the
+/// transcript comparison. Renamed or tagged forks are kept, as are orphans
+/// (the copy is the only one left) and forks other sessions were forked
+/// from, which would be orphaned in turn.
+fn prune_candidates(sessions: &[Session]) -> Vec<(&Session, &Session)> {
+    let by_id: std::collections::HashMap<&str, &Session> =
+        sessions.iter().map(|s| (s.id.as_str(), s)).collect();
+    let parents: std::collections::HashSet<&str> = sessions
+        .iter()
+        .filter_map(|s| s.forked_from.as_deref())
+        .collect();
+    sessions
+        .iter()
+        .filter(|s| s.redundant_fork && !s.archived)
+        .filter(|fork| {
+            let renamed = fork
+                .name
+                .as_deref()
+                .is_some_and(|n| !n.trim_end().ends_with("(Fork)"));
+            !renamed && fork.tag.is_none() && !parents.contains(fork.id.as_str())
+        })
+        .filter_map(|fork| {
+            let parent = by_id.get(fork.forked_from.as_deref()?)?;
+            Some((fork, *parent))
+        })
+        .collect()
+}
+
+fn run_forks_prune(config: &remote::Config, dry_run: bool) -> Result<()> {
+    let mut discovery = claude_code::find_all_sessions_with_summary(config, Some("local"), false)?;
+    state::ForkOverrides::load().apply(&mut discovery.sessions);
+
+    let verb = if dry_run { "Would delete" } else { "Deleted" };
+    let (mut pruned, mut reclaimed) = (0usize, 0u64);
+    let mut batch = undo::Batch::begin("forks prune")?;
+    for (fork, parent) in prune_candidates(&discovery.sessions) {
+        match claude_code::fork_is_redundant(&fork.filepath, &parent.filepath) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                eprintln!("Warning: Could not compare fork {}: {}", fork.id, e);
+                continue;
+            }
+        }
+        if !dry_run {
+            batch
+                .trash(&fork.filepath)
+                .with_context(|| format!("Failed to delete {}", fork.filepath.display()))?;
+        }
+        println!(
+            "{} {} ({}, fork of {})",
+            verb,
+            fork.id,
+            fork.project,
+            &parent.id[..8.min(parent.id.len())]
+        );
+        pruned += 1;
+        reclaimed += fork.size;
+    }
+
+    if pruned == 0 {
+        println!("No redundant forks");
+    } else {
+        println!(
+            "{} {} redundant fork(s) ({})",
+            verb,
+            pruned,
+            stats::format_size(reclaimed)
+        );
+    }
+    print_undo_hint(&batch);
+    Ok(())
+}
+
+/// `cc-sessions undo`: reverse the newest batch of `forks prune`,
+/// `fsck --fix`, `archive`, `cache vacuum`, or a picker delete.
+fn run_undo(dry_run: bool) -> Result<()> {
+    let root = undo::undo_dir()?;
+    let Some((dir, journal)) = undo::latest(&root)? else {
+        println!("Nothing to undo");
+        return Ok(());
+    };
+    let when = dates::format_timestamp(
+        SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(journal.created),
+    );
+    if dry_run {
+        println!("Would undo `{}` from {}:", journal.command, when);
+        for action in journal.actions.iter().rev() {
+            println!("  restore {}", action.original().display());
+        }
+        return Ok(());
+    }
+
+    println!("Undoing `{}` from {}", journal.command, when);
+    let (roots, archive_dir) = (claude_code::local_projects_dirs()?, archive::archive_dir()?);
+    let results = undo::undo(&dir, journal, |archived| {
+        claude_code::restore_archived(archived, &archive_dir, &roots)
+    });
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+    for (action, result) in &results {
+        match result {
+            Ok(()) => println!("Restored {}", action.original().display()),
+            Err(e) => eprintln!(
+                "Warning: Failed to restore {}: {:#}",
+                action.original().display(),
+                e
+            ),
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!(
+            "{} action(s) could not be undone; fix the cause and run `cc-sessions undo` again",
+            failed
+        );
+    }
+    Ok(())
+}
+
+/// `cc-sessions forks orphans|reparent|flatten|reset`.
+fn run_forks(config: &remote::Config, args: &Args, action: &ForksCommand) -> Result<()> {
+    let discovery =
+        claude_code::find_all_sessions_with_summary(config, args.remote.as_deref(), args.deep)?;
+    for failure in &discovery.failures {
+        eprintln!(
+            "Warning: Failed to load sessions from '{}': {}",
+            failure.source_name, failure.reason
+        );
+    }
+    let mut sessions = discovery.sessions;
+    let mut overrides = state::ForkOverrides::load();
+
+    match action {
+        ForksCommand::Prune { .. } => unreachable!("dispatched to run_forks_prune"),
+        ForksCommand::Orphans => {
+            overrides.apply(&mut sessions);
+            print_orphans(&find_orphans(&sessions));
+            return Ok(());
+        }
+        ForksCommand::Reparent { fork, parent } => {
+            let fork = find_session_by_prefix(&sessions, fork)?;
+            let parent = find_session_by_prefix(&sessions, parent)?;
+            if fork.id == parent.id {
+                anyhow::bail!("A session can't be its own parent");
+            }
+            let (fork_id, parent_id) = (fork.id.clone(), parent.id.clone());
+            // Reject decisions that would make the fork its own ancestor.
+            overrides.apply(&mut sessions);
+            if is_ancestor(&sessions, &fork_id, &parent_id) {
+                anyhow::bail!(
+                    "{} descends from {}; that would create a cycle",
+                    parent_id,
+                    fork_id
+                );
+            }
+            overrides.set(
+                &fork_id,
+                state::ForkDecision::Reparent {
+                    parent: parent_id.clone(),
+                },
+            );
+            println!("{} is now a fork of {}", fork_id, parent_id);
+        }
+        ForksCommand::Flatten { fork } => {
+            overrides.apply(&mut sessions);
+            let fork = find_session_by_prefix(&sessions, fork)?;
+            if fork.forked_from.is_none() {
+                if overrides.forks.get(&fork.id) == Some(&state::ForkDecision::Flatten) {
+                    println!("{} is already shown as a root session", fork.id);
+                    return Ok(());
+                }
+                anyhow::bail!("{} is not a fork", fork.id);
+            }
+            println!("{} is now shown as a root session", fork.id);
+            overrides.set(&fork.id, state::ForkDecision::Flatten);
+        }
+        ForksCommand::Reset { fork } => {
+            let fork = find_session_by_prefix(&sessions, fork)?;
+            if !overrides.reset(&fork.id) {
+                println!("No decision recorded for {}", fork.id);
+                return Ok(());
+            }
+            println!("{} uses its recorded parent again", fork.id);
+        }
+    }
+    overrides.save()?;
+    sync_state_dir(&config.settings);
+    Ok(())
+}
+
+/// A named profile must be created before use, so a typo in `--profile`
+/// doesn't silently run with an empty environment. `remote add` creates it.
+fn check_profile_exists(args: &Args) -> Result<()> {
+    let Some(name) = profile::active() else {
+        return Ok(());
+    };
+    let creates_profile = matches!(
+        args.command,
+        Some(Command::Remote {
+            action: RemoteCommand::Add(_)
+        })
+    );
+    let path = remote::get_config_path()?;
+    if !creates_profile && !path.exists() {
+        anyhow::bail!(
+            "Profile '{}' doesn't exist: create {} or run `cc-sessions --profile {} remote add <name> <host>`",
+            name,
+            path.display(),
+            name
+        );
+    }
+    Ok(())
+}
+
+fn no_remotes_message() -> String {
+    match remote::get_config_path() {
+        Ok(path) => format!("No remotes configured. Add remotes to {}", path.display()),
+        Err(_) => "No remotes configured".to_string(),
+    }
+}
+
+/// Merge fork decisions with `settings.state_sync_dir`, if set. Runs on
+/// every invocation: it's two small files, and a decision made on another
+/// machine should show up without waiting for a sync.
+fn sync_state_dir(settings: &remote::Settings) {
+    let Some(dir) = &settings.state_sync_dir else {
+        return;
+    };
+    if let Err(e) = remote::expand_path(dir).and_then(|dir| state::sync_with_dir(&dir)) {
+        eprintln!("Warning: Failed to sync state with {}: {:#}", dir, e);
+    }
+}
+
+/// Forks whose parent isn't among `sessions`.
+fn find_orphans(sessions: &[Session]) -> Vec<&Session> {
+    let ids: std::collections::HashSet<&str> = sessions.iter().map(|s| s.id.as_str()).collect();
+    sessions
+        .iter()
+        .filter(|s| s.forked_from.as_deref().is_some_and(|p| !ids.contains(p)))
+        .collect()
+}
+
+fn print_orphans(orphans: &[&Session]) {
+    if orphans.is_empty() {
+        println!("No orphaned forks");
+        return;
+    }
+    println!(
+        "{:<8} {:<16} {:<36} {:<36} SUMMARY",
+        "SOURCE", "PROJECT", "FORK", "MISSING PARENT"
+    );
+    for s in orphans {
+        println!(
+            "{:<8} {:<16} {:<36} {:<36} {}",
+            s.source.display_name(),
+            s.project,
+            s.id,
+            s.forked_from.as_deref().unwrap_or_default(),
+            format_session_desc(s, 40)
+        );
+    }
+    println!(
+        "{} orphaned fork(s). Resolve with `cc-sessions forks reparent <fork> <parent>` or `forks flatten <fork>`",
+        orphans.len()
+    );
+}
+
+/// Look up a session by full ID or unique ID prefix.
+fn find_session_by_prefix<'a>(sessions: &'a [Session], prefix: &str) -> Result<&'a Session> {
+    let mut matches = sessions.iter().filter(|s| s.id.starts_with(prefix));
+    let first = matches
+        .next()
+        .with_context(|| format!("No session matches '{}'", prefix))?;
+    // The same session can be seen through several sources (e.g. a remote
+    // cache of a session that also exists locally).
+    if matches.any(|s| s.id != first.id) {
+        anyhow::bail!(
+            "'{}' matches more than one session; use more characters",
+            prefix
+        );
+    }
+    Ok(first)
+}
+
+/// `cc-sessions export <id>`.
+fn run_export(
+    config: &remote::Config,
+    args: &Args,
+    id: &str,
+    format: export::ExportFormat,
+    output: Option<&Path>,
+) -> Result<()> {
+    let discovery =
+        claude_code::find_all_sessions_with_summary(config, args.remote.as_deref(), args.deep)?;
+    for failure in &discovery.failures {
+        eprintln!(
+            "Warning: Failed to load sessions from '{}': {}",
+            failure.source_name, failure.reason
+        );
+    }
+    let session = find_session_by_prefix(&discovery.sessions, id)?;
+    let messages = claude_code::read_messages(&session.filepath)?;
+    let rendered = match format {
+        export::ExportFormat::Json => format!("{}\n", export::render_json(session, &messages)?),
+        export::ExportFormat::Markdown => {
+            export::render_markdown(session, &messages, 1, messages.len())
+        }
+    };
+    match output {
+        Some(path) => {
+            std::fs::write(path, rendered)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!("Wrote {} message(s) to {}", messages.len(), path.display());
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+/// `cc-sessions show <id>`.
+fn run_show(config: &remote::Config, args: &Args, id: &str) -> Result<()> {
+    use std::io::Write as _;
+
+    let discovery =
+        claude_code::find_all_sessions_with_summary(config, args.remote.as_deref(), args.deep)?;
+    for failure in &discovery.failures {
+        eprintln!(
+            "Warning: Failed to load sessions from '{}': {}",
+            failure.source_name, failure.reason
+        );
+    }
+    let session = find_session_by_prefix(&discovery.sessions, id)?;
+    let messages = claude_code::read_messages(&session.filepath)?;
+    let rendered = plain::render(&render_transcript(session, &messages)).into_owned();
+    // A pager quit early is not an error
+    match std::io::stdout().lock().write_all(rendered.as_bytes()) {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// A whole transcript for `show`: a header, then every message as `follow`
+/// renders it, with a blank line between messages.
+fn render_transcript(session: &Session, messages: &[session::Message]) -> String {
+    let mut out = format!(
+        "{}{} │ {} │ {} │ {} message(s){}\n",
+        colors::BOLD,
+        format_session_desc(session, 80),
+        session.id,
+        session.source.display_name(),
+        messages.len(),
+        colors::RESET
+    );
+    for message in messages {
+        let lines = follow::render(message);
+        if lines.is_empty() {
+            continue;
+        }
+        out.push('\n');
+        for line in lines {
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// `cc-sessions share <id>`.
+fn run_share(
+    config: &remote::Config,
+    args: &Args,
+    id: &str,
+    range: Option<share::Range>,
+    redact: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let discovery =
+        claude_code::find_all_sessions_with_summary(config, args.remote.as_deref(), args.deep)?;
+    for failure in &discovery.failures {
+        eprintln!(
+            "Warning: Failed to load sessions from '{}': {}",
+            failure.source_name, failure.reason
+        );
+    }
+    let session = find_session_by_prefix(&discovery.sessions, id)?;
+    let messages = claude_code::read_messages(&session.filepath)?;
+    let selected = match range {
+        Some(range) => range.select(messages.len())?,
+        None => 0..messages.len(),
+    };
+    let mut markdown = export::render_markdown(
+        session,
+        &messages[selected.clone()],
+        selected.start + 1,
+        messages.len(),
+    );
+    if redact {
+        let redacted = redact::Redactor::new(&config.share.redact).redact(&markdown);
+        if redacted.count > 0 {
+            eprintln!("Redacted {} value(s)", redacted.count);
+        }
+        markdown = redacted.text;
+    }
+    if dry_run {
+        print!("{}", markdown);
+        return Ok(());
+    }
+
+    let short_id = &session.id[..session.id.len().min(8)];
+    let url = share::upload(
+        &config.share,
+        &format!("{}-{}.md", session.project, short_id),
+        &format!("Claude Code session {} in {}", short_id, session.project),
+        &markdown,
+    )?;
+    println!("{}", url);
+    Ok(())
+}
+
+/// Transcript entries `follow` shows before new ones.
+const FOLLOW_BACKLOG: usize = 20;
+
+/// `cc-sessions follow <id>`.
+fn run_follow(config: &remote::Config, args: &Args, id: &str, lines: usize) -> Result<()> {
+    let discovery =
+        claude_code::find_all_sessions_with_summary(config, args.remote.as_deref(), args.deep)?;
+    for failure in &discovery.failures {
+        eprintln!(
+            "Warning: Failed to load sessions from '{}': {}",
+            failure.source_name, failure.reason
+        );
+    }
+    let session = find_session_by_prefix(&discovery.sessions, id)?;
+    eprintln!("Following [{}]", format_session_desc(session, 60));
+    follow_session(config, session, lines)
+}
+
+/// Follow a local transcript in place, or a remote one on its machine.
+fn follow_session(config: &remote::Config, session: &Session, lines: usize) -> Result<()> {
+    let source = match &session.source {
+        _ if session.archived => anyhow::bail!("Archived sessions aren't being written to"),
+        SessionSource::Local => follow::Source::File(session.filepath.clone()),
+        SessionSource::Remote { name, .. } => {
+            let remote = config
+                .remotes
+                .get(name)
+                .with_context(|| format!("Remote '{}' is no longer configured", name))?;
+            follow::Source::Ssh {
+                target: remote.require_ssh(name, "follow")?,
+                path: remote::remote_file_path(&config.settings, name, remote, &session.filepath)?,
+            }
+        }
+        SessionSource::Team { owner, .. } => {
+            anyhow::bail!(
+                "Team sessions are published copies; ask {} for a fresh one",
+                owner
+            )
+        }
+    };
+    follow::follow(&source, lines)
+}
+
+/// `cc-sessions ws save|list|remove`.
+fn run_ws(config: &remote::Config, args: &Args, action: &WsCommand) -> Result<()> {
+    let mut workspaces = state::Workspaces::load();
+    if let WsCommand::Remove { name, ids } = action {
+        let Some(removed) = workspaces.remove(name, ids) else {
+            anyhow::bail!("No workspace named '{}'", name);
+        };
+        if ids.is_empty() {
+            println!("Deleted workspace '{}' ({} session(s))", name, removed);
+        } else {
+            println!("Removed {} session(s) from '{}'", removed, name);
+        }
+        return workspaces.save();
+    }
+
+    let discovery =
+        claude_code::find_all_sessions_with_summary(config, args.remote.as_deref(), args.deep)?;
+    for failure in &discovery.failures {
+        eprintln!(
+            "Warning: Failed to load sessions from '{}': {}",
+            failure.source_name, failure.reason
+        );
+    }
+    let sessions = discovery.sessions;
+    match action {
+        WsCommand::Remove { .. } => unreachable!("handled above"),
+        WsCommand::Save { name, ids } => {
+            let matched = ids
+                .iter()
+                .map(|prefix| find_session_by_prefix(&sessions, prefix))
+                .collect::<Result<Vec<_>>>()?;
+            let added = workspaces.add(name, matched.iter().map(|s| s.id.as_str()));
+            workspaces.save()?;
+            let total = workspaces.get(name).map_or(0, |ids| ids.len());
+            println!(
+                "Workspace '{}': {} session(s) ({} added); open it with --workspace {}",
+                name, total, added, name
+            );
+        }
+        WsCommand::List => {
+            if workspaces.workspaces.is_empty() {
+                println!("No workspaces; save one with `cc-sessions ws save NAME IDS...`");
+            }
+            let by_id: std::collections::HashMap<&str, &Session> =
+                sessions.iter().map(|s| (s.id.as_str(), s)).collect();
+            for (name, ids) in &workspaces.workspaces {
+                println!("{} ({} session(s))", name, ids.len());
+                for id in ids {
+                    match by_id.get(id.as_str()) {
+                        Some(s) => println!("  {}  {}", id, format_session_desc(s, 60)),
+                        None => println!("  {}  (not found)", id),
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The session resumed before the most recent resume (`cc-sessions -`). A
+/// session cached from a remote that also exists locally opens locally.
+fn previous_session(sessions: &[Session]) -> Result<&Session> {
+    let history = state::ResumeHistory::load();
+    let Some(id) = history.previous() else {
+        anyhow::bail!("`cc-sessions -` needs two sessions resumed through cc-sessions first");
+    };
+    sessions
+        .iter()
+        .filter(|s| s.id == id)
+        .min_by_key(|s| !s.source.is_local())
+        .with_context(|| format!("The previously resumed session {} no longer exists", id))
+}
+
+/// Match recorded cwds against `dir`, or with `nested` against `dir` and
+/// anything beneath it. Recorded cwds may or may not be canonical (e.g.
+/// macOS /var vs /private/var), so both spellings of `dir` count.
+fn path_matcher(dir: &Path, nested: bool) -> impl Fn(&Path) -> bool + '_ {
+    let canonical = dir.canonicalize().ok();
+    move |recorded| {
+        let hit = |d: &Path| {
+            if nested {
+                repo::is_within(recorded, d)
+            } else {
+                recorded == d
+            }
+        };
+        hit(dir) || canonical.as_deref().is_some_and(hit)
+    }
+}
+
+/// The most recently modified local session whose project is `dir`
+/// (`--last`). Only an exact match counts: a session started in a
+/// subdirectory belongs to that directory.
+fn last_session_in<'a>(sessions: &'a [Session], dir: &Path) -> Result<&'a Session> {
+    let matches = path_matcher(dir, false);
+    sessions
+        .iter()
+        .filter(|s| s.source.is_local())
+        .filter(|s| matches(Path::new(&s.project_path)))
+        .max_by_key(|s| s.modified)
+        .with_context(|| {
+            format!(
+                "No sessions in {} (run without --last to pick one)",
+                dir.display()
+            )
+        })
+}
+
+/// Keep only the sessions saved in workspace `name`.
+fn filter_to_workspace(sessions: &mut Vec<Session>, name: &str) -> Result<()> {
+    let workspaces = state::Workspaces::load();
+    let Some(ids) = workspaces.get(name) else {
+        anyhow::bail!("No workspace named '{}' (see `cc-sessions ws list`)", name);
+    };
+    sessions.retain(|s| ids.contains(&s.id));
+    let missing = ids
+        .iter()
+        .filter(|id| !sessions.iter().any(|s| &s.id == *id))
+        .count();
+    if missing > 0 {
+        eprintln!(
+            "Warning: {} session(s) of workspace '{}' were not found",
+            missing, name
+        );
+    }
+    Ok(())
+}
+
+/// Whether `ancestor` appears on `id`'s parent chain.
+fn is_ancestor(sessions: &[Session], ancestor: &str, id: &str) -> bool {
+    let parents: std::collections::HashMap<&str, &str> = sessions
+        .iter()
+        .filter_map(|s| Some((s.id.as_str(), s.forked_from.as_deref()?)))
+        .collect();
+    let mut current = id;
+    let mut steps = 0;
+    while let Some(&parent) = parents.get(current) {
+        if parent == ancestor {
+            return true;
+        }
+        current = parent;
+        steps += 1;
+        if steps > parents.len() {
+            return false; // Pre-existing cycle elsewhere in the chain
+        }
+    }
+    false
+}
+
+/// `cc-sessions verify <remote>`: list cached files that differ from the remote.
+fn run_verify(config: &remote::Config, name: &str, strict: bool) -> Result<()> {
+    let remote_config = config
+        .remotes
+        .get(name)
+        .with_context(|| format!("Unknown remote '{}'", name))?;
+    let verification = remote::verify_remote(name, remote_config, &config.settings)?;
+    let drift = verification.drift;
+
+    if drift.is_empty() {
+        let compared = if verification.by_checksum {
+            "checksums"
+        } else {
+            "sizes"
+        };
+        println!("{}: cache matches remote ({} verified)", name, compared);
+        return Ok(());
+    }
+    println!("{}: {} file(s) differ from the remote", name, drift.len());
+    for d in &drift {
+        println!("  {:<8} {}", d.kind.label(), d.path);
+    }
+    println!("Run `cc-sessions --sync-only` to bring the cache up to date");
+
+    if strict {
+        anyhow::bail!("Strict mode: cache for '{}' has drifted", name);
+    }
+    Ok(())
+}
+
+/// `cc-sessions remote <action>`.
+fn run_remote(config: &remote::Config, args: &Args, action: &RemoteCommand) -> Result<()> {
+    match action {
+        RemoteCommand::Du { top } => run_remote_du(config, args, *top),
+        RemoteCommand::Check => run_remote_check(config, args),
+        RemoteCommand::Add(add) => run_remote_add(config, add),
+        RemoteCommand::Remove { name } => run_remote_remove(config, name),
+        RemoteCommand::Rename { old, new } => run_remote_rename(config, old, new),
+        RemoteCommand::List => run_remote_list(config),
+    }
+}
+
+/// Configured remotes passing `--remote`, sorted by name.
+fn selected_remotes<'a>(
+    config: &'a remote::Config,
+    args: &Args,
+) -> Vec<(&'a String, &'a remote::RemoteConfig)> {
+    let mut remotes: Vec<_> = config
+        .remotes
+        .iter()
+        .filter(|(name, _)| config.source_matches(args.remote.as_deref(), name))
+        .collect();
+    remotes.sort_by_key(|(name, _)| *name);
+    remotes
+}
+
+/// `cc-sessions remote du`: what each remote's transcripts take up there and
+/// in the local cache, with the biggest projects and sessions to prune.
+fn run_remote_du(config: &remote::Config, args: &Args, top: usize) -> Result<()> {
+    use rayon::prelude::*;
+
+    let remotes = selected_remotes(config, args);
+    if remotes.is_empty() {
+        println!("{}", no_remotes_message());
+        return Ok(());
+    }
+    let results: Vec<_> = remotes
+        .par_iter()
+        .map(|(name, remote)| remote::remote_disk_usage(name, remote))
+        .collect();
+
+    let mut failures = 0;
+    for ((name, _), result) in remotes.iter().zip(results) {
+        let cached = remote::get_remote_cache_dir(&config.settings, name)
+            .map(|dir| remote::dir_size(&dir))
+            .unwrap_or(0);
+        let usage = match result {
+            Ok(usage) => usage,
+            Err(e) => {
+                eprintln!("Warning: Failed to measure '{}': {:#}", name, e);
+                failures += 1;
+                continue;
+            }
+        };
+        println!(
+            "{}: {} on remote, {} cached locally",
+            name,
+            stats::format_size(usage.total),
+            stats::format_size(cached)
+        );
+        for (heading, entries) in [
+            ("Largest projects", &usage.projects),
+            ("Largest sessions", &usage.sessions),
+        ] {
+            if entries.is_empty() || top == 0 {
+                continue;
+            }
+            println!("  {}:", heading);
+            for (path, bytes) in entries.iter().take(top) {
+                println!("    {:>9}  {}", stats::format_size(*bytes), path);
+            }
+        }
+    }
+    if args.strict && failures > 0 {
+        anyhow::bail!("Strict mode: {} remote(s) could not be measured", failures);
+    }
+    Ok(())
+}
+
+/// `cc-sessions remote check`: smoke-test every remote (e.g. after network
+/// or VPN changes) and print one row per remote.
+fn run_remote_check(config: &remote::Config, args: &Args) -> Result<()> {
+    use rayon::prelude::*;
+
+    let remotes = selected_remotes(config, args);
+    if remotes.is_empty() {
+        println!("{}", no_remotes_message());
+        return Ok(());
+    }
+    let results: Vec<remote::RemoteHealth> = remotes
+        .par_iter()
+        .map(|(name, remote)| remote::check_remote(name, remote))
+        .collect();
+
+    let names: Vec<&str> = remotes.iter().map(|(name, _)| name.as_str()).collect();
+    print!("{}", render_remote_checks(&names, &results));
+    let unhealthy = results.iter().filter(|h| !h.is_healthy()).count();
+    if args.strict && unhealthy > 0 {
+        anyhow::bail!("Strict mode: {} remote(s) failed checks", unhealthy);
+    }
+    Ok(())
+}
+
+fn render_remote_checks(names: &[&str], results: &[remote::RemoteHealth]) -> String {
+    let width = names
+        .iter()
+        .map(|n| n.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+    let mark = |ok: bool| if ok { "ok" } else { "missing" };
+    let connected = |transport| match transport {
+        remote::Transport::Ssh | remote::Transport::Sftp => "ok",
+        remote::Transport::Rsyncd => "rsyncd",
+    };
+    // Without rsync, sync copies files with sftp instead
+    let sync_tool = |health: &remote::RemoteHealth| {
+        if health.rsync && health.transport != remote::Transport::Sftp {
+            "ok"
+        } else {
+            "sftp"
+        }
+    };
+    let mut out = format!(
+        "{:<width$}  {:<7}  {:<7}  {:<8}  {}\n",
+        "REMOTE", "SSH", "RSYNC", "PROJECTS", "CLAUDE"
+    );
+    let mut errors = String::new();
+    for (name, health) in names.iter().zip(results) {
+        let row = match &health.ssh_error {
+            Some(error) => {
+                errors.push_str(&format!("{}: {}\n", name, error));
+                format!("{:<7}  {:<7}  {:<8}  {}", "failed", "-", "-", "-")
+            }
+            None => format!(
+                "{:<7}  {:<7}  {:<8}  {}",
+                connected(health.transport),
+                sync_tool(health),
+                mark(health.projects_dir),
+                health.claude.map_or("n/a", mark)
+            ),
+        };
+        out.push_str(&format!("{:<width$}  {}\n", name, row));
+    }
+    if !errors.is_empty() {
+        out.push('\n');
+        out.push_str(&errors);
+    }
+    out
+}
+
+/// `cc-sessions remote list`: one row per configured remote.
+fn run_remote_list(config: &remote::Config) -> Result<()> {
+    if config.remotes.is_empty() {
+        println!("No remotes configured. Add one with `cc-sessions remote add <name> <host>`");
+        return Ok(());
+    }
+    let mut names: Vec<&String> = config.remotes.keys().collect();
+    names.sort();
+    let rows: Vec<[String; 4]> = names
+        .iter()
+        .map(|name| {
+            let remote = &config.remotes[*name];
+            let last_sync = remote::last_sync_time(&config.settings, name)
+                .map(|t| match format_time_relative(t).as_str() {
+                    "now" => "just now".to_string(),
+                    age => format!("{} ago", age),
+                })
+                .unwrap_or_else(|| "never".to_string());
+            let target = match remote.transport {
+                remote::Transport::Ssh | remote::Transport::Sftp => remote::ssh_target(remote),
+                remote::Transport::Rsyncd => remote::rsync_source(name, remote)
+                    .map(|url| url.trim_end_matches('/').to_string())
+                    .unwrap_or_else(|e| e.to_string()),
+            };
+            [
+                name.to_string(),
+                target,
+                remote.group.clone().unwrap_or_default(),
+                last_sync,
+            ]
+        })
+        .collect();
+    let header = ["NAME", "TARGET", "GROUP", "LAST SYNC"];
+    let widths: Vec<usize> = (0..3)
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([header[i].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in std::iter::once(header.map(String::from)).chain(rows) {
+        println!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2]
+        );
+    }
+    Ok(())
+}
+
+/// `cc-sessions remote add`: append a table to `remotes.toml`, keeping the
+/// rest of the file (comments included) as it was.
+fn run_remote_add(config: &remote::Config, add: &AddRemoteArgs) -> Result<()> {
+    let name = &add.name;
+    if !config_edit::is_valid_name(name) {
+        anyhow::bail!(
+            "Invalid remote name '{}': use letters, digits, '-' and '_'",
+            name
+        );
+    }
+    if config.remotes.contains_key(name) {
+        anyhow::bail!("Remote '{}' already exists", name);
+    }
+    let mut fields = vec![("host", add.host.as_str())];
+    if let Some(module) = &add.rsyncd_module {
+        fields.extend([("transport", "rsyncd"), ("module", module.as_str())]);
+    }
+    for (key, value) in [
+        ("user", &add.user),
+        ("projects_dir", &add.projects_dir),
+        ("label", &add.label),
+        ("group", &add.group),
+    ] {
+        if let Some(value) = value {
+            fields.push((key, value.as_str()));
+        }
+    }
+
+    let (path, mut content) = remote::read_config_text()?;
+    if content.trim().is_empty() {
+        content = format!("version = {}\n", config_migration::CONFIG_VERSION);
+    }
+    let edited = config_edit::add_remote(&content, name, &fields);
+    remote::write_config_text(&path, &edited, |c| c.remotes.contains_key(name))?;
+    println!("Added remote '{}' to {}", name, path.display());
+    println!("Check it with `cc-sessions remote check --remote {}`", name);
+    Ok(())
+}
+
+/// `cc-sessions remote remove`: cut the remote's table from `remotes.toml`.
+fn run_remote_remove(config: &remote::Config, name: &str) -> Result<()> {
+    if !config.remotes.contains_key(name) {
+        anyhow::bail!("Unknown remote '{}'", name);
+    }
+    let (path, content) = remote::read_config_text()?;
+    let edited = config_edit::remove_remote(&content, name)?;
+    remote::write_config_text(&path, &edited, |c| !c.remotes.contains_key(name))?;
+    println!("Removed remote '{}' from {}", name, path.display());
+    println!("Its cached sessions stay until `cc-sessions cache vacuum`");
+    Ok(())
+}
+
+/// `cc-sessions remote rename`: rename the remote's tables (and a default
+/// `remote = ...`) in `remotes.toml`, and move its cache dir and scan index so
+/// nothing has to be synced or rescanned. The cache moves first and is moved
+/// back if the config can't be written, so the two never disagree.
+fn run_remote_rename(config: &remote::Config, old: &str, new: &str) -> Result<()> {
+    if !config.remotes.contains_key(old) {
+        anyhow::bail!("Unknown remote '{}'", old);
+    }
+    if !config_edit::is_valid_name(new) {
+        anyhow::bail!(
+            "Invalid remote name '{}': use letters, digits, '-' and '_'",
+            new
+        );
+    }
+    if config.remotes.contains_key(new) {
+        anyhow::bail!("Remote '{}' already exists", new);
+    }
+    let (path, content) = remote::read_config_text()?;
+    let edited = config_edit::rename_remote(&content, old, new)?;
+
+    let old_cache = remote::get_remote_cache_dir(&config.settings, old)?;
+    let new_cache = remote::get_remote_cache_dir(&config.settings, new)?;
+    if new_cache.exists() {
+        anyhow::bail!(
+            "Cache dir for '{}' already exists: {} (remove it or run `cc-sessions cache vacuum`)",
+            new,
+            new_cache.display()
+        );
+    }
+    let moved = old_cache.exists();
+    if moved {
+        std::fs::rename(&old_cache, &new_cache).with_context(|| {
+            format!(
+                "Failed to move {} to {}",
+                old_cache.display(),
+                new_cache.display()
+            )
+        })?;
+    }
+    if let Err(e) = remote::write_config_text(&path, &edited, |c| {
+        c.remotes.contains_key(new) && !c.remotes.contains_key(old)
+    }) {
+        if moved && let Err(undo) = std::fs::rename(&new_cache, &old_cache) {
+            eprintln!(
+                "Warning: could not move {} back to {}: {}",
+                new_cache.display(),
+                old_cache.display(),
+                undo
+            );
+        }
+        return Err(e);
+    }
+
+    // Carry the scan index over so the next run doesn't rescan every
+    // session. Losing it only costs that rescan.
+    if let Err(e) = metadata_cache::move_root(&old_cache, &new_cache) {
+        eprintln!("Warning: could not move scan index for '{}': {}", old, e);
+    }
+
+    println!(
+        "Renamed remote '{}' to '{}' in {}",
+        old,
+        new,
+        path.display()
+    );
+    if moved {
+        println!("Moved cached sessions to {}", new_cache.display());
+    }
+    Ok(())
+}
+
+/// `cc-sessions fsck`: strict pass over every source, then optional repairs.
+fn run_fsck(config: &remote::Config, args: &Args, fix: bool) -> Result<()> {
+    let filter = args.remote.as_deref();
+    let mut roots = Vec::new();
+    if config.source_matches(filter, "local") {
+        for (i, root) in claude_code::local_projects_dirs()?.into_iter().enumerate() {
+            roots.push((claude_code::local_root_name(i, &root), root));
+        }
+    }
+    for name in config.remotes.keys() {
+        if config.source_matches(filter, name) {
+            roots.push((
+                name.clone(),
+                remote::get_remote_cache_dir(&config.settings, name)?,
+            ));
+        }
+    }
+
+    let reports: Vec<fsck::SourceReport> = roots
+        .iter()
+        .filter(|(_, root)| root.exists())
+        .map(|(name, root)| fsck::check_root(root, name))
+        .collect();
+    print!("{}", fsck::render_report(&reports));
+
+    let findings = || reports.iter().flat_map(|r| &r.findings);
+    let mut remaining = findings().count();
+    if fix {
+        let mut batch = undo::Batch::begin("fsck --fix")?;
+        for finding in findings().filter(|f| f.fixable) {
+            match fsck::fix(finding, &mut batch) {
+                Ok(done) => {
+                    println!("{}", done);
+                    remaining -= 1;
+                }
+                Err(e) => eprintln!("Warning: {:#}", e),
+            }
+        }
+        print_undo_hint(&batch);
+    }
+
+    if args.strict && remaining > 0 {
+        anyhow::bail!("Strict mode: {} problem(s) remain", remaining);
+    }
+    Ok(())
+}
+
+/// `cc-sessions stats`: pick the view, then the table or JSON form of it.
+/// `--since` / `--until` were applied with the other filters.
+fn run_stats(sessions: &[Session], stats_args: &StatsArgs, config: &remote::Config) -> Result<()> {
+    let sessions: Vec<&Session> = sessions.iter().collect();
+    let sessions: &[&Session] = &sessions;
+
+    if stats_args.costs {
+        let prices = pricing::PriceTable::new(&config.pricing);
+        let rows = stats::costs_by_project(sessions, &prices);
+        if stats_args.json {
+            println!("{}", stats::render_costs_json(&rows)?);
+        } else if stats_args.csv {
+            print!("{}", stats::render_costs_csv(&rows));
+        } else {
+            print!("{}", stats::render_costs(&rows));
+        }
+        return Ok(());
+    }
+
+    if stats_args.mcp {
+        let rows = stats::mcp_usage(sessions, &claude_code::configured_mcp_servers());
+        if stats_args.json {
+            println!("{}", stats::render_mcp_json(&rows)?);
+        } else {
+            print!("{}", stats::render_mcp(&rows));
+        }
+        return Ok(());
+    }
+
+    if let Some(n) = stats_args.top {
+        let top = stats::top_sessions(sessions, stats_args.by, n);
+        if stats_args.json {
+            println!("{}", stats::render_top_json(&top, stats_args.by)?);
+        } else {
+            print!("{}", stats::render_top(&top));
+        }
+        return Ok(());
+    }
+
+    if stats_args.trend {
+        let weeks = stats::weekly_tokens(sessions, stats_args.weeks, SystemTime::now());
+        if stats_args.json {
+            println!("{}", stats::render_trend_json(&weeks)?);
+        } else {
+            print!("{}", stats::render_trend(&weeks));
+        }
+        return Ok(());
+    }
+
+    let rows = stats::by_project(sessions);
+    if stats_args.json {
+        println!("{}", stats::render_json(&rows)?);
+    } else if stats_args.by_source {
+        print!("{}", stats::render_source_matrix(&rows));
+    } else {
+        print!("{}", stats::render_projects(&rows));
+    }
+    Ok(())
+}
+
+fn run_digest(sessions: &[Session], digest_args: &DigestArgs, args: &Args) -> Result<()> {
+    let now = SystemTime::now();
+    let today = dates::day_of(now);
+    let day = std::time::Duration::from_secs(dates::SECS_PER_DAY);
+    let (since, until, period) = match (&args.since, &args.until) {
+        (None, Some(_)) => anyhow::bail!("digest --until needs --since"),
+        (Some(since), until) => {
+            let since = dates::parse_period_start(since, now)?;
+            let until = until
+                .as_deref()
+                .map(|u| dates::parse_period_end(u, now))
+                .transpose()?;
+            let first = dates::format_day(dates::day_of(since));
+            let period = match until {
+                Some(t) => format!(
+                    "{} to {}",
+                    first,
+                    dates::format_day(dates::day_of(t).saturating_sub(1))
+                ),
+                None => format!("since {}", first),
+            };
+            (since, until, period)
+        }
+        (None, _) if digest_args.yesterday => {
+            let yesterday = today.saturating_sub(1);
+            let period = format!("yesterday ({})", dates::format_day(yesterday));
+            let start = dates::start_of_day(yesterday);
+            (start, Some(start + day), period)
+        }
+        (None, _) => (
+            dates::start_of_day(today),
+            None,
+            format!("today ({})", dates::format_day(today)),
+        ),
+    };
+    let sessions: Vec<&Session> = sessions.iter().collect();
+    let projects = digest::collect(&sessions, since, until);
+    print!(
+        "{}",
+        digest::render(&projects, &period, |s| {
+            claude_code::read_plan(&s.filepath, s.plan_offset?)
+        })
+    );
+    Ok(())
+}
+
+/// Fill in flags the user didn't pass from the config's `[defaults]`.
+fn apply_config_defaults(args: &mut Args, defaults: &remote::Defaults) {
+    // --tsv / --json / --print0 default to every session; a table-sized
+    // count would silently truncate pipelines.
+    if args.count.is_none() && !args.tsv && !args.json && args.print0.is_none() {
+        args.count = defaults.count;
+    }
+    args.min_turns = args.min_turns.or(defaults.min_turns);
+    args.sort = args.sort.or(defaults.sort);
+    if args.project.is_none() {
+        args.project = defaults.project.clone();
+    }
+    if args.remote.is_none() {
+        args.remote = defaults.remote.clone();
+    }
+    args.no_sync |= defaults.no_sync && !args.sync;
+    args.include_forks |= defaults.include_forks;
+    args.return_to_picker |= defaults.return_to_picker && !args.cd;
+    args.flat_forks |= defaults.flat_forks;
+    args.named_first |= defaults.named_first;
+    args.plain |= defaults.plain;
+}
+
+/// A view's columns, plus RESUMED when sorting by it and OWNER when team
+/// sessions are listed.
+fn layout_config(
+    config: &layout::ViewConfig,
+    view: View,
+    args: &Args,
+    sessions: &[Session],
+) -> layout::ViewConfig {
+    let mut config = config.clone();
+    // BRANCH shows up once any session recorded one, unless the columns are
+    // configured explicitly
+    if config.columns.is_none() && sessions.iter().any(|s| s.git_branch.is_some()) {
+        config = config.including(view, Column::Branch, Column::Project);
+    }
+    if args.sort == Some(session::SortKey::Resumed) {
+        config = config.including(view, Column::Resumed, Column::Modified);
+    }
+    if sessions.iter().any(|s| s.source.owner().is_some()) {
+        config = config.including(view, Column::Owner, Column::Source);
+    }
+    config
+}
+
+/// `--sort`, else relevance inside a git repo, else the newest first.
+fn sort_order(args: &Args, in_repo: bool) -> SortOrder {
+    let fallback = if in_repo {
+        session::SortKey::Relevance
+    } else {
+        session::SortKey::default()
+    };
+    SortOrder {
+        key: args.sort.unwrap_or(fallback),
+        named_first: args.named_first,
+    }
+}
+
+fn enforce_strict_mode(
+    strict: bool,
+    sync_failures: usize,
+    discovery_failures: usize,
+) -> Result<()> {
+    if !strict {
+        return Ok(());
+    }
+
+    if sync_failures > 0 {
+        anyhow::bail!("Strict mode: {} sync source(s) failed", sync_failures);
+    }
+
+    if discovery_failures > 0 {
+        anyhow::bail!(
+            "Strict mode: {} discovery source(s) failed",
+            discovery_failures
+        );
+    }
+
+    Ok(())
+}
+
+// =============================================================================
+// Display Functions
+// =============================================================================
+
+/// Project and summary cells for list output, as OSC 8 links to the
+/// project directory (local sessions only) and the session file when
+/// `links` is set. The project is elided and padded to `project_width`.
+fn list_link_cells(
+    session: &Session,
+    desc: String,
+    project_width: usize,
+    links: bool,
+) -> (String, String) {
+    let project_url = (links
+        && matches!(session.source, SessionSource::Local)
+        && !session.project_path.is_empty())
+    .then(|| hyperlink::file_url(std::path::Path::new(&session.project_path)));
+    let project = hyperlink::padded(
+        project_url.as_deref(),
+        &layout::elide_middle(&session.project, project_width),
+        project_width,
+    );
+    let desc = if links {
+        hyperlink::link(&hyperlink::file_url(&session.filepath), &desc)
+    } else {
+        desc
+    };
+    (project, desc)
+}
+
+/// Plain text of a fixed layout column. SUMMARY is rendered by callers,
+/// which decorate it per view.
+fn layout_cell(session: &Session, column: Column) -> String {
+    match column {
+        Column::Id => session.id.clone(),
+        Column::Created => format_time_relative(session.created),
+        Column::Modified => format_time_relative(session.modified),
+        Column::Resumed => session
+            .resumed
+            .map(format_time_relative)
+            .unwrap_or_else(|| "-".to_string()),
+        // Partially indexed sessions only know a lower bound.
+        Column::Turns if session.partial => format!("{}+", session.turn_count),
+        Column::Turns => session.turn_count.to_string(),
+        Column::Activity => activity_sparkline(&session.active_hours, 8),
+        Column::Permissions => {
+            stats::format_permissions(session.permission_denials(), session.permission_requests)
+        }
+        Column::Fork => fork_marker(session),
+        Column::Source => session.source.label().to_string(),
+        Column::Owner => session.source.owner().unwrap_or_default().to_string(),
+        Column::Project => session.project.clone(),
+        Column::Branch => session.git_branch.clone().unwrap_or_default(),
+        Column::Summary => String::new(),
+    }
+}
+
+/// `↳` for forks, `↳=` for forks with nothing written after forking.
+/// Spelled out with the parent's ID prefix in plain mode.
+fn fork_marker(session: &Session) -> String {
+    let Some(parent) = &session.forked_from else {
+        return String::new();
+    };
+    match (plain::enabled(), session.redundant_fork) {
+        (false, true) => "↳=".to_string(),
+        (false, false) => "↳".to_string(),
+        (true, redundant) => format!(
+            "[fork of {}{}]",
+            parent.get(..8).unwrap_or(parent),
+            if redundant { ", unchanged" } else { "" }
+        ),
+    }
+}
+
+/// One `--list` table row.
+fn format_list_row(session: &Session, layout: &Layout, links: bool) -> String {
+    // Without a FORK column the marker leads the summary.
+    let marker = match fork_marker(session) {
+        m if m.is_empty() || layout.has(Column::Fork) => String::new(),
+        m => format!("{} ", m),
+    };
+    let width = layout
+        .summary_width()
+        .saturating_sub(marker.chars().count());
+    let desc = format!("{}{}", marker, format_session_desc(session, width));
+    let desc = if session.name.is_some() {
+        format!("{}{}{}", colors::YELLOW, desc, colors::RESET)
+    } else {
+        desc
+    };
+    let project_width = layout
+        .slots()
+        .iter()
+        .find(|s| s.column == Column::Project)
+        .map_or(0, |s| s.width);
+    let (project, desc) = list_link_cells(session, desc, project_width, links);
+
+    let mut row = String::new();
+    for slot in layout.slots() {
+        match slot.column {
+            Column::Summary => row.push_str(&desc),
+            Column::Project => {
+                row.push_str(&project);
+                row.push(' ');
+            }
+            Column::Source => {
+                let label = session.source.label();
+                row.push_str(&session.source.paint(&slot.pad(label)));
+                row.push(' ');
+            }
+            column => {
+                row.push_str(&slot.pad(&layout_cell(session, column)));
+                row.push(' ');
+            }
+        }
+    }
+    row
+}
+
+fn print_sessions(
+    sessions: &[&Session],
+    count: usize,
+    config: &layout::ViewConfig,
+    debug: bool,
+    notices: &[String],
+    picker_hint: bool,
+) {
+    use std::io::IsTerminal;
+
+    let links = hyperlink::enabled() && !plain::enabled();
+    let shown: Vec<&Session> = sessions.iter().copied().take(count).collect();
+    let mut layout = Layout::new(View::List, config, debug);
+    layout.fit(shown.iter().copied(), layout_cell);
+    // Piped output has no width to fit; keep a readable fixed summary.
+    let term_width = std::io::stdout()
+        .is_terminal()
+        .then(|| crossterm::terminal::size().ok())
+        .flatten()
+        .map(|(w, _)| w as usize);
+    layout.fit_summary(term_width, 50);
+    let ruler = "─".repeat(layout.total_width());
+
+    println!("{}", layout.header());
+    println!("{}", plain::render(&ruler));
+    for session in shown {
+        println!(
+            "{}",
+            plain::render(&format_list_row(session, &layout, links))
+        );
+    }
+    println!("{}", plain::render(&ruler));
+    for notice in notices {
+        println!("{}", plain::render(notice));
+    }
+    if debug {
+        for notice in clock_skew_notices(sessions) {
+            println!("{}", plain::render(&notice));
+        }
+        println!("Total: {} sessions", sessions.len());
+    } else if picker_hint {
+        println!("Run without --list for interactive picker; use --fork to fork when resuming");
+    }
+}
+
+/// Column order for `--list --tsv`. Append new columns at the end only;
+/// scripts address fields by position.
+const TSV_COLUMNS: [&str; 12] = [
+    "id",
+    "source",
+    "project",
+    "project_path",
+    "created",
+    "modified",
+    "turns",
+    "forked_from",
+    "name",
+    "tag",
+    "summary",
+    "file",
+];
+
+/// One `--tsv` record. Tabs and newlines inside values become spaces so
+/// every session is exactly one line of `TSV_COLUMNS.len()` fields.
+fn format_session_tsv(session: &Session) -> String {
+    let clean = |s: &str| s.replace(['\t', '\n', '\r'], " ");
+    let fields: [String; TSV_COLUMNS.len()] = [
+        session.id.clone(),
+        session.source.display_name().to_string(),
+        session.project.clone(),
+        session.project_path.clone(),
+        dates::format_timestamp(session.created),
+        dates::format_timestamp(session.modified),
+        session.turn_count.to_string(),
+        session.forked_from.clone().unwrap_or_default(),
+        session.name.clone().unwrap_or_default(),
+        session.tag.clone().unwrap_or_default(),
+        session
+            .summary
+            .as_deref()
+            .or(session.first_message.as_deref())
+            .unwrap_or_default()
+            .to_string(),
+        session.filepath.display().to_string(),
+    ];
+    fields.map(|f| clean(&f)).join("\t")
+}
+
+fn print_sessions_tsv(sessions: &[&Session], count: usize) {
+    for session in sessions.iter().take(count) {
+        println!("{}", format_session_tsv(session));
+    }
+}
+
+/// Keep only sessions recorded inside `root`. Leaves `sessions` untouched
+/// and returns false when none are, so an unknown repo doesn't yield an
+/// empty picker.
+fn filter_to_repo(sessions: &mut Vec<Session>, root: &std::path::Path) -> bool {
+    let matches = path_matcher(root, true);
+    let inside = |s: &Session| matches(Path::new(&s.project_path));
+    if !sessions.iter().any(inside) {
+        return false;
+    }
+    sessions.retain(inside);
+    true
+}
+
+/// `--print0`: NUL-terminated paths or IDs, written as raw bytes so any
+/// filename survives the trip through `xargs -0`.
+fn print_sessions_nul(sessions: &[&Session], field: Print0Field, count: usize) -> Result<()> {
+    use std::io::Write;
+    let mut out = std::io::stdout().lock();
+    for session in sessions.iter().take(count) {
+        match field {
+            Print0Field::Path => out.write_all(session.filepath.as_os_str().as_encoded_bytes())?,
+            Print0Field::Id => out.write_all(session.id.as_bytes())?,
+        }
+        out.write_all(b"\0")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Non-interactive transcript search (`--grep`): file paths of matching
+/// sessions. Reads full transcripts; there's no prebuilt index to consult.
+fn grep_sessions(sessions: &[Session], query: &SearchQuery) -> std::collections::HashSet<PathBuf> {
+    use rayon::prelude::*;
+
+    let progress = progress::Progress::with_matches("searched", sessions.len());
+    progress::run(&progress, || {
+        sessions
+            .par_iter()
+            .with_max_len(1)
+            .filter(|s| {
+                let text = if s.archived {
+                    archived_search_text(s)
+                } else {
+                    claude_code::transcript_text(&s.filepath, query.options.include_tools)
+                };
+                let matched = query.matches(&text);
+                progress.inc();
+                if matched {
+                    progress.add_match();
+                }
+                matched
+            })
+            .map(|s| s.filepath.clone())
+            .collect()
+    })
+}
+
+/// `cc-sessions search` arguments as one query; an argument with spaces was
+/// quoted in the shell, so it stays a phrase.
+fn search_query_from_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            if arg.contains(char::is_whitespace) && !arg.contains('"') {
+                format!("\"{}\"", arg)
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `cc-sessions search`: file paths of matching sessions. The persistent
+/// index is refreshed for these sessions first, then narrows which
+/// transcripts are read to confirm a match. Archived sessions match on
+/// their metadata, as with `--grep`.
+fn search_sessions(
+    sessions: &[Session],
+    query: &fulltext::Query,
+    include_tools: bool,
+) -> std::collections::HashSet<PathBuf> {
+    use rayon::prelude::*;
+
+    let mut index = fulltext::FullTextIndex::load(include_tools);
+    let targets: Vec<&Path> = sessions
+        .iter()
+        .filter(|s| !s.archived)
+        .map(|s| s.filepath.as_path())
+        .collect();
+    refresh_fulltext_index(&mut index, &targets);
+    let candidates = index.candidates(query);
+    sessions
+        .par_iter()
+        .filter(|s| {
+            if s.archived {
+                return query.matches_text(&archived_search_text(s));
+            }
+            candidates
+                .as_ref()
+                .is_none_or(|c| c.contains(s.filepath.as_path()))
+                && query.matches_file(&s.filepath)
+        })
+        .map(|s| s.filepath.clone())
+        .collect()
+}
+
+/// Index new and changed transcripts among `targets` and save the index.
+fn refresh_fulltext_index(index: &mut fulltext::FullTextIndex, targets: &[&Path]) -> usize {
+    let stale = index.stale(targets);
+    let count = stale.len();
+    if count > 0 {
+        let progress = progress::Progress::new("indexed", count);
+        progress::run(&progress, || index.update(stale, &progress));
+    }
+    if let Err(e) = index.save() {
+        eprintln!("Warning: Failed to save search index: {}", e);
+    }
+    count
+}
+
+/// What search matches for an archived session: its listed metadata, so
+/// searching doesn't decompress the archive.
+fn archived_search_text(session: &Session) -> String {
+    [
+        session.name.as_deref(),
+        session.tag.as_deref(),
+        session.summary.as_deref(),
+        session.first_message.as_deref(),
+        Some(session.project_path.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// One line per remote whose recent syncs failed, e.g. "⚠ devbox: last 3
+/// syncs failed — showing data from 2d ago", until a sync succeeds, then
+/// one per source whose sessions couldn't be loaded this run. Shown in the
+/// picker header and under the `--list` table, where startup warnings have
+/// scrolled away.
+fn source_notices(
+    config: &remote::Config,
+    remote_filter: Option<&str>,
+    failures: &[claude_code::DiscoveryFailure],
+) -> Vec<String> {
+    remote::sync_warnings(config, remote_filter)
+        .iter()
+        .map(format_sync_warning)
+        .chain(failures.iter().map(|f| {
+            format!(
+                "⚠ {}: sessions couldn't be loaded — {}",
+                f.source_name, f.reason
+            )
+        }))
+        .collect()
+}
+
+/// One line per remote with sessions that appeared or grew since they were
+/// last shown (`state::SeenSessions`), typically brought in by a sync.
+fn change_notices(sessions: &[Session], seen: &state::SeenSessions, hint: bool) -> Vec<String> {
+    let mut counts: std::collections::BTreeMap<&str, (usize, usize)> = Default::default();
+    for session in sessions {
+        if session.source.is_local() {
+            continue;
+        }
+        let count = counts.entry(session.source.display_name()).or_default();
+        match seen.change(session) {
+            Some(state::Change::New) => count.0 += 1,
+            Some(state::Change::Updated) => count.1 += 1,
+            None => {}
+        }
+    }
+    counts
+        .into_iter()
+        .filter(|(_, counts)| *counts != (0, 0))
+        .map(|(source, (new, updated))| {
+            let parts: Vec<String> = [(new, "new"), (updated, "updated")]
+                .into_iter()
+                .filter(|(n, _)| *n > 0)
+                .map(|(n, label)| format!("{} {}", n, label))
+                .collect();
+            let hint = if hint {
+                " (--new-only to list them)"
+            } else {
+                ""
+            };
+            format!("✚ {}: {} since last sync{}", source, parts.join(", "), hint)
+        })
+        .collect()
+}
+
+/// Seen sessions are best-effort, like the rest of the state.
+fn save_seen(seen: &state::SeenSessions) {
+    if let Err(e) = seen.save() {
+        eprintln!("Warning: Failed to save seen sessions: {:#}", e);
+    }
+}
+
+/// One line per source whose clock was found running ahead (`--debug`), so
+/// corrected times aren't mistaken for recorded ones.
+fn clock_skew_notices(sessions: &[&Session]) -> Vec<String> {
+    let skews: std::collections::BTreeMap<&str, std::time::Duration> = sessions
+        .iter()
+        .filter_map(|s| Some((s.source.display_name(), s.clock_skew?)))
+        .collect();
+    skews
+        .into_iter()
+        .map(|(source, skew)| {
+            format!(
+                "⏱ {}: clock runs {} ahead of this machine; its times are shifted back",
+                source,
+                stats::format_duration(skew)
+            )
+        })
+        .collect()
+}
+
+fn format_sync_warning(warning: &remote::SyncWarning) -> String {
+    let attempts = match warning.failures {
+        1 => "last sync failed".to_string(),
+        n => format!("last {} syncs failed", n),
+    };
+    let data = match warning.last_sync.map(format_time_relative).as_deref() {
+        None => "no data synced yet".to_string(),
+        Some("now") => "showing data from just now".to_string(),
+        Some(age) => format!("showing data from {} ago", age),
+    };
+    format!(
+        "⚠ {}: {} — {} (--retry-failed to retry)",
+        warning.remote_name, attempts, data
+    )
+}
+
+fn format_time_relative(time: SystemTime) -> String {
+    let now = SystemTime::now();
+
+    // Handle future timestamps (clock skew, filesystem issues)
+    let secs = match now.duration_since(time) {
+        Ok(d) => d.as_secs(),
+        Err(_) => return "?".to_string(), // Future timestamp
+    };
+
+    if secs < 60 {
+        "now".to_string()
+    } else if secs < 3600 {
+        format!("{}m", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h", secs / 3600)
+    } else if secs < 604800 {
+        format!("{}d", secs / 86400)
+    } else {
+        format!("{}w", secs / 604800)
+    }
+}
+
+/// Message activity over a session's lifetime in `width` cells of block
+/// characters, each as tall as its share of the busiest cell; gaps are
+/// blank. A cell covers at least an hour, so a short burst fills only the
+/// first cell while a multi-day conversation spreads across all of them.
+fn activity_sparkline(active_hours: &std::collections::BTreeMap<u32, u32>, width: usize) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let (Some((&first, _)), Some((&last, _))) = (
+        active_hours.first_key_value(),
+        active_hours.last_key_value(),
+    ) else {
+        return String::new();
+    };
+    if width == 0 {
+        return String::new();
+    }
+    let hours_per_cell = ((last - first) as usize / width + 1).max(1);
+    let mut cells = vec![0u32; width];
+    for (&hour, &count) in active_hours {
+        let cell = ((hour - first) as usize / hours_per_cell).min(width - 1);
+        cells[cell] += count;
+    }
+    let peak = cells.iter().copied().max().unwrap_or(0).max(1) as usize;
+    let used = (last - first) as usize / hours_per_cell + 1;
+    cells[..used.min(width)]
+        .iter()
+        .map(|&count| match count as usize {
+            0 => ' ',
+            n => BLOCKS[((n * BLOCKS.len()).div_ceil(peak) - 1).min(BLOCKS.len() - 1)],
+        })
+        .collect()
+}
+
+fn filter_forks_for_list(sessions: &[Session], include_forks: bool) -> Vec<&Session> {
+    if include_forks {
+        return sessions.iter().collect();
+    }
+
+    sessions
+        .iter()
+        .filter(|s| s.forked_from.is_none())
+        .collect()
+}
+
+// =============================================================================
+// Preview Mode (internal, replaces jaq dependency)
+// =============================================================================
+
+/// Print formatted transcript preview for a session file.
+/// Used internally by skim's preview command.
+fn print_session_preview(filepath: &Path) -> Result<()> {
+    let content = generate_preview_content(filepath)?;
+    print!("{}", plain::render(&content));
+    Ok(())
 }
 
 /// Extract first text block from a message entry, borrowing from the JSON value
@@ -437,21 +3294,41 @@ fn extract_message_text(entry: &serde_json::Value) -> Option<&str> {
 /// Generate preview content as a string (for skim's preview pane). Skim is
 /// configured with `:wrap`, so we emit untruncated lines and let the pane
 /// handle overflow — no arbitrary width caps.
-fn generate_preview_content(filepath: &PathBuf) -> Result<String> {
+fn generate_preview_content(filepath: &Path) -> Result<String> {
     use std::fmt::Write as _;
-    use std::fs::File;
-    use std::io::{BufRead, BufReader};
-
-    let file = File::open(filepath).context("Could not open session file")?;
-    let mut reader = BufReader::new(file);
 
     let mut output = String::new();
-    let mut line = String::new();
-    let mut line_count = 0;
+    for (role_glyph, first_line) in preview_messages(filepath)? {
+        let color = if role_glyph == 'U' {
+            colors::CYAN
+        } else {
+            colors::YELLOW
+        };
+        let _ = writeln!(output, "{color}{role_glyph}: {first_line}{}", colors::RESET);
+    }
+
+    if output.is_empty() {
+        output.push_str("(empty session)");
+    }
+
+    Ok(output)
+}
+
+/// The first line of each of the first 100 user ('U') and assistant ('A')
+/// messages, skipping system content.
+fn preview_messages(filepath: &Path) -> Result<Vec<(char, String)>> {
     const MAX_LINES: usize = 100;
+    Ok(read_preview_messages(archive::open(filepath)?, MAX_LINES))
+}
+
+/// Up to `max` messages for `preview_messages`. Lines that don't parse (a
+/// fragment cut off by a byte range) are skipped.
+fn read_preview_messages(mut reader: impl std::io::BufRead, max: usize) -> Vec<(char, String)> {
+    let mut messages = Vec::new();
+    let mut line = String::new();
 
     while reader.read_line(&mut line).map(|n| n > 0).unwrap_or(false) {
-        if line_count >= MAX_LINES {
+        if messages.len() >= max {
             break;
         }
         if !claude_code::line_mentions_content_type(line.as_bytes()) {
@@ -466,31 +3343,137 @@ fn generate_preview_content(filepath: &PathBuf) -> Result<String> {
                 continue;
             }
         };
-        line.clear();
+        line.clear();
+
+        let role_glyph = match entry.get("type").and_then(|v| v.as_str()) {
+            Some("user") => 'U',
+            Some("assistant") => 'A',
+            _ => continue,
+        };
+
+        let Some(text) = extract_message_text(&entry) else {
+            continue;
+        };
+        if role_glyph == 'U' && is_system_content(text) {
+            continue;
+        }
+
+        let first_line = text.lines().next().unwrap_or(text);
+        messages.push((role_glyph, first_line.to_string()));
+    }
+
+    messages
+}
+
+/// Messages a stale remote wrote after its cached copy of the transcript,
+/// newest last, shown above the cached preview (`settings.live_preview`).
+fn live_preview_section(fetched: &live_preview::Fetched, remote: &str) -> String {
+    use std::fmt::Write as _;
+    const MAX_MESSAGES: usize = 20;
+
+    let synced = match fetched.synced {
+        Some(time) => format!("last synced {} ago", format_time_relative(time)),
+        None => "never synced".to_string(),
+    };
+    let bytes = match &fetched.result {
+        Ok(bytes) => bytes,
+        Err(reason) => {
+            return format!(
+                "{}Couldn't fetch newer messages from {} ({}); {}{}\n\n",
+                colors::DIM,
+                remote,
+                reason,
+                synced,
+                colors::RESET
+            );
+        }
+    };
+    let messages = read_preview_messages(bytes.as_slice(), usize::MAX);
+    if messages.is_empty() {
+        return format!(
+            "{}No new messages on {} ({}){}\n\n",
+            colors::DIM,
+            remote,
+            synced,
+            colors::RESET
+        );
+    }
+    let mut out = format!(
+        "{}{} new message(s) on {} ({}):{}\n",
+        colors::GREEN,
+        messages.len(),
+        remote,
+        synced,
+        colors::RESET
+    );
+    for (role_glyph, first_line) in &messages[messages.len().saturating_sub(MAX_MESSAGES)..] {
+        let color = if *role_glyph == 'U' {
+            colors::CYAN
+        } else {
+            colors::YELLOW
+        };
+        let _ = writeln!(out, "{color}{role_glyph}: {first_line}{}", colors::RESET);
+    }
+    let _ = writeln!(out, "{}── cached ──{}", colors::DIM, colors::RESET);
+    out
+}
 
-        let (role_glyph, color) = match entry.get("type").and_then(|v| v.as_str()) {
-            Some("user") => ('U', colors::CYAN),
-            Some("assistant") => ('A', colors::YELLOW),
-            _ => continue,
-        };
+/// Two transcripts in columns `width` chars wide overall, turn by turn: each
+/// user message starts on the same row on both sides, so the point where
+/// the sessions diverge lines up.
+fn side_by_side(left: &[(char, String)], right: &[(char, String)], width: usize) -> String {
+    use std::fmt::Write as _;
 
-        let Some(text) = extract_message_text(&entry) else {
-            continue;
-        };
-        if role_glyph == 'U' && is_system_content(text) {
-            continue;
+    fn turns(messages: &[(char, String)]) -> Vec<&[(char, String)]> {
+        let mut turns: Vec<&[(char, String)]> = Vec::new();
+        let mut start = 0;
+        for (i, (role, _)) in messages.iter().enumerate() {
+            if *role == 'U' && i > start {
+                turns.push(&messages[start..i]);
+                start = i;
+            }
         }
-
-        let first_line = text.lines().next().unwrap_or(text);
-        let _ = writeln!(output, "{color}{role_glyph}: {first_line}{}", colors::RESET);
-        line_count += 1;
+        if start < messages.len() {
+            turns.push(&messages[start..]);
+        }
+        turns
     }
 
-    if output.is_empty() {
-        output.push_str("(empty session)");
+    fn cell(message: Option<&(char, String)>, column: usize) -> String {
+        let Some((role, text)) = message else {
+            return " ".repeat(column);
+        };
+        let color = if *role == 'U' {
+            colors::CYAN
+        } else {
+            colors::YELLOW
+        };
+        let text: String = format!("{}: {}", role, text).chars().take(column).collect();
+        let pad = column - text.chars().count();
+        format!("{color}{text}{}{}", colors::RESET, " ".repeat(pad))
     }
 
-    Ok(output)
+    let column = width.saturating_sub(3).max(2) / 2;
+    let (left, right) = (turns(left), turns(right));
+    let mut out = String::new();
+    for i in 0..left.len().max(right.len()) {
+        let l = left.get(i).copied().unwrap_or_default();
+        let r = right.get(i).copied().unwrap_or_default();
+        for row in 0..l.len().max(r.len()) {
+            let _ = writeln!(
+                out,
+                "{}{} │ {}{}",
+                cell(l.get(row), column),
+                colors::DIM,
+                colors::RESET,
+                cell(r.get(row), column).trim_end()
+            );
+        }
+    }
+    if out.is_empty() {
+        out.push_str("(empty sessions)");
+    }
+    out
 }
 
 /// Check if content is system/XML content that should be skipped in previews
@@ -505,12 +3488,8 @@ struct Message {
 }
 
 /// Generate preview showing matching messages with full conversation context
-fn generate_search_preview(filepath: &PathBuf, pattern: &str) -> Result<String> {
-    use std::fs::File;
-    use std::io::{BufRead, BufReader};
-
-    let file = File::open(filepath).context("Could not open session file")?;
-    let mut reader = BufReader::new(file);
+fn generate_search_preview(filepath: &Path, query: &SearchQuery) -> Result<String> {
+    let mut reader = archive::open(filepath)?;
 
     // Collect all messages first (filter out progress/attachment lines before
     // the JSON parse — large sessions are dominated by those).
@@ -536,6 +3515,19 @@ fn generate_search_preview(filepath: &PathBuf, pattern: &str) -> Result<String>
             _ => continue,
         };
 
+        if role == "user" && query.options.include_tools {
+            let content = entry.get("message").and_then(|m| m.get("content"));
+            for text in content
+                .into_iter()
+                .flat_map(claude_code::iter_tool_result_texts)
+            {
+                messages.push(Message {
+                    role: "tool".to_owned(),
+                    text: text.to_owned(),
+                });
+            }
+        }
+
         if let Some(text) = extract_message_text(&entry) {
             if role == "user" && is_system_content(text) {
                 continue;
@@ -547,15 +3539,15 @@ fn generate_search_preview(filepath: &PathBuf, pattern: &str) -> Result<String>
         }
     }
 
-    let pattern_lower = pattern.to_lowercase();
     let mut output = String::new();
     let mut match_count = 0;
     const MAX_MATCHES: usize = 10; // Fewer matches since we show full context
 
     output.push_str(&format!(
-        "{}Searching for: \"{}\"{}\n\n",
+        "{}Searching for: \"{}\"{}{}\n\n",
         colors::GREEN,
-        pattern,
+        query.pattern,
+        format_search_modes(query.options),
         colors::RESET
     ));
 
@@ -563,7 +3555,7 @@ fn generate_search_preview(filepath: &PathBuf, pattern: &str) -> Result<String>
     let matching_indices: Vec<usize> = messages
         .iter()
         .enumerate()
-        .filter(|(_, m)| m.text.to_lowercase().contains(&pattern_lower))
+        .filter(|(_, m)| query.matches(&m.text))
         .map(|(i, _)| i)
         .collect();
 
@@ -604,7 +3596,7 @@ fn generate_search_preview(filepath: &PathBuf, pattern: &str) -> Result<String>
 
         // Show matching message (highlighted)
         let msg = &messages[match_idx];
-        output.push_str(&format_matching_message(msg, pattern));
+        output.push_str(&format_matching_message(msg, query));
         shown_indices.insert(match_idx);
         match_count += 1;
 
@@ -631,9 +3623,18 @@ fn generate_search_preview(filepath: &PathBuf, pattern: &str) -> Result<String>
     Ok(output)
 }
 
+/// One-letter transcript prefix: U(ser), A(ssistant), T(ool output).
+fn role_prefix(role: &str) -> &'static str {
+    match role {
+        "user" => "U",
+        "tool" => "T",
+        _ => "A",
+    }
+}
+
 /// Format a context message (dimmed, truncated if too long)
 fn format_context_message(msg: &Message) -> String {
-    let prefix = if msg.role == "user" { "U" } else { "A" };
+    let prefix = role_prefix(&msg.role);
     const MAX_CONTEXT_LINES: usize = 10;
     let lines: Vec<&str> = msg.text.lines().collect();
 
@@ -663,23 +3664,36 @@ fn format_context_message(msg: &Message) -> String {
     output
 }
 
-/// Format a matching message (colored, with highlights)
-fn format_matching_message(msg: &Message, pattern: &str) -> String {
-    let (prefix, color) = if msg.role == "user" {
-        ("U", colors::CYAN)
+/// Describe non-default search modes for the preview banner, e.g. " (case-sensitive)"
+fn format_search_modes(options: SearchOptions) -> String {
+    let modes: Vec<&str> = [
+        (options.case_sensitive, "case-sensitive"),
+        (options.whole_word, "whole-word"),
+        (options.include_tools, "incl. tool output"),
+    ]
+    .into_iter()
+    .filter_map(|(on, name)| on.then_some(name))
+    .collect();
+    if modes.is_empty() {
+        String::new()
     } else {
-        ("A", colors::YELLOW)
+        format!(" ({})", modes.join(", "))
+    }
+}
+
+/// Format a matching message (colored, with highlights)
+fn format_matching_message(msg: &Message, query: &SearchQuery) -> String {
+    let color = match msg.role.as_str() {
+        "user" => colors::CYAN,
+        "tool" => colors::GREEN,
+        _ => colors::YELLOW,
     };
+    let prefix = role_prefix(&msg.role);
 
-    let pattern_lower = pattern.to_lowercase();
     let mut output = String::new();
 
     for (i, line) in msg.text.lines().enumerate() {
-        let formatted_line = if line.to_lowercase().contains(&pattern_lower) {
-            highlight_match(line, pattern)
-        } else {
-            line.to_string()
-        };
+        let formatted_line = highlight_match(line, &query.pattern, query.options);
 
         let leader = if i == 0 {
             format!("{}: ", prefix)
@@ -698,59 +3712,22 @@ fn format_matching_message(msg: &Message, pattern: &str) -> String {
 }
 
 /// Highlight matching text with bold/inverse (Unicode-safe)
-fn highlight_match(text: &str, pattern: &str) -> String {
-    if pattern.is_empty() {
+fn highlight_match(text: &str, pattern: &str, options: SearchOptions) -> String {
+    let ranges = search::match_ranges(text, pattern, options);
+    if ranges.is_empty() {
         return text.to_owned();
     }
 
-    // Fast path: ASCII-only text and pattern. Lowercasing preserves byte
-    // positions, so we lower once and match_indices gives us offsets directly.
-    // This is O(n) vs. the generic path's per-position re-lowering.
-    if text.is_ascii() && pattern.is_ascii() {
-        let text_lower = text.to_ascii_lowercase();
-        let pattern_lower = pattern.to_ascii_lowercase();
-        let mut result = String::with_capacity(text.len() + 16);
-        let mut last = 0;
-        for (i, _) in text_lower.match_indices(&pattern_lower) {
-            result.push_str(&text[last..i]);
-            result.push_str(colors::BOLD_INVERSE);
-            result.push_str(&text[i..i + pattern.len()]);
-            result.push_str(colors::RESET);
-            last = i + pattern.len();
-        }
-        result.push_str(&text[last..]);
-        return result;
-    }
-
-    // Generic path: handles case-fold expansion (ß → ss, İ → i̇). Walk the
-    // original by char, lower only the pattern-sized window at each position.
-    let pattern_lower = pattern.to_lowercase();
-    let pattern_char_count = pattern.chars().count();
     let mut result = String::with_capacity(text.len() + 16);
-    let mut last_end = 0;
-
-    let indices: Vec<usize> = text
-        .char_indices()
-        .map(|(i, _)| i)
-        .chain(std::iter::once(text.len()))
-        .collect();
-
-    let mut i = 0;
-    while i + pattern_char_count < indices.len() {
-        let start = indices[i];
-        let end = indices[i + pattern_char_count];
-        if text[start..end].to_lowercase() == pattern_lower {
-            result.push_str(&text[last_end..start]);
-            result.push_str(colors::BOLD_INVERSE);
-            result.push_str(&text[start..end]);
-            result.push_str(colors::RESET);
-            last_end = end;
-            i += pattern_char_count;
-        } else {
-            i += 1;
-        }
+    let mut last = 0;
+    for range in ranges {
+        result.push_str(&text[last..range.start]);
+        result.push_str(colors::BOLD_INVERSE);
+        result.push_str(&text[range.clone()]);
+        result.push_str(colors::RESET);
+        last = range.end;
     }
-    result.push_str(&text[last_end..]);
+    result.push_str(&text[last..]);
     result
 }
 
@@ -758,11 +3735,350 @@ fn highlight_match(text: &str, pattern: &str) -> String {
 // Session Resume
 // =============================================================================
 
-/// Escape a string for safe inclusion in single-quoted shell argument.
-/// Handles single quotes by ending the quote, adding escaped quote, reopening.
-/// Only used for remote SSH commands where shell invocation is unavoidable.
-fn shell_escape(s: &str) -> String {
-    s.replace("'", "'\\''")
+/// Show the picker and open the chosen session. With `return_to_picker`,
+/// the picker comes back once claude exits (or fails to start); only the
+/// resumed session is re-read, since nothing else changed meanwhile. After
+/// a batch action the picker always comes back, with the sessions it
+/// touched re-read.
+fn run_picker(
+    sessions: &mut Vec<Session>,
+    on_select: OnSelect,
+    return_to_picker: bool,
+    sort: SortOrder,
+    ranking: &ranking::Ranking,
+    mut pick: impl FnMut(&[Session]) -> Result<Option<Picked>>,
+) -> Result<()> {
+    let return_to_picker = return_to_picker && on_select != OnSelect::PrintDir;
+    loop {
+        let id = match pick(sessions)? {
+            None => return Ok(()),
+            Some(Picked::Open(id)) => id,
+            Some(Picked::Changed(ids)) => {
+                for id in ids {
+                    if let Some(index) = sessions.iter().position(|s| s.id == id) {
+                        refresh_picked(sessions, index, ranking);
+                    }
+                }
+                sort.sort(sessions);
+                continue;
+            }
+        };
+        let Some(index) = sessions.iter().position(|s| s.id == id) else {
+            return Ok(());
+        };
+        let result = open_session(&sessions[index], on_select);
+        if !return_to_picker {
+            return result;
+        }
+        if let Err(e) = result {
+            eprintln!("Error: {:#}", e);
+        }
+        refresh_picked(sessions, index, ranking);
+        sort.sort(sessions);
+    }
+}
+
+/// Re-read `sessions[index]` from disk, dropping it if its file is gone.
+fn refresh_picked(sessions: &mut Vec<Session>, index: usize, ranking: &ranking::Ranking) {
+    match claude_code::refresh_session(&sessions[index]) {
+        Some(mut fresh) => {
+            state::ResumeHistory::load().apply(std::slice::from_mut(&mut fresh));
+            ranking.apply(std::slice::from_mut(&mut fresh));
+            sessions[index] = fresh;
+        }
+        None => {
+            sessions.remove(index);
+        }
+    }
+}
+
+/// Act on a picked session: resume/fork it, or print its directory.
+fn open_session(session: &Session, on_select: OnSelect) -> Result<()> {
+    let filepath = if session.archived && on_select != OnSelect::PrintDir {
+        let restored = claude_code::unarchive_session(session)?;
+        eprintln!("Restored session {} from the archive", session.id);
+        restored
+    } else {
+        session.filepath.clone()
+    };
+    if on_select == OnSelect::Resume {
+        let mut history = state::ResumeHistory::load();
+        history.record(&session.id);
+        if let Err(e) = history.save() {
+            eprintln!("Warning: Failed to save resume history: {}", e);
+        }
+    }
+    let pulled;
+    let (session, filepath) = match &session.source {
+        SessionSource::Remote { .. } if pull() && on_select != OnSelect::PrintDir => {
+            pulled = pull_session(session)?;
+            (&pulled, pulled.filepath.clone())
+        }
+        _ => (session, filepath),
+    };
+    match on_select {
+        OnSelect::Resume => resume_session(session, &filepath, false),
+        OnSelect::Fork => resume_session(session, &filepath, true),
+        OnSelect::PrintDir => print_project_dir(session),
+    }
+}
+
+/// `--pull`: whether remote sessions are copied here and resumed locally.
+static PULL: OnceLock<bool> = OnceLock::new();
+
+fn pull() -> bool {
+    PULL.get().copied().unwrap_or(false)
+}
+
+/// Copy a remote session into the local projects dir under its `path_map`
+/// directory, and return it as the local session claude will resume.
+fn pull_session(session: &Session) -> Result<Session> {
+    let dir = local_project_dir(session)?;
+    let filepath = claude_code::pull_transcript(
+        &session.filepath,
+        &session.project_path,
+        &dir,
+        &claude_code::get_claude_projects_dir()?,
+    )?;
+    eprintln!(
+        "Pulled session {} from {} into {}",
+        session.id,
+        session.source.display_name(),
+        filepath.display()
+    );
+    Ok(Session {
+        source: SessionSource::Local,
+        project_path: dir.to_string_lossy().into_owned(),
+        filepath,
+        local_path: None,
+        ..session.clone()
+    })
+}
+
+/// Run the editor command on the session's project directory and wait for
+/// it, so terminal editors get the screen until they exit.
+fn open_in_editor(session: &Session, configured: Option<&str>) -> Result<()> {
+    let dir = local_project_dir(session)?;
+    let editor = configured
+        .map(str::to_string)
+        .or_else(|| {
+            default_editor(
+                on_path("code"),
+                std::env::var("VISUAL").ok(),
+                std::env::var("EDITOR").ok(),
+            )
+        })
+        .context("No editor found: set `editor` under [settings], or $EDITOR")?;
+    let status = std::process::Command::new("sh")
+        .args(["-c", &editor_command_line(&editor, &dir.to_string_lossy())])
+        .current_dir(&dir)
+        .status()
+        .with_context(|| format!("Failed to run editor '{}'", editor))?;
+    if !status.success() {
+        anyhow::bail!("Editor '{}' exited with {}", editor, status);
+    }
+    Ok(())
+}
+
+/// Editor to use without `settings.editor`: VS Code's `code` if installed,
+/// else `$VISUAL`, else `$EDITOR`.
+fn default_editor(
+    has_code: bool,
+    visual: Option<String>,
+    editor: Option<String>,
+) -> Option<String> {
+    if has_code {
+        return Some("code".to_string());
+    }
+    visual
+        .into_iter()
+        .chain(editor)
+        .find(|e| !e.trim().is_empty())
+}
+
+/// Shell command line opening `path`: `{path}` in `editor` is replaced by
+/// the quoted path, otherwise the path is appended.
+fn editor_command_line(editor: &str, path: &str) -> String {
+    let quoted = format!("'{}'", shell_escape(path));
+    if editor.contains("{path}") {
+        editor.replace("{path}", &quoted)
+    } else {
+        format!("{} {}", editor, quoted)
+    }
+}
+
+/// A remote's `resume_command` with `{id}` and `{dir}` replaced by the
+/// quoted session ID and project dir, `{fork}` by `--fork-session` when
+/// forking (otherwise by nothing), and `{args}` by the quoted arguments
+/// after `--`. A placeholder inside quotes (`sh -c "cd {dir}"`) is part of
+/// a command for another shell, so its quoted value is escaped once more
+/// for the quotes around it and reaches that shell still quoted.
+fn resume_command_line(template: &str, id: &str, dir: &str, fork: bool) -> String {
+    let placeholders = [
+        ("{id}", format!("'{}'", shell_escape(id))),
+        ("{dir}", format!("'{}'", shell_escape(dir))),
+        (
+            "{fork}",
+            if fork { "--fork-session" } else { "" }.to_string(),
+        ),
+        (
+            "{args}",
+            quoted_args(claude_args()).trim_start().to_string(),
+        ),
+    ];
+    let mut line = String::new();
+    let mut quote = None;
+    let mut chars = template.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if let Some((name, value)) = placeholders
+            .iter()
+            .find(|(name, _)| template[i..].starts_with(name))
+        {
+            line.push_str(&requote(value, quote));
+            // Placeholders are ASCII, one char per byte
+            for _ in 1..name.len() {
+                chars.next();
+            }
+            continue;
+        }
+        line.push(c);
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None | Some('"'), '\\') => line.extend(chars.next().map(|(_, c)| c)),
+            _ => {}
+        }
+    }
+    line
+}
+
+/// `word`, already quoted for a shell, written so that it comes out of the
+/// `quote` it is placed in unchanged.
+fn requote(word: &str, quote: Option<char>) -> String {
+    match quote {
+        None => word.to_string(),
+        Some('\'') => shell_escape(word),
+        Some(_) => word
+            .chars()
+            .flat_map(|c| {
+                let escape = matches!(c, '\\' | '$' | '`' | '"').then_some('\\');
+                escape.into_iter().chain([c])
+            })
+            .collect(),
+    }
+}
+
+/// Whether `program` is an executable file in a `$PATH` directory.
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| {
+            dir.join(program)
+                .metadata()
+                .is_ok_and(|m| m.is_file() && is_executable(&m))
+        })
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    true
+}
+
+/// `--cd`: print only the project path on stdout (the picker UI draws on
+/// stderr/tty), so `$(cc-sessions --cd)` captures exactly the directory.
+fn print_project_dir(session: &Session) -> Result<()> {
+    println!("{}", local_project_dir(session)?.display());
+    Ok(())
+}
+
+/// The session's project directory on this machine: its own for local
+/// sessions, the remote's `path_map` equivalent for remote ones.
+fn local_project_dir(session: &Session) -> Result<PathBuf> {
+    let path = &session.project_path;
+    if path.is_empty() {
+        anyhow::bail!("Session {} has no project path recorded", session.id);
+    }
+    let dir = match (&session.source, &session.local_path) {
+        (SessionSource::Local, _) => PathBuf::from(path),
+        (SessionSource::Remote { .. }, Some(local)) => local.clone(),
+        (SessionSource::Remote { name, host, .. }, None) => anyhow::bail!(
+            "Session is on remote '{}': its directory is {} on {} (no `path_map` entry for it)",
+            name,
+            path,
+            host
+        ),
+        (SessionSource::Team { owner, .. }, _) => anyhow::bail!(
+            "Session is {}'s: its directory is {} on their machine",
+            owner,
+            path
+        ),
+    };
+    if !dir.is_dir() {
+        anyhow::bail!("Project directory no longer exists: {}", dir.display());
+    }
+    Ok(dir)
+}
+
+/// `cc-sessions shell-init <shell>`: a `ccd` function wrapping `--cd`.
+/// Extra arguments pass through, e.g. `ccd --project api`.
+fn shell_init(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash | Shell::Zsh => {
+            r#"ccd() {
+    local dir
+    dir="$(command cc-sessions --cd "$@")" && [ -n "$dir" ] && cd -- "$dir"
+}
+"#
+        }
+        Shell::Fish => {
+            r#"function ccd
+    set -l dir (command cc-sessions --cd $argv); and test -n "$dir"; and cd -- $dir
+end
+"#
+        }
+    }
+}
+
+/// The registration script for `completions`: it calls the binary back with
+/// `COMPLETE=<shell>` set, so completions follow the installed version.
+fn completion_script(shell: Shell) -> Result<String> {
+    use clap_complete::env::{Bash, EnvCompleter, Fish, Zsh};
+
+    let completer: &dyn EnvCompleter = match shell {
+        Shell::Bash => &Bash,
+        Shell::Zsh => &Zsh,
+        Shell::Fish => &Fish,
+    };
+    let mut script = Vec::new();
+    completer.write_registration(
+        "COMPLETE",
+        "cc-sessions",
+        "cc-sessions",
+        "cc-sessions",
+        &mut script,
+    )?;
+    String::from_utf8(script).context("Completion script is not UTF-8")
+}
+
+/// Arguments after `--`, passed on to every `claude` started by resuming
+/// or forking.
+static CLAUDE_ARGS: OnceLock<Vec<String>> = OnceLock::new();
+
+fn claude_args() -> &'static [String] {
+    CLAUDE_ARGS.get().map_or(&[], Vec::as_slice)
+}
+
+/// `args` single-quoted for a shell, each preceded by a space.
+fn quoted_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| format!(" '{}'", shell_escape(arg)))
+        .collect()
 }
 
 /// Resume or fork a session, handling both local and remote sessions.
@@ -799,12 +4115,49 @@ fn resume_session(session: &Session, filepath: &std::path::Path, fork: bool) ->
             // Invoke claude directly — no shell, no escaping needed
             let mut cmd = Command::new("claude");
             cmd.current_dir(project_path).args(["-r", &session.id]);
+            if let Some(dir) = claude_code::claude_dir_for(filepath) {
+                cmd.env("CLAUDE_CONFIG_DIR", dir);
+            }
             if fork {
                 cmd.arg("--fork-session");
             }
+            cmd.args(claude_args());
             cmd.status()?
         }
-        SessionSource::Remote { name, host, user } => {
+        SessionSource::Team { name, owner, .. } => anyhow::bail!(
+            "Session is {}'s, published to team '{}': team sessions can be previewed and searched, not resumed",
+            owner,
+            name
+        ),
+        SessionSource::Remote {
+            name,
+            resume: session::RemoteResume::Unavailable,
+            ..
+        } => anyhow::bail!(
+            "'{}' is an rsync daemon remote; set resume_command for it to resume its sessions",
+            name
+        ),
+        SessionSource::Remote {
+            name,
+            resume: session::RemoteResume::Command(template),
+            ..
+        } => {
+            println!(
+                "{} remote session {} on {} in {}",
+                action, session.id, name, session.project_path
+            );
+            if !claude_args().is_empty() && !template.contains("{args}") {
+                eprintln!(
+                    "Warning: resume_command of '{}' has no {{args}}; ignoring the arguments after --",
+                    name
+                );
+            }
+            let command = resume_command_line(template, &session.id, project_path, fork);
+            Command::new("sh").args(["-c", &command]).status()?
+        }
+        SessionSource::Remote {
+            name, host, user, ..
+        } => {
             let ssh_target = match user {
                 Some(u) => format!("{}@{}", u, host),
                 None => host.clone(),
@@ -818,10 +4171,11 @@ fn resume_session(session: &Session, filepath: &std::path::Path, fork: bool) ->
             // Remote requires shell string — escape for safe single-quoting
             let fork_flag = if fork { " --fork-session" } else { "" };
             let claude_cmd = format!(
-                "cd '{}' && claude -r '{}'{}",
+                "cd '{}' && claude -r '{}'{}{}",
                 shell_escape(project_path),
                 shell_escape(&session.id),
-                fork_flag
+                fork_flag,
+                quoted_args(claude_args())
             );
 
             // -t allocates a pseudo-TTY (required for claude's interactive mode)
@@ -844,6 +4198,24 @@ fn resume_session(session: &Session, filepath: &std::path::Path, fork: bool) ->
 // Interactive Mode (skim - no external dependencies)
 // =============================================================================
 
+/// Delegate selection to an external picker. There is no fork drill-down, so
+/// forks are listed inline with a `↳` marker. Returns the chosen session ID.
+fn external_picker_mode(
+    picker_cmd: &str,
+    sessions: &[Session],
+    mut layout: Layout,
+    timing: &mut timing::Timing,
+) -> Result<Option<String>> {
+    layout.fit_summary(None, 80);
+    let candidates: Vec<&Session> = sessions.iter().collect();
+    timing.lap("picker startup");
+    let selected = picker::pick(picker_cmd, &candidates, |s| {
+        let prefix = if s.forked_from.is_some() { "↳ " } else { "" };
+        format_session_row_simple(prefix, s, &layout)
+    })?;
+    Ok(selected.map(|session| session.id.clone()))
+}
+
 /// Build a map of parent session ID → child sessions (forks)
 fn build_fork_tree(sessions: &[Session]) -> std::collections::HashMap<&str, Vec<&Session>> {
     use std::collections::HashMap;
@@ -867,18 +4239,21 @@ fn build_subtree_header(
     search_pattern: Option<&str>,
     search_count: Option<usize>,
     fork: bool,
+    flat: bool,
     focus: Option<&str>,
     session_by_id: &std::collections::HashMap<&str, &Session>,
-    debug: bool,
+    layout: &Layout,
 ) -> String {
     // When searching, show esc to clear; otherwise show navigation hints
     let (nav_hint, focus_info) = if search_pattern.is_some() {
         ("esc to clear", String::new())
     } else {
-        let hint = if focus.is_some() {
+        let hint = if flat {
+            "ctrl-t drill-down │ ctrl-o agents │ ctrl-f follow │ ctrl-x editor │ tab+ctrl-v compare │ tab+enter batch │ alt-s/p filter │ alt-w workspace"
+        } else if focus.is_some() {
             "← back"
         } else {
-            "→ into forks"
+            "→ into forks │ ctrl-t flat │ ctrl-o agents │ ctrl-f follow │ ctrl-x editor │ tab+ctrl-v compare │ tab+enter batch │ alt-s/p filter │ alt-w workspace"
         };
         let info = focus
             .and_then(|id| session_by_id.get(id))
@@ -903,77 +4278,109 @@ fn build_subtree_header(
         (None, _, false) => format!("Select session │ {}{}", nav_hint, focus_info),
     };
 
-    let legend = build_column_legend(debug);
+    let legend = build_column_legend(layout);
     format!("{}\n{}", status_line, legend)
 }
 
-/// Width (in columns) consumed by the fixed fields before SUMMARY:
-/// prefix (2) + CRE (4+1) + MOD (4+1) + MSG (3+1) + SOURCE (6+1) + PROJECT (12+1).
-const FIXED_COLS: usize = 36;
-
-/// Simple session row format (no tree glyphs). `desc_width` is the budget for
-/// the trailing summary column — caller computes it from the available pane
-/// width so we only truncate when we actually run out of space.
-fn format_session_row_simple(
-    prefix: &str,
-    session: &Session,
-    debug: bool,
-    desc_width: usize,
-) -> String {
-    let created = format_time_relative(session.created);
-    let modified = format_time_relative(session.modified);
-    let source = session.source.display_name();
-    let id_prefix = if debug {
-        format!("{:<6}", &session.id[..5.min(session.id.len())])
-    } else {
-        String::new()
-    };
-    let msgs = format!("{:>3}", session.turn_count);
-
-    // PROJECT column is fixed at 12 chars so FIXED_COLS arithmetic holds.
-    // Long project names are middle-elided (keeps both prefix and suffix
-    // readable — `claude-cli-internal` → `claud…ternal`).
-    let project = elide_middle(&session.project, 12);
-
-    let desc = format_session_desc(session, desc_width);
+/// Header line naming the active alt-s/alt-p filters; `None` without any.
+fn filter_header(source: Option<&String>, project: Option<&String>) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(source) = source {
+        parts.push(format!("source: {} (alt-s next)", source));
+    }
+    if let Some(project) = project {
+        parts.push(format!("project: {} (alt-p clear)", project));
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    parts.push("esc clears".to_string());
+    Some(format!("Filtered │ {}", parts.join(" │ ")))
+}
 
-    format!(
-        "{}{}{:<4} {:<4} {} {:<6} {:<12} {}",
-        prefix, id_prefix, created, modified, msgs, source, project, desc,
-    )
+/// Picker row (no tree glyphs): `prefix` then the layout's columns. SUMMARY
+/// uses the width set by `Layout::fit_summary`, so callers size it from the
+/// available pane width and we only truncate when we actually run out of
+/// space.
+fn format_session_row_simple(prefix: &str, session: &Session, layout: &Layout) -> String {
+    format_session_row_tree(prefix, "", session, layout)
 }
 
-/// Middle-elide a string to at most `max` chars. Keeps roughly equal head and
-/// tail, inserts `…` between them. Returns a `Cow` to avoid allocating when
-/// the input already fits.
-fn elide_middle(s: &str, max: usize) -> Cow<'_, str> {
-    let chars: Vec<char> = s.chars().collect();
-    if chars.len() <= max {
-        return Cow::Borrowed(s);
+/// Picker row with `tree` glyphs leading the SUMMARY cell (flat fork view),
+/// so the other columns stay aligned.
+fn format_session_row_tree(prefix: &str, tree: &str, session: &Session, layout: &Layout) -> String {
+    let mut row = String::from(prefix);
+    for slot in layout.slots() {
+        match slot.column {
+            Column::Summary => {
+                let width = slot.width.saturating_sub(tree.chars().count());
+                row.push_str(tree);
+                row.push_str(&format_session_desc(session, width));
+            }
+            column => {
+                row.push_str(&slot.pad(&layout_cell(session, column)));
+                row.push(' ');
+            }
+        }
     }
-    let head = (max - 1) / 2;
-    let tail = max - 1 - head;
-    let mut out = String::with_capacity(max);
-    out.extend(&chars[..head]);
-    out.push('…');
-    out.extend(&chars[chars.len() - tail..]);
-    Cow::Owned(out)
+    row
 }
 
-/// Available width for the SUMMARY column given the list pane width.
-/// Floors at a small minimum so very narrow terminals still show something.
-fn desc_budget(pane_width: u16, debug: bool) -> usize {
-    let fixed = FIXED_COLS + if debug { 6 } else { 0 };
-    (pane_width as usize).saturating_sub(fixed).max(20)
+/// Picker layout from config, with fixed columns fitted to `sessions`.
+fn picker_layout(sessions: &[Session], config: &layout::ViewConfig, debug: bool) -> Layout {
+    let mut layout = Layout::new(View::Picker, config, debug);
+    layout.fit(sessions, layout_cell);
+    layout
 }
 
 /// Build column legend for interactive mode
-fn build_column_legend(debug: bool) -> String {
-    let id_col = if debug { "ID    " } else { "" };
-    format!("  {}CRE  MOD  MSG SOURCE PROJECT      SUMMARY", id_col)
+fn build_column_legend(layout: &Layout) -> String {
+    format!("  {}", layout.header())
+}
+
+/// Flat fork view: each of `roots` followed by its forks, depth-first and
+/// newest first, paired with the tree glyphs that draw the hierarchy.
+fn flatten_fork_tree<'a>(
+    roots: &[&'a Session],
+    children_map: &std::collections::HashMap<&str, Vec<&'a Session>>,
+) -> Vec<(&'a Session, String)> {
+    fn visit<'a>(
+        session: &'a Session,
+        indent: &str,
+        children_map: &std::collections::HashMap<&str, Vec<&'a Session>>,
+        seen: &mut std::collections::HashSet<&'a str>,
+        out: &mut Vec<(&'a Session, String)>,
+    ) {
+        let Some(children) = children_map.get(session.id.as_str()) else {
+            return;
+        };
+        for (i, child) in children.iter().enumerate() {
+            // A fork cycle in corrupt metadata would otherwise never end.
+            if !seen.insert(child.id.as_str()) {
+                continue;
+            }
+            let last = i + 1 == children.len();
+            out.push((
+                *child,
+                format!("{}{}", indent, if last { "└─ " } else { "├─ " }),
+            ));
+            let indent = format!("{}{}", indent, if last { "   " } else { "│  " });
+            visit(child, &indent, children_map, seen, out);
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    let mut out = Vec::new();
+    for root in roots {
+        if seen.insert(root.id.as_str()) {
+            out.push((*root, String::new()));
+            visit(root, "", children_map, &mut seen, &mut out);
+        }
+    }
+    out
 }
 
-/// Compute visible sessions based on current search and subtree focus state.
+/// Compute visible sessions based on current search and subtree focus state./// Compute visible sessions based on current search and subtree focus state.
 /// Search mode takes priority and temporarily replaces subtree/root views.
 fn visible_sessions_for_view<'a>(
     sessions: &'a [Session],
@@ -1012,56 +4419,151 @@ fn visible_sessions_for_view<'a>(
         .collect()
 }
 
-fn interactive_mode(sessions: &[Session], fork: bool, debug: bool) -> Result<()> {
+/// Picker state that outlives one `interactive_mode` call, so returning to
+/// the picker after a resume keeps the view, search, and caches.
+#[derive(Default)]
+struct PickerMemory {
+    state: InteractiveState,
+    search_index: claude_code::SearchIndex,
+    shared: Arc<PickerShared>,
+    started: bool,
+    /// One-shot message for the next header, e.g. why a key did nothing.
+    flash: Option<String>,
+}
+
+/// Built-in picker settings fixed for the whole run.
+struct PickerOptions<'a> {
+    on_select: OnSelect,
+    search_defaults: SearchOptions,
+    /// Lines shown above the header (e.g. failing remotes).
+    notices: &'a [String],
+    /// `settings.editor`, for ctrl+x.
+    editor: Option<&'a str>,
+    /// Where remote transcripts live, for ctrl+f.
+    config: &'a remote::Config,
+}
+
+/// Run the built-in picker until a session is chosen, a batch action
+/// changes sessions, or the user quits (`None`).
+fn interactive_mode(
+    sessions: &[Session],
+    mut layout: Layout,
+    options: &PickerOptions,
+    timing: &mut timing::Timing,
+    memory: &mut PickerMemory,
+) -> Result<Option<Picked>> {
     use crossterm::event::{KeyCode, KeyModifiers};
+
+    let PickerOptions {
+        on_select,
+        search_defaults,
+        notices,
+        editor,
+        config,
+    } = *options;
+    let fork = on_select == OnSelect::Fork;
     use std::collections::HashMap;
 
     let session_by_id: HashMap<&str, &Session> =
         sessions.iter().map(|s| (s.id.as_str(), s)).collect();
     let children_map = build_fork_tree(sessions);
+    // Alt+S cycles through these: local first, then remotes by name.
+    let mut sources: Vec<(bool, &str)> = sessions
+        .iter()
+        .map(|s| (!s.source.is_local(), s.source.display_name()))
+        .collect();
+    sources.sort_unstable();
+    sources.dedup();
 
-    // Kick off the transcript search index on a background thread so the picker
-    // renders immediately. By the time the user has typed a query and hit
-    // Ctrl+S the index is almost certainly ready; if not, the join blocks
-    // briefly. Memory stays low for list mode and for interactive mode until
-    // the index actually materializes.
-    let index_targets: Vec<(String, PathBuf)> = sessions
+    // Transcript text is only extracted once the user searches; later
+    // searches re-read just the files that changed since.
+    let index_targets: Vec<(&str, &Path)> = sessions
         .iter()
-        .map(|s| (s.id.clone(), s.filepath.clone()))
+        .filter(|s| !s.archived)
+        .map(|s| (s.id.as_str(), s.filepath.as_path()))
         .collect();
-    let mut index_handle = Some(std::thread::spawn(move || {
-        claude_code::build_search_index(index_targets)
-    }));
-    let mut search_index: Option<claude_code::SearchIndex> = None;
+    let PickerMemory {
+        state,
+        search_index,
+        shared,
+        started: picker_started,
+        flash,
+    } = memory;
+    if config.settings.live_preview {
+        shared
+            .live
+            .get_or_init(|| live_preview::LivePreviews::new(config));
+    }
+
+    // Rows are rendered once per list width and reused by every view (root,
+    // subtree, search results); each pass only picks which ones to show.
+    let mut rows: HashMap<&str, Arc<SessionItem>> = HashMap::new();
+    let mut tree_rows: HashMap<&str, Arc<SessionItem>> = HashMap::new(); // Flat fork view
+    let mut rows_width = None;
 
-    let mut state = InteractiveState::default();
+    let mut search_history = state::SearchHistory::load();
 
     loop {
         // Re-query each loop so terminal resizes between skim invocations are
         // picked up. Preview pane is configured as right:50%, so the list pane
         // gets roughly the other half.
         let (term_w, _) = crossterm::terminal::size().unwrap_or((160, 40));
-        let desc_width = desc_budget(term_w / 2, debug);
+        layout.fit_summary(Some(term_w as usize / 2), 80);
+        if rows_width != Some(layout.total_width()) {
+            rows.clear();
+            tree_rows.clear();
+            rows_width = Some(layout.total_width());
+        }
 
-        let focus = state.focus().map(String::as_str);
-        let visible_sessions = visible_sessions_for_view(
+        let flat = state.flat();
+        // The flat view shows every subtree at once; the focus stack is only
+        // set aside until drill-down is toggled back on.
+        let focus = state.focus().map(String::as_str).filter(|_| !flat);
+        let mut visible_sessions = visible_sessions_for_view(
             sessions,
             &session_by_id,
             &children_map,
             state.search_results(),
             focus,
         );
+        if let Some(source) = state.source_filter() {
+            visible_sessions.retain(|s| s.source.display_name() == source);
+        }
+        if let Some(project) = state.project_filter() {
+            visible_sessions.retain(|s| &s.project == project);
+        }
+        // Tree glyphs per row; search results stay a plain list.
+        let mut trees: HashMap<&str, String> = HashMap::new();
+        if flat && state.search_results().is_none() {
+            let flattened = flatten_fork_tree(&visible_sessions, &children_map);
+            visible_sessions = flattened.iter().map(|(s, _)| *s).collect();
+            trees = flattened
+                .into_iter()
+                .map(|(s, tree)| (s.id.as_str(), tree))
+                .collect();
+        }
 
         let search_count = state.search_results().map(|r| r.len());
         let search_pattern = state.search_pattern().map(String::as_str);
-        let header = build_subtree_header(
+        let search_query = search_pattern.map(|p| SearchQuery::parse(p, search_defaults));
+        let mut header = build_subtree_header(
             search_pattern,
             search_count,
             fork,
+            flat,
             focus,
             &session_by_id,
-            debug,
+            &layout,
         );
+        if let Some(filters) = filter_header(state.source_filter(), state.project_filter()) {
+            header = format!("{}\n{}", filters, header);
+        }
+        if let Some(flash) = flash.take() {
+            header = format!("{}\n{}", flash, header);
+        }
+        if !notices.is_empty() {
+            header = format!("{}\n{}", notices.join("\n"), header);
+        }
 
         let options = SkimOptionsBuilder::default()
             .height("100%")
@@ -1071,8 +4573,19 @@ fn interactive_mode(sessions: &[Session], fork: bool, debug: bool) -> Result<()>
             .prompt("filter> ")
             .reverse(false)
             .no_sort(true)
+            .multi(true)
             .bind(vec![
                 "ctrl-s:accept".to_string(),
+                "ctrl-r:accept".to_string(),
+                "ctrl-o:accept".to_string(),
+                "ctrl-t:accept".to_string(),
+                "ctrl-x:accept".to_string(),
+                "ctrl-f:accept".to_string(),
+                "ctrl-v:accept".to_string(),
+                "alt-s:accept".to_string(),
+                "alt-p:accept".to_string(),
+                "alt-e:accept".to_string(),
+                "alt-w:accept".to_string(),
                 "right:accept".to_string(),
                 "left:accept".to_string(),
             ])
@@ -1081,66 +4594,224 @@ fn interactive_mode(sessions: &[Session], fork: bool, debug: bool) -> Result<()>
 
         let (tx, rx): (SkimItemSender, SkimItemReceiver) = unbounded();
 
+        *shared.search.lock().unwrap() = search_query.clone();
         let items: Vec<Arc<dyn SkimItem>> = visible_sessions
             .iter()
             .map(|session| {
-                let prefix = if focus == Some(session.id.as_str()) {
-                    "▷ "
-                } else if children_map.contains_key(session.id.as_str()) {
-                    "▶ "
-                } else {
-                    "  "
-                };
-                Arc::new(SessionItem {
-                    filepath: session.filepath.clone(),
-                    display: format_session_row_simple(prefix, session, debug, desc_width),
-                    session_id: session.id.clone(),
-                    named: session.name.is_some(),
-                    search_pattern: search_pattern.map(str::to_owned),
-                }) as Arc<dyn SkimItem>
+                let id = session.id.as_str();
+                if focus == Some(id) {
+                    // The focused parent's marker is the only per-view difference.
+                    return Arc::new(SessionItem::new("▷ ", "", session, &layout, shared))
+                        as Arc<dyn SkimItem>;
+                }
+                if let Some(tree) = trees.get(id) {
+                    let row = tree_rows.entry(id).or_insert_with(|| {
+                        Arc::new(SessionItem::new("  ", tree, session, &layout, shared))
+                    });
+                    return Arc::clone(row) as Arc<dyn SkimItem>;
+                }
+                let row = rows.entry(id).or_insert_with(|| {
+                    let prefix = if children_map.contains_key(id) {
+                        "▶ "
+                    } else {
+                        "  "
+                    };
+                    Arc::new(SessionItem::new(prefix, "", session, &layout, shared))
+                });
+                Arc::clone(row) as Arc<dyn SkimItem>
             })
             .collect();
         let _ = tx.send(items);
         drop(tx);
 
+        // Render the rows the cursor starts on while the user reads the list.
+        // Search previews depend on the query, so only plain ones are cached.
+        let _prewarm = search_query.is_none().then(|| {
+            let paths = visible_sessions
+                .iter()
+                .take(preview_cache::PREWARM_COUNT)
+                .map(|s| s.filepath.clone())
+                .collect();
+            shared.previews.prewarm(paths, generate_preview_content)
+        });
+
+        if !*picker_started {
+            timing.lap("picker startup");
+            *picker_started = true;
+        }
         let out =
             Skim::run_with(options, Some(rx)).map_err(|e| anyhow::anyhow!("skim failed: {}", e))?;
+        // Time spent in the picker is the user's, not ours.
+        timing.reset();
 
         if out.is_abort {
             match state.apply(StateAction::Esc) {
-                StateEffect::Exit => return Ok(()),
+                StateEffect::Exit => return Ok(None),
                 _ => continue,
             }
         }
 
         let key = (out.final_key.code, out.final_key.modifiers);
 
-        if key == (KeyCode::Char('s'), KeyModifiers::CONTROL) {
-            let effect = state.apply(StateAction::CtrlS {
-                query: out.query.to_string(),
-            });
-            let StateEffect::RunSearch { pattern } = effect else {
-                continue;
-            };
-            // Materialize the background index on first search.
-            let index = search_index.get_or_insert_with(|| {
-                index_handle
-                    .take()
-                    .and_then(|h| h.join().ok())
-                    .unwrap_or_default()
-            });
-            // Index is built with make_ascii_lowercase(); fold the query the
-            // same way so non-ASCII letters compare identically on both sides.
-            let pattern_lower = pattern.to_ascii_lowercase();
-            let matched_ids: std::collections::HashSet<String> = index
+        // Ctrl+S searches the typed query; Ctrl+R recalls a past one.
+        let search_request = if key == (KeyCode::Char('s'), KeyModifiers::CONTROL) {
+            Some(out.query.to_string())
+        } else if key == (KeyCode::Char('r'), KeyModifiers::CONTROL) {
+            match pick_search_history(&search_history.queries)? {
+                Some(query) => Some(query),
+                None => continue,
+            }
+        } else {
+            None
+        };
+
+        if let Some(query) = search_request {
+            let effect = state.apply(StateAction::CtrlS { query });
+            let StateEffect::RunSearch { pattern } = effect else {
+                continue;
+            };
+            let query = SearchQuery::parse(&pattern, search_defaults);
+            if query.pattern.is_empty() {
+                continue;
+            }
+            // Extract new or changed transcripts, with progress on the first
+            // (full) pass.
+            let stale = search_index.stale(&index_targets);
+            if !stale.is_empty() {
+                let progress = progress::Progress::new("indexed", stale.len());
+                progress::run(&progress, || {
+                    search_index.update(stale, search_defaults.include_tools, &progress)
+                });
+                timing.lap("search index");
+            }
+            let matched_ids = run_transcript_search(
+                search_index,
+                search_defaults.include_tools,
+                &query,
+                &session_by_id,
+            );
+            timing.lap("search");
+            search_history.record(&pattern);
+            if let Err(e) = search_history.save() {
+                eprintln!("Warning: Failed to save search history: {}", e);
+            }
+            let _ = state.apply(StateAction::ApplySearchResults {
+                pattern,
+                matched_ids,
+            });
+            continue;
+        }
+
+        // Ctrl+O: browse the selected session's subagent transcripts.
+        if key == (KeyCode::Char('o'), KeyModifiers::CONTROL) {
+            let selected = out
+                .selected_items
+                .first()
+                .and_then(|m| session_by_id.get(m.output().as_ref()).copied());
+            if let Some(session) = selected {
+                let runs = claude_code::find_subagent_runs(&session.filepath);
+                if runs.is_empty() {
+                    *flash = Some(format!(
+                        "No agent runs in [{}]",
+                        format_session_desc(session, 30)
+                    ));
+                } else {
+                    pick_subagent_run(session, &runs, shared)?;
+                }
+            }
+            continue;
+        }
+
+        if key == (KeyCode::Char('t'), KeyModifiers::CONTROL) {
+            let _ = state.apply(StateAction::ToggleFlat);
+            continue;
+        }
+
+        // Alt+S / Alt+P: narrow to one source, or to the highlighted
+        // session's project.
+        if key == (KeyCode::Char('s'), KeyModifiers::ALT) {
+            let sources = sources.iter().map(|(_, name)| name.to_string()).collect();
+            let _ = state.apply(StateAction::CycleSource { sources });
+            continue;
+        }
+        // Alt+E: expand or collapse approved plans in the preview.
+        if key == (KeyCode::Char('e'), KeyModifiers::ALT) {
+            shared.expand_plans.fetch_xor(true, Ordering::Relaxed);
+            continue;
+        }
+        if key == (KeyCode::Char('p'), KeyModifiers::ALT) {
+            let project = out
+                .selected_items
+                .first()
+                .and_then(|m| session_by_id.get(m.output().as_ref()))
+                .map(|s| s.project.clone());
+            let _ = state.apply(StateAction::ToggleProject { project });
+            continue;
+        }
+
+        // Alt+W: add the sessions marked with Tab (or the highlighted one)
+        // to a workspace.
+        if key == (KeyCode::Char('w'), KeyModifiers::ALT) {
+            let ids: Vec<&str> = out
+                .selected_items
+                .iter()
+                .filter_map(|m| session_by_id.get(m.output().as_ref()))
+                .map(|s| s.id.as_str())
+                .collect();
+            if !ids.is_empty() {
+                *flash = add_to_workspace(ids)?;
+            }
+            continue;
+        }
+
+        // Ctrl+X: open the selected session's project in an editor, then
+        // come back here.
+        if key == (KeyCode::Char('x'), KeyModifiers::CONTROL) {
+            let selected = out
+                .selected_items
+                .first()
+                .and_then(|m| session_by_id.get(m.output().as_ref()).copied());
+            if let Some(session) = selected
+                && let Err(e) = open_in_editor(session, editor)
+            {
+                *flash = Some(format!("⚠ {:#}", e));
+            }
+            continue;
+        }
+
+        // Ctrl+F: follow the selected session's transcript until q, then
+        // come back here.
+        if key == (KeyCode::Char('f'), KeyModifiers::CONTROL) {
+            let selected = out
+                .selected_items
+                .first()
+                .and_then(|m| session_by_id.get(m.output().as_ref()).copied());
+            if let Some(session) = selected {
+                eprintln!(
+                    "Following [{}] (q to return)",
+                    format_session_desc(session, 60)
+                );
+                if let Err(e) = follow_session(config, session, FOLLOW_BACKLOG) {
+                    *flash = Some(format!("⚠ {:#}", e));
+                }
+            }
+            continue;
+        }
+
+        // Ctrl+V: compare the two sessions marked with Tab.
+        if key == (KeyCode::Char('v'), KeyModifiers::CONTROL) {
+            let marked: Vec<&Session> = out
+                .selected_items
                 .iter()
-                .filter(|(_, text)| text.contains(&pattern_lower))
-                .map(|(id, _)| id.clone())
+                .filter_map(|m| session_by_id.get(m.output().as_ref()).copied())
                 .collect();
-            let _ = state.apply(StateAction::ApplySearchResults {
-                pattern,
-                matched_ids,
-            });
+            if let [a, b] = marked[..] {
+                if let Some(id) = compare_sessions([a, b], &layout)? {
+                    return Ok(Some(Picked::Open(id)));
+                }
+            } else {
+                *flash = Some("Mark two sessions with tab, then press ctrl-v to compare".into());
+            }
             continue;
         }
 
@@ -1163,15 +4834,537 @@ fn interactive_mode(sessions: &[Session], fork: bool, debug: bool) -> Result<()>
             continue;
         }
 
+        // Enter with several marked: act on all of them.
+        if out.selected_items.len() > 1 {
+            let marked: Vec<&Session> = out
+                .selected_items
+                .iter()
+                .filter_map(|m| session_by_id.get(m.output().as_ref()).copied())
+                .collect();
+            let Some(action) = pick_batch_action(marked.len())? else {
+                continue;
+            };
+            let outcome = run_batch_action(action, &marked, sessions)?;
+            *flash = outcome.message;
+            if !outcome.changed.is_empty() {
+                return Ok(Some(Picked::Changed(outcome.changed)));
+            }
+            continue;
+        }
+
         // Enter: select session
         let selected_id = out.selected_items.first().map(|m| m.output().to_string());
         if let StateEffect::Select { session_id } = state.apply(StateAction::Enter { selected_id })
-            && let Some(session) = session_by_id.get(session_id.as_str())
+            && session_by_id.contains_key(session_id.as_str())
         {
-            resume_session(session, &session.filepath, fork)?;
-            return Ok(());
+            return Ok(Some(Picked::Open(session_id)));
+        }
+    }
+}
+
+/// Add `ids` to a workspace the user names; the header message, if any.
+fn add_to_workspace(ids: Vec<&str>) -> Result<Option<String>> {
+    let mut workspaces = state::Workspaces::load();
+    let names: Vec<String> = workspaces.workspaces.keys().cloned().collect();
+    let header = format!(
+        "Add {} session(s) to a workspace │ type a name and press enter, or pick one with nothing typed │ esc to cancel",
+        ids.len()
+    );
+    let Some(name) = prompt_name(&header, "workspace> ", &names)? else {
+        return Ok(None);
+    };
+    let added = workspaces.add(&name, ids);
+    Ok(Some(match workspaces.save() {
+        Ok(()) => format!("Added {} session(s) to workspace '{}'", added, name),
+        Err(e) => format!("⚠ {:#}", e),
+    }))
+}
+
+/// What Enter does to several marked sessions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BatchAction {
+    Export(export::ExportFormat),
+    Tag,
+    Workspace,
+    Delete,
+}
+
+impl BatchAction {
+    const ALL: [BatchAction; 5] = [
+        BatchAction::Export(export::ExportFormat::Json),
+        BatchAction::Export(export::ExportFormat::Markdown),
+        BatchAction::Tag,
+        BatchAction::Workspace,
+        BatchAction::Delete,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            BatchAction::Export(export::ExportFormat::Json) => "Export as JSON",
+            BatchAction::Export(export::ExportFormat::Markdown) => "Export as Markdown",
+            BatchAction::Tag => "Tag (local sessions)",
+            BatchAction::Workspace => "Add to a workspace",
+            BatchAction::Delete => "Delete (local sessions, undoable)",
+        }
+    }
+}
+
+/// The result of a batch action: a header message, and the sessions whose
+/// files changed or went away.
+#[derive(Default)]
+struct BatchOutcome {
+    message: Option<String>,
+    changed: Vec<String>,
+}
+
+/// Ask what to do with `count` marked sessions.
+fn pick_batch_action(count: usize) -> Result<Option<BatchAction>> {
+    let header = format!(
+        "{} sessions marked │ enter to pick an action, esc to go back",
+        count
+    );
+    let labels: Vec<String> = BatchAction::ALL
+        .iter()
+        .map(|a| a.label().to_string())
+        .collect();
+    let Some(label) = pick_from_menu(&header, "action> ", &labels)? else {
+        return Ok(None);
+    };
+    Ok(BatchAction::ALL.into_iter().find(|a| a.label() == label))
+}
+
+/// Carry out `action` on the `marked` sessions. `all` supplies the tags
+/// already in use, offered when tagging.
+fn run_batch_action(
+    action: BatchAction,
+    marked: &[&Session],
+    all: &[Session],
+) -> Result<BatchOutcome> {
+    // Transcripts of remote and team sessions are copies; changing them
+    // here would be undone (or not) by the next sync.
+    let (local, skipped): (Vec<&Session>, Vec<&Session>) =
+        marked.iter().partition(|s| s.source.is_local());
+    let skipped_note = if skipped.is_empty() {
+        String::new()
+    } else {
+        format!(" ({} non-local skipped)", skipped.len())
+    };
+    let outcome = match action {
+        BatchAction::Export(format) => {
+            let cwd = std::env::current_dir()?.display().to_string();
+            let header = format!(
+                "Export {} session(s) │ type a directory (created if missing) and press enter, or enter for the current one │ esc to cancel",
+                marked.len()
+            );
+            let Some(dir) = prompt_name(&header, "directory> ", &[cwd])? else {
+                return Ok(BatchOutcome::default());
+            };
+            let dir = remote::expand_path(&dir)?;
+            let (written, failures) = export_sessions(marked, &dir, format);
+            BatchOutcome {
+                message: Some(batch_message(
+                    format!("Exported {} session(s) to {}", written, dir.display()),
+                    &failures,
+                )),
+                changed: Vec::new(),
+            }
+        }
+        BatchAction::Workspace => BatchOutcome {
+            message: add_to_workspace(marked.iter().map(|s| s.id.as_str()).collect())?,
+            changed: Vec::new(),
+        },
+        _ if local.is_empty() => BatchOutcome {
+            message: Some("Only local sessions can be tagged or deleted".to_string()),
+            changed: Vec::new(),
+        },
+        BatchAction::Tag => {
+            let mut tags: Vec<String> = all.iter().filter_map(|s| s.tag.clone()).collect();
+            tags.sort_unstable();
+            tags.dedup();
+            let header = format!(
+                "Tag {} session(s){} │ type a tag and press enter, or pick one with nothing typed │ esc to cancel",
+                local.len(),
+                skipped_note
+            );
+            let Some(tag) = prompt_name(&header, "tag> ", &tags)? else {
+                return Ok(BatchOutcome::default());
+            };
+            let (changed, failures) = tag_sessions(&local, &tag);
+            BatchOutcome {
+                message: Some(batch_message(
+                    format!(
+                        "Tagged {} session(s) '{}'{}",
+                        changed.len(),
+                        tag,
+                        skipped_note
+                    ),
+                    &failures,
+                )),
+                changed,
+            }
+        }
+        BatchAction::Delete => {
+            let confirm = format!("Delete {} session(s)", local.len());
+            let header = format!(
+                "Delete {} session(s){}? `cc-sessions undo` restores them │ esc to go back",
+                local.len(),
+                skipped_note
+            );
+            let choices = ["Keep them".to_string(), confirm.clone()];
+            if pick_from_menu(&header, "delete> ", &choices)? != Some(confirm) {
+                return Ok(BatchOutcome::default());
+            }
+            let mut batch = undo::Batch::begin("picker delete")?;
+            let (changed, failures) = trash_sessions(&mut batch, &local);
+            BatchOutcome {
+                message: Some(batch_message(
+                    format!(
+                        "Deleted {} session(s){}; undo with `cc-sessions undo`",
+                        changed.len(),
+                        skipped_note
+                    ),
+                    &failures,
+                )),
+                changed,
+            }
+        }
+    };
+    Ok(outcome)
+}
+
+/// `summary`, plus the first failure and how many there were.
+fn batch_message(summary: String, failures: &[(String, anyhow::Error)]) -> String {
+    match failures {
+        [] => summary,
+        [(id, e), ..] => format!(
+            "⚠ {}; {} failed, e.g. {}: {:#}",
+            summary,
+            failures.len(),
+            &id[..8.min(id.len())],
+            e
+        ),
+    }
+}
+
+/// Write each session's transcript to `dir` as `<id>.json` or `<id>.md`.
+/// Returns how many were written and the failures.
+fn export_sessions(
+    sessions: &[&Session],
+    dir: &Path,
+    format: export::ExportFormat,
+) -> (usize, Vec<(String, anyhow::Error)>) {
+    let mut failures = Vec::new();
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        let e = anyhow::Error::new(e).context(format!("Failed to create {}", dir.display()));
+        return (0, vec![(String::new(), e)]);
+    }
+    let mut written = 0;
+    for session in sessions {
+        let result = claude_code::read_messages(&session.filepath).and_then(|messages| {
+            let (rendered, extension) = match format {
+                export::ExportFormat::Json => (
+                    format!("{}\n", export::render_json(session, &messages)?),
+                    "json",
+                ),
+                export::ExportFormat::Markdown => (
+                    export::render_markdown(session, &messages, 1, messages.len()),
+                    "md",
+                ),
+            };
+            let path = dir.join(format!("{}.{}", session.id, extension));
+            std::fs::write(&path, rendered)
+                .with_context(|| format!("Failed to write {}", path.display()))
+        });
+        match result {
+            Ok(()) => written += 1,
+            Err(e) => failures.push((session.id.clone(), e)),
+        }
+    }
+    (written, failures)
+}
+
+/// Tag each (local) session; returns the IDs tagged and the failures.
+fn tag_sessions(sessions: &[&Session], tag: &str) -> (Vec<String>, Vec<(String, anyhow::Error)>) {
+    let mut tagged = Vec::new();
+    let mut failures = Vec::new();
+    for session in sessions {
+        let result = if session.archived {
+            Err(anyhow::anyhow!("archived; resume it once to restore it"))
+        } else {
+            claude_code::append_tag(&session.filepath, &session.id, tag)
+        };
+        match result {
+            Ok(()) => tagged.push(session.id.clone()),
+            Err(e) => failures.push((session.id.clone(), e)),
+        }
+    }
+    (tagged, failures)
+}
+
+/// Move each (local) session's transcript, and its directory of subagent
+/// runs if there is one, into `batch`'s trash. Returns the IDs deleted and
+/// the failures.
+fn trash_sessions(
+    batch: &mut undo::Batch,
+    sessions: &[&Session],
+) -> (Vec<String>, Vec<(String, anyhow::Error)>) {
+    let mut deleted = Vec::new();
+    let mut failures = Vec::new();
+    for session in sessions {
+        let side_dir = session.filepath.with_extension("");
+        let result = batch.trash(&session.filepath).and_then(|()| {
+            if !session.archived && side_dir.is_dir() {
+                batch.trash(&side_dir)?;
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => deleted.push(session.id.clone()),
+            Err(e) => failures.push((session.id.clone(), e)),
         }
     }
+    (deleted, failures)
+}
+
+/// A small picker over `choices`; the one chosen, if any.
+fn pick_from_menu(header: &str, prompt: &str, choices: &[String]) -> Result<Option<String>> {
+    let options = SkimOptionsBuilder::default()
+        .height("40%")
+        .header(header)
+        .prompt(prompt)
+        .reverse(true)
+        .no_sort(true)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build skim options: {}", e))?;
+
+    let (tx, rx): (SkimItemSender, SkimItemReceiver) = unbounded();
+    let items: Vec<Arc<dyn SkimItem>> = choices
+        .iter()
+        .map(|c| Arc::new(c.clone()) as Arc<dyn SkimItem>)
+        .collect();
+    let _ = tx.send(items);
+    drop(tx);
+
+    let out =
+        Skim::run_with(options, Some(rx)).map_err(|e| anyhow::anyhow!("skim failed: {}", e))?;
+    if out.is_abort {
+        return Ok(None);
+    }
+    Ok(out.selected_items.first().map(|m| m.output().to_string()))
+}
+
+/// Ask for a name under `header`: a typed one (new or existing), or with
+/// nothing typed, the highlighted one of `existing`.
+fn prompt_name(header: &str, prompt: &str, existing: &[String]) -> Result<Option<String>> {
+    let options = SkimOptionsBuilder::default()
+        .height("40%")
+        .header(header)
+        .prompt(prompt)
+        .reverse(true)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build skim options: {}", e))?;
+
+    let (tx, rx): (SkimItemSender, SkimItemReceiver) = unbounded();
+    let items: Vec<Arc<dyn SkimItem>> = existing
+        .iter()
+        .map(|name| Arc::new(name.clone()) as Arc<dyn SkimItem>)
+        .collect();
+    let _ = tx.send(items);
+    drop(tx);
+
+    let out =
+        Skim::run_with(options, Some(rx)).map_err(|e| anyhow::anyhow!("skim failed: {}", e))?;
+    if out.is_abort {
+        return Ok(None);
+    }
+    let typed = out.query.trim();
+    if !typed.is_empty() {
+        return Ok(Some(typed.to_string()));
+    }
+    Ok(out.selected_items.first().map(|m| m.output().to_string()))
+}
+
+/// Show recent searches in a secondary picker; returns the chosen query.
+fn pick_search_history(queries: &[String]) -> Result<Option<String>> {
+    if queries.is_empty() {
+        return Ok(None);
+    }
+    pick_from_menu(
+        "Recent searches │ enter to re-run, esc to cancel",
+        "history> ",
+        queries,
+    )
+}
+
+/// List a session's subagent runs with their transcripts in the preview.
+/// Runs can't be resumed, so Enter and Esc both return to the session list.
+fn pick_subagent_run(
+    session: &Session,
+    runs: &[claude_code::SubagentRun],
+    shared: &Arc<PickerShared>,
+) -> Result<()> {
+    let header = format!(
+        "Agent runs of [{}] │ esc to go back\n  {:>4} {:>4}  TASK",
+        format_session_desc(session, 40),
+        "MOD",
+        "MSG"
+    );
+    let options = SkimOptionsBuilder::default()
+        .height("100%")
+        .preview("")
+        .preview_window("right:50%:wrap")
+        .header(&header)
+        .prompt("agents> ")
+        .reverse(false)
+        .no_sort(true)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build skim options: {}", e))?;
+
+    let (tx, rx): (SkimItemSender, SkimItemReceiver) = unbounded();
+    let items: Vec<Arc<dyn SkimItem>> = runs
+        .iter()
+        .map(|run| {
+            Arc::new(SubagentItem {
+                filepath: run.filepath.clone(),
+                display: format_subagent_row(run),
+                header: format!(
+                    "{}agent {} of {}{}\n\n",
+                    colors::DIM,
+                    run.agent_id,
+                    session.id,
+                    colors::RESET
+                ),
+                shared: Arc::clone(shared),
+            }) as Arc<dyn SkimItem>
+        })
+        .collect();
+    let _ = tx.send(items);
+    drop(tx);
+
+    Skim::run_with(options, Some(rx)).map_err(|e| anyhow::anyhow!("skim failed: {}", e))?;
+    Ok(())
+}
+
+/// Show two sessions' transcripts side by side. Enter resumes the
+/// highlighted one (its ID is returned); Esc goes back to the list.
+fn compare_sessions(sessions: [&Session; 2], layout: &Layout) -> Result<Option<String>> {
+    let [a, b] = if sessions[1].created < sessions[0].created {
+        [sessions[1], sessions[0]]
+    } else {
+        sessions
+    };
+    let transcripts = Arc::new([
+        preview_messages(&a.filepath)?,
+        preview_messages(&b.filepath)?,
+    ]);
+    let header = format!(
+        "Compare: left [{}] │ right [{}]\nenter to resume, esc to go back\n{}",
+        format_session_desc(a, 30),
+        format_session_desc(b, 30),
+        build_column_legend(layout)
+    );
+    let options = SkimOptionsBuilder::default()
+        .height("100%")
+        .preview("")
+        .preview_window("down:80%")
+        .header(&header)
+        .prompt("compare> ")
+        .reverse(true)
+        .no_sort(true)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build skim options: {}", e))?;
+
+    let (tx, rx): (SkimItemSender, SkimItemReceiver) = unbounded();
+    let items: Vec<Arc<dyn SkimItem>> = [("◧ ", a), ("◨ ", b)]
+        .into_iter()
+        .map(|(prefix, session)| {
+            Arc::new(CompareItem {
+                display: format_session_row_simple(prefix, session, layout),
+                session_id: session.id.clone(),
+                transcripts: Arc::clone(&transcripts),
+            }) as Arc<dyn SkimItem>
+        })
+        .collect();
+    let _ = tx.send(items);
+    drop(tx);
+
+    let out =
+        Skim::run_with(options, Some(rx)).map_err(|e| anyhow::anyhow!("skim failed: {}", e))?;
+    if out.is_abort {
+        return Ok(None);
+    }
+    Ok(out.selected_items.first().map(|m| m.output().to_string()))
+}
+
+/// Agent run row: modified, user turns, and the task it was given.
+fn format_subagent_row(run: &claude_code::SubagentRun) -> String {
+    format!(
+        "  {:>4} {:>4}  {}",
+        format_time_relative(run.modified),
+        run.turn_count,
+        run.first_prompt.as_deref().unwrap_or("(no prompt)")
+    )
+}
+
+/// Match a query against the transcript index in parallel, with a progress
+/// line on stderr for slow searches. The index is ASCII-lowercased, so
+/// case-sensitive queries use it as a prefilter and re-read the candidate
+/// transcripts to confirm. `in:tools` queries against an index built without
+/// tool output skip the prefilter and re-read every transcript; `-in:tools`
+/// queries against one with tool output confirm their hits. Archived
+/// sessions aren't indexed; their metadata is matched instead.
+fn run_transcript_search(
+    index: &claude_code::SearchIndex,
+    index_includes_tools: bool,
+    query: &SearchQuery,
+    session_by_id: &std::collections::HashMap<&str, &Session>,
+) -> std::collections::HashSet<String> {
+    use rayon::prelude::*;
+
+    let progress = progress::Progress::with_matches("searched", index.texts().len());
+    let mut matched: std::collections::HashSet<String> = progress::run(&progress, || {
+        index
+            .texts()
+            .par_iter()
+            .filter(|(id, text)| {
+                let confirm = || {
+                    session_by_id.get(id.as_str()).is_some_and(|s| {
+                        let full =
+                            claude_code::transcript_text(&s.filepath, query.options.include_tools);
+                        query.matches(&full)
+                    })
+                };
+                let matched = if query.covered_by_index(index_includes_tools) {
+                    query.matches_lowercased(text)
+                        && (query.settled_by_index(index_includes_tools) || confirm())
+                } else {
+                    confirm()
+                };
+                progress.inc();
+                if matched {
+                    progress.add_match();
+                }
+                matched
+            })
+            .map(|(id, _)| id.clone())
+            .collect()
+    });
+    matched.extend(
+        session_by_id
+            .values()
+            .filter(|s| s.archived && query.matches(&archived_search_text(s)))
+            .map(|s| s.id.clone()),
+    );
+    matched
+}
+
+/// State every picker row consults when rendering its preview.
+#[derive(Default)]
+struct PickerShared {
+    previews: Arc<preview_cache::PreviewCache>, // Shared with the prewarm thread
+    search: std::sync::Mutex<Option<SearchQuery>>, // When set, previews show matching lines
+    expand_plans: std::sync::atomic::AtomicBool, // Toggled with alt-e
+    live: std::sync::OnceLock<live_preview::LivePreviews>, // Set if settings.live_preview
 }
 
 /// Session item for skim display
@@ -1179,8 +5372,41 @@ struct SessionItem {
     filepath: PathBuf,
     display: String,
     session_id: String,
-    named: bool,                    // Has a custom title — render bold+yellow
-    search_pattern: Option<String>, // When set, preview shows matching lines
+    named: bool, // Has a custom title — render bold+yellow
+    source_color: Option<(std::ops::Range<usize>, ratatui::style::Color)>, // SOURCE cell color
+    header: String, // Prepended to the preview
+    plan_offset: Option<u64>,
+    remote: Option<String>, // Remote name, for live previews
+    shared: Arc<PickerShared>,
+}
+
+impl SessionItem {
+    fn new(
+        prefix: &str,
+        tree: &str,
+        session: &Session,
+        layout: &Layout,
+        shared: &Arc<PickerShared>,
+    ) -> Self {
+        SessionItem {
+            filepath: session.filepath.clone(),
+            display: format_session_row_tree(prefix, tree, session, layout),
+            session_id: session.id.clone(),
+            named: session.name.is_some(),
+            source_color: session
+                .source
+                .color()
+                .zip(layout.char_range(Column::Source))
+                .map(|(color, range)| (range, ratatui_color(color))),
+            header: preview_header(session),
+            plan_offset: session.plan_offset,
+            remote: match &session.source {
+                SessionSource::Remote { name, .. } => Some(name.clone()),
+                SessionSource::Local | SessionSource::Team { .. } => None,
+            },
+            shared: Arc::clone(shared),
+        }
+    }
 }
 
 impl SkimItem for SessionItem {
@@ -1196,7 +5422,11 @@ impl SkimItem for SessionItem {
                 .fg(Color::Yellow)
                 .add_modifier(Modifier::BOLD);
         }
-        context.to_line(Cow::Borrowed(&self.display))
+        let line = context.to_line(Cow::Borrowed(&self.display));
+        match &self.source_color {
+            Some((range, color)) => recolor(line, range.clone(), *color),
+            None => line,
+        }
     }
 
     fn output(&self) -> Cow<'_, str> {
@@ -1204,17 +5434,315 @@ impl SkimItem for SessionItem {
     }
 
     fn preview(&self, _context: PreviewContext) -> ItemPreview {
-        let result = match &self.search_pattern {
-            Some(pattern) => generate_search_preview(&self.filepath, pattern),
-            None => generate_preview_content(&self.filepath),
+        let search = self.shared.search.lock().unwrap().clone();
+        let result = match search {
+            Some(query) => generate_search_preview(&self.filepath, &query),
+            None => {
+                let plan = self
+                    .plan_offset
+                    .and_then(|offset| claude_code::read_plan(&self.filepath, offset))
+                    .map(|plan| {
+                        let expanded = self.shared.expand_plans.load(Ordering::Relaxed);
+                        plan_section(&plan, expanded)
+                    })
+                    .unwrap_or_default();
+                let live = self
+                    .remote
+                    .as_deref()
+                    .zip(self.shared.live.get())
+                    .and_then(|(remote, live)| {
+                        let fetched = live.get(remote, &self.filepath)?;
+                        Some(live_preview_section(&fetched, remote))
+                    })
+                    .unwrap_or_default();
+                self.shared
+                    .previews
+                    .get_or_render(&self.filepath, generate_preview_content)
+                    .map(|content| format!("{}{}{}", plan, live, content))
+            }
         };
         match result {
-            Ok(content) => ItemPreview::AnsiText(content),
+            Ok(content) => styled_preview(format!("{}{}", self.header, content)),
+            // A team session published without its transcript
+            Err(_) if !self.filepath.exists() => styled_preview(self.header.clone()),
+            Err(_) => ItemPreview::Text("(failed to load preview)".to_string()),
+        }
+    }
+}
+
+/// Subagent transcript row in the agent-run picker.
+struct SubagentItem {
+    filepath: PathBuf,
+    display: String,
+    header: String, // Prepended to the preview
+    shared: Arc<PickerShared>,
+}
+
+impl SkimItem for SubagentItem {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.display)
+    }
+
+    fn preview(&self, _context: PreviewContext) -> ItemPreview {
+        match self
+            .shared
+            .previews
+            .get_or_render(&self.filepath, generate_preview_content)
+        {
+            Ok(content) => styled_preview(format!("{}{}", self.header, content)),
             Err(_) => ItemPreview::Text("(failed to load preview)".to_string()),
         }
     }
 }
 
+/// One of the two sessions in the compare picker; both preview the pair.
+struct CompareItem {
+    display: String,
+    session_id: String,
+    transcripts: Arc<[Vec<(char, String)>; 2]>,
+}
+
+impl SkimItem for CompareItem {
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.display)
+    }
+
+    fn output(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.session_id)
+    }
+
+    fn preview(&self, context: PreviewContext) -> ItemPreview {
+        let [left, right] = &*self.transcripts;
+        styled_preview(side_by_side(left, right, context.width))
+    }
+}
+
+/// A preview pane with colors, or plain text in plain mode.
+fn styled_preview(text: String) -> ItemPreview {
+    if plain::enabled() {
+        ItemPreview::Text(plain::render(&text).into_owned())
+    } else {
+        ItemPreview::AnsiText(text)
+    }
+}
+
+/// Set the foreground of the chars in `range`, splitting spans at its edges.
+fn recolor(
+    line: ratatui::text::Line<'_>,
+    range: std::ops::Range<usize>,
+    color: ratatui::style::Color,
+) -> ratatui::text::Line<'_> {
+    use ratatui::text::Span;
+
+    let mut spans = Vec::with_capacity(line.spans.len() + 2);
+    let mut pos = 0;
+    for span in line.spans {
+        let len = span.content.chars().count();
+        let (start, end) = (pos, pos + len);
+        pos = end;
+        if end <= range.start || start >= range.end {
+            spans.push(span);
+            continue;
+        }
+        // Byte offsets of the overlap within this span
+        let byte_at = |i: usize| {
+            span.content
+                .char_indices()
+                .nth(i)
+                .map_or(span.content.len(), |(b, _)| b)
+        };
+        let from = byte_at(range.start.saturating_sub(start));
+        let to = byte_at(range.end.min(end) - start);
+        let (head, middle, tail) = (
+            &span.content[..from],
+            &span.content[from..to],
+            &span.content[to..],
+        );
+        for (text, style) in [
+            (head, span.style),
+            (middle, span.style.fg(color)),
+            (tail, span.style),
+        ] {
+            if !text.is_empty() {
+                spans.push(Span::styled(text.to_string(), style));
+            }
+        }
+    }
+    let mut out = ratatui::text::Line::from(spans).style(line.style);
+    out.alignment = line.alignment;
+    out
+}
+
+fn ratatui_color(color: session::SourceColor) -> ratatui::style::Color {
+    use ratatui::style::Color;
+    use session::SourceColor;
+    match color {
+        SourceColor::Red => Color::Red,
+        SourceColor::Green => Color::Green,
+        SourceColor::Yellow => Color::Yellow,
+        SourceColor::Blue => Color::Blue,
+        SourceColor::Magenta => Color::Magenta,
+        SourceColor::Cyan => Color::Cyan,
+        SourceColor::White => Color::White,
+    }
+}
+
+/// First preview line: the source label (in its color) and project path.
+fn preview_header(session: &Session) -> String {
+    let partial = if session.partial {
+        format!(
+            "\n{}Partially indexed ({}): turn and token counts are lower bounds; use --deep{}",
+            colors::DIM,
+            stats::format_size(session.size),
+            colors::RESET
+        )
+    } else {
+        String::new()
+    };
+    // Team sessions: whose, and whether there is a transcript to show
+    let (owner, unpublished) = match session.source.owner() {
+        Some(owner) if !session.filepath.exists() => (
+            format!(" {}", owner),
+            format!(
+                "\n{}Metadata only: {} didn't publish the transcript{}\n\n{}",
+                colors::DIM,
+                owner,
+                colors::RESET,
+                session.first_message.as_deref().unwrap_or_default()
+            ),
+        ),
+        Some(owner) => (format!(" {}", owner), String::new()),
+        None => (String::new(), String::new()),
+    };
+    format!(
+        "{}{} {}{}{}{}{}{}{}{}\n\n",
+        session.source.paint(session.source.label()),
+        owner,
+        colors::DIM,
+        session.project_path,
+        colors::RESET,
+        partial,
+        mcp_section(&session.mcp_calls),
+        denied_section(&session.denied_tools),
+        open_todos_section(&session.open_todos),
+        unpublished
+    )
+}
+
+/// Tool calls refused permission: `Denied: Bash ×2, Write ×1`.
+fn denied_section(denied: &std::collections::BTreeMap<String, u32>) -> String {
+    if denied.is_empty() {
+        return String::new();
+    }
+    let tools: Vec<String> = denied
+        .iter()
+        .map(|(tool, count)| format!("{} ×{}", tool, count))
+        .collect();
+    format!(
+        "\n{}Denied: {}{}",
+        colors::YELLOW,
+        tools.join(", "),
+        colors::RESET
+    )
+}
+
+/// The MCP servers the session called, with calls per tool:
+/// `MCP: github (search ×5, create_issue ×2) │ slack (post ×1)`.
+fn mcp_section(calls: &std::collections::BTreeMap<String, u32>) -> String {
+    let mut servers: Vec<(&str, Vec<String>)> = Vec::new();
+    for (name, count) in calls {
+        let Some((server, tool)) = session::mcp_tool(name) else {
+            continue;
+        };
+        let call = format!("{} ×{}", tool, count);
+        match servers.last_mut() {
+            Some((last, tools)) if *last == server => tools.push(call),
+            _ => servers.push((server, vec![call])),
+        }
+    }
+    if servers.is_empty() {
+        return String::new();
+    }
+    let servers: Vec<String> = servers
+        .into_iter()
+        .map(|(server, tools)| format!("{} ({})", server, tools.join(", ")))
+        .collect();
+    format!(
+        "\n{}MCP: {}{}",
+        colors::DIM,
+        servers.join(" │ "),
+        colors::RESET
+    )
+}
+
+/// Lines of a collapsed plan.
+const PLAN_PREVIEW_LINES: usize = 8;
+
+/// The session's approved plan, shown above the transcript since it usually
+/// says best what the session was about. Long plans are cut to their first
+/// lines unless `expanded` (alt-e).
+fn plan_section(plan: &str, expanded: bool) -> String {
+    use std::fmt::Write as _;
+
+    let lines: Vec<&str> = plan.trim().lines().collect();
+    let long = lines.len() > PLAN_PREVIEW_LINES;
+    let (marker, hint) = match (long, expanded) {
+        (true, false) => ('▸', ""),
+        (true, true) => ('▾', " (alt-e collapses)"),
+        (false, _) => ('▾', ""),
+    };
+    let mut out = format!(
+        "{}{}{} Plan{}{}{}{}\n",
+        colors::GREEN,
+        colors::BOLD,
+        marker,
+        colors::RESET,
+        colors::DIM,
+        hint,
+        colors::RESET
+    );
+    let shown = if long && !expanded {
+        PLAN_PREVIEW_LINES
+    } else {
+        lines.len()
+    };
+    for line in &lines[..shown] {
+        let _ = writeln!(out, "  {}", line);
+    }
+    if shown < lines.len() {
+        let _ = writeln!(
+            out,
+            "  {}… {} more lines (alt-e expands){}",
+            colors::DIM,
+            lines.len() - shown,
+            colors::RESET
+        );
+    }
+    out.push('\n');
+    out
+}
+
+/// Unfinished items of the session's last todo list, in-progress ones
+/// marked ◐.
+fn open_todos_section(todos: &[session::OpenTodo]) -> String {
+    use std::fmt::Write as _;
+
+    if todos.is_empty() {
+        return String::new();
+    }
+    let mut out = format!(
+        "\n{}Open todos ({}):{}",
+        colors::YELLOW,
+        todos.len(),
+        colors::RESET
+    );
+    for todo in todos {
+        let mark = if todo.in_progress { '◐' } else { '○' };
+        let _ = write!(out, "\n  {} {}", mark, todo.content);
+    }
+    out
+}
+
 // =============================================================================
 // Tests (general functionality)
 // =============================================================================
@@ -1222,6 +5750,7 @@ impl SkimItem for SessionItem {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cc_sessions::normalize_summary;
 
     // =========================================================================
     // Project filter logic - The -p flag behavior
@@ -1330,38 +5859,180 @@ mod tests {
     }
 
     #[test]
-    fn format_time_relative_weeks() {
+    fn format_time_relative_weeks() {
+        use std::time::Duration;
+        let time = SystemTime::now() - Duration::from_secs(604800 * 3);
+        assert_eq!(format_time_relative(time), "3w");
+    }
+
+    #[test]
+    fn activity_sparkline_spreads_long_sessions_and_not_bursts() {
+        use std::collections::BTreeMap;
+        assert_eq!(activity_sparkline(&BTreeMap::new(), 8), "");
+        // Twenty messages within one hour: a single full cell.
+        assert_eq!(activity_sparkline(&BTreeMap::from([(1000, 20)]), 8), "█");
+        // Three days, quiet overnight: one cell per 9 hours.
+        let days = BTreeMap::from([(1000, 8), (1003, 8), (1030, 2), (1050, 16), (1071, 4)]);
+        assert_eq!(activity_sparkline(&days, 8), "█  ▁ █ ▂");
+    }
+
+    #[test]
+    fn format_time_relative_future() {
+        use std::time::Duration;
+        let time = SystemTime::now() + Duration::from_secs(3600);
+        assert_eq!(format_time_relative(time), "?");
+    }
+
+    #[test]
+    fn remote_check_table_lists_failures_below() {
+        let results = [
+            remote::RemoteHealth {
+                ssh_error: None,
+                rsync: true,
+                projects_dir: true,
+                claude: Some(false),
+                ..Default::default()
+            },
+            remote::RemoteHealth {
+                transport: remote::Transport::Rsyncd,
+                ssh_error: None,
+                rsync: true,
+                projects_dir: true,
+                claude: None,
+            },
+            remote::RemoteHealth {
+                ssh_error: Some("Connection timed out".to_string()),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            render_remote_checks(&["devbox", "build1", "camelot"], &results),
+            "\
+REMOTE   SSH      RSYNC    PROJECTS  CLAUDE
+devbox   ok       ok       ok        missing
+build1   rsyncd   ok       ok        n/a
+camelot  failed   -        -         -
+
+camelot: Connection timed out
+"
+        );
+    }
+
+    #[test]
+    fn sync_warning_names_failures_and_data_age() {
         use std::time::Duration;
-        let time = SystemTime::now() - Duration::from_secs(604800 * 3);
-        assert_eq!(format_time_relative(time), "3w");
+        let mut warning = remote::SyncWarning {
+            remote_name: "devbox".to_string(),
+            failures: 3,
+            last_sync: Some(SystemTime::now() - Duration::from_secs(86400 * 2)),
+        };
+        assert_eq!(
+            format_sync_warning(&warning),
+            "⚠ devbox: last 3 syncs failed — showing data from 2d ago (--retry-failed to retry)"
+        );
+        warning.failures = 1;
+        warning.last_sync = None;
+        assert_eq!(
+            format_sync_warning(&warning),
+            "⚠ devbox: last sync failed — no data synced yet (--retry-failed to retry)"
+        );
     }
 
     #[test]
-    fn format_time_relative_future() {
+    fn change_notices_count_unseen_remote_sessions() {
+        let remote = |id: &str| {
+            let mut session = test_session(id);
+            session.source = SessionSource::Remote {
+                name: "devbox".to_string(),
+                host: "devbox".to_string(),
+                user: None,
+                label: None,
+                color: None,
+                resume: session::RemoteResume::Ssh,
+            };
+            session
+        };
+        let mut sessions = vec![test_session("local"), remote("a"), remote("b")];
+        let mut seen = state::SeenSessions::default();
+        seen.start(&sessions);
+        assert!(change_notices(&sessions, &seen, true).is_empty());
+
+        sessions[1].size += 10;
+        sessions.push(remote("c"));
+        sessions.push(test_session("local2"));
+        assert_eq!(
+            change_notices(&sessions, &seen, true),
+            ["✚ devbox: 1 new, 1 updated since last sync (--new-only to list them)"]
+        );
+        assert_eq!(
+            change_notices(&sessions[..3], &seen, false),
+            ["✚ devbox: 1 updated since last sync"]
+        );
+    }
+
+    #[test]
+    fn subagent_row_shows_age_turns_and_task() {
         use std::time::Duration;
-        let time = SystemTime::now() + Duration::from_secs(3600);
-        assert_eq!(format_time_relative(time), "?");
+        let mut run = claude_code::SubagentRun {
+            filepath: PathBuf::from("/p/s/subagents/agent-a1.jsonl"),
+            agent_id: "a1".to_string(),
+            first_prompt: Some("Find failing tests".to_string()),
+            turn_count: 3,
+            modified: SystemTime::now() - Duration::from_secs(7200),
+        };
+        assert_eq!(format_subagent_row(&run), "    2h    3  Find failing tests");
+        run.first_prompt = None;
+        assert!(format_subagent_row(&run).ends_with("(no prompt)"));
     }
 
     // =========================================================================
     // Fork list and tree view
     // =========================================================================
 
+    /// Default picker layout with a 40-column summary.
+    fn test_layout(debug: bool) -> Layout {
+        let mut layout = Layout::new(View::Picker, &layout::ViewConfig::default(), debug);
+        layout.fit_summary(None, 40);
+        layout
+    }
+
+    /// Minimal local session; the library's own fixture is test-only.
     fn test_session(id: &str) -> Session {
         Session {
             id: id.to_string(),
             project: "test-project".to_string(),
             project_path: "/tmp/test-project".to_string(),
             filepath: PathBuf::from(format!("/tmp/{}.jsonl", id)),
-            created: SystemTime::now(),
-            modified: SystemTime::now(),
+            created: std::time::SystemTime::now(),
+            modified: std::time::SystemTime::now(),
             first_message: None,
             summary: Some("test summary".to_string()),
             name: None,
             tag: None,
+            git_branch: None,
             turn_count: 1,
             source: SessionSource::Local,
             forked_from: None,
+            redundant_fork: false,
+            repo_root: None,
+            tokens: cc_sessions::session::TokenUsage::default(),
+            model_usage: std::collections::BTreeMap::new(),
+            size: 0,
+            first_activity: None,
+            last_activity: None,
+            active_hours: std::collections::BTreeMap::new(),
+            open_todos: Vec::new(),
+            plan_offset: None,
+            mcp_calls: std::collections::BTreeMap::new(),
+            permission_requests: 0,
+            denied_tools: std::collections::BTreeMap::new(),
+            partial: false,
+            archived: false,
+            resumed: None,
+            resume_count: 0,
+            relevance: 0.0,
+            clock_skew: None,
+            local_path: None,
         }
     }
 
@@ -1399,6 +6070,511 @@ mod tests {
         assert!(!children_map.contains_key("child2"));
     }
 
+    #[test]
+    fn print_project_dir_rejects_remote_and_missing_dirs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut session = test_session("abc");
+        session.project_path = tmp.path().display().to_string();
+        assert!(print_project_dir(&session).is_ok());
+
+        session.project_path = tmp.path().join("gone").display().to_string();
+        assert!(print_project_dir(&session).is_err());
+
+        session.project_path = tmp.path().display().to_string();
+        session.source = SessionSource::Remote {
+            name: "devbox".to_string(),
+            host: "devbox".to_string(),
+            user: None,
+            label: None,
+            color: None,
+            resume: session::RemoteResume::Ssh,
+        };
+        let err = print_project_dir(&session).unwrap_err().to_string();
+        assert!(err.contains("devbox"));
+
+        // Mapped to a local checkout, the remote session has a directory.
+        session.local_path = Some(tmp.path().to_path_buf());
+        assert_eq!(local_project_dir(&session).unwrap(), tmp.path());
+        session.local_path = Some(tmp.path().join("gone"));
+        assert!(print_project_dir(&session).is_err());
+    }
+
+    #[test]
+    fn show_renders_every_line_of_every_message() {
+        let long = (1..=150)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let messages: Vec<session::Message> = [
+            (session::Role::User, "Explain"),
+            (session::Role::Assistant, long.as_str()),
+        ]
+        .into_iter()
+        .map(|(role, text)| session::Message {
+            role,
+            timestamp: Some("2025-01-15T10:00:00Z".to_string()),
+            model: None,
+            text: vec![text.to_string()],
+            tool_calls: Vec::new(),
+            tool_results: Vec::new(),
+            usage: None,
+            compact_summary: false,
+        })
+        .collect();
+        let shown = render_transcript(&test_session("abc"), &messages);
+        assert!(shown.contains("2 message(s)"));
+        assert!(shown.contains(&format!("{}U{} Explain", colors::CYAN, colors::RESET)));
+        assert!(shown.contains(&format!("{}A{} line 1\n", colors::YELLOW, colors::RESET)));
+        assert!(shown.ends_with("line 150\n"));
+    }
+
+    #[test]
+    fn batch_export_and_delete_cover_every_marked_session() {
+        let tmp = tempfile::tempdir().unwrap();
+        let sessions: Vec<Session> = ["a", "b"]
+            .iter()
+            .map(|id| {
+                let mut session = test_session(id);
+                session.filepath = tmp.path().join(format!("{id}.jsonl"));
+                std::fs::write(
+                    &session.filepath,
+                    r#"{"type":"user","message":{"content":"hi"}}"#,
+                )
+                .unwrap();
+                session
+            })
+            .collect();
+        let marked: Vec<&Session> = sessions.iter().collect();
+        std::fs::create_dir_all(tmp.path().join("a/subagents")).unwrap();
+
+        let out = tmp.path().join("out");
+        let (written, failures) = export_sessions(&marked, &out, export::ExportFormat::Markdown);
+        assert_eq!((written, failures.len()), (2, 0));
+        assert!(out.join("a.md").exists() && out.join("b.md").exists());
+
+        let mut batch = undo::Batch::begin_in(&tmp.path().join("undo"), "picker delete");
+        let (deleted, failures) = trash_sessions(&mut batch, &marked);
+        assert_eq!(deleted, ["a", "b"]);
+        assert!(failures.is_empty());
+        assert!(!tmp.path().join("a.jsonl").exists() && !tmp.path().join("a").exists());
+    }
+
+    #[test]
+    fn remote_label_and_color_fill_the_source_column() {
+        let mut session = test_session("abc");
+        session.source = SessionSource::Remote {
+            name: "devbox".to_string(),
+            host: "devbox".to_string(),
+            user: None,
+            label: Some("dev".to_string()),
+            color: Some(session::SourceColor::Green),
+            resume: session::RemoteResume::Ssh,
+        };
+        let mut layout = Layout::new(View::List, &layout::ViewConfig::default(), false);
+        layout.fit_summary(None, 20);
+        let row = format_list_row(&session, &layout, false);
+        assert!(row.contains("\x1b[32mdev     \x1b[0m "));
+        assert!(!row.contains("devbox"));
+        assert!(preview_header(&session).starts_with("\x1b[32mdev\x1b[0m "));
+
+        // Picker rows stay plain text; skim colors the SOURCE span
+        let layout = test_layout(false);
+        let row = format_session_row_simple("  ", &session, &layout);
+        let range = layout.char_range(Column::Source).unwrap();
+        let cell: String = row.chars().skip(range.start).take(range.len()).collect();
+        assert_eq!(cell, "dev   ");
+    }
+
+    #[test]
+    fn long_plans_collapse_to_their_first_lines() {
+        let strip = |s: String| {
+            s.replace(colors::GREEN, "")
+                .replace(colors::BOLD, "")
+                .replace(colors::DIM, "")
+                .replace(colors::RESET, "")
+        };
+        assert_eq!(
+            strip(plan_section("# Fix\n1. Test\n", false)),
+            "▾ Plan\n  # Fix\n  1. Test\n\n"
+        );
+        let plan: String = (1..=10).map(|i| format!("{i}. step\n")).collect();
+        let collapsed = strip(plan_section(&plan, false));
+        assert!(collapsed.starts_with("▸ Plan\n  1. step\n"));
+        assert!(collapsed.ends_with("  8. step\n  … 2 more lines (alt-e expands)\n\n"));
+        let expanded = strip(plan_section(&plan, true));
+        assert!(expanded.starts_with("▾ Plan (alt-e collapses)\n"));
+        assert!(expanded.ends_with("  10. step\n\n"));
+    }
+
+    #[test]
+    fn live_preview_section_skips_cut_off_lines() {
+        let bytes = concat!(
+            "t\":\"tail of a line cut by the byte range\"}\n",
+            r#"{"type":"user","message":{"content":"run the migration"}}"#,
+            "\n",
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Done.\nAll green"}]}}"#,
+            "\n",
+        );
+        let fetched = live_preview::Fetched {
+            synced: None,
+            result: Ok(bytes.as_bytes().to_vec()),
+        };
+        let section = live_preview_section(&fetched, "devbox");
+        assert!(section.contains("2 new message(s) on devbox (never synced)"));
+        assert!(section.contains("U: run the migration"));
+        assert!(section.contains("A: Done.") && !section.contains("All green"));
+
+        let failed = live_preview::Fetched {
+            synced: None,
+            result: Err("devbox didn't answer within 2s".to_string()),
+        };
+        assert!(live_preview_section(&failed, "devbox").contains("Couldn't fetch"));
+    }
+
+    #[test]
+    fn preview_header_lists_open_todos() {
+        let mut session = test_session("abc");
+        assert!(!preview_header(&session).contains("Open todos"));
+        session.open_todos = vec![
+            session::OpenTodo {
+                content: "Fix parser".to_string(),
+                in_progress: true,
+            },
+            session::OpenTodo {
+                content: "Update docs".to_string(),
+                in_progress: false,
+            },
+        ];
+        session.mcp_calls = std::collections::BTreeMap::from([
+            ("mcp__github__search".to_string(), 5),
+            ("mcp__github__create_issue".to_string(), 2),
+            ("mcp__slack__post".to_string(), 1),
+        ]);
+        let header = preview_header(&session);
+        assert!(header.contains("MCP: github (create_issue ×2, search ×5) │ slack (post ×1)"));
+        assert!(!header.contains("Denied:"));
+        session.denied_tools =
+            std::collections::BTreeMap::from([("Bash".to_string(), 2), ("Write".to_string(), 1)]);
+        assert!(preview_header(&session).contains("Denied: Bash ×2, Write ×1"));
+        assert!(header.contains("Open todos (2):"));
+        assert!(header.contains("\n  ◐ Fix parser\n  ○ Update docs\n"));
+    }
+
+    #[test]
+    fn recolor_splits_spans_at_range_edges() {
+        use ratatui::style::{Color, Style};
+        use ratatui::text::{Line, Span};
+        let line = Line::from(vec![
+            Span::raw("ab"),
+            Span::styled("cdé", Style::default().bg(Color::Blue)),
+            Span::raw("fg"),
+        ]);
+        let out = recolor(line, 1..4, Color::Green);
+        let parts: Vec<(&str, Option<Color>)> = out
+            .spans
+            .iter()
+            .map(|s| (s.content.as_ref(), s.style.fg))
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("a", None),
+                ("b", Some(Color::Green)),
+                ("cd", Some(Color::Green)),
+                ("é", None),
+                ("fg", None)
+            ]
+        );
+        assert_eq!(out.spans[2].style.bg, Some(Color::Blue));
+    }
+
+    #[test]
+    fn list_link_cells_link_local_projects_and_session_files() {
+        let session = test_session("abc");
+        let (project, desc) = list_link_cells(&session, "summary".to_string(), 16, true);
+        assert!(project.starts_with("\x1b]8;;file:///tmp/test-project\x1b\\test-project"));
+        assert!(desc.contains("file:///tmp/abc.jsonl"));
+
+        let mut remote = test_session("def");
+        remote.source = SessionSource::Remote {
+            name: "devbox".to_string(),
+            host: "devbox".to_string(),
+            user: None,
+            label: None,
+            color: None,
+            resume: session::RemoteResume::Ssh,
+        };
+        let (project, _) = list_link_cells(&remote, "summary".to_string(), 16, true);
+        assert_eq!(project, format!("{:<16}", "test-project"));
+
+        let (project, desc) = list_link_cells(&session, "summary".to_string(), 16, false);
+        assert_eq!(
+            (project.trim_end(), desc.as_str()),
+            ("test-project", "summary")
+        );
+    }
+
+    #[test]
+    fn last_session_is_the_newest_local_one_in_exactly_this_dir() {
+        let at = |id: &str, path: &str, secs: u64| {
+            let mut s = test_session(id);
+            s.project_path = path.to_string();
+            s.modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+            s
+        };
+        let mut remote = at("remote", "/src/api", 40);
+        remote.source = SessionSource::Remote {
+            name: "devbox".to_string(),
+            host: "devbox".to_string(),
+            user: None,
+            label: None,
+            color: None,
+            resume: session::RemoteResume::Ssh,
+        };
+        let sessions = vec![
+            at("old", "/src/api", 10),
+            at("new", "/src/api", 20),
+            at("sub", "/src/api/web", 30),
+            remote,
+        ];
+        let dir = std::path::Path::new("/src/api");
+        assert_eq!(last_session_in(&sessions, dir).unwrap().id, "new");
+        assert!(last_session_in(&sessions, std::path::Path::new("/src")).is_err());
+    }
+
+    #[test]
+    fn filter_to_repo_keeps_sessions_under_root_or_falls_back() {
+        let mut inside = test_session("in");
+        inside.project_path = "/src/api/web".to_string();
+        let mut sibling = test_session("sib");
+        sibling.project_path = "/src/api-v2".to_string();
+        let mut sessions = vec![inside, sibling];
+
+        assert!(filter_to_repo(
+            &mut sessions,
+            std::path::Path::new("/src/api")
+        ));
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, "in");
+
+        assert!(!filter_to_repo(
+            &mut sessions,
+            std::path::Path::new("/elsewhere")
+        ));
+        assert_eq!(sessions.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn path_matcher_accepts_both_spellings_of_the_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let real = tmp.path().join("real");
+        std::fs::create_dir(&real).unwrap();
+        let link = tmp.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+        let canonical = real.canonicalize().unwrap();
+
+        let exact = path_matcher(&link, false);
+        assert!(exact(&link) && exact(&canonical));
+        assert!(!exact(&canonical.join("sub")));
+        let nested = path_matcher(&link, true);
+        assert!(nested(&canonical.join("sub")) && nested(&link.join("sub")));
+        assert!(!nested(tmp.path()));
+    }
+
+    #[test]
+    fn print0_defaults_to_paths() {
+        let args = Args::try_parse_from(["cc-sessions", "-0"]).unwrap();
+        assert!(matches!(args.print0, Some(Print0Field::Path)));
+        let args =
+            Args::try_parse_from(["cc-sessions", "--grep", "foo", "--print0", "id"]).unwrap();
+        assert!(matches!(args.print0, Some(Print0Field::Id)));
+        assert!(Args::try_parse_from(["cc-sessions", "--list", "--tsv", "-0"]).is_err());
+    }
+
+    #[test]
+    fn low_priority_only_applies_to_runs_without_picker_or_claude() {
+        let settings = remote::Settings::default();
+        let background = |argv: &[&str]| {
+            let args = Args::try_parse_from([&["cc-sessions"][..], argv].concat()).unwrap();
+            is_background_run(&args, &settings)
+        };
+        assert!(background(&["--sync-only"]));
+        assert!(background(&["--list"]));
+        assert!(background(&["index"]));
+        assert!(background(&["stats"]));
+        assert!(!background(&["--last"]));
+        assert!(!background(&["resume", "auth"]));
+        assert!(!background(&["top"]));
+    }
+
+    #[test]
+    fn stats_by_source_only_applies_to_project_totals() {
+        assert!(Args::try_parse_from(["cc-sessions", "stats", "--by-source"]).is_ok());
+        for other in [&["--top", "5"][..], &["--trend"], &["--costs"], &["--mcp"]] {
+            let argv = [&["cc-sessions", "stats", "--by-source"][..], other].concat();
+            assert!(Args::try_parse_from(argv).is_err(), "{other:?}");
+        }
+    }
+
+    #[test]
+    fn period_flags_are_shared_by_every_command() {
+        let args = Args::try_parse_from(["cc-sessions", "stats", "--since", "30d"]).unwrap();
+        assert_eq!(args.since.as_deref(), Some("30d"));
+        let args =
+            Args::try_parse_from(["cc-sessions", "--until", "2025-06-01", "--list"]).unwrap();
+        assert_eq!(args.until.as_deref(), Some("2025-06-01"));
+        assert!(
+            Args::try_parse_from(["cc-sessions", "digest", "--yesterday", "--since", "3d"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn completion_scripts_call_back_into_the_binary() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = completion_script(shell).unwrap();
+            assert!(script.contains("COMPLETE="), "{script}");
+            assert!(script.contains("cc-sessions"));
+        }
+    }
+
+    #[test]
+    fn config_defaults_fill_only_missing_flags() {
+        let defaults: remote::Defaults = toml::from_str(
+            "count = 40\nmin_turns = 2\nsort = \"turns\"\nproject = \"api\"\nno_sync = true",
+        )
+        .unwrap();
+
+        let mut args = Args::try_parse_from(["cc-sessions", "--list", "--min-turns", "5"]).unwrap();
+        apply_config_defaults(&mut args, &defaults);
+        assert_eq!(args.count, Some(40));
+        assert_eq!(args.min_turns, Some(5));
+        assert_eq!(args.sort, Some(session::SortKey::Turns));
+        assert_eq!(args.project.as_deref(), Some("api"));
+        assert!(args.no_sync);
+
+        let mut args = Args::try_parse_from(["cc-sessions", "--list", "--tsv", "--sync"]).unwrap();
+        apply_config_defaults(&mut args, &defaults);
+        assert_eq!(args.count, None);
+        assert!(!args.no_sync);
+    }
+
+    #[test]
+    fn sort_by_turns_breaks_ties_by_recency() {
+        let mut old = test_session("old");
+        old.turn_count = 3;
+        old.modified = SystemTime::UNIX_EPOCH;
+        let mut new = test_session("new");
+        new.turn_count = 3;
+        let mut big = test_session("big");
+        big.turn_count = 9;
+        big.modified = SystemTime::UNIX_EPOCH;
+        let mut sessions = vec![old, big, new];
+        session::SortKey::Turns.sort(&mut sessions);
+        let ids: Vec<&str> = sessions.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["big", "new", "old"]);
+    }
+
+    #[test]
+    fn named_first_keeps_sort_order_within_groups() {
+        let mut sessions: Vec<Session> = ["a", "b", "c", "d"]
+            .iter()
+            .enumerate()
+            .map(|(i, id)| {
+                let mut s = test_session(id);
+                s.turn_count = 10 - i;
+                s.name = (i % 2 == 1).then(|| format!("name {}", id));
+                s
+            })
+            .collect();
+        let mut order = SortOrder {
+            key: session::SortKey::Turns,
+            named_first: true,
+        };
+        order.sort(&mut sessions);
+        let ids: Vec<&str> = sessions.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["b", "d", "a", "c"]);
+        order.named_first = false;
+        order.sort(&mut sessions);
+        let ids: Vec<&str> = sessions.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn tsv_record_has_fixed_columns_without_embedded_separators() {
+        let mut session = test_session("abc");
+        session.summary = Some("fix\tthe\nbug".to_string());
+        session.tag = Some("wip".to_string());
+        session.created = std::time::UNIX_EPOCH;
+
+        let record = format_session_tsv(&session);
+        let fields: Vec<&str> = record.split('\t').collect();
+        assert_eq!(fields.len(), TSV_COLUMNS.len());
+        assert_eq!(fields[0], "abc");
+        assert_eq!(fields[4], "1970-01-01T00:00:00Z");
+        assert_eq!(fields[7], "");
+        assert_eq!(fields[9], "wip");
+        assert_eq!(fields[10], "fix the bug");
+        assert!(!record.contains('\x1b'));
+    }
+
+    #[test]
+    fn find_orphans_lists_forks_with_missing_parents() {
+        let root = test_session("root");
+        let mut child = test_session("child");
+        child.forked_from = Some("root".to_string());
+        let mut orphan = test_session("orphan");
+        orphan.forked_from = Some("deleted".to_string());
+
+        let sessions = vec![root, child, orphan];
+        let orphans = find_orphans(&sessions);
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].id, "orphan");
+    }
+
+    #[test]
+    fn prune_keeps_forks_that_have_forks_of_their_own() {
+        let root = test_session("root");
+        let mut child = test_session("child");
+        child.forked_from = Some("root".to_string());
+        child.redundant_fork = true;
+        let mut grandchild = test_session("grandchild");
+        grandchild.forked_from = Some("child".to_string());
+        grandchild.redundant_fork = true;
+
+        let sessions = vec![root, child, grandchild];
+        let ids: Vec<(&str, &str)> = prune_candidates(&sessions)
+            .into_iter()
+            .map(|(fork, parent)| (fork.id.as_str(), parent.id.as_str()))
+            .collect();
+        assert_eq!(ids, [("grandchild", "child")]);
+    }
+
+    #[test]
+    fn is_ancestor_follows_parent_chain() {
+        let root = test_session("root");
+        let mut child = test_session("child");
+        child.forked_from = Some("root".to_string());
+        let mut grandchild = test_session("grandchild");
+        grandchild.forked_from = Some("child".to_string());
+
+        let sessions = vec![root, child, grandchild];
+        assert!(is_ancestor(&sessions, "root", "grandchild"));
+        assert!(!is_ancestor(&sessions, "grandchild", "root"));
+    }
+
+    #[test]
+    fn find_session_by_prefix_requires_unique_match() {
+        let sessions = vec![test_session("abc-1"), test_session("abd-2")];
+        assert_eq!(
+            find_session_by_prefix(&sessions, "abc").unwrap().id,
+            "abc-1"
+        );
+        assert!(find_session_by_prefix(&sessions, "ab").is_err());
+        assert!(find_session_by_prefix(&sessions, "zzz").is_err());
+    }
+
     #[test]
     fn build_fork_tree_handles_nested_forks() {
         // root -> child -> grandchild
@@ -1416,20 +6592,57 @@ mod tests {
         assert!(!children_map.contains_key("grandchild"));
     }
 
+    #[test]
+    fn flatten_fork_tree_indents_forks_under_their_parents() {
+        use std::time::Duration;
+        let at = |id: &str, parent: Option<&str>, age: u64| {
+            let mut s = test_session(id);
+            s.forked_from = parent.map(str::to_string);
+            s.modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1000 - age);
+            s
+        };
+        let sessions = vec![
+            at("root", None, 0),
+            at("a", Some("root"), 1),
+            at("a1", Some("a"), 2),
+            at("b", Some("root"), 3),
+            at("other", None, 4),
+        ];
+        let children_map = build_fork_tree(&sessions);
+        let roots = vec![&sessions[0], &sessions[4]];
+        let flat: Vec<(&str, String)> = flatten_fork_tree(&roots, &children_map)
+            .into_iter()
+            .map(|(s, tree)| (s.id.as_str(), tree))
+            .collect();
+        assert_eq!(
+            flat,
+            [
+                ("root", String::new()),
+                ("a", "├─ ".to_string()),
+                ("a1", "│  └─ ".to_string()),
+                ("b", "└─ ".to_string()),
+                ("other", String::new()),
+            ]
+        );
+
+        let row = format_session_row_tree("  ", "│  └─ ", &sessions[2], &test_layout(false));
+        assert!(row.ends_with("│  └─ test summary"));
+    }
+
     // =========================================================================
     // Column legend and header formatting
     // =========================================================================
 
     #[test]
     fn build_column_legend_without_debug() {
-        let legend = build_column_legend(false);
+        let legend = build_column_legend(&test_layout(false));
         assert_eq!(legend, "  CRE  MOD  MSG SOURCE PROJECT      SUMMARY");
         assert!(!legend.contains("ID"));
     }
 
     #[test]
     fn build_column_legend_with_debug() {
-        let legend = build_column_legend(true);
+        let legend = build_column_legend(&test_layout(true));
         assert!(legend.contains("ID"));
         assert!(legend.contains("CRE"));
         assert!(legend.contains("MSG"));
@@ -1440,7 +6653,15 @@ mod tests {
         use std::collections::HashMap;
         let session_by_id: HashMap<&str, &Session> = HashMap::new();
 
-        let header = build_subtree_header(None, None, false, None, &session_by_id, false);
+        let header = build_subtree_header(
+            None,
+            None,
+            false,
+            false,
+            None,
+            &session_by_id,
+            &test_layout(false),
+        );
         assert!(header.contains("Select session"));
         assert!(header.contains("→ into forks"));
         assert!(header.contains("CRE")); // Legend line
@@ -1451,7 +6672,15 @@ mod tests {
         use std::collections::HashMap;
         let session_by_id: HashMap<&str, &Session> = HashMap::new();
 
-        let header = build_subtree_header(None, None, true, None, &session_by_id, false);
+        let header = build_subtree_header(
+            None,
+            None,
+            true,
+            false,
+            None,
+            &session_by_id,
+            &test_layout(false),
+        );
         assert!(header.contains("FORK mode"));
     }
 
@@ -1460,7 +6689,15 @@ mod tests {
         use std::collections::HashMap;
         let session_by_id: HashMap<&str, &Session> = HashMap::new();
 
-        let header = build_subtree_header(Some("api"), Some(5), false, None, &session_by_id, false);
+        let header = build_subtree_header(
+            Some("api"),
+            Some(5),
+            false,
+            false,
+            None,
+            &session_by_id,
+            &test_layout(false),
+        );
         assert!(header.contains("search: \"api\""));
         assert!(header.contains("(5 matches)"));
         assert!(header.contains("esc to clear"));
@@ -1473,10 +6710,42 @@ mod tests {
         let mut session_by_id: HashMap<&str, &Session> = HashMap::new();
         session_by_id.insert("focused", &session);
 
-        let header =
-            build_subtree_header(None, None, false, Some("focused"), &session_by_id, false);
+        let header = build_subtree_header(
+            None,
+            None,
+            false,
+            false,
+            Some("focused"),
+            &session_by_id,
+            &test_layout(false),
+        );
         assert!(header.contains("← back"));
         assert!(!header.contains("→ into forks"));
+
+        let header = build_subtree_header(
+            None,
+            None,
+            false,
+            true,
+            Some("focused"),
+            &session_by_id,
+            &test_layout(false),
+        );
+        assert!(header.contains("ctrl-t drill-down"));
+        assert!(!header.contains("← back"));
+    }
+
+    #[test]
+    fn filter_header_names_active_filters() {
+        assert_eq!(filter_header(None, None), None);
+        let devbox = "devbox".to_string();
+        let api = "api".to_string();
+        assert_eq!(
+            filter_header(Some(&devbox), Some(&api)).as_deref(),
+            Some(
+                "Filtered │ source: devbox (alt-s next) │ project: api (alt-p clear) │ esc clears"
+            )
+        );
     }
 
     // =========================================================================
@@ -1486,7 +6755,7 @@ mod tests {
     #[test]
     fn format_session_row_simple_basic() {
         let session = test_session("test-id");
-        let row = format_session_row_simple("  ", &session, false, 40);
+        let row = format_session_row_simple("  ", &session, &test_layout(false));
 
         // Should contain project name and source
         assert!(row.contains("test-proj"));
@@ -1500,7 +6769,7 @@ mod tests {
     #[test]
     fn format_session_row_simple_with_debug() {
         let session = test_session("abcdef-1234");
-        let row = format_session_row_simple("▶ ", &session, true, 40);
+        let row = format_session_row_simple("▶ ", &session, &test_layout(true));
 
         // Should contain first 5 chars of ID
         assert!(row.contains("abcde"));
@@ -1508,37 +6777,11 @@ mod tests {
         assert!(row.starts_with("▶ "));
     }
 
-    #[test]
-    fn elide_middle_passthrough_when_fits() {
-        assert_eq!(elide_middle("short", 12), "short");
-        assert_eq!(elide_middle("exactly-12ch", 12), "exactly-12ch");
-    }
-
-    #[test]
-    fn elide_middle_shortens_long_names() {
-        let out = elide_middle("claude-cli-internal", 12);
-        assert_eq!(out.chars().count(), 12);
-        assert!(out.contains('…'));
-        // Keeps head and tail readable
-        assert!(out.starts_with("claud"));
-        assert!(out.ends_with("ternal"));
-    }
-
-    #[test]
-    fn desc_budget_scales_with_pane_width() {
-        // 200-col pane → 200 − 36 fixed = 164
-        assert_eq!(desc_budget(200, false), 164);
-        // Debug adds 6 for the ID prefix
-        assert_eq!(desc_budget(200, true), 158);
-        // Narrow pane floors at 20
-        assert_eq!(desc_budget(40, false), 20);
-    }
-
     #[test]
     fn format_session_row_simple_shows_turn_count() {
         let mut session = test_session("test");
         session.turn_count = 42;
-        let row = format_session_row_simple("  ", &session, false, 40);
+        let row = format_session_row_simple("  ", &session, &test_layout(false));
 
         // Turn count should be right-aligned in 3 chars
         assert!(row.contains(" 42 "));
@@ -1548,6 +6791,29 @@ mod tests {
     // Shell escaping (security)
     // =========================================================================
 
+    #[test]
+    fn arguments_after_double_dash_are_kept_for_claude() {
+        let args =
+            Args::try_parse_from(["cc-sessions", "--fork", "--", "--model", "opus", "-p"]).unwrap();
+        assert!(args.fork);
+        assert_eq!(args.claude_args, ["--model", "opus", "-p"]);
+        let args = Args::try_parse_from(["cc-sessions", "-", "--", "--model", "opus"]).unwrap();
+        assert_eq!(args.previous.as_deref(), Some("-"));
+        assert_eq!(args.claude_args, ["--model", "opus"]);
+        let args = Args::try_parse_from(["cc-sessions", "resume", "auth", "--", "--model", "opus"])
+            .unwrap();
+        assert!(
+            matches!(args.command, Some(Command::Resume { query, claude_args }) if query == "auth" && claude_args == ["--model", "opus"])
+        );
+        assert_eq!(
+            quoted_args(&[
+                "--append-system-prompt".to_string(),
+                "it's $HOME".to_string()
+            ]),
+            " '--append-system-prompt' 'it'\\''s $HOME'"
+        );
+    }
+
     #[test]
     fn shell_escape_no_quotes() {
         assert_eq!(shell_escape("hello"), "hello");
@@ -1561,6 +6827,101 @@ mod tests {
         assert_eq!(shell_escape("'quoted'"), "'\\''quoted'\\''");
     }
 
+    #[test]
+    fn resume_command_fills_in_quoted_placeholders() {
+        let template =
+            "attach b1 -- sh -c 'cd \"$1\" && shift && exec claude -r \"$@\"' _ {dir} {id} {fork}";
+        assert_eq!(
+            resume_command_line(template, "abc-1", "/src/it's", true),
+            "attach b1 -- sh -c 'cd \"$1\" && shift && exec claude -r \"$@\"' _ '/src/it'\\''s' 'abc-1' --fork-session"
+        );
+        // Inside double quotes the value is still single-quoted for the inner shell
+        assert_eq!(
+            resume_command_line(
+                "sh -c \"cd {dir} && claude -r {id}\"",
+                "abc-1",
+                "/$x",
+                false
+            ),
+            "sh -c \"cd '/\\$x' && claude -r 'abc-1'\""
+        );
+        assert_eq!(
+            resume_command_line("attach {id}{fork}", "abc-1", "/src", false),
+            "attach 'abc-1'"
+        );
+    }
+
+    #[test]
+    fn resume_command_passes_hostile_dirs_through_unchanged() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = "/src/$(touch pwned) `touch pwned` \"q\" it's $HOME \\n";
+        let templates = [
+            "sh -c 'printf %s \"$1\" > out' _ {dir}",
+            "sh -c \"printf %s {dir} > out\"",
+            "sh -c 'printf %s {dir} > out'",
+            "sh -c \"sh -c 'printf %s \\\"\\$1\\\" > out' _ {dir}\"",
+        ];
+        for template in templates {
+            let command = resume_command_line(template, "abc-1", dir, false);
+            let status = std::process::Command::new("sh")
+                .args(["-c", &command])
+                .current_dir(tmp.path())
+                .status()
+                .unwrap();
+            assert!(status.success(), "{}", command);
+            let out = std::fs::read_to_string(tmp.path().join("out")).unwrap();
+            assert_eq!(out, dir, "{}", command);
+            assert!(!tmp.path().join("pwned").exists(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn editor_gets_the_quoted_project_path() {
+        assert_eq!(
+            editor_command_line("code", "/src/it's"),
+            "code '/src/it'\\''s'"
+        );
+        assert_eq!(
+            editor_command_line("nvim -c 'cd {path}' {path}", "/a b"),
+            "nvim -c 'cd '/a b'' '/a b'"
+        );
+        assert_eq!(
+            default_editor(true, Some("vim".into()), None).as_deref(),
+            Some("code")
+        );
+        assert_eq!(
+            default_editor(false, Some(" ".into()), Some("hx".into())).as_deref(),
+            Some("hx")
+        );
+        assert_eq!(default_editor(false, None, None), None);
+    }
+
+    #[test]
+    fn side_by_side_aligns_turns() {
+        let msg = |role: char, text: &str| (role, text.to_string());
+        let left = [msg('U', "fix it"), msg('A', "done"), msg('U', "thanks")];
+        let right = [
+            msg('U', "fix it"),
+            msg('A', "looking"),
+            msg('A', "a much longer answer"),
+            msg('U', "ok"),
+        ];
+        let plain = [colors::CYAN, colors::YELLOW, colors::DIM, colors::RESET]
+            .iter()
+            .fold(side_by_side(&left, &right, 25), |s, code| {
+                s.replace(code, "")
+            });
+        assert_eq!(
+            plain,
+            "\
+U: fix it   │ U: fix it
+A: done     │ A: looking
+            │ A: a much l
+U: thanks   │ U: ok
+"
+        );
+    }
+
     #[test]
     fn shell_escape_multiple_quotes() {
         assert_eq!(shell_escape("a'b'c"), "a'\\''b'\\''c");
@@ -1580,7 +6941,7 @@ mod tests {
 
     #[test]
     fn highlight_match_basic() {
-        let result = highlight_match("hello world", "world");
+        let result = highlight_match("hello world", "world", SearchOptions::default());
         assert!(result.contains(colors::BOLD_INVERSE));
         assert!(result.contains("world"));
         assert!(result.contains(colors::RESET));
@@ -1588,7 +6949,7 @@ mod tests {
 
     #[test]
     fn highlight_match_case_insensitive() {
-        let result = highlight_match("Hello World", "world");
+        let result = highlight_match("Hello World", "world", SearchOptions::default());
         // Should highlight "World" (preserving original case)
         assert!(result.contains("World"));
         assert!(result.contains(colors::BOLD_INVERSE));
@@ -1596,12 +6957,15 @@ mod tests {
 
     #[test]
     fn highlight_match_empty_pattern() {
-        assert_eq!(highlight_match("hello", ""), "hello");
+        assert_eq!(
+            highlight_match("hello", "", SearchOptions::default()),
+            "hello"
+        );
     }
 
     #[test]
     fn highlight_match_no_match() {
-        let result = highlight_match("hello", "xyz");
+        let result = highlight_match("hello", "xyz", SearchOptions::default());
         assert!(!result.contains(colors::BOLD_INVERSE));
         assert_eq!(result, "hello");
     }
@@ -1609,7 +6973,7 @@ mod tests {
     #[test]
     fn highlight_match_multibyte_chars() {
         // Test with emoji and Unicode - should not panic
-        let result = highlight_match("hello 🌍 world", "world");
+        let result = highlight_match("hello 🌍 world", "world", SearchOptions::default());
         assert!(result.contains(colors::BOLD_INVERSE));
     }
 
@@ -1618,10 +6982,47 @@ mod tests {
         // ß lowercases to "ss" - pattern "ss" should still work
         // The text has ß, searching for "ss" should not find it (different chars)
         // But searching for "ß" in text with "ß" should work
-        let result = highlight_match("Straße", "ße");
+        let result = highlight_match("Straße", "ße", SearchOptions::default());
         assert!(result.contains(colors::BOLD_INVERSE));
     }
 
+    #[test]
+    fn highlight_match_case_sensitive_and_whole_word() {
+        let cs = SearchOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        let result = highlight_match("arc Arc", "Arc", cs);
+        assert_eq!(
+            result,
+            format!("arc {}Arc{}", colors::BOLD_INVERSE, colors::RESET)
+        );
+
+        let word = SearchOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+        let result = highlight_match("archive arc", "arc", word);
+        assert_eq!(
+            result,
+            format!("archive {}arc{}", colors::BOLD_INVERSE, colors::RESET)
+        );
+    }
+
+    #[test]
+    fn format_search_modes_lists_enabled_modes() {
+        assert_eq!(format_search_modes(SearchOptions::default()), "");
+        let all = SearchOptions {
+            case_sensitive: true,
+            whole_word: true,
+            include_tools: true,
+        };
+        assert_eq!(
+            format_search_modes(all),
+            " (case-sensitive, whole-word, incl. tool output)"
+        );
+    }
+
     #[test]
     fn search_results_replace_subtree_until_esc() {
         use std::collections::{HashMap, HashSet};
@@ -0,0 +1,396 @@
+//! Persistent per-file scan cache.
+//!
+//! Discovery re-reads every session file on every invocation; with thousands
+//! of sessions that dominates startup. This cache remembers each file's scan
+//! result keyed by path and fingerprinted by (mtime, size), so unchanged files
+//! are skipped entirely. One cache file per projects root:
+//!
+//! ```text
+//! ~/.cache/cc-sessions/index/<root path with / → ->.json
+//! ```
+//!
+//! The on-disk format carries a version; bump `CACHE_VERSION` whenever the
+//! cached value's shape or semantics change so stale entries are discarded
+//! instead of silently missing new fields. `--reindex` ignores the stored
+//! entries for one run and writes each cache afresh, for when a fingerprint
+//! can't tell (a file rewritten within the same mtime tick at the same size).
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
+
+static REINDEX: OnceLock<bool> = OnceLock::new();
+
+/// Ignore stored entries for this run (`--reindex`), rescanning every file.
+pub fn set_reindex(reindex: bool) {
+    let _ = REINDEX.set(reindex);
+}
+
+/// Whether this run ignores stored entries (`--reindex`); other on-disk
+/// indexes honor it too.
+pub fn reindexing() -> bool {
+    REINDEX.get().copied().unwrap_or(false)
+}
+
+const CACHE_VERSION: u32 = 14;
+
+/// Identifies a file's content without reading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    mtime_ns: u128,
+    size: u64,
+}
+
+impl Fingerprint {
+    pub fn of(metadata: &fs::Metadata) -> Self {
+        let mtime_ns = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        Self {
+            mtime_ns,
+            size: metadata.len(),
+        }
+    }
+
+    /// (mtime in ns, size), for indexes with their own on-disk format.
+    pub fn parts(self) -> (u128, u64) {
+        (self.mtime_ns, self.size)
+    }
+
+    pub fn from_parts(mtime_ns: u128, size: u64) -> Self {
+        Self { mtime_ns, size }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry<T> {
+    fingerprint: Fingerprint,
+    value: T,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile<T> {
+    version: u32,
+    entries: HashMap<PathBuf, Entry<T>>,
+}
+
+/// Scan results for one projects root, keyed by absolute file path.
+#[derive(Debug)]
+pub struct MetadataCache<T> {
+    path: Option<PathBuf>,
+    entries: HashMap<PathBuf, Entry<T>>,
+    dirty: bool,
+}
+
+impl<T> Default for MetadataCache<T> {
+    fn default() -> Self {
+        Self {
+            path: None,
+            entries: HashMap::new(),
+            dirty: false,
+        }
+    }
+}
+
+/// Directory holding all metadata cache files.
+pub fn cache_dir() -> Result<PathBuf> {
+    Ok(crate::profile::scoped(crate::paths::CACHE.dir()?).join("index"))
+}
+
+/// Cache file name for a projects root: path separators become dashes,
+/// mirroring how Claude Code names its per-project directories.
+pub fn cache_file_name(root: &Path) -> String {
+    let flat: String = root
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c == '/' || c == '\\' { '-' } else { c })
+        .collect();
+    format!("{}.json", flat)
+}
+
+/// Cache files (with sizes) not belonging to any of `live_roots`, plus
+/// leftover `.tmp` files from interrupted saves. Roots disappear when a
+/// remote is removed or its cache dir moves; their files would never be
+/// read again.
+pub fn stale_cache_files(live_roots: &[PathBuf]) -> Result<Vec<(PathBuf, u64)>> {
+    stale_cache_files_in(&cache_dir()?, live_roots)
+}
+
+fn stale_cache_files_in(dir: &Path, live_roots: &[PathBuf]) -> Result<Vec<(PathBuf, u64)>> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let live: std::collections::HashSet<String> =
+        live_roots.iter().map(|r| cache_file_name(r)).collect();
+    let mut stale: Vec<(PathBuf, u64)> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| !live.contains(e.file_name().to_string_lossy().as_ref()))
+        .filter_map(|e| Some((e.path(), e.metadata().ok().filter(|m| m.is_file())?.len())))
+        .collect();
+    stale.sort();
+    Ok(stale)
+}
+
+/// Carry `old_root`'s cache over to `new_root` after the directory itself
+/// was renamed. Entries are keyed by absolute path, so each one is rewritten
+/// under the new root; moving the file alone would miss on every lookup.
+pub fn move_root(old_root: &Path, new_root: &Path) -> Result<()> {
+    move_root_in(&cache_dir()?, old_root, new_root)
+}
+
+fn move_root_in(dir: &Path, old_root: &Path, new_root: &Path) -> Result<()> {
+    let old_file = dir.join(cache_file_name(old_root));
+    if !old_file.exists() {
+        return Ok(());
+    }
+    // The values are carried over untouched, whatever type they hold.
+    let mut cache: MetadataCache<serde_json::Value> = MetadataCache::load_from(old_file.clone());
+    cache.rebase(old_root, new_root);
+    cache.path = Some(dir.join(cache_file_name(new_root)));
+    cache.save()?;
+    fs::remove_file(&old_file).with_context(|| format!("Failed to remove {}", old_file.display()))
+}
+
+impl<T: Clone + Serialize + DeserializeOwned> MetadataCache<T> {
+    /// Load the cache for `root`. Missing, corrupt, or outdated files yield
+    /// an empty cache — the worst case is a full rescan.
+    pub fn load(root: &Path) -> Self {
+        let Ok(dir) = cache_dir() else {
+            return Self::default();
+        };
+        let path = dir.join(cache_file_name(root));
+        if reindexing() {
+            Self::fresh(path)
+        } else {
+            Self::load_from(path)
+        }
+    }
+
+    /// An empty cache that replaces whatever is stored at `path` on save.
+    fn fresh(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            entries: HashMap::new(),
+            dirty: true,
+        }
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let entries = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<CacheFile<T>>(&bytes).ok())
+            .filter(|file| file.version == CACHE_VERSION)
+            .map(|file| file.entries)
+            .unwrap_or_default();
+        Self {
+            path: Some(path),
+            entries,
+            dirty: false,
+        }
+    }
+
+    /// Cached value for `path` if its fingerprint still matches.
+    pub fn get(&self, path: &Path, fingerprint: Fingerprint) -> Option<&T> {
+        self.entries
+            .get(path)
+            .filter(|e| e.fingerprint == fingerprint)
+            .map(|e| &e.value)
+    }
+
+    /// Cached value for `path` regardless of fingerprint: the file's last
+    /// known scan, for callers that can update it incrementally.
+    pub fn previous(&self, path: &Path) -> Option<&T> {
+        self.entries.get(path).map(|e| &e.value)
+    }
+
+    pub fn insert(&mut self, path: PathBuf, fingerprint: Fingerprint, value: T) {
+        self.entries.insert(path, Entry { fingerprint, value });
+        self.dirty = true;
+    }
+
+    /// Drop entries for files that no longer exist.
+    pub fn retain_paths(&mut self, keep: &std::collections::HashSet<&Path>) {
+        let before = self.entries.len();
+        self.entries.retain(|p, _| keep.contains(p.as_path()));
+        self.dirty |= self.entries.len() != before;
+    }
+
+    /// Re-key entries under `old_root` to the same relative path under
+    /// `new_root`.
+    fn rebase(&mut self, old_root: &Path, new_root: &Path) {
+        self.entries = std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(|(path, entry)| match path.strip_prefix(old_root) {
+                Ok(rest) => (new_root.join(rest), entry),
+                Err(_) => (path, entry),
+            })
+            .collect();
+        self.dirty = true;
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Persist if anything changed since load.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create cache dir: {}", parent.display()))?;
+        }
+        let file = CacheFile {
+            version: CACHE_VERSION,
+            entries: self.entries.clone(),
+        };
+        let bytes = serde_json::to_vec(&file).context("Failed to serialize metadata cache")?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, bytes)
+            .with_context(|| format!("Failed to write metadata cache: {}", tmp.display()))?;
+        fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to replace metadata cache: {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fp(mtime_ns: u128, size: u64) -> Fingerprint {
+        Fingerprint { mtime_ns, size }
+    }
+
+    #[test]
+    fn cache_file_name_flattens_path() {
+        assert_eq!(
+            cache_file_name(Path::new("/home/knight/.claude/projects")),
+            "-home-knight-.claude-projects.json"
+        );
+    }
+
+    #[test]
+    fn get_requires_matching_fingerprint() {
+        let mut cache: MetadataCache<String> = MetadataCache::default();
+        let path = PathBuf::from("/tmp/a.jsonl");
+        cache.insert(path.clone(), fp(1, 10), "scan".to_string());
+
+        assert_eq!(
+            cache.get(&path, fp(1, 10)).map(String::as_str),
+            Some("scan")
+        );
+        assert!(cache.get(&path, fp(2, 10)).is_none());
+        assert!(cache.get(&path, fp(1, 11)).is_none());
+    }
+
+    #[test]
+    fn save_and_reload_round_trips() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("cache.json");
+
+        let mut cache: MetadataCache<String> = MetadataCache::load_from(file.clone());
+        cache.insert(PathBuf::from("/x.jsonl"), fp(5, 50), "v".to_string());
+        cache.save().unwrap();
+
+        let reloaded: MetadataCache<String> = MetadataCache::load_from(file);
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(
+            reloaded
+                .get(Path::new("/x.jsonl"), fp(5, 50))
+                .map(String::as_str),
+            Some("v")
+        );
+    }
+
+    #[test]
+    fn moved_root_keeps_cache_hits() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (old, new) = (Path::new("/cache/box"), Path::new("/cache/devbox"));
+        let mut cache: MetadataCache<String> =
+            MetadataCache::load_from(tmp.path().join(cache_file_name(old)));
+        cache.insert(old.join("-p/a.jsonl"), fp(5, 50), "v".to_string());
+        cache.save().unwrap();
+
+        move_root_in(tmp.path(), old, new).unwrap();
+        assert!(!tmp.path().join(cache_file_name(old)).exists());
+        let moved: MetadataCache<String> =
+            MetadataCache::load_from(tmp.path().join(cache_file_name(new)));
+        assert_eq!(moved.len(), 1);
+        assert_eq!(
+            moved
+                .get(&new.join("-p/a.jsonl"), fp(5, 50))
+                .map(String::as_str),
+            Some("v")
+        );
+    }
+
+    #[test]
+    fn fresh_cache_replaces_stored_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("cache.json");
+        let mut cache: MetadataCache<String> = MetadataCache::load_from(file.clone());
+        cache.insert(PathBuf::from("/gone.jsonl"), fp(5, 50), "v".to_string());
+        cache.save().unwrap();
+
+        let fresh: MetadataCache<String> = MetadataCache::fresh(file.clone());
+        assert_eq!(fresh.len(), 0);
+        fresh.save().unwrap();
+        let reloaded: MetadataCache<String> = MetadataCache::load_from(file);
+        assert_eq!(reloaded.len(), 0);
+    }
+
+    #[test]
+    fn version_mismatch_discards_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("cache.json");
+        fs::write(&file, r#"{"version":0,"entries":{}}"#).unwrap();
+        let cache: MetadataCache<String> = MetadataCache::load_from(file.clone());
+        assert_eq!(cache.len(), 0);
+
+        fs::write(&file, "garbage").unwrap();
+        let cache: MetadataCache<String> = MetadataCache::load_from(file);
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn stale_cache_files_keeps_live_roots() {
+        let tmp = tempfile::tempdir().unwrap();
+        let live = PathBuf::from("/home/knight/.claude/projects");
+        fs::write(tmp.path().join(cache_file_name(&live)), "{}").unwrap();
+        fs::write(tmp.path().join("-gone-remote.json"), "{}").unwrap();
+        fs::write(tmp.path().join("-gone-remote.tmp"), "{").unwrap();
+
+        let stale = stale_cache_files_in(tmp.path(), &[live]).unwrap();
+        let names: Vec<String> = stale
+            .iter()
+            .map(|(p, _)| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["-gone-remote.json", "-gone-remote.tmp"]);
+    }
+
+    #[test]
+    fn retain_paths_prunes_deleted_files() {
+        let mut cache: MetadataCache<u8> = MetadataCache::default();
+        cache.insert(PathBuf::from("/keep.jsonl"), fp(1, 1), 1);
+        cache.insert(PathBuf::from("/gone.jsonl"), fp(1, 1), 2);
+
+        let keep = [Path::new("/keep.jsonl")].into_iter().collect();
+        cache.retain_paths(&keep);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(Path::new("/keep.jsonl"), fp(1, 1)).is_some());
+    }
+}
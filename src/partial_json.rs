@@ -0,0 +1,366 @@
+//! Field extraction from JSON that may be cut off.
+//!
+//! Oversized transcript lines are only kept in part, so they can't be handed
+//! to `serde_json`. This walks a possibly truncated document and copies out
+//! the values at a few requested paths, stopping cleanly wherever the input
+//! ends. Everything else is skipped without being decoded, and long strings
+//! are kept only up to a cap, so a multi-megabyte tool result costs a pass
+//! over its bytes rather than an allocation of its size.
+//!
+//! Paths are key sequences from the root; array elements are addressed by
+//! their decimal index (`["message", "content", "0", "text"]`).
+
+use serde_json::{Map, Value};
+
+/// Containers nested deeper than this end the walk (malformed input).
+const MAX_DEPTH: usize = 64;
+
+/// Sparse copy of `input` holding only the values found at `paths`. Strings
+/// longer than `max_string` bytes (or cut off by the end of input) are kept
+/// truncated; objects and arrays at a requested path are kept if they are
+/// complete and at most `max_string` bytes, unless a longer requested path
+/// continues into them. An empty path requests the root value itself.
+pub fn extract(input: &[u8], paths: &[&[&str]], max_string: usize) -> Value {
+    let mut walker = Walker {
+        input,
+        pos: 0,
+        paths,
+        max_string,
+        path: Vec::new(),
+        found: Vec::new(),
+    };
+    let _ = walker.value();
+
+    let mut root = Value::Null;
+    for (path, value) in walker.found {
+        set_path(&mut root, &path, value);
+    }
+    root
+}
+
+/// Ran out of input (or hit malformed JSON); what was found so far stands.
+struct Stop;
+
+struct Walker<'a> {
+    input: &'a [u8],
+    pos: usize,
+    paths: &'a [&'a [&'a str]],
+    max_string: usize,
+    path: Vec<String>,
+    found: Vec<(Vec<String>, Value)>,
+}
+
+impl Walker<'_> {
+    fn is_requested(&self) -> bool {
+        self.paths.iter().any(|p| p.iter().eq(self.path.iter()))
+    }
+
+    /// Whether a requested path runs through (strictly below) the current one.
+    fn leads_deeper(&self) -> bool {
+        self.paths
+            .iter()
+            .any(|p| p.len() > self.path.len() && p.iter().zip(&self.path).all(|(a, b)| a == b))
+    }
+
+    fn peek(&mut self) -> Result<u8, Stop> {
+        while let Some(&b) = self.input.get(self.pos) {
+            if !b.is_ascii_whitespace() {
+                return Ok(b);
+            }
+            self.pos += 1;
+        }
+        Err(Stop)
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Stop> {
+        if self.peek()? != byte {
+            return Err(Stop);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<(), Stop> {
+        let start = self.pos;
+        let requested = self.is_requested();
+        match self.peek()? {
+            b'"' => {
+                let (text, closed) = self.string(requested);
+                if requested {
+                    self.found.push((self.path.clone(), Value::String(text)));
+                }
+                if closed { Ok(()) } else { Err(Stop) }
+            }
+            b'{' | b'[' if self.path.len() >= MAX_DEPTH => Err(Stop),
+            open @ (b'{' | b'[') => {
+                let descend = self.leads_deeper();
+                if open == b'{' {
+                    self.object()?;
+                } else {
+                    self.array()?;
+                }
+                if requested && !descend && self.pos - start <= self.max_string {
+                    let raw = &self.input[start..self.pos];
+                    if let Ok(value) = serde_json::from_slice(raw) {
+                        self.found.push((self.path.clone(), value));
+                    }
+                }
+                Ok(())
+            }
+            _ => {
+                let start = self.pos;
+                while self
+                    .input
+                    .get(self.pos)
+                    .is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace())
+                {
+                    self.pos += 1;
+                }
+                if self.pos == self.input.len() {
+                    return Err(Stop); // `tru`, `12` of `123`: can't tell yet
+                }
+                let value =
+                    serde_json::from_slice(&self.input[start..self.pos]).map_err(|_| Stop)?;
+                if requested {
+                    self.found.push((self.path.clone(), value));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<(), Stop> {
+        self.expect(b'{')?;
+        if self.peek()? == b'}' {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            if self.peek()? != b'"' {
+                return Err(Stop);
+            }
+            let (key, closed) = self.string(true);
+            if !closed {
+                return Err(Stop);
+            }
+            self.expect(b':')?;
+            self.path.push(key);
+            let result = self.value();
+            self.path.pop();
+            result?;
+            match self.peek()? {
+                b',' => self.pos += 1,
+                b'}' => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return Err(Stop),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<(), Stop> {
+        self.expect(b'[')?;
+        if self.peek()? == b']' {
+            self.pos += 1;
+            return Ok(());
+        }
+        for index in 0.. {
+            self.path.push(index.to_string());
+            let result = self.value();
+            self.path.pop();
+            result?;
+            match self.peek()? {
+                b',' => self.pos += 1,
+                b']' => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => return Err(Stop),
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a string starting at its opening quote. Returns the decoded text
+    /// (up to `max_string` bytes, empty unless `keep`) and whether the closing
+    /// quote was reached before the end of input.
+    fn string(&mut self, keep: bool) -> (String, bool) {
+        self.pos += 1;
+        let mut out = Vec::new();
+        loop {
+            let rest = &self.input[self.pos..];
+            let Some(i) = memchr::memchr2(b'"', b'\\', rest) else {
+                if keep {
+                    push_capped(&mut out, rest, self.max_string);
+                }
+                self.pos = self.input.len();
+                return (utf8_prefix(out), false);
+            };
+            if keep {
+                push_capped(&mut out, &rest[..i], self.max_string);
+            }
+            self.pos += i + 1;
+            if rest[i] == b'"' {
+                return (utf8_prefix(out), true);
+            }
+            let Some(c) = self.escape() else {
+                return (utf8_prefix(out), false);
+            };
+            if keep {
+                let mut utf8 = [0; 4];
+                push_capped(
+                    &mut out,
+                    c.encode_utf8(&mut utf8).as_bytes(),
+                    self.max_string,
+                );
+            }
+        }
+    }
+
+    /// Decode the escape after a backslash; `None` if the input ends first.
+    fn escape(&mut self) -> Option<char> {
+        let b = *self.input.get(self.pos)?;
+        self.pos += 1;
+        Some(match b {
+            b'n' => '\n',
+            b't' => '\t',
+            b'r' => '\r',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'u' => {
+                let high = self.hex4()?;
+                if (0xD800..0xDC00).contains(&high)
+                    && self.input.get(self.pos..self.pos + 2) == Some(b"\\u")
+                {
+                    self.pos += 2;
+                    let low = self.hex4()?;
+                    if (0xDC00..0xE000).contains(&low) {
+                        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+                            .unwrap_or(char::REPLACEMENT_CHARACTER)
+                    } else {
+                        char::REPLACEMENT_CHARACTER
+                    }
+                } else {
+                    char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER)
+                }
+            }
+            other => other as char, // `\"`, `\\`, `\/`
+        })
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = self.input.get(self.pos..self.pos + 4)?;
+        self.pos += 4;
+        u32::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+    }
+}
+
+fn push_capped(out: &mut Vec<u8>, bytes: &[u8], cap: usize) {
+    let room = cap.saturating_sub(out.len());
+    out.extend_from_slice(&bytes[..bytes.len().min(room)]);
+}
+
+/// `bytes` as text, dropping a code point split by truncation.
+fn utf8_prefix(mut bytes: Vec<u8>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => {
+            let valid = e.utf8_error().valid_up_to();
+            bytes = e.into_bytes();
+            bytes.truncate(valid);
+            String::from_utf8(bytes).unwrap_or_default()
+        }
+    }
+}
+
+/// Store `value` at `path` under `root`, creating objects (and arrays, for
+/// numeric segments) on the way.
+fn set_path(root: &mut Value, path: &[String], value: Value) {
+    let Some((first, rest)) = path.split_first() else {
+        *root = value;
+        return;
+    };
+    let slot = match first.parse::<usize>() {
+        Ok(index) => {
+            if !root.is_array() {
+                *root = Value::Array(Vec::new());
+            }
+            let items = root.as_array_mut().unwrap();
+            if items.len() <= index {
+                items.resize(index + 1, Value::Null);
+            }
+            &mut items[index]
+        }
+        Err(_) => {
+            if !root.is_object() {
+                *root = Value::Object(Map::new());
+            }
+            root.as_object_mut()
+                .unwrap()
+                .entry(first.clone())
+                .or_insert(Value::Null)
+        }
+    };
+    set_path(slot, rest, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PATHS: &[&[&str]] = &[
+        &["type"],
+        &["cwd"],
+        &["message", "content"],
+        &["message", "content", "0", "text"],
+        &["message", "usage"],
+    ];
+
+    #[test]
+    fn extracts_requested_fields_and_skips_the_rest() {
+        let input = r#"{"cwd":"/src/a\"b","skip":{"type":"nested"},"type":"assistant",
+            "message":{"content":[{"type":"text","text":"caf\u00e9 \ud83d\ude00"}],"usage":{"output_tokens":5}}}"#
+            .as_bytes();
+        assert_eq!(
+            extract(input, PATHS, 100),
+            json!({
+                "cwd": "/src/a\"b",
+                "type": "assistant",
+                "message": {"content": [{"text": "café 😀"}], "usage": {"output_tokens": 5}},
+            })
+        );
+    }
+
+    #[test]
+    fn truncated_input_keeps_what_was_read() {
+        let input =
+            br#"{"type":"user","cwd":"/x","message":{"content":"a very long paste that was cut o"#;
+        assert_eq!(
+            extract(input, PATHS, 12),
+            json!({"type": "user", "cwd": "/x", "message": {"content": "a very long "}})
+        );
+        // Cut inside a multi-byte character or an escape.
+        assert_eq!(
+            extract(
+                "{\"type\":\"caf\u{e9}".as_bytes().split_last().unwrap().1,
+                PATHS,
+                100
+            ),
+            json!({"type": "caf"})
+        );
+        assert_eq!(extract(br#"{"type":"a\"#, PATHS, 100), json!({"type": "a"}));
+        assert_eq!(extract(br#"{"cwd":tr"#, PATHS, 100), Value::Null);
+    }
+
+    #[test]
+    fn oversized_containers_are_dropped() {
+        let input = br#"{"message":{"usage":{"input_tokens":123456789}}}"#;
+        assert_eq!(extract(input, PATHS, 10), Value::Null);
+        assert_eq!(
+            extract(br#"{"x":1} trailing"#, &[&[]], 100),
+            json!({"x": 1})
+        );
+    }
+}
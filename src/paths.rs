@@ -0,0 +1,219 @@
+//! Where cc-sessions keeps its own files, per the XDG base directory spec.
+//!
+//! Each kind of file lives in a `cc-sessions` dir under its base:
+//!
+//! ```text
+//! config  $XDG_CONFIG_HOME  (default ~/.config)       remotes.toml
+//! cache   $XDG_CACHE_HOME   (default ~/.cache)        remotes/, index/
+//! state   $XDG_STATE_HOME   (default ~/.local/state)  history, workspaces, forks
+//! data    $XDG_DATA_HOME    (default ~/.local/share)  archive/
+//! ```
+//!
+//! Unset, empty, or relative variables fall back to the default, as the spec
+//! requires. Named profiles nest inside these dirs (see `profile.rs`).
+//!
+//! Older versions always used the defaults. `migrate` moves such a dir to the
+//! XDG location once, the first time it runs with the variable set.
+
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = "cc-sessions";
+
+/// One XDG base directory.
+pub struct BaseDir {
+    /// What goes there, for messages
+    pub kind: &'static str,
+    var: &'static str,
+    /// Relative to the home directory
+    default: &'static str,
+}
+
+pub const CONFIG: BaseDir = BaseDir {
+    kind: "config",
+    var: "XDG_CONFIG_HOME",
+    default: ".config",
+};
+
+pub const CACHE: BaseDir = BaseDir {
+    kind: "cache",
+    var: "XDG_CACHE_HOME",
+    default: ".cache",
+};
+
+pub const STATE: BaseDir = BaseDir {
+    kind: "state",
+    var: "XDG_STATE_HOME",
+    default: ".local/state",
+};
+
+pub const DATA: BaseDir = BaseDir {
+    kind: "data",
+    var: "XDG_DATA_HOME",
+    default: ".local/share",
+};
+
+impl BaseDir {
+    /// `cc-sessions` under this base, honoring its XDG variable.
+    pub fn dir(&self) -> Result<PathBuf> {
+        Ok(self.resolve(&home()?, std::env::var_os(self.var)))
+    }
+
+    /// Where versions before XDG support kept it.
+    fn legacy_dir(&self, home: &Path) -> PathBuf {
+        home.join(self.default).join(APP_DIR)
+    }
+
+    fn resolve(&self, home: &Path, value: Option<OsString>) -> PathBuf {
+        match value.map(PathBuf::from).filter(|p| p.is_absolute()) {
+            Some(base) => base.join(APP_DIR),
+            None => self.legacy_dir(home),
+        }
+    }
+}
+
+fn home() -> Result<PathBuf> {
+    dirs::home_dir().context("Could not find home directory")
+}
+
+/// What `migrate` did with a legacy dir.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Migration {
+    /// Nothing to do: no legacy dir, it is the current dir, or it is pinned
+    None,
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// Both exist; the legacy one is left alone and ignored
+    Conflict {
+        legacy: PathBuf,
+        current: PathBuf,
+    },
+}
+
+/// Move `base`'s legacy dir to its XDG location if only the legacy one
+/// exists. `pinned` is a configured path that must stay where it is (an
+/// explicit `settings.cache_dir`); a legacy dir containing it is still in
+/// use, so it is left alone.
+pub fn migrate(base: &BaseDir, pinned: Option<&Path>) -> Result<Migration> {
+    let home = home()?;
+    let legacy = base.legacy_dir(&home);
+    let current = base.dir()?;
+    migrate_dir(&legacy, &current, pinned)
+}
+
+fn migrate_dir(legacy: &Path, current: &Path, pinned: Option<&Path>) -> Result<Migration> {
+    if legacy == current || !legacy.exists() || pinned.is_some_and(|p| p.starts_with(legacy)) {
+        return Ok(Migration::None);
+    }
+    if current.exists() {
+        return Ok(Migration::Conflict {
+            legacy: legacy.to_path_buf(),
+            current: current.to_path_buf(),
+        });
+    }
+    if let Some(parent) = current.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    // A plain rename: across filesystems it fails rather than half-copying
+    fs::rename(legacy, current).with_context(|| {
+        format!(
+            "Failed to move {} to {}; move it by hand",
+            legacy.display(),
+            current.display()
+        )
+    })?;
+    Ok(Migration::Moved {
+        from: legacy.to_path_buf(),
+        to: current.to_path_buf(),
+    })
+}
+
+/// Run `migrate` for each base and report the outcome on stderr. Failures
+/// are warnings: the new location simply starts out empty.
+pub fn migrate_and_report(bases: &[&BaseDir], pinned: Option<&Path>) {
+    for base in bases {
+        match migrate(base, pinned) {
+            Ok(Migration::None) => {}
+            Ok(Migration::Moved { from, to }) => eprintln!(
+                "Moved {} dir {} to {} (${})",
+                base.kind,
+                from.display(),
+                to.display(),
+                base.var
+            ),
+            Ok(Migration::Conflict { legacy, current }) => eprintln!(
+                "Warning: Both {} and {} exist; using {} (${}). Merge or delete the other one",
+                legacy.display(),
+                current.display(),
+                current.display(),
+                base.var
+            ),
+            Err(e) => eprintln!("Warning: {:#}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_honors_only_absolute_xdg_values() {
+        let home = Path::new("/home/me");
+        let resolve = |value: Option<&str>| STATE.resolve(home, value.map(OsString::from));
+        assert_eq!(
+            resolve(None),
+            PathBuf::from("/home/me/.local/state/cc-sessions")
+        );
+        assert_eq!(
+            resolve(Some("")),
+            PathBuf::from("/home/me/.local/state/cc-sessions")
+        );
+        assert_eq!(
+            resolve(Some("rel/state")),
+            PathBuf::from("/home/me/.local/state/cc-sessions")
+        );
+        assert_eq!(
+            resolve(Some("/xdg/state")),
+            PathBuf::from("/xdg/state/cc-sessions")
+        );
+    }
+
+    #[test]
+    fn migrate_moves_legacy_dir_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let legacy = tmp.path().join("old/cc-sessions");
+        let current = tmp.path().join("xdg/cc-sessions");
+        fs::create_dir_all(legacy.join("index")).unwrap();
+        fs::write(legacy.join("index/x.json"), "{}").unwrap();
+
+        let pinned = legacy.join("remotes");
+        assert_eq!(
+            migrate_dir(&legacy, &current, Some(&pinned)).unwrap(),
+            Migration::None
+        );
+        assert_eq!(
+            migrate_dir(&legacy, &current, None).unwrap(),
+            Migration::Moved {
+                from: legacy.clone(),
+                to: current.clone()
+            }
+        );
+        assert!(current.join("index/x.json").exists() && !legacy.exists());
+        assert_eq!(
+            migrate_dir(&legacy, &current, None).unwrap(),
+            Migration::None
+        );
+
+        fs::create_dir_all(&legacy).unwrap();
+        assert!(matches!(
+            migrate_dir(&legacy, &current, None).unwrap(),
+            Migration::Conflict { .. }
+        ));
+    }
+}
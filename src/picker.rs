@@ -0,0 +1,140 @@
+//! External picker delegation (`--picker fzf`).
+//!
+//! Instead of the built-in skim UI, candidates are written to another
+//! program's stdin as tab-separated lines whose first field is the session
+//! file path, and the chosen line is read back from its stdout:
+//!
+//! ```text
+//! <filepath>\t<row as shown in the built-in picker>
+//! ```
+//!
+//! For fzf (and sk, which shares its flags) we add the delimiter, hidden
+//! path column, and a `--preview` that calls back into this binary. Any
+//! other command runs as configured; it can build its own preview from
+//! `$CC_SESSIONS_PREVIEW`, e.g. `fzf --preview "$CC_SESSIONS_PREVIEW {1}"`.
+
+use crate::session::Session;
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Env var holding the shell-quoted preview helper invocation.
+pub const PREVIEW_ENV: &str = "CC_SESSIONS_PREVIEW";
+
+/// Names that select the built-in skim UI.
+pub fn is_builtin(picker: &str) -> bool {
+    matches!(picker.trim(), "" | "builtin" | "skim")
+}
+
+/// One candidate line: file path, then the display row. Tabs and newlines
+/// inside the row would break field splitting, so they become spaces.
+pub fn candidate_line(session: &Session, row: &str) -> String {
+    let row: String = row
+        .chars()
+        .map(|c| if c == '\t' || c == '\n' { ' ' } else { c })
+        .collect();
+    format!("{}\t{}", session.filepath.display(), row)
+}
+
+/// Whether the picker's program takes fzf-style flags.
+fn speaks_fzf(picker: &str) -> bool {
+    let program = picker.split_whitespace().next().unwrap_or_default();
+    let name = Path::new(program)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    matches!(name, "fzf" | "sk")
+}
+
+/// Shell command line for the picker, with fzf integration flags appended
+/// for fzf-compatible programs. `preview` is the quoted preview helper.
+pub fn command_line(picker: &str, preview: &str) -> String {
+    if !speaks_fzf(picker) {
+        return picker.to_string();
+    }
+    format!(
+        "{} --delimiter='\\t' --with-nth=2.. --nth=2.. --ansi --no-sort \
+         --preview='{} {{1}}' --preview-window=right:60%:wrap",
+        picker,
+        crate::shell_escape(preview)
+    )
+}
+
+/// Run the picker over `sessions` (rendered with `row`) and return the
+/// chosen session, or `None` if the user cancelled.
+pub fn pick<'a>(
+    picker: &str,
+    sessions: &'a [&'a Session],
+    row: impl Fn(&Session) -> String,
+) -> Result<Option<&'a Session>> {
+    let exe = std::env::current_exe().context("Failed to locate cc-sessions binary")?;
+    let preview = format!(
+        "'{}'{} --preview",
+        crate::shell_escape(&exe.to_string_lossy()),
+        if crate::plain::enabled() {
+            " --plain"
+        } else {
+            ""
+        }
+    );
+
+    let mut child = Command::new("sh")
+        .args(["-c", &command_line(picker, &preview)])
+        .env(PREVIEW_ENV, &preview)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start picker '{}'", picker))?;
+
+    {
+        let mut stdin = child.stdin.take().context("Picker stdin unavailable")?;
+        for session in sessions {
+            // The picker may exit early (e.g. Esc); stop writing when it does.
+            if writeln!(stdin, "{}", candidate_line(session, &row(session))).is_err() {
+                break;
+            }
+        }
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        // fzf: 1 = no match, 130 = cancelled. Neither is an error for us.
+        return Ok(None);
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let Some(path) = stdout.lines().next().and_then(|l| l.split('\t').next()) else {
+        return Ok(None);
+    };
+    Ok(sessions
+        .iter()
+        .copied()
+        .find(|s| s.filepath.as_os_str() == path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidate_line_keeps_path_as_first_field() {
+        let session = Session::fixture("abc");
+        let line = candidate_line(&session, "2h  1h  3 local\tproj  fix\nthe bug");
+        let fields: Vec<&str> = line.split('\t').collect();
+        assert_eq!(
+            fields,
+            ["/tmp/abc.jsonl", "2h  1h  3 local proj  fix the bug"]
+        );
+    }
+
+    #[test]
+    fn fzf_gets_integration_flags_other_commands_do_not() {
+        let fzf = command_line("/usr/local/bin/fzf --height=50%", "'/bin/cc' --preview");
+        assert!(fzf.starts_with("/usr/local/bin/fzf --height=50% --delimiter="));
+        assert!(fzf.contains("--preview=''\\''/bin/cc'\\'' --preview {1}'"));
+
+        assert_eq!(command_line("peco", "x"), "peco");
+        assert!(is_builtin("skim"));
+        assert!(!is_builtin("fzf"));
+    }
+}
@@ -0,0 +1,127 @@
+//! Plain output (`--plain`) for screen readers and logs.
+//!
+//! The list, its notices, and previews are built with ANSI colors, box
+//! drawing, and glyph indicators. In plain mode they pass through `render`
+//! on the way out, which drops escape sequences and swaps each glyph for
+//! ASCII or a bracketed label. Markers whose width matters for column
+//! alignment (★, ⧖, and the fork marker) are spelled out where they are
+//! built instead, by checking `enabled`.
+//!
+//! The mode is chosen once at startup, like the profile, so nothing has to
+//! thread it through.
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Select plain output for this run.
+pub fn enable(plain: bool) {
+    let _ = ENABLED.set(plain);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
+/// Glyphs and their plain replacements.
+const GLYPHS: &[(char, &str)] = &[
+    ('★', "[named]"),
+    ('⧖', "[archived]"),
+    ('↳', "[fork]"),
+    ('⚠', "Warning:"),
+    ('✚', "New:"),
+    ('⏱', "Clock:"),
+    ('◐', "[in progress]"),
+    ('○', "[todo]"),
+    ('▸', "[collapsed]"),
+    ('▾', "[expanded]"),
+    ('─', "-"),
+    ('═', "="),
+    ('│', "|"),
+    ('├', "|"),
+    ('└', "`"),
+    ('…', "..."),
+    ('—', "-"),
+    ('×', "x"),
+    ('→', "->"),
+    ('←', "<-"),
+    // Activity sparkline, lowest to highest
+    ('▁', "1"),
+    ('▂', "2"),
+    ('▃', "3"),
+    ('▄', "4"),
+    ('▅', "5"),
+    ('▆', "6"),
+    ('▇', "7"),
+    ('█', "8"),
+];
+
+/// `text` as it should be printed: unchanged normally, plain in plain mode.
+pub fn render(text: &str) -> Cow<'_, str> {
+    if enabled() {
+        Cow::Owned(to_plain(text))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+fn to_plain(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            skip_escape(&mut chars);
+            continue;
+        }
+        match GLYPHS.iter().find(|(glyph, _)| *glyph == c) {
+            Some((_, replacement)) => out.push_str(replacement),
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+/// Skip the rest of an escape sequence: CSI (`ESC [ … final`, colors) or
+/// OSC (`ESC ] … BEL` or `ESC ] … ESC \`, hyperlinks).
+fn skip_escape(chars: &mut std::str::Chars<'_>) {
+    match chars.next() {
+        Some('[') => {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+        Some(']') => {
+            while let Some(c) = chars.next() {
+                if c == '\x07' {
+                    break;
+                }
+                if c == '\x1b' {
+                    chars.next();
+                    break;
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_plain_strips_escapes_and_labels_glyphs() {
+        assert_eq!(
+            to_plain("\x1b[33m★ fix parser\x1b[0m │ \x1b]8;;file:///a\x1b\\api\x1b]8;;\x1b\\"),
+            "[named] fix parser | api"
+        );
+        assert_eq!(
+            to_plain("⚠ devbox: sync failed — search ×2 ▁█"),
+            "Warning: devbox: sync failed - search x2 18"
+        );
+        assert_eq!(to_plain("── cached ──"), "-- cached --");
+    }
+}
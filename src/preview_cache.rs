@@ -0,0 +1,118 @@
+//! Rendered previews for the built-in picker.
+//!
+//! Skim asks for a preview every time the cursor lands on a row, and each one
+//! means parsing the transcript. Rendered previews are memoized per file and
+//! invalidated by (mtime, size), so a session still being written refreshes.
+//! When a picker opens, a background thread fills in the top rows while the
+//! user is still reading the list, so the first cursor movements are instant.
+
+use crate::metadata_cache::Fingerprint;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Rows pre-rendered when a picker opens.
+pub const PREWARM_COUNT: usize = 30;
+
+/// Renders the preview for one session file.
+pub type Render = fn(&Path) -> Result<String>;
+
+#[derive(Debug, Default)]
+pub struct PreviewCache {
+    entries: Mutex<HashMap<PathBuf, (Fingerprint, String)>>,
+}
+
+impl PreviewCache {
+    /// Cached preview for `path` if the file is unchanged, else render and
+    /// remember it. Failed renders aren't cached.
+    pub fn get_or_render(&self, path: &Path, render: Render) -> Result<String> {
+        let fingerprint = fs::metadata(path).ok().map(|m| Fingerprint::of(&m));
+        if let Some(fingerprint) = fingerprint
+            && let Some((cached, text)) = self.entries.lock().unwrap().get(path)
+            && *cached == fingerprint
+        {
+            return Ok(text.clone());
+        }
+        // Render outside the lock so the picker and the prewarm thread don't
+        // serialize on each other's parsing.
+        let text = render(path)?;
+        if let Some(fingerprint) = fingerprint {
+            self.entries
+                .lock()
+                .unwrap()
+                .insert(path.to_path_buf(), (fingerprint, text.clone()));
+        }
+        Ok(text)
+    }
+
+    /// Render `paths` in order on a background thread. Stops early once the
+    /// returned handle is dropped (e.g. when the picker is rebuilt).
+    pub fn prewarm(self: &Arc<Self>, paths: Vec<PathBuf>, render: Render) -> Prewarm {
+        let cancel = Arc::new(AtomicBool::new(false));
+        std::thread::spawn({
+            let cache = Arc::clone(self);
+            let cancel = Arc::clone(&cancel);
+            move || cache.warm(&paths, render, &cancel)
+        });
+        Prewarm { cancel }
+    }
+
+    fn warm(&self, paths: &[PathBuf], render: Render, cancel: &AtomicBool) {
+        for path in paths {
+            if cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            let _ = self.get_or_render(path, render);
+        }
+    }
+}
+
+/// Handle to a running prewarm; dropping it cancels the remaining work
+/// without waiting for the file currently being rendered.
+pub struct Prewarm {
+    cancel: Arc<AtomicBool>,
+}
+
+impl Drop for Prewarm {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    static RENDERS: AtomicUsize = AtomicUsize::new(0);
+
+    fn counting_render(path: &Path) -> Result<String> {
+        RENDERS.fetch_add(1, Ordering::SeqCst);
+        Ok(fs::read_to_string(path)?)
+    }
+
+    #[test]
+    fn prewarmed_previews_are_reused_until_the_file_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let a = tmp.path().join("a.jsonl");
+        let b = tmp.path().join("b.jsonl");
+        fs::write(&a, "first").unwrap();
+        fs::write(&b, "other").unwrap();
+
+        let cache = PreviewCache::default();
+        cache.warm(&[a.clone(), b], counting_render, &AtomicBool::new(false));
+        let after_prewarm = RENDERS.load(Ordering::SeqCst);
+        assert_eq!(cache.get_or_render(&a, counting_render).unwrap(), "first");
+        assert_eq!(RENDERS.load(Ordering::SeqCst), after_prewarm);
+
+        fs::write(&a, "first, then more").unwrap();
+        assert_eq!(
+            cache.get_or_render(&a, counting_render).unwrap(),
+            "first, then more"
+        );
+        assert_eq!(RENDERS.load(Ordering::SeqCst), after_prewarm + 1);
+    }
+}
@@ -0,0 +1,173 @@
+//! Model pricing for cost estimates.
+//!
+//! Prices are USD per million tokens, matched against the transcript's
+//! `message.model` by longest prefix so dated snapshots
+//! (`claude-sonnet-4-5-20250929`) resolve to their family. Built-in list
+//! prices can be overridden or extended from the config:
+//!
+//! ```toml
+//! [pricing.claude-opus-4-5]
+//! input = 5.0
+//! output = 25.0
+//! cache_write = 6.25
+//! cache_read = 0.5
+//! ```
+//!
+//! Estimates only: they ignore batch/priority discounts and long-context
+//! surcharges, and assume 5-minute cache writes.
+
+use crate::session::TokenUsage;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// USD per million tokens for one model family.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_write: f64,
+    #[serde(default)]
+    pub cache_read: f64,
+}
+
+const fn price(input: f64, output: f64, cache_write: f64, cache_read: f64) -> ModelPrice {
+    ModelPrice {
+        input,
+        output,
+        cache_write,
+        cache_read,
+    }
+}
+
+/// Published list prices at the time of writing, keyed by model-id prefix.
+const BUILTIN_PRICES: &[(&str, ModelPrice)] = &[
+    ("claude-opus-4-5", price(5.0, 25.0, 6.25, 0.50)),
+    ("claude-opus-4", price(15.0, 75.0, 18.75, 1.50)),
+    ("claude-sonnet-4", price(3.0, 15.0, 3.75, 0.30)),
+    ("claude-3-7-sonnet", price(3.0, 15.0, 3.75, 0.30)),
+    ("claude-3-5-sonnet", price(3.0, 15.0, 3.75, 0.30)),
+    ("claude-haiku-4-5", price(1.0, 5.0, 1.25, 0.10)),
+    ("claude-3-5-haiku", price(0.80, 4.0, 1.0, 0.08)),
+    ("claude-3-opus", price(15.0, 75.0, 18.75, 1.50)),
+    ("claude-3-haiku", price(0.25, 1.25, 0.30, 0.03)),
+];
+
+impl ModelPrice {
+    fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.input as f64 * self.input
+            + usage.output as f64 * self.output
+            + usage.cache_creation as f64 * self.cache_write
+            + usage.cache_read as f64 * self.cache_read)
+            / 1e6
+    }
+}
+
+/// Estimated spend for some usage, plus whatever couldn't be priced.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Cost {
+    pub usd: f64,
+    /// Tokens from models with no known price (excluded from `usd`).
+    pub unpriced_tokens: u64,
+}
+
+impl std::ops::AddAssign for Cost {
+    fn add_assign(&mut self, rhs: Self) {
+        self.usd += rhs.usd;
+        self.unpriced_tokens += rhs.unpriced_tokens;
+    }
+}
+
+/// Built-in prices merged with config overrides.
+#[derive(Debug)]
+pub struct PriceTable {
+    /// Longest prefix first, so the first match is the most specific.
+    entries: Vec<(String, ModelPrice)>,
+}
+
+impl PriceTable {
+    pub fn new(overrides: &HashMap<String, ModelPrice>) -> Self {
+        let mut merged: HashMap<String, ModelPrice> = BUILTIN_PRICES
+            .iter()
+            .map(|(prefix, price)| (prefix.to_string(), *price))
+            .collect();
+        merged.extend(overrides.iter().map(|(k, v)| (k.clone(), *v)));
+        let mut entries: Vec<(String, ModelPrice)> = merged.into_iter().collect();
+        entries.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        Self { entries }
+    }
+
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        self.entries
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .map(|(_, price)| *price)
+    }
+
+    /// Price a session's per-model usage.
+    pub fn cost(&self, model_usage: &BTreeMap<String, TokenUsage>) -> Cost {
+        let mut cost = Cost::default();
+        for (model, usage) in model_usage {
+            match self.price(model) {
+                Some(price) => cost.usd += price.cost(usage),
+                None => cost.unpriced_tokens += usage.total(),
+            }
+        }
+        cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_prefix_wins() {
+        let table = PriceTable::new(&HashMap::new());
+        assert_eq!(table.price("claude-opus-4-5-20251101").unwrap().input, 5.0);
+        assert_eq!(table.price("claude-opus-4-1-20250805").unwrap().input, 15.0);
+        assert_eq!(
+            table.price("claude-sonnet-4-5-20250929").unwrap().output,
+            15.0
+        );
+        assert!(table.price("<synthetic>").is_none());
+    }
+
+    #[test]
+    fn overrides_replace_and_extend_builtins() {
+        let overrides = HashMap::from([
+            ("claude-sonnet-4".to_string(), price(1.0, 2.0, 0.0, 0.0)),
+            ("gpt-spam".to_string(), price(9.0, 9.0, 0.0, 0.0)),
+        ]);
+        let table = PriceTable::new(&overrides);
+        assert_eq!(table.price("claude-sonnet-4-5").unwrap().input, 1.0);
+        assert_eq!(table.price("gpt-spam-1").unwrap().output, 9.0);
+    }
+
+    #[test]
+    fn cost_sums_models_and_tracks_unpriced() {
+        let table = PriceTable::new(&HashMap::new());
+        let usage = BTreeMap::from([
+            (
+                "claude-sonnet-4-5".to_string(),
+                TokenUsage {
+                    input: 1_000_000,
+                    output: 100_000,
+                    cache_creation: 0,
+                    cache_read: 2_000_000,
+                },
+            ),
+            (
+                "mystery-model".to_string(),
+                TokenUsage {
+                    output: 42,
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let cost = table.cost(&usage);
+        // 3.00 input + 1.50 output + 0.60 cache reads
+        assert!((cost.usd - 5.10).abs() < 1e-9);
+        assert_eq!(cost.unpriced_tokens, 42);
+    }
+}
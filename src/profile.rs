@@ -0,0 +1,78 @@
+//! Named configuration profiles (`--profile work` or `CC_SESSIONS_PROFILE`).
+//!
+//! A profile is a separate environment: its own config file (remotes,
+//! settings, defaults), remote cache, scan index, state, and archive, so one
+//! client's sessions never show up under another's. The default profile uses
+//! the base dirs from `paths.rs`; a named one nests each of them under
+//! `profiles/<name>/` (shown with the XDG defaults):
+//!
+//! ```text
+//! ~/.config/cc-sessions/profiles/work/remotes.toml
+//! ~/.cache/cc-sessions/profiles/work/{remotes,index}/
+//! ~/.local/state/cc-sessions/profiles/work/
+//! ~/.local/share/cc-sessions/profiles/work/archive/
+//! ```
+//!
+//! Local sessions are shared unless the profile's config points
+//! `settings.claude_dir` at another Claude Code config dir.
+//!
+//! The profile is chosen once at startup, before the config is read, and
+//! every path helper consults it, so nothing has to thread it through.
+
+use anyhow::{Result, bail};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Environment variable selecting a profile when `--profile` isn't given.
+pub const ENV_VAR: &str = "CC_SESSIONS_PROFILE";
+
+static ACTIVE: OnceLock<Option<String>> = OnceLock::new();
+
+/// Select the profile for this run: `flag`, else `$CC_SESSIONS_PROFILE`,
+/// else the default. Empty means the default too.
+pub fn activate(flag: Option<&str>) -> Result<()> {
+    let env = std::env::var(ENV_VAR).ok();
+    let name = flag.or(env.as_deref()).filter(|n| !n.is_empty());
+    if let Some(name) = name
+        && !crate::config_edit::is_valid_name(name)
+    {
+        bail!(
+            "Invalid profile name '{}': use letters, digits, '-' and '_'",
+            name
+        );
+    }
+    let _ = ACTIVE.set(name.map(str::to_string));
+    Ok(())
+}
+
+/// The named profile in use, if any.
+pub fn active() -> Option<&'static str> {
+    ACTIVE.get().and_then(|name| name.as_deref())
+}
+
+/// `base` for the default profile, `base/profiles/<name>` for a named one.
+pub fn scoped(base: PathBuf) -> PathBuf {
+    scoped_to(base, active())
+}
+
+fn scoped_to(base: PathBuf, profile: Option<&str>) -> PathBuf {
+    match profile {
+        Some(name) => base.join("profiles").join(name),
+        None => base,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_profiles_nest_under_profiles() {
+        let base = PathBuf::from("/home/me/.cache/cc-sessions");
+        assert_eq!(scoped_to(base.clone(), None), base);
+        assert_eq!(
+            scoped_to(base, Some("acme")),
+            PathBuf::from("/home/me/.cache/cc-sessions/profiles/acme")
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! Progress reporting for phases that block the terminal.
+//!
+//! Workers bump lock-free counters on a shared [`Progress`] (or report what
+//! each of a fixed number of slots is doing on a [`SlotProgress`]); the
+//! waiting thread renders a one-line spinner on stderr. Nothing is drawn unless the
+//! phase outlasts `SHOW_AFTER` and stderr is a terminal, so fast phases and
+//! piped output stay clean.
+
+use std::io::{IsTerminal, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const TICK: Duration = Duration::from_millis(80);
+const SHOW_AFTER: Duration = Duration::from_millis(150);
+
+/// Shared counters for a unit-of-work phase ("searched 12/300 files").
+#[derive(Debug)]
+pub struct Progress {
+    verb: &'static str,
+    total: usize,
+    done: AtomicUsize,
+    matches: Option<AtomicUsize>,
+    scope: Option<String>,
+}
+
+impl Progress {
+    /// Progress over `total` files, rendered as "`verb` done/total files".
+    pub fn new(verb: &'static str, total: usize) -> Self {
+        Self {
+            verb,
+            total,
+            done: AtomicUsize::new(0),
+            matches: None,
+            scope: None,
+        }
+    }
+
+    /// Name what the files belong to ("scanned 12/300 files in devbox"),
+    /// for phases that run once per source.
+    pub fn in_scope(mut self, scope: &str) -> Self {
+        self.scope = Some(scope.to_string());
+        self
+    }
+
+    /// Like [`Progress::new`], additionally tracking a running match count.
+    pub fn with_matches(verb: &'static str, total: usize) -> Self {
+        Self {
+            matches: Some(AtomicUsize::new(0)),
+            ..Self::new(verb, total)
+        }
+    }
+
+    pub fn inc(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_match(&self) {
+        if let Some(m) = &self.matches {
+            m.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn status_line(&self) -> String {
+        let done = self.done.load(Ordering::Relaxed).min(self.total);
+        let mut line = format!(
+            "{} {}/{} files",
+            self.verb,
+            format_count(done),
+            format_count(self.total)
+        );
+        if let Some(scope) = &self.scope {
+            line.push_str(&format!(" in {}", scope));
+        }
+        if let Some(m) = &self.matches {
+            let m = m.load(Ordering::Relaxed);
+            line.push_str(&format!(
+                ", {} match{}",
+                format_count(m),
+                if m == 1 { "" } else { "es" }
+            ));
+        }
+        line
+    }
+}
+
+/// Shared state for a phase that works through a queue on a few slots
+/// ("synced 3/10 remotes · 1: devbox · 2: idle").
+#[derive(Debug)]
+pub struct SlotProgress {
+    verb: &'static str,
+    noun: &'static str,
+    total: usize,
+    done: AtomicUsize,
+    slots: Vec<Mutex<Option<String>>>,
+}
+
+impl SlotProgress {
+    /// Progress over `total` items of `noun` (plural) worked on by `slots`
+    /// workers at a time.
+    pub fn new(verb: &'static str, noun: &'static str, total: usize, slots: usize) -> Self {
+        Self {
+            verb,
+            noun,
+            total,
+            done: AtomicUsize::new(0),
+            slots: (0..slots).map(|_| Mutex::new(None)).collect(),
+        }
+    }
+
+    /// Slot `slot` took up `item`.
+    pub fn start(&self, slot: usize, item: &str) {
+        *self.slots[slot].lock().unwrap() = Some(item.to_string());
+    }
+
+    /// Slot `slot` finished its item.
+    pub fn finish(&self, slot: usize) {
+        *self.slots[slot].lock().unwrap() = None;
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Something [`run`] can render while waiting.
+pub trait Status: Sync {
+    fn status_line(&self) -> String;
+}
+
+impl Status for Progress {
+    fn status_line(&self) -> String {
+        Progress::status_line(self)
+    }
+}
+
+impl Status for SlotProgress {
+    fn status_line(&self) -> String {
+        let done = self.done.load(Ordering::Relaxed).min(self.total);
+        let mut line = format!("{} {}/{} {}", self.verb, done, self.total, self.noun);
+        for (i, slot) in self.slots.iter().enumerate() {
+            let slot = slot.lock().unwrap();
+            line.push_str(&format!(
+                " · {}: {}",
+                i + 1,
+                slot.as_deref().unwrap_or("idle")
+            ));
+        }
+        line
+    }
+}
+
+static HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Draw nothing for the rest of the run, even on a terminal (for shell
+/// completion, whose stderr is the prompt being typed).
+pub fn hide() {
+    HIDDEN.store(true, Ordering::Relaxed);
+}
+
+/// Run `work` on a scoped thread while rendering `progress` from this one.
+pub fn run<T: Send>(progress: &impl Status, work: impl FnOnce() -> T + Send) -> T {
+    std::thread::scope(|scope| {
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        let handle = scope.spawn(move || {
+            let _done = done_tx; // dropped (disconnecting) even if `work` panics
+            work()
+        });
+        // Wakes as soon as the work ends, so fast phases cost no extra tick.
+        spin_until(
+            |timeout| {
+                matches!(
+                    done_rx.recv_timeout(timeout),
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected)
+                )
+            },
+            progress,
+        );
+        handle
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e))
+    })
+}
+
+/// Draw `progress` every tick until `wait(tick)` reports the work finished.
+fn spin_until(mut wait: impl FnMut(Duration) -> bool, progress: &impl Status) {
+    let interactive = std::io::stderr().is_terminal() && !HIDDEN.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let mut drawn = false;
+    let mut frame = 0;
+
+    while !wait(TICK) {
+        if interactive && start.elapsed() >= SHOW_AFTER {
+            let mut err = std::io::stderr().lock();
+            let _ = write!(
+                err,
+                "\r\x1b[2K{} {}",
+                SPINNER[frame % SPINNER.len()],
+                progress.status_line()
+            );
+            let _ = err.flush();
+            drawn = true;
+            frame += 1;
+        }
+    }
+
+    if drawn {
+        let mut err = std::io::stderr().lock();
+        let _ = write!(err, "\r\x1b[2K");
+        let _ = err.flush();
+    }
+}
+
+/// Format an integer with thousands separators: 3088 → "3,088".
+pub fn format_count(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_count_groups_thousands() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1240), "1,240");
+        assert_eq!(format_count(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn status_line_reports_counts_and_matches() {
+        let p = Progress::with_matches("searched", 3088);
+        for _ in 0..1240 {
+            p.inc();
+        }
+        for _ in 0..12 {
+            p.add_match();
+        }
+        assert_eq!(p.status_line(), "searched 1,240/3,088 files, 12 matches");
+
+        let p = Progress::new("indexed", 2);
+        p.inc();
+        assert_eq!(p.status_line(), "indexed 1/2 files");
+
+        let p = Progress::new("scanned", 2941).in_scope("devbox");
+        assert_eq!(p.status_line(), "scanned 0/2,941 files in devbox");
+    }
+
+    #[test]
+    fn slot_status_names_each_slot() {
+        let p = SlotProgress::new("synced", "remotes", 10, 2);
+        p.start(0, "devbox");
+        p.start(1, "gpu");
+        p.finish(1);
+        assert_eq!(
+            Status::status_line(&p),
+            "synced 1/10 remotes · 1: devbox · 2: idle"
+        );
+    }
+
+    #[test]
+    fn run_returns_work_result() {
+        let p = Progress::new("indexed", 1);
+        let out = run(&p, || {
+            p.inc();
+            42
+        });
+        assert_eq!(out, 42);
+
+        // Quick work returns without waiting out a spinner tick.
+        let start = Instant::now();
+        run(&p, || ());
+        assert!(start.elapsed() < TICK);
+    }
+}
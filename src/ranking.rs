@@ -0,0 +1,189 @@
+//! Relevance ordering (`--sort relevance`), the default inside a git repo.
+//!
+//! Each session gets a score blending three signals, each between 0 and 1:
+//!
+//! - repo: 1 if it ran in the current repo (a remote one counts through its
+//!   `path_map`), 0.5 if it ran in a same-named repo elsewhere
+//! - recency: halves every `HALF_LIFE_DAYS` since it was last modified
+//! - frecency: how often it was resumed through cc-sessions, fading the
+//!   same way since the last resume
+//!
+//! and weighted by the `[ranking]` config section.
+
+use crate::session::Session;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Days for the recency and frecency signals to halve.
+const HALF_LIFE_DAYS: f64 = 3.0;
+
+/// Resumes at which the frecency signal is full strength.
+const FREQUENT_RESUMES: f64 = 10.0;
+
+/// `[ranking]` config section: how much each signal counts.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Weights {
+    #[serde(default = "default_repo")]
+    pub repo: f64,
+    #[serde(default = "default_recency")]
+    pub recency: f64,
+    #[serde(default = "default_frecency")]
+    pub frecency: f64,
+}
+
+fn default_repo() -> f64 {
+    2.0
+}
+
+fn default_recency() -> f64 {
+    1.0
+}
+
+fn default_frecency() -> f64 {
+    1.0
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            repo: default_repo(),
+            recency: default_recency(),
+            frecency: default_frecency(),
+        }
+    }
+}
+
+/// Scores sessions against the current repo (if any) at a fixed time.
+#[derive(Debug, Clone)]
+pub struct Ranking {
+    weights: Weights,
+    /// The repo root and its canonical form (recorded cwds may be either)
+    repo: Option<(PathBuf, Option<PathBuf>)>,
+    now: SystemTime,
+}
+
+impl Ranking {
+    pub fn new(weights: &Weights, repo_root: Option<&Path>, now: SystemTime) -> Self {
+        Self {
+            weights: weights.clone(),
+            repo: repo_root.map(|root| (root.to_path_buf(), root.canonicalize().ok())),
+            now,
+        }
+    }
+
+    /// Set `Session::relevance` on every session.
+    pub fn apply(&self, sessions: &mut [Session]) {
+        for session in sessions {
+            session.relevance = self.score(session);
+        }
+    }
+
+    pub fn score(&self, session: &Session) -> f64 {
+        let frequency =
+            (1.0 + f64::from(session.resume_count)).ln() / (1.0 + FREQUENT_RESUMES).ln();
+        let frecency = frequency.min(1.0) * session.resumed.map_or(0.0, |t| self.decay(t));
+        self.weights.repo * self.repo_signal(session)
+            + self.weights.recency * self.decay(session.modified)
+            + self.weights.frecency * frecency
+    }
+
+    fn repo_signal(&self, session: &Session) -> f64 {
+        let Some((root, canonical)) = &self.repo else {
+            return 0.0;
+        };
+        let inside = |path: &Path| {
+            crate::repo::is_within(path, root)
+                || canonical
+                    .as_deref()
+                    .is_some_and(|c| crate::repo::is_within(path, c))
+        };
+        let here = if session.source.is_local() {
+            inside(Path::new(&session.project_path))
+        } else {
+            session.local_path.as_deref().is_some_and(inside)
+        };
+        if here {
+            1.0
+        } else if root
+            .file_name()
+            .is_some_and(|name| *name == *session.project)
+        {
+            0.5
+        } else {
+            0.0
+        }
+    }
+
+    /// 1 at `now`, halving every `HALF_LIFE_DAYS` before it.
+    fn decay(&self, time: SystemTime) -> f64 {
+        let days = self
+            .now
+            .duration_since(time)
+            .map_or(0.0, |age| age.as_secs_f64() / 86400.0);
+        0.5f64.powf(days / HALF_LIFE_DAYS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{RemoteResume, SessionSource, SortKey};
+    use std::time::Duration;
+
+    fn session(id: &str, project_path: &str, age_days: u64, now: SystemTime) -> Session {
+        let mut session = Session::fixture(id);
+        session.project_path = project_path.to_string();
+        session.project = Path::new(project_path)
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        session.modified = now - Duration::from_secs(age_days * 86400);
+        session
+    }
+
+    #[test]
+    fn repo_sessions_lead_then_recency_and_resumes() {
+        let now = SystemTime::now();
+        let ranking = Ranking::new(&Weights::default(), Some(Path::new("/src/api")), now);
+        let mut sessions = vec![
+            session("elsewhere-new", "/src/web", 0, now),
+            session("repo-old", "/src/api/server", 10, now),
+            session("repo-new", "/src/api", 1, now),
+            session("repo-old-resumed", "/src/api", 10, now),
+        ];
+        sessions[3].resume_count = 2;
+        sessions[3].resumed = Some(now);
+
+        let mut remote = session("remote-same-name", "/home/me/api", 0, now);
+        remote.source = SessionSource::Remote {
+            name: "devbox".to_string(),
+            host: "devbox".to_string(),
+            user: None,
+            label: None,
+            color: None,
+            resume: RemoteResume::Ssh,
+        };
+        sessions.push(remote);
+
+        ranking.apply(&mut sessions);
+        SortKey::Relevance.sort(&mut sessions);
+        let order: Vec<&str> = sessions.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(
+            order,
+            [
+                "repo-new",
+                "repo-old-resumed",
+                "repo-old",
+                "remote-same-name",
+                "elsewhere-new"
+            ]
+        );
+
+        // Outside a repo only recency and resumes count
+        let ranking = Ranking::new(&Weights::default(), None, now);
+        assert_eq!(ranking.repo_signal(&sessions[0]), 0.0);
+    }
+}
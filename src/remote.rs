@@ -16,37 +16,117 @@
 //! ## Config Format
 //!
 //! ```toml
+//! version = 1  # Schema version; older files are migrated on load
+//!
 //! [remotes.devbox]
 //! host = "devbox"  # SSH config alias
+//! label = "dev"    # Optional: shown in the SOURCE column instead of "devbox"
+//! color = "green"  # Optional: label color
+//!
+//! group = "work"  # Optional: sync or filter together (`--group work`, `--remote @work`)
+//! sync_interval = "15m"  # Optional: `--sync-only` skips it until this much time has passed
+//!
+//! # Optional: local checkouts of remote directories, for `--cd` and Ctrl+X
+//! path_map = { "/home/me/work" = "~/src" }
+//!
+//! [remotes.build1]  # rsync daemon, no SSH: synced and browsed, not resumed
+//! transport = "rsyncd"
+//! host = "build1.example.com"  # "host:port" for a non-standard port
+//! module = "claude"
+//! projects_dir = "projects"  # Optional: path inside the module
+//! password_file = "~/.config/cc-sessions/build1.pass"  # Optional
+//! resume_command = "buildctl attach build1 -- sh -c 'cd \"$1\" && shift && exec claude -r \"$@\"' _ {dir} {id} {fork} {args}"
 //!
 //! [remotes.workstation]
 //! host = "192.168.1.100"
 //! user = "ec2-user"  # Optional for raw hosts
+//! group = "work"
+//!
+//! [remotes.router]  # No rsync on the host: copy changed files with sftp
+//! host = "router"
+//! transport = "sftp"
 //!
 //! [settings]
 //! cache_dir = "~/.cache/cc-sessions/remotes"
 //! stale_threshold = 3600  # Seconds before auto-sync
+//! sync_cooldown = 60  # Seconds auto-sync leaves a remote alone after any attempt
+//! search_tool_output = false  # Include tool results in Ctrl+S search
+//! picker = "fzf --height=60%"  # External picker instead of the built-in UI
+//! threads = 2  # Worker threads for scanning, search, and sync (default: one per core)
+//! low_priority = true  # Run (and sync) at reduced CPU priority
+//! full_scan_limit_mb = 100  # Larger transcripts are only scanned at head/tail (0 = no limit)
+//! live_preview = true  # Preview stale remote sessions' newest messages over SSH
+//!
+//! [defaults]  # Used when the matching flag isn't given
+//! min_turns = 2
+//! sort = "turns"
+//!
+//! [ranking]  # Optional: weights for `--sort relevance`, the default inside a git repo
+//! repo = 2.0
+//! recency = 1.0
+//! frecency = 1.0
+//!
+//! [teams.platform]  # Optional: browse sessions teammates publish here (`team publish`)
+//! path = "/mnt/shared/cc-sessions"
+//!
+//! [share]  # Optional: where `share` uploads excerpts (default: a secret GitHub gist)
+//! backend = "gist"  # or "post" with url = "https://…"
+//! redact = ["corp.internal"]  # Extra strings to hide
 //! ```
 
+use crate::progress;
 use anyhow::{Context, Result};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 // =============================================================================
 // Configuration
 // =============================================================================
 
-/// Top-level config file structure
+/// Top-level config file structure. The `version` key is handled by
+/// `config_migration` before deserializing.
 #[derive(Debug, Deserialize, Default)]
 pub struct Config {
     #[serde(default)]
     pub remotes: HashMap<String, RemoteConfig>,
     #[serde(default)]
     pub settings: Settings,
+    /// Fallbacks for CLI flags (see `Defaults`)
+    #[serde(default)]
+    pub defaults: Defaults,
+    /// Column layout for `--list` and the picker (see `layout.rs`)
+    #[serde(default)]
+    pub layout: crate::layout::LayoutConfig,
+    /// Per-model price overrides for `stats --costs` (see `pricing.rs`)
+    #[serde(default)]
+    pub pricing: HashMap<String, crate::pricing::ModelPrice>,
+    /// Weights for `--sort relevance` (see `ranking.rs`)
+    #[serde(default)]
+    pub ranking: crate::ranking::Weights,
+    /// Upload backend for `share` (see `share.rs`)
+    #[serde(default)]
+    pub share: crate::share::ShareConfig,
+    /// Shared locations teammates publish sessions to (see `team.rs`)
+    #[serde(default)]
+    pub teams: HashMap<String, crate::team::TeamConfig>,
+    /// More local session roots (see `LocalConfig`)
+    #[serde(default)]
+    pub local: LocalConfig,
+}
+
+/// `[local]`: where else this machine's sessions live.
+#[derive(Debug, Deserialize, Default)]
+pub struct LocalConfig {
+    /// Claude Code projects dirs listed alongside the main one, e.g. one a
+    /// devcontainer bind-mounts its config dir from
+    #[serde(default)]
+    pub projects_dirs: Vec<String>,
 }
 
 /// Configuration for a single remote machine
@@ -58,10 +138,126 @@ pub struct RemoteConfig {
     pub user: Option<String>,
     /// Override for non-standard projects directory
     pub projects_dir: Option<String>,
+    /// Short name for the SOURCE column and preview header (defaults to the key)
+    pub label: Option<String>,
+    /// Color for the label: red, green, yellow, blue, magenta, cyan, white
+    pub color: Option<crate::session::SourceColor>,
+    /// Group name for `--group` / `--remote @group`
+    pub group: Option<String>,
+    /// Minimum time between syncs by `--sync-only` ("15m", "6h", "1d")
+    #[serde(default, deserialize_with = "deserialize_interval")]
+    pub sync_interval: Option<Duration>,
+    /// Remote directory → equivalent local directory (`~` allowed locally)
+    #[serde(default)]
+    pub path_map: BTreeMap<String, String>,
+    /// How sessions are fetched: rsync over SSH (default, with an sftp
+    /// fallback), sftp only, or from an rsync daemon
+    #[serde(default)]
+    pub transport: Transport,
+    /// rsync daemon module holding the projects dir (`transport = "rsyncd"`)
+    pub module: Option<String>,
+    /// rsync daemon password file (`--password-file`)
+    pub password_file: Option<String>,
+    /// Local command that resumes a session instead of `ssh -t`: `{id}` and
+    /// `{dir}` are replaced by the quoted session ID and project dir, `{fork}`
+    /// by `--fork-session` when forking
+    pub resume_command: Option<String>,
+}
+
+/// How a remote is reached.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    #[default]
+    Ssh,
+    /// SSH without rsync: changed files are found by size and copied with
+    /// `sftp`
+    Sftp,
+    /// An rsync daemon (`rsync://`): sync only, nothing runs on the remote
+    Rsyncd,
+}
+
+impl Transport {
+    /// Whether commands can be run on the remote over SSH.
+    pub fn has_ssh(self) -> bool {
+        matches!(self, Transport::Ssh | Transport::Sftp)
+    }
+}
+
+impl RemoteConfig {
+    /// The SSH target for features that run commands on the remote, or an
+    /// error naming `what` for rsync daemon remotes.
+    pub fn require_ssh(&self, name: &str, what: &str) -> Result<String> {
+        match self.transport {
+            Transport::Ssh | Transport::Sftp => Ok(ssh_target(self)),
+            Transport::Rsyncd => {
+                anyhow::bail!("'{}' is an rsync daemon remote; {} needs SSH", name, what)
+            }
+        }
+    }
+
+    /// The local equivalent of `remote_path` under `path_map`. The longest
+    /// mapped directory containing it wins, matched by whole components.
+    pub fn local_path(&self, remote_path: &str) -> Option<PathBuf> {
+        let remote_path = Path::new(remote_path);
+        let (rest, local) = self
+            .path_map
+            .iter()
+            .filter_map(|(from, to)| Some((remote_path.strip_prefix(from).ok()?, to)))
+            .min_by_key(|(rest, _)| rest.components().count())?;
+        let local = expand_path(local).ok()?;
+        Some(if rest.as_os_str().is_empty() {
+            local
+        } else {
+            local.join(rest)
+        })
+    }
+}
+
+impl Config {
+    /// Names of the remotes in `group`, sorted. Errors if there are none, so
+    /// a misspelled group doesn't quietly select nothing.
+    pub fn group_members(&self, group: &str) -> Result<Vec<&str>> {
+        let mut members: Vec<&str> = self
+            .remotes
+            .iter()
+            .filter(|(_, remote)| remote.group.as_deref() == Some(group))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if members.is_empty() {
+            anyhow::bail!("No remotes in group '{}'", group);
+        }
+        members.sort_unstable();
+        Ok(members)
+    }
+
+    /// Check a `--remote` value before it is used: `@group` must name a
+    /// group with members.
+    pub fn check_remote_filter(&self, filter: Option<&str>) -> Result<()> {
+        match filter.and_then(|f| f.strip_prefix('@')) {
+            Some(group) => self.group_members(group).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the source `source_name` ("local" or a remote name) passes a
+    /// `--remote` filter: its own name, or `@group` for remotes in the group.
+    pub fn source_matches(&self, filter: Option<&str>, source_name: &str) -> bool {
+        match filter {
+            None => true,
+            Some(filter) => match filter.strip_prefix('@') {
+                Some(group) => self
+                    .remotes
+                    .get(source_name)
+                    .is_some_and(|remote| remote.group.as_deref() == Some(group)),
+                None => filter == source_name,
+            },
+        }
+    }
 }
 
 /// Global settings
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
     /// Directory to cache remote sessions
     #[serde(default = "default_cache_dir")]
@@ -69,6 +265,50 @@ pub struct Settings {
     /// Seconds before a cache is considered stale (default: 1 hour)
     #[serde(default = "default_stale_threshold")]
     pub stale_threshold: u64,
+    /// Seconds after any sync attempt (successful or not, by any run) during
+    /// which auto-sync skips the remote; 0 disables (default: 1 minute)
+    #[serde(default = "default_sync_cooldown")]
+    pub sync_cooldown: u64,
+    /// Include tool output in transcript search by default (`in:tools` / `-in:tools` per query)
+    #[serde(default)]
+    pub search_tool_output: bool,
+    /// External picker command replacing the built-in UI (`--picker`)
+    #[serde(default)]
+    pub picker: Option<String>,
+    /// Worker threads for discovery, search, and sync (default: one per core)
+    #[serde(default)]
+    pub threads: Option<usize>,
+    /// Lower CPU priority (`nice`) for runs that never open the picker or
+    /// `claude` (`--sync-only`, `--list`, `index`, ...), inherited by rsync/ssh
+    #[serde(default)]
+    pub low_priority: bool,
+    /// Transcripts larger than this (MB) are only scanned at their head and
+    /// tail unless `--deep` is given; 0 disables the limit
+    #[serde(default = "default_full_scan_limit_mb")]
+    pub full_scan_limit_mb: u64,
+    /// Remotes synced at once; the rest wait for a free slot (default: the
+    /// worker thread count)
+    #[serde(default)]
+    pub max_concurrent_syncs: Option<usize>,
+    /// Command the picker's ctrl+x runs on a project directory (default:
+    /// `code`, else `$VISUAL` / `$EDITOR`)
+    #[serde(default)]
+    pub editor: Option<String>,
+    /// Merge fork decisions with each remote's copy after syncing it
+    #[serde(default)]
+    pub sync_state: bool,
+    /// Folder synced by other means (Syncthing, a git checkout) to merge
+    /// fork decisions with on every run
+    #[serde(default)]
+    pub state_sync_dir: Option<String>,
+    /// Claude Code config dir whose sessions are listed and resumed locally
+    /// (like `CLAUDE_CONFIG_DIR`; default: ~/.claude)
+    #[serde(default)]
+    pub claude_dir: Option<String>,
+    /// In the picker, fetch what a stale remote wrote since its last sync
+    /// for the highlighted session's preview
+    #[serde(default)]
+    pub live_preview: bool,
 }
 
 impl Default for Settings {
@@ -76,23 +316,126 @@ impl Default for Settings {
         Self {
             cache_dir: default_cache_dir(),
             stale_threshold: default_stale_threshold(),
+            sync_cooldown: default_sync_cooldown(),
+            search_tool_output: false,
+            picker: None,
+            threads: None,
+            low_priority: false,
+            full_scan_limit_mb: default_full_scan_limit_mb(),
+            max_concurrent_syncs: None,
+            editor: None,
+            sync_state: false,
+            state_sync_dir: None,
+            claude_dir: None,
+            live_preview: false,
         }
     }
 }
 
+impl Settings {
+    /// `full_scan_limit_mb` in bytes; `None` when disabled.
+    pub fn full_scan_limit(&self) -> Option<u64> {
+        (self.full_scan_limit_mb > 0).then(|| self.full_scan_limit_mb * 1024 * 1024)
+    }
+}
+
+/// Values used for CLI flags the user didn't pass. Flags on the command
+/// line always win; boolean flags can only be turned on here.
+#[derive(Debug, Deserialize, Default)]
+pub struct Defaults {
+    /// `--count` for the table (`--tsv` / `--print0` still print everything)
+    pub count: Option<usize>,
+    /// `--min-turns`
+    pub min_turns: Option<usize>,
+    /// `--sort`
+    pub sort: Option<crate::session::SortKey>,
+    /// `--project`
+    pub project: Option<String>,
+    /// `--remote`
+    pub remote: Option<String>,
+    /// `--no-sync` (overridden by an explicit `--sync`)
+    #[serde(default)]
+    pub no_sync: bool,
+    /// `--include-forks`
+    #[serde(default)]
+    pub include_forks: bool,
+    /// `--return-to-picker` (ignored with `--cd`)
+    #[serde(default)]
+    pub return_to_picker: bool,
+    /// `--flat-forks`
+    #[serde(default)]
+    pub flat_forks: bool,
+    /// `--named-first`
+    #[serde(default)]
+    pub named_first: bool,
+    /// `--plain`
+    #[serde(default)]
+    pub plain: bool,
+}
+
 fn default_cache_dir() -> String {
-    "~/.cache/cc-sessions/remotes".to_string()
+    // Without a home dir, the tilde can't expand either; sync reports that
+    crate::paths::CACHE
+        .dir()
+        .map(|dir| {
+            let dir = crate::profile::scoped(dir).join("remotes");
+            dir.to_string_lossy().into_owned()
+        })
+        .unwrap_or_else(|_| "~/.cache/cc-sessions/remotes".to_string())
 }
 
 fn default_stale_threshold() -> u64 {
     3600 // 1 hour
 }
 
+fn default_sync_cooldown() -> u64 {
+    60
+}
+
+fn default_full_scan_limit_mb() -> u64 {
+    100
+}
+
+fn deserialize_interval<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    parse_interval(&raw)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Parse an interval like "30s", "15m", "6h", "1d", or "2w".
+pub fn parse_interval(raw: &str) -> Result<Duration> {
+    let raw = raw.trim();
+    let unit = raw.chars().last().unwrap_or(' ');
+    let unit_secs = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86_400,
+        'w' => 7 * 86_400,
+        _ => 0,
+    };
+    match raw[..raw.len() - unit.len_utf8().min(raw.len())].parse::<u64>() {
+        Ok(n) if unit_secs > 0 && n > 0 => n
+            .checked_mul(unit_secs)
+            .map(Duration::from_secs)
+            .with_context(|| format!("Interval '{}' is out of range", raw)),
+        _ => anyhow::bail!(
+            "Invalid interval '{}': expected a number and a unit (s, m, h, d, w), e.g. \"15m\"",
+            raw
+        ),
+    }
+}
+
 // =============================================================================
 // Config Loading
 // =============================================================================
 
-/// Load remote configuration from ~/.config/cc-sessions/remotes.toml
+/// Load remote configuration from ~/.config/cc-sessions/remotes.toml (or the
+/// active profile's copy)
 pub fn load_config() -> Result<Config> {
     let config_path = get_config_path()?;
 
@@ -104,16 +447,66 @@ pub fn load_config() -> Result<Config> {
     let content = fs::read_to_string(&config_path)
         .with_context(|| format!("Failed to read config file: {}", config_path.display()))?;
 
-    let config: Config = toml::from_str(&content)
+    let table = crate::config_migration::load_and_migrate(&config_path, &content)?;
+    let config: Config = toml::Value::Table(table)
+        .try_into()
         .with_context(|| format!("Failed to parse config file: {}", config_path.display()))?;
 
     Ok(config)
 }
 
 /// Get the config file path
-fn get_config_path() -> Result<PathBuf> {
-    let home = dirs::home_dir().context("Could not find home directory")?;
-    Ok(home.join(".config/cc-sessions/remotes.toml"))
+pub fn get_config_path() -> Result<PathBuf> {
+    Ok(crate::profile::scoped(crate::paths::CONFIG.dir()?).join("remotes.toml"))
+}
+
+/// The config file's path and text, for commands that edit it. A missing
+/// file reads as empty.
+pub fn read_config_text() -> Result<(PathBuf, String)> {
+    let path = get_config_path()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to read config file: {}", path.display()));
+        }
+    };
+    Ok((path, content))
+}
+
+/// Replace the config file with edited text, but only if it still parses
+/// and `verify` confirms the edit took effect. Written to a temporary file
+/// and renamed, so the config is never left half-written.
+pub fn write_config_text(
+    path: &Path,
+    content: &str,
+    verify: impl FnOnce(&Config) -> bool,
+) -> Result<()> {
+    let parsed: Config = toml::from_str(content)
+        .context("Edit would leave the config unparseable; edit it by hand instead")?;
+    if !verify(&parsed) {
+        anyhow::bail!(
+            "Could not make this edit automatically (the remote may be written as an inline table); edit {} by hand",
+            path.display()
+        );
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create config dir: {}", parent.display()))?;
+    }
+    let tmp = path.with_extension("toml.tmp");
+    fs::write(&tmp, content)
+        .with_context(|| format!("Failed to write config file: {}", tmp.display()))?;
+    fs::rename(&tmp, path)
+        .with_context(|| format!("Failed to replace config file: {}", path.display()))?;
+    Ok(())
+}
+
+/// When `remote_name` was last fully synced, if ever.
+pub fn last_sync_time(settings: &Settings, remote_name: &str) -> Option<SystemTime> {
+    let cache_dir = get_remote_cache_dir(settings, remote_name).ok()?;
+    get_last_sync_time(&cache_dir.join(LAST_SYNC_FILE)).ok()
 }
 
 // =============================================================================
@@ -140,14 +533,86 @@ pub fn ssh_target(remote: &RemoteConfig) -> String {
     }
 }
 
-/// Get the remote projects directory (or default ~/.claude/projects)
+/// Get the remote projects directory (or default ~/.claude/projects). For
+/// an rsync daemon this is the path inside the module (default: its root).
 pub fn remote_projects_dir(remote: &RemoteConfig) -> &str {
+    if remote.transport == Transport::Rsyncd {
+        return remote.projects_dir.as_deref().unwrap_or("");
+    }
     remote
         .projects_dir
         .as_deref()
         .unwrap_or("~/.claude/projects")
 }
 
+/// Where the cached transcript at `cached` lives on `remote`: the same path
+/// relative to its projects dir as to its cache dir.
+pub fn remote_file_path(
+    settings: &Settings,
+    remote_name: &str,
+    remote: &RemoteConfig,
+    cached: &Path,
+) -> Result<String> {
+    let cache_dir = get_remote_cache_dir(settings, remote_name)?;
+    let relative = cached.strip_prefix(&cache_dir).with_context(|| {
+        format!(
+            "{} is not in the cache for '{}'",
+            cached.display(),
+            remote_name
+        )
+    })?;
+    Ok(format!(
+        "{}/{}",
+        remote_projects_dir(remote).trim_end_matches('/'),
+        relative.display()
+    ))
+}
+
+/// Where rsync reads the projects dir from, with the trailing slash that
+/// makes it copy the contents rather than the directory itself.
+pub fn rsync_source(remote_name: &str, remote: &RemoteConfig) -> Result<String> {
+    let dir = remote_projects_dir(remote).trim_end_matches('/');
+    match remote.transport {
+        Transport::Ssh | Transport::Sftp => Ok(format!("{}:{}/", ssh_target(remote), dir)),
+        Transport::Rsyncd => {
+            let module = remote.module.as_deref().with_context(|| {
+                format!(
+                    "Remote '{}' uses transport = \"rsyncd\" but has no module",
+                    remote_name
+                )
+            })?;
+            let dir = dir.trim_start_matches('/');
+            let path = if dir.is_empty() {
+                module.to_string()
+            } else {
+                format!("{}/{}", module, dir)
+            };
+            Ok(format!("rsync://{}/{}/", ssh_target(remote), path))
+        }
+    }
+}
+
+/// rsync options selecting the transport: `-e ssh`, or the daemon's
+/// password file.
+fn rsync_transport_args(remote: &RemoteConfig) -> Result<Vec<String>> {
+    match remote.transport {
+        Transport::Ssh | Transport::Sftp => Ok(vec!["-e".to_string(), "ssh".to_string()]),
+        Transport::Rsyncd => Ok(match &remote.password_file {
+            Some(file) => vec![format!("--password-file={}", expand_path(file)?.display())],
+            None => Vec::new(),
+        }),
+    }
+}
+
+/// Quote a remote path for a shell command line, leaving a leading `~/`
+/// outside the quotes so the remote shell still expands it.
+pub fn shell_path(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => format!("~/'{}'", crate::shell_escape(rest)),
+        None => format!("'{}'", crate::shell_escape(path)),
+    }
+}
+
 // =============================================================================
 // Sync Operations
 // =============================================================================
@@ -157,12 +622,21 @@ pub fn remote_projects_dir(remote: &RemoteConfig) -> &str {
 /// Uses rsync with:
 /// - `-a`: Archive mode (preserves timestamps, permissions)
 /// - `-z`: Compression for transfer
+/// - `-i`: Itemized changes, counted into the result's `changes`
 /// - `--delete`: Remove files deleted on remote
-/// - `-e ssh`: Use SSH transport
+/// - `-e ssh`: Use SSH transport (an rsync daemon is addressed by URL)
+///
+/// Over SSH, a missing rsync (on either end) falls back to `sftp_sync`, as
+/// does `transport = "sftp"` from the start.
+///
+/// With `project`, only project directories that could hold sessions of a
+/// matching project are transferred (see `project_filter_rules`). The rest of
+/// the cache is left alone and the remote isn't marked as freshly synced.
 pub fn sync_remote(
     remote_name: &str,
     remote: &RemoteConfig,
     settings: &Settings,
+    project: Option<&str>,
 ) -> Result<SyncResult> {
     let cache_dir = get_remote_cache_dir(settings, remote_name)?;
 
@@ -170,50 +644,121 @@ pub fn sync_remote(
     fs::create_dir_all(&cache_dir)
         .with_context(|| format!("Failed to create cache dir: {}", cache_dir.display()))?;
 
-    let target = ssh_target(remote);
-    let remote_path = remote_projects_dir(remote);
+    let start = std::time::Instant::now();
+    let started_at = SystemTime::now();
 
-    // rsync source: user@host:~/.claude/projects/
-    // The trailing slash is important - it copies contents, not the directory itself
-    let source = format!("{}:{}/", target, remote_path);
-    let dest = format!("{}/", cache_dir.display());
+    let project_rules = project.map(project_filter_rules).unwrap_or_default();
+    let itemized = match remote.transport {
+        Transport::Sftp => None,
+        Transport::Ssh | Transport::Rsyncd => {
+            run_rsync(remote_name, remote, &cache_dir, "-azi", &project_rules)?
+        }
+    };
+    let changes = match itemized {
+        Some(itemized) => SyncChanges::from_drift(&parse_itemized_changes(&itemized)),
+        None => sftp_sync(remote_name, remote, &cache_dir, project)?,
+    };
+    let duration = start.elapsed();
 
-    let start = std::time::Instant::now();
+    // Stamp the start, so a schedule that re-runs every N minutes isn't
+    // pushed back by however long the transfer took.
+    if project.is_none() {
+        update_last_sync(&cache_dir, started_at)?;
+    }
+    stamp(&cache_dir.join(LAST_TRANSFER_FILE), SystemTime::now())?;
 
+    Ok(SyncResult {
+        remote_name: remote_name.to_string(),
+        duration,
+        changes,
+    })
+}
+
+/// Run rsync from the remote's projects dir into `cache_dir` with `flags`
+/// (`-azi` to sync, `-anci` to verify) and the excludes that keep cache
+/// markers and lock files out of it. Returns the itemized output, or `None`
+/// when an SSH remote can't be reached this way because rsync isn't
+/// installed here or there.
+fn run_rsync(
+    remote_name: &str,
+    remote: &RemoteConfig,
+    cache_dir: &Path,
+    flags: &str,
+    filter_rules: &[String],
+) -> Result<Option<String>> {
+    // rsync source: user@host:~/.claude/projects/ or rsync://host/module/
+    let source = rsync_source(remote_name, remote)?;
+    let dest = format!("{}/", cache_dir.display());
     let output = Command::new("rsync")
+        .args([flags, "--delete"])
+        .args(rsync_transport_args(remote)?)
         .args([
-            "-az",
-            "--delete",
-            "-e",
-            "ssh",
             "--exclude",
             "*.lock", // Don't sync lock files
             "--exclude",
             LAST_SYNC_FILE, // Protect local staleness marker from --delete
-            &source,
-            &dest,
+            "--exclude",
+            LAST_TRANSFER_FILE,
+            "--exclude",
+            LAST_ATTEMPT_FILE,
+            "--exclude",
+            SYNC_FAILURES_FILE,
         ])
-        .output()
-        .context("Failed to execute rsync")?;
-
-    let duration = start.elapsed();
+        .args(filter_rules)
+        .args([&source, &dest])
+        .output();
+    let output = match output {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && remote.transport.has_ssh() => {
+            return Ok(None);
+        }
+        output => output.context("Failed to execute rsync")?,
+    };
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
+        if remote.transport.has_ssh() && remote_rsync_missing(&stderr) {
+            return Ok(None);
+        }
         anyhow::bail!(
             "rsync failed for remote '{}': {}",
             remote_name,
             stderr.trim()
         );
     }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+}
 
-    // Update last sync timestamp
-    update_last_sync(&cache_dir)?;
+/// Whether rsync failed because the remote shell couldn't find rsync there
+/// (bash says "command not found", dash and busybox "not found").
+fn remote_rsync_missing(stderr: &str) -> bool {
+    stderr.contains("rsync: command not found") || stderr.contains("rsync: not found")
+}
 
-    Ok(SyncResult {
-        remote_name: remote_name.to_string(),
-        duration,
-    })
+/// rsync filter rules limiting a sync to project directories whose name
+/// could contain `project` (the `--project` filter). Directory names encode
+/// the session's full cwd with separators replaced, and the project name is
+/// a case-insensitive substring of it, so letters match either case and any
+/// other non-alphanumeric character matches anything. This can pull in a
+/// few extra directories but never misses one. Excluded directories are
+/// protected from `--delete`, so the rest of the cache stays as it was.
+fn project_filter_rules(project: &str) -> Vec<String> {
+    let pattern: String = project
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphabetic() {
+                format!("[{}{}]", c.to_ascii_lowercase(), c.to_ascii_uppercase())
+            } else if c.is_ascii_alphanumeric() {
+                c.to_string()
+            } else {
+                "?".to_string()
+            }
+        })
+        .collect();
+    vec![
+        format!("--include=/*{}*/", pattern),
+        format!("--include=/*{}*/**", pattern),
+        "--exclude=/*".to_string(),
+    ]
 }
 
 /// Result of a sync operation
@@ -221,6 +766,58 @@ pub fn sync_remote(
 pub struct SyncResult {
     pub remote_name: String,
     pub duration: Duration,
+    pub changes: SyncChanges,
+}
+
+/// Session files a sync added, updated, or deleted in the cache. Subagent
+/// transcripts and other files are left out of the counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncChanges {
+    pub new: usize,
+    pub updated: usize,
+    pub deleted: usize,
+}
+
+impl SyncChanges {
+    /// Count the session files among a sync's itemized changes (from the
+    /// cache's point of view: missing files were added, extra ones deleted).
+    fn from_drift(drift: &[Drift]) -> Self {
+        let mut changes = Self::default();
+        for d in drift {
+            let is_session = d.path.matches('/').count() == 1
+                && crate::claude_code::is_session_file_name(Path::new(&d.path));
+            if !is_session {
+                continue;
+            }
+            match d.kind {
+                DriftKind::Missing => changes.new += 1,
+                DriftKind::Changed => changes.updated += 1,
+                DriftKind::Extra => changes.deleted += 1,
+            }
+        }
+        changes
+    }
+
+    /// "4 new sessions, 7 updated, 1 deleted"; "no session changes" if
+    /// nothing changed.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.new > 0 {
+            let plural = if self.new == 1 { "" } else { "s" };
+            parts.push(format!("{} new session{}", self.new, plural));
+        }
+        if self.updated > 0 {
+            parts.push(format!("{} updated", self.updated));
+        }
+        if self.deleted > 0 {
+            parts.push(format!("{} deleted", self.deleted));
+        }
+        if parts.is_empty() {
+            "no session changes".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
 }
 
 /// Failure details for a remote sync attempt.
@@ -243,6 +840,263 @@ impl SyncSummary {
     }
 }
 
+// =============================================================================
+// SFTP Fallback
+// =============================================================================
+
+/// Sync without rsync: list the remote's files and sizes over SSH, copy the
+/// new and changed ones with one batched `sftp` run (`get -p` keeps their
+/// mtimes, as `rsync -a` does), and delete cached files the remote no longer
+/// has. Transcripts only ever grow, so comparing sizes finds the changes.
+fn sftp_sync(
+    remote_name: &str,
+    remote: &RemoteConfig,
+    cache_dir: &Path,
+    project: Option<&str>,
+) -> Result<SyncChanges> {
+    let drift = sftp_drift(remote_name, remote, cache_dir, project)?;
+    let fetch: Vec<&str> = drift
+        .iter()
+        .filter(|d| d.kind != DriftKind::Extra)
+        .map(|d| d.path.as_str())
+        .collect();
+    if !fetch.is_empty() {
+        sftp_fetch(remote_name, remote, cache_dir, &fetch)?;
+    }
+    for d in drift.iter().filter(|d| d.kind == DriftKind::Extra) {
+        let path = cache_dir.join(&d.path);
+        fs::remove_file(&path).with_context(|| format!("Failed to delete {}", path.display()))?;
+        // Like --delete, drop directories left empty
+        for dir in path.ancestors().skip(1) {
+            if dir == cache_dir || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(SyncChanges::from_drift(&drift))
+}
+
+/// How the cache differs from the remote by file size, limited to project
+/// directories `project` could match, like `project_filter_rules`.
+fn sftp_drift(
+    remote_name: &str,
+    remote: &RemoteConfig,
+    cache_dir: &Path,
+    project: Option<&str>,
+) -> Result<Vec<Drift>> {
+    let target = remote.require_ssh(remote_name, "sftp sync")?;
+    let script = format!(
+        "cd {} && find . -type f ! -name '*.lock' -exec wc -c {{}} +",
+        shell_path(remote_projects_dir(remote))
+    );
+    let output = Command::new("ssh")
+        .args([&target, &script])
+        .output()
+        .context("Failed to execute ssh")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Listing sessions on '{}' failed: {}",
+            remote_name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let wanted = |path: &str| {
+        is_synced_file(path)
+            && project.is_none_or(|project| {
+                path.split_once('/')
+                    .is_some_and(|(dir, _)| project_dir_matches(project, dir))
+            })
+    };
+    let mut remote_files = parse_file_sizes(&String::from_utf8_lossy(&output.stdout));
+    remote_files.retain(|path, _| wanted(path));
+    let mut cached = cached_file_sizes(cache_dir);
+    cached.retain(|path, _| wanted(path));
+    Ok(size_drift(&remote_files, &cached))
+}
+
+/// Copy `paths` (relative to the projects dir) from the remote into the
+/// cache in one `sftp` batch. A file deleted on the remote since it was
+/// listed is skipped (`-get`), and caught by the next sync.
+fn sftp_fetch(
+    remote_name: &str,
+    remote: &RemoteConfig,
+    cache_dir: &Path,
+    paths: &[&str],
+) -> Result<()> {
+    use std::fmt::Write as _;
+    use std::io::Write as _;
+
+    let target = remote.require_ssh(remote_name, "sftp sync")?;
+    let dir = sftp_path(remote_projects_dir(remote));
+    let mut batch = String::new();
+    for path in paths {
+        anyhow::ensure!(
+            is_plain_relative(path),
+            "Refusing to fetch {:?} from '{}' outside the cache",
+            path,
+            remote_name
+        );
+        let local = cache_dir.join(path);
+        if let Some(parent) = local.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let _ = writeln!(
+            batch,
+            "-get -p {} {}",
+            sftp_quote(&format!("{}/{}", dir, path)),
+            sftp_quote(&local.display().to_string())
+        );
+    }
+
+    let mut child = Command::new("sftp")
+        .args(["-q", "-b", "-", &target])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("Failed to execute sftp")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(batch.as_bytes())
+            .context("Failed to send commands to sftp")?;
+    }
+    let output = child.wait_with_output().context("Failed to execute sftp")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "sftp failed for remote '{}': {}",
+            remote_name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Whether sync mirrors the file at `path` (relative to the projects dir):
+/// everything but lock files and the cache's own markers, the same files
+/// `run_rsync` excludes.
+fn is_synced_file(path: &str) -> bool {
+    !path.ends_with(".lock")
+        && ![
+            LAST_SYNC_FILE,
+            LAST_TRANSFER_FILE,
+            LAST_ATTEMPT_FILE,
+            SYNC_FAILURES_FILE,
+        ]
+        .contains(&path)
+}
+
+/// Parse `wc -c` output for files found from `.` (`  1234 ./dir/file`) into
+/// relative path → size. `total` lines are skipped. The paths end up joined
+/// onto the cache dir, so anything that could point outside it (`..`, an
+/// absolute path) is dropped with a warning rather than trusted.
+fn parse_file_sizes(output: &str) -> BTreeMap<String, u64> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (size, path) = line.trim_start().split_once(char::is_whitespace)?;
+            let path = path.trim_start();
+            if path == "total" {
+                return None;
+            }
+            match path.strip_prefix("./").filter(|p| is_plain_relative(p)) {
+                Some(path) => Some((path.to_string(), size.parse().ok()?)),
+                None => {
+                    eprintln!("Warning: ignoring unexpected remote path {:?}", path);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// A non-empty relative path made only of ordinary names: no `..`, `.`,
+/// root, or drive prefix, so joining it stays inside the base dir.
+fn is_plain_relative(path: &str) -> bool {
+    let path = Path::new(path);
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+/// Relative path → size of every file in the cache.
+fn cached_file_sizes(cache_dir: &Path) -> BTreeMap<String, u64> {
+    walkdir::WalkDir::new(cache_dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(cache_dir).ok()?;
+            let path = relative.to_str()?.replace(std::path::MAIN_SEPARATOR, "/");
+            Some((path, entry.metadata().ok()?.len()))
+        })
+        .collect()
+}
+
+/// Drift of `cached` from `remote` (both relative path → size), by path.
+fn size_drift(remote: &BTreeMap<String, u64>, cached: &BTreeMap<String, u64>) -> Vec<Drift> {
+    let mut drift: Vec<Drift> = remote
+        .iter()
+        .filter_map(|(path, size)| {
+            let kind = match cached.get(path) {
+                None => DriftKind::Missing,
+                Some(cached_size) if cached_size != size => DriftKind::Changed,
+                Some(_) => return None,
+            };
+            Some(Drift {
+                kind,
+                path: path.clone(),
+            })
+        })
+        .chain(
+            cached
+                .keys()
+                .filter(|path| !remote.contains_key(*path))
+                .map(|path| Drift {
+                    kind: DriftKind::Extra,
+                    path: path.clone(),
+                }),
+        )
+        .collect();
+    drift.sort_by(|a, b| a.path.cmp(&b.path));
+    drift
+}
+
+/// Whether a project directory name could hold sessions of `project`: the
+/// glob `project_filter_rules` gives rsync, as a plain match.
+fn project_dir_matches(project: &str, dir: &str) -> bool {
+    let pattern: Vec<char> = project.chars().collect();
+    let name: Vec<char> = dir.chars().collect();
+    pattern.is_empty()
+        || name.windows(pattern.len()).any(|window| {
+            window.iter().zip(&pattern).all(|(&c, &p)| {
+                if p.is_ascii_alphanumeric() {
+                    c.eq_ignore_ascii_case(&p)
+                } else {
+                    true
+                }
+            })
+        })
+}
+
+/// A remote path as sftp takes it: sftp starts in the home directory, and
+/// doesn't expand `~` everywhere.
+fn sftp_path(path: &str) -> String {
+    let path = path.trim_end_matches('/');
+    match path.strip_prefix("~/") {
+        Some(rest) => rest.to_string(),
+        None if path == "~" => ".".to_string(),
+        None => path.to_string(),
+    }
+}
+
+/// Quote an argument for an sftp batch file.
+fn sftp_quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
 // =============================================================================
 // Staleness Tracking
 // =============================================================================
@@ -251,6 +1105,19 @@ const LAST_SYNC_FILE: &str = ".last_sync";
 
 /// Check if a remote's cache is stale (older than threshold)
 pub fn is_stale(remote_name: &str, settings: &Settings) -> Result<bool> {
+    synced_longer_ago_than(
+        remote_name,
+        settings,
+        Duration::from_secs(settings.stale_threshold),
+    )
+}
+
+/// Whether a remote was last synced more than `max_age` ago (or never).
+fn synced_longer_ago_than(
+    remote_name: &str,
+    settings: &Settings,
+    max_age: Duration,
+) -> Result<bool> {
     let cache_dir = get_remote_cache_dir(settings, remote_name)?;
     let last_sync_path = cache_dir.join(LAST_SYNC_FILE);
 
@@ -260,9 +1127,9 @@ pub fn is_stale(remote_name: &str, settings: &Settings) -> Result<bool> {
 
     let last_sync = get_last_sync_time(&last_sync_path)?;
     let now = SystemTime::now();
-    let age = now.duration_since(last_sync).unwrap_or(Duration::MAX);
+    let age = now.duration_since(last_sync).unwrap_or(Duration::ZERO);
 
-    Ok(age.as_secs() > settings.stale_threshold)
+    Ok(age > max_age)
 }
 
 /// Read the timestamp from .last_sync file
@@ -276,35 +1143,215 @@ fn get_last_sync_time(path: &PathBuf) -> Result<SystemTime> {
 }
 
 /// Update the .last_sync timestamp file
-fn update_last_sync(cache_dir: &Path) -> Result<()> {
-    let last_sync_path = cache_dir.join(LAST_SYNC_FILE);
-    let now = SystemTime::now()
+fn update_last_sync(cache_dir: &Path, synced_at: SystemTime) -> Result<()> {
+    stamp(&cache_dir.join(LAST_SYNC_FILE), synced_at)
+}
+
+/// When any sync of a remote last started, whether or not it succeeded.
+/// Stamped before rsync runs, so a run started meanwhile skips it too.
+const LAST_ATTEMPT_FILE: &str = ".last_attempt";
+
+/// Whether a sync of the remote started less than `sync_cooldown` ago, so
+/// auto-sync leaves it alone: it just synced, or just failed to.
+fn in_cooldown(remote_name: &str, settings: &Settings) -> bool {
+    let cooldown = Duration::from_secs(settings.sync_cooldown);
+    get_remote_cache_dir(settings, remote_name)
+        .and_then(|dir| get_last_sync_time(&dir.join(LAST_ATTEMPT_FILE)))
+        .is_ok_and(|attempt| attempt.elapsed().unwrap_or_default() < cooldown)
+}
+
+fn record_sync_attempt(settings: &Settings, remote_name: &str) -> Result<()> {
+    let cache_dir = get_remote_cache_dir(settings, remote_name)?;
+    fs::create_dir_all(&cache_dir)
+        .with_context(|| format!("Failed to create cache dir: {}", cache_dir.display()))?;
+    stamp(&cache_dir.join(LAST_ATTEMPT_FILE), SystemTime::now())
+}
+
+fn stamp(path: &Path, time: SystemTime) -> Result<()> {
+    let secs = time
         .duration_since(UNIX_EPOCH)
-        .unwrap()
+        .unwrap_or_default()
         .as_secs();
-    fs::write(&last_sync_path, now.to_string()).context("Failed to update .last_sync file")?;
+    fs::write(path, secs.to_string())
+        .with_context(|| format!("Failed to update {}", path.display()))
+}
+
+// =============================================================================
+// Clock Skew
+// =============================================================================
+
+/// When the last sync of any kind (including `--project` ones, which leave
+/// `.last_sync` alone) finished. Nothing in the cache was copied later.
+const LAST_TRANSFER_FILE: &str = ".last_transfer";
+
+/// How far a remote's newest session may run past its last transfer before
+/// the remote's clock counts as ahead. Absorbs coarse stamps and small drift.
+const SKEW_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// When data was last copied from a remote (in this machine's time), for
+/// judging its clock. Caches from before `.last_transfer` existed fall back
+/// to `.last_sync`.
+pub fn last_transfer_time(settings: &Settings, remote_name: &str) -> Option<SystemTime> {
+    let cache_dir = get_remote_cache_dir(settings, remote_name).ok()?;
+    get_last_sync_time(&cache_dir.join(LAST_TRANSFER_FILE))
+        .or_else(|_| get_last_sync_time(&cache_dir.join(LAST_SYNC_FILE)))
+        .ok()
+}
+
+/// How far ahead of this machine a remote's clock runs, judged by its newest
+/// session time (stamped by the remote's clock) against the last transfer
+/// (stamped by ours): no copied file can have been written after it. `None`
+/// within `SKEW_TOLERANCE`. A clock running behind just makes sessions look
+/// older and can't be observed this way.
+pub fn observed_clock_skew(last_transfer: SystemTime, newest: SystemTime) -> Option<Duration> {
+    newest
+        .duration_since(last_transfer)
+        .ok()
+        .filter(|skew| *skew > SKEW_TOLERANCE)
+}
+
+/// Consecutive failed syncs, kept next to `.last_sync` until one succeeds.
+const SYNC_FAILURES_FILE: &str = ".sync_failures";
+
+/// A remote whose latest sync attempts failed, so its sessions may be old.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncWarning {
+    pub remote_name: String,
+    /// Failed attempts since the last successful sync.
+    pub failures: u32,
+    /// When the cached data was last synced (`None`: never).
+    pub last_sync: Option<SystemTime>,
+}
+
+/// Count a failed sync, or clear the count after a successful one.
+fn record_sync_outcome(cache_dir: &Path, succeeded: bool) -> Result<()> {
+    let path = cache_dir.join(SYNC_FAILURES_FILE);
+    if succeeded {
+        if path.exists() {
+            fs::remove_file(&path).context("Failed to clear sync failure count")?;
+        }
+        return Ok(());
+    }
+    let failures = read_sync_failures(cache_dir) + 1;
+    fs::create_dir_all(cache_dir)
+        .with_context(|| format!("Failed to create cache dir: {}", cache_dir.display()))?;
+    fs::write(&path, failures.to_string()).context("Failed to record sync failure")?;
     Ok(())
 }
 
+fn read_sync_failures(cache_dir: &Path) -> u32 {
+    fs::read_to_string(cache_dir.join(SYNC_FAILURES_FILE))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Remotes passing `remote_filter` whose most recent syncs failed, sorted by
+/// name. Read from disk, so failures from earlier runs (e.g. a cron
+/// `--sync-only`) are reported too.
+pub fn sync_warnings(config: &Config, remote_filter: Option<&str>) -> Vec<SyncWarning> {
+    let mut warnings: Vec<SyncWarning> = config
+        .remotes
+        .keys()
+        .filter(|name| config.source_matches(remote_filter, name))
+        .filter_map(|name| {
+            let cache_dir = get_remote_cache_dir(&config.settings, name).ok()?;
+            let failures = read_sync_failures(&cache_dir);
+            (failures > 0).then(|| SyncWarning {
+                remote_name: name.clone(),
+                failures,
+                last_sync: get_last_sync_time(&cache_dir.join(LAST_SYNC_FILE)).ok(),
+            })
+        })
+        .collect();
+    warnings.sort_by(|a, b| a.remote_name.cmp(&b.remote_name));
+    warnings
+}
+
+/// Which remotes a sync pass covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncPolicy {
+    /// Every remote (`--sync`).
+    All,
+    /// Remotes older than `stale_threshold` and not attempted within
+    /// `sync_cooldown` (auto-sync).
+    Stale,
+    /// Remotes whose `sync_interval` has elapsed, plus those without one
+    /// (`--sync-only`).
+    Scheduled,
+    /// Remotes whose most recent syncs failed (`--retry-failed`).
+    Failed,
+}
+
+impl SyncPolicy {
+    fn is_due(self, name: &str, remote: &RemoteConfig, settings: &Settings) -> bool {
+        match self {
+            SyncPolicy::All => true,
+            SyncPolicy::Stale => {
+                is_stale(name, settings).unwrap_or(true) && !in_cooldown(name, settings)
+            }
+            SyncPolicy::Scheduled => remote.sync_interval.is_none_or(|interval| {
+                synced_longer_ago_than(name, settings, interval).unwrap_or(true)
+            }),
+            SyncPolicy::Failed => {
+                get_remote_cache_dir(settings, name).is_ok_and(|dir| read_sync_failures(&dir) > 0)
+            }
+        }
+    }
+}
+
 /// Sync remotes, optionally checking staleness first. Individual rsync
 /// invocations run concurrently — each blocks on a separate SSH connection,
-/// so wall-clock is max(rsync) not sum(rsync).
-fn sync_remotes(config: &Config, check_staleness: bool) -> Result<SyncSummary> {
-    use rayon::prelude::*;
-
+/// so wall-clock is max(rsync) not sum(rsync) — but at most
+/// `max_concurrent_syncs` at a time, so a slow uplink isn't split ten ways.
+fn sync_remotes(
+    config: &Config,
+    policy: SyncPolicy,
+    group: Option<&str>,
+    project: Option<&str>,
+) -> Result<SyncSummary> {
+    if let Some(group) = group {
+        config.group_members(group)?;
+    }
     let targets: Vec<(&String, &RemoteConfig)> = config
         .remotes
         .iter()
-        .filter(|(name, _)| !check_staleness || is_stale(name, &config.settings).unwrap_or(true))
+        .filter(|(_, remote)| group.is_none_or(|g| remote.group.as_deref() == Some(g)))
+        .filter(|(name, remote)| policy.is_due(name, remote, &config.settings))
         .collect();
+    for (name, _) in &targets {
+        if let Err(e) = record_sync_attempt(&config.settings, name) {
+            eprintln!("Warning: {:#}", e);
+        }
+    }
 
-    let outcomes: Vec<_> = targets
-        .into_par_iter()
-        .map(|(name, remote)| (name, sync_remote(name, remote, &config.settings)))
-        .collect();
+    let slots = config
+        .settings
+        .max_concurrent_syncs
+        .filter(|&n| n > 0)
+        .unwrap_or_else(rayon::current_num_threads)
+        .min(targets.len());
+    let progress = progress::SlotProgress::new("synced", "remotes", targets.len(), slots);
+    let outcomes = progress::run(&progress, || {
+        run_in_slots(&targets, slots, |slot, &(name, remote)| {
+            progress.start(slot, name);
+            let outcome = sync_remote(name, remote, &config.settings, project);
+            progress.finish(slot);
+            (name, outcome)
+        })
+    });
 
     let mut summary = SyncSummary::default();
     for (name, outcome) in outcomes {
+        // A project-only sync leaves the other projects as old as they were,
+        // so it can't clear an earlier failure.
+        let record = outcome.is_err() || project.is_none();
+        if record
+            && let Err(e) = get_remote_cache_dir(&config.settings, name)
+                .and_then(|dir| record_sync_outcome(&dir, outcome.is_ok()))
+        {
+            eprintln!("Warning: {:#}", e);
+        }
         match outcome {
             Ok(result) => summary.successes.push(result),
             Err(e) => {
@@ -318,19 +1365,475 @@ fn sync_remotes(config: &Config, check_staleness: bool) -> Result<SyncSummary> {
         }
     }
 
+    // One at a time: each merge reads and may rewrite the local state file.
+    if config.settings.sync_state && project.is_none() {
+        for result in &summary.successes {
+            // An rsync daemon has no shell to read or write the file with
+            let Some(remote) = config
+                .remotes
+                .get(&result.remote_name)
+                .filter(|remote| remote.transport.has_ssh())
+            else {
+                continue;
+            };
+            if let Err(e) = sync_user_state(remote) {
+                eprintln!(
+                    "Warning: Failed to sync fork decisions with '{}': {:#}",
+                    result.remote_name, e
+                );
+            }
+        }
+    }
+
     Ok(summary)
 }
 
-/// Sync remotes if they are stale
+/// Merge fork decisions with the copy in the remote's state dir, both ways:
+/// local state gains the remote's newer entries, and the remote's file is
+/// replaced if it is missing any of ours.
+fn sync_user_state(remote: &RemoteConfig) -> Result<()> {
+    use std::io::Write;
+
+    let target = ssh_target(remote);
+    let dir = "~/.local/state/cc-sessions";
+    let path = shell_path(&format!("{}/{}", dir, crate::state::FORK_OVERRIDES_FILE));
+    let output = Command::new("ssh")
+        .args([&target, &format!("cat {} 2>/dev/null || true", path)])
+        .output()
+        .context("Failed to execute ssh")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "ssh failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let existing = Some(text.as_ref()).filter(|t| !t.trim().is_empty());
+    let Some(merged) = crate::state::merge_fork_overrides(existing)? else {
+        return Ok(());
+    };
+
+    let tmp = shell_path(&format!(
+        "{}/{}.tmp",
+        dir,
+        crate::state::FORK_OVERRIDES_FILE
+    ));
+    let mut child = Command::new("ssh")
+        .args([
+            &target,
+            &format!(
+                "mkdir -p {} && cat > {} && mv {} {}",
+                shell_path(dir),
+                tmp,
+                tmp,
+                path
+            ),
+        ])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("Failed to execute ssh")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(merged.as_bytes())
+            .context("Failed to send state to ssh")?;
+    }
+    let output = child.wait_with_output().context("Failed to execute ssh")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "ssh failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Apply `work` to every item using at most `slots` threads; each takes the
+/// next queued item when it finishes one. `work` gets its slot index for
+/// progress reporting. Results are in item order.
+fn run_in_slots<T: Sync, R: Send>(
+    items: &[T],
+    slots: usize,
+    work: impl Fn(usize, &T) -> R + Sync,
+) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<R>>> = items.iter().map(|_| Mutex::new(None)).collect();
+    std::thread::scope(|scope| {
+        for slot in 0..slots.max(1) {
+            let (next, results, work) = (&next, &results, &work);
+            scope.spawn(move || {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else { break };
+                    *results[i].lock().unwrap() = Some(work(slot, item));
+                }
+            });
+        }
+    });
+    results
+        .into_iter()
+        .map(|r| r.into_inner().unwrap().expect("every item is processed"))
+        .collect()
+}
+
+/// Sync remotes if they are stale, optionally only those in `group`
 ///
 /// Returns the list of remotes that were synced
-pub fn sync_if_stale(config: &Config) -> Result<SyncSummary> {
-    sync_remotes(config, true)
+pub fn sync_if_stale(config: &Config, group: Option<&str>) -> Result<SyncSummary> {
+    sync_remotes(config, SyncPolicy::Stale, group, None)
+}
+
+/// Sync all configured remotes (or all in `group`) regardless of staleness.
+/// With `project`, only that project's directories are transferred.
+pub fn sync_all(
+    config: &Config,
+    group: Option<&str>,
+    project: Option<&str>,
+) -> Result<SyncSummary> {
+    sync_remotes(config, SyncPolicy::All, group, project)
+}
+
+/// Sync remotes that are due by their own `sync_interval`; remotes without
+/// one always sync. Lets a frequent cron entry drive per-remote cadences.
+pub fn sync_scheduled(config: &Config, group: Option<&str>) -> Result<SyncSummary> {
+    sync_remotes(config, SyncPolicy::Scheduled, group, None)
+}
+
+/// Sync only the remotes (optionally in `group`) whose last syncs failed,
+/// the ones flagged by `sync_warnings`.
+pub fn sync_failed(config: &Config, group: Option<&str>) -> Result<SyncSummary> {
+    sync_remotes(config, SyncPolicy::Failed, group, None)
+}
+
+// =============================================================================
+// Cache Verification
+// =============================================================================
+
+/// How a cached file differs from its remote original.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriftKind {
+    /// Same path, different contents.
+    Changed,
+    /// On the remote but not in the cache.
+    Missing,
+    /// In the cache but deleted on the remote.
+    Extra,
+}
+
+impl DriftKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            DriftKind::Changed => "changed",
+            DriftKind::Missing => "missing",
+            DriftKind::Extra => "extra",
+        }
+    }
+}
+
+/// One cached file that doesn't match the remote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drift {
+    pub kind: DriftKind,
+    /// Path relative to the projects dir.
+    pub path: String,
+}
+
+/// What `verify_remote` found.
+#[derive(Debug)]
+pub struct Verification {
+    pub drift: Vec<Drift>,
+    /// Whether contents were compared by checksum (rsync), not by size (sftp).
+    pub by_checksum: bool,
+}
+
+/// Compare a remote's cache with the remote by content checksum.
+///
+/// Runs the sync's rsync invocation with `-n` (dry run), `-c` (compare
+/// checksums, not size+mtime), and `-i` (itemize), so nothing is copied and
+/// the answer reflects exactly what the next sync would change. Remotes
+/// synced with sftp are compared by size instead, as their sync does.
+pub fn verify_remote(
+    remote_name: &str,
+    remote: &RemoteConfig,
+    settings: &Settings,
+) -> Result<Verification> {
+    let cache_dir = get_remote_cache_dir(settings, remote_name)?;
+    if !cache_dir.exists() {
+        anyhow::bail!(
+            "No cache for remote '{}' yet (run with --sync first)",
+            remote_name
+        );
+    }
+
+    let itemized = match remote.transport {
+        Transport::Sftp => None,
+        Transport::Ssh | Transport::Rsyncd => {
+            run_rsync(remote_name, remote, &cache_dir, "-anci", &[])?
+        }
+    };
+    Ok(match itemized {
+        Some(itemized) => Verification {
+            drift: parse_itemized_changes(&itemized),
+            by_checksum: true,
+        },
+        None => Verification {
+            drift: sftp_drift(remote_name, remote, &cache_dir, None)?,
+            by_checksum: false,
+        },
+    })
+}
+
+/// Parse `rsync -i` output (`YXcstpoguax path`) into file-level drift.
+/// Directory entries and attribute-only updates (e.g. a touched mtime with
+/// identical contents) are not drift.
+fn parse_itemized_changes(output: &str) -> Vec<Drift> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (item, path) = line.split_once(' ')?;
+            let path = path.trim_start().to_string();
+            if item == "*deleting" {
+                return (!path.ends_with('/')).then_some(Drift {
+                    kind: DriftKind::Extra,
+                    path,
+                });
+            }
+            let bytes = item.as_bytes();
+            if bytes.len() < 4 || bytes[1] != b'f' {
+                return None;
+            }
+            let kind = if item[2..].starts_with('+') {
+                DriftKind::Missing
+            } else if bytes[2] == b'c' || bytes[3] == b's' {
+                DriftKind::Changed
+            } else {
+                return None;
+            };
+            Some(Drift { kind, path })
+        })
+        .collect()
+}
+
+// =============================================================================
+// Cache Maintenance
+// =============================================================================
+
+/// A remote cache directory that no configured remote owns any more.
+#[derive(Debug)]
+pub struct OrphanedCache {
+    pub name: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+/// Find cache directories under `settings.cache_dir` for remotes that have
+/// been removed from (or renamed in) the config. Only directories carrying a
+/// `.last_sync` marker count, so anything else kept there (by the user or
+/// another tool) is never taken for a cache. Includes leftovers holding
+/// nothing but the marker.
+pub fn find_orphaned_caches(config: &Config) -> Result<Vec<OrphanedCache>> {
+    let cache_base = expand_path(&config.settings.cache_dir)?;
+    let Ok(entries) = fs::read_dir(&cache_base) else {
+        return Ok(Vec::new()); // No cache yet
+    };
+
+    let mut orphans: Vec<OrphanedCache> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|e| e.path().join(LAST_SYNC_FILE).is_file())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            (!config.remotes.contains_key(&name)).then(|| OrphanedCache {
+                bytes: dir_size(&e.path()),
+                path: e.path(),
+                name,
+            })
+        })
+        .collect();
+    orphans.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(orphans)
+}
+
+/// Total size of regular files under `path` (best-effort).
+pub fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+// =============================================================================
+// Disk Usage
+// =============================================================================
+
+/// Space taken by a projects directory, largest entries first.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DiskUsage {
+    pub total: u64,
+    /// Project directories (including subagent transcripts) and their sizes.
+    pub projects: Vec<(String, u64)>,
+    /// Session transcripts as `project/<id>.jsonl`.
+    pub sessions: Vec<(String, u64)>,
+}
+
+/// Measure a remote's projects directory with one `du` over SSH.
+pub fn remote_disk_usage(name: &str, remote: &RemoteConfig) -> Result<DiskUsage> {
+    let target = remote.require_ssh(name, "remote du")?;
+    let command = format!("cd {} && du -ak .", shell_path(remote_projects_dir(remote)));
+    let output = Command::new("ssh")
+        .args(["-o", "BatchMode=yes", &target, &command])
+        .output()
+        .context("Failed to execute ssh")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("du failed on {}: {}", remote.host, stderr.trim());
+    }
+    Ok(parse_du_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `du -ak .` run inside a projects directory: `.` is the total,
+/// `./<project>` the per-project totals, `./<project>/<id>.jsonl` sessions.
+pub fn parse_du_output(output: &str) -> DiskUsage {
+    let mut usage = DiskUsage::default();
+    for line in output.lines() {
+        let Some((kb, path)) = line.split_once('\t') else {
+            continue;
+        };
+        let Ok(kb) = kb.trim().parse::<u64>() else {
+            continue;
+        };
+        let bytes = kb * 1024;
+        if path == "." {
+            usage.total = bytes;
+            continue;
+        }
+        let Some(path) = path.strip_prefix("./") else {
+            continue;
+        };
+        match path.split('/').collect::<Vec<_>>()[..] {
+            [project] if !project.starts_with('.') => {
+                usage.projects.push((project.to_string(), bytes));
+            }
+            [_, file] if file.ends_with(".jsonl") => {
+                usage.sessions.push((path.to_string(), bytes));
+            }
+            _ => {}
+        }
+    }
+    for entries in [&mut usage.projects, &mut usage.sessions] {
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    }
+    usage
+}
+
+// =============================================================================
+// Health Check
+// =============================================================================
+
+/// What a remote needs for sync and resume to work.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RemoteHealth {
+    pub transport: Transport,
+    /// Why connecting failed; the other checks are meaningless when set.
+    pub ssh_error: Option<String>,
+    pub rsync: bool,
+    pub projects_dir: bool,
+    /// `None` when it can't be checked (rsync daemon)
+    pub claude: Option<bool>,
+}
+
+impl RemoteHealth {
+    /// Missing rsync over SSH isn't a failure: sync falls back to sftp.
+    pub fn is_healthy(&self) -> bool {
+        self.ssh_error.is_none() && self.projects_dir && self.claude != Some(false)
+    }
 }
 
-/// Sync all configured remotes regardless of staleness
-pub fn sync_all(config: &Config) -> Result<SyncSummary> {
-    sync_remotes(config, false)
+/// Probe a remote with one non-interactive SSH session. `rsync` and
+/// `claude` are looked up in the same environment sync and resume use.
+/// An rsync daemon is asked to list the projects dir instead.
+pub fn check_remote(name: &str, remote: &RemoteConfig) -> RemoteHealth {
+    if remote.transport == Transport::Rsyncd {
+        return check_rsync_daemon(name, remote);
+    }
+    let script = format!(
+        "echo connected; \
+         command -v rsync >/dev/null 2>&1 && echo rsync; \
+         [ -d {} ] && echo projects; \
+         command -v claude >/dev/null 2>&1 && echo claude; \
+         true",
+        shell_path(remote_projects_dir(remote))
+    );
+    let output = Command::new("ssh")
+        .args([
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ConnectTimeout=10",
+            &ssh_target(remote),
+            &script,
+        ])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => RemoteHealth {
+            transport: remote.transport,
+            ..parse_health_output(&String::from_utf8_lossy(&output.stdout))
+        },
+        Ok(output) => RemoteHealth {
+            ssh_error: Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+            ..RemoteHealth::default()
+        },
+        Err(e) => RemoteHealth {
+            ssh_error: Some(format!("Failed to execute ssh: {}", e)),
+            ..RemoteHealth::default()
+        },
+    }
+}
+
+/// Parse the probe's output: one word per check that passed. Anything
+/// printed by the remote's shell startup files is ignored.
+fn parse_health_output(output: &str) -> RemoteHealth {
+    let passed = |check: &str| output.lines().any(|l| l.trim() == check);
+    RemoteHealth {
+        ssh_error: (!passed("connected")).then(|| "no response from remote shell".to_string()),
+        rsync: passed("rsync"),
+        projects_dir: passed("projects"),
+        claude: Some(passed("claude")),
+        ..RemoteHealth::default()
+    }
+}
+
+fn check_rsync_daemon(name: &str, remote: &RemoteConfig) -> RemoteHealth {
+    let failed = |error: String| RemoteHealth {
+        transport: Transport::Rsyncd,
+        ssh_error: Some(error),
+        ..RemoteHealth::default()
+    };
+    let (source, transport_args) =
+        match rsync_source(name, remote).and_then(|s| Ok((s, rsync_transport_args(remote)?))) {
+            Ok(args) => args,
+            Err(e) => return failed(format!("{:#}", e)),
+        };
+    let output = Command::new("rsync")
+        .args(["--list-only", "--contimeout=10"])
+        .args(transport_args)
+        .arg(&source)
+        .output();
+    match output {
+        Ok(output) if output.status.success() => RemoteHealth {
+            transport: Transport::Rsyncd,
+            ssh_error: None,
+            rsync: true,
+            projects_dir: true,
+            claude: None,
+        },
+        Ok(output) => failed(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => failed(format!("Failed to execute rsync: {}", e)),
+    }
 }
 
 // =============================================================================
@@ -341,12 +1844,56 @@ pub fn sync_all(config: &Config) -> Result<SyncSummary> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn clock_skew_is_what_sessions_run_past_the_last_transfer() {
+        let transfer = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let hour = Duration::from_secs(3600);
+        assert_eq!(observed_clock_skew(transfer, transfer + hour), Some(hour));
+        assert_eq!(
+            observed_clock_skew(transfer, transfer + SKEW_TOLERANCE),
+            None
+        );
+        assert_eq!(observed_clock_skew(transfer, transfer - hour), None);
+    }
+
+    #[test]
+    fn path_map_translates_the_longest_matching_directory() {
+        let remote: RemoteConfig = toml::from_str(
+            r#"
+            host = "devbox"
+            path_map = { "/home/ian/work" = "/src", "/home/ian/work/api" = "/checkouts/api" }
+            "#,
+        )
+        .unwrap();
+        let local = |p: &str| remote.local_path(p);
+        assert_eq!(local("/home/ian/work"), Some(PathBuf::from("/src")));
+        assert_eq!(
+            local("/home/ian/work/ui/x"),
+            Some(PathBuf::from("/src/ui/x"))
+        );
+        assert_eq!(
+            local("/home/ian/work/api/v2"),
+            Some(PathBuf::from("/checkouts/api/v2"))
+        );
+        assert_eq!(local("/home/ian/workshop"), None);
+        assert_eq!(local("/tmp"), None);
+    }
+
     #[test]
     fn ssh_target_with_user() {
         let remote = RemoteConfig {
             host: "192.168.1.100".to_string(),
             user: Some("ec2-user".to_string()),
             projects_dir: None,
+            label: None,
+            color: None,
+            group: None,
+            sync_interval: None,
+            path_map: BTreeMap::new(),
+            transport: Transport::Ssh,
+            module: None,
+            password_file: None,
+            resume_command: None,
         };
         assert_eq!(ssh_target(&remote), "ec2-user@192.168.1.100");
     }
@@ -357,16 +1904,69 @@ mod tests {
             host: "devbox".to_string(),
             user: None,
             projects_dir: None,
+            label: None,
+            color: None,
+            group: None,
+            sync_interval: None,
+            path_map: BTreeMap::new(),
+            transport: Transport::Ssh,
+            module: None,
+            password_file: None,
+            resume_command: None,
         };
         assert_eq!(ssh_target(&remote), "devbox");
     }
 
+    #[test]
+    fn rsync_source_for_each_transport() {
+        let parse = |toml: &str| -> RemoteConfig { toml::from_str(toml).unwrap() };
+        let ssh = parse("host = \"devbox\"\nuser = \"me\"");
+        assert_eq!(
+            rsync_source("devbox", &ssh).unwrap(),
+            "me@devbox:~/.claude/projects/"
+        );
+        assert!(ssh.require_ssh("devbox", "follow").is_ok());
+
+        let daemon = parse("host = \"build1:8730\"\ntransport = \"rsyncd\"\nmodule = \"claude\"");
+        assert_eq!(
+            rsync_source("build1", &daemon).unwrap(),
+            "rsync://build1:8730/claude/"
+        );
+        let nested = RemoteConfig {
+            projects_dir: Some("/projects/".to_string()),
+            ..daemon.clone()
+        };
+        assert_eq!(
+            rsync_source("build1", &nested).unwrap(),
+            "rsync://build1:8730/claude/projects/"
+        );
+        let err = daemon.require_ssh("build1", "follow").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'build1' is an rsync daemon remote; follow needs SSH"
+        );
+        let no_module = RemoteConfig {
+            module: None,
+            ..daemon
+        };
+        assert!(rsync_source("build1", &no_module).is_err());
+    }
+
     #[test]
     fn remote_projects_dir_default() {
         let remote = RemoteConfig {
             host: "test".to_string(),
             user: None,
             projects_dir: None,
+            label: None,
+            color: None,
+            group: None,
+            sync_interval: None,
+            path_map: BTreeMap::new(),
+            transport: Transport::Ssh,
+            module: None,
+            password_file: None,
+            resume_command: None,
         };
         assert_eq!(remote_projects_dir(&remote), "~/.claude/projects");
     }
@@ -377,11 +1977,366 @@ mod tests {
             host: "test".to_string(),
             user: None,
             projects_dir: Some("/home/custom/.claude/projects".to_string()),
+            label: None,
+            color: None,
+            group: None,
+            sync_interval: None,
+            path_map: BTreeMap::new(),
+            transport: Transport::Ssh,
+            module: None,
+            password_file: None,
+            resume_command: None,
         };
         assert_eq!(
             remote_projects_dir(&remote),
             "/home/custom/.claude/projects"
         );
+
+        let settings = Settings {
+            cache_dir: "/cache".to_string(),
+            ..Settings::default()
+        };
+        let cached = Path::new("/cache/test/-src-app/abc.jsonl");
+        assert_eq!(
+            remote_file_path(&settings, "test", &remote, cached).unwrap(),
+            "/home/custom/.claude/projects/-src-app/abc.jsonl"
+        );
+        assert!(remote_file_path(&settings, "other", &remote, cached).is_err());
+    }
+
+    #[test]
+    fn find_orphaned_caches_skips_configured_remotes_and_unmarked_dirs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = tmp.path();
+        fs::create_dir_all(base.join("devbox/-home-arthur-grail")).unwrap();
+        fs::write(base.join("devbox/-home-arthur-grail/a.jsonl"), "{}").unwrap();
+        fs::create_dir_all(base.join("oldbox")).unwrap();
+        fs::write(base.join("oldbox").join(LAST_SYNC_FILE), "12345").unwrap();
+        // Not a sync cache: no marker
+        fs::create_dir_all(base.join("notes")).unwrap();
+        fs::write(base.join("notes/todo.txt"), "keep me").unwrap();
+
+        let config: Config = toml::from_str(&format!(
+            "[remotes.devbox]\nhost = \"devbox\"\n\n[settings]\ncache_dir = \"{}\"\n",
+            base.display()
+        ))
+        .unwrap();
+        let orphans = find_orphaned_caches(&config).unwrap();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].name, "oldbox");
+        assert_eq!(orphans[0].bytes, 5);
+    }
+
+    #[test]
+    fn sync_changes_count_session_files_only() {
+        let output = "\
+cd+++++++++ -home-arthur-new/
+>f+++++++++ -home-arthur-new/0b8f8a4e-1c2d-4e5f-8a9b-0c1d2e3f4a5b.jsonl
+>f+++++++++ -home-arthur-new/0b8f8a4e-1c2d-4e5f-8a9b-0c1d2e3f4a5b/subagents/agent-1a2b.jsonl
+>f.st...... -home-arthur-grail/1d2e3f4a-5b6c-4d7e-8f9a-0b1c2d3e4f5a.jsonl
+>f.st...... -home-arthur-grail/2e3f4a5b-6c7d-4e8f-9a0b-1c2d3e4f5a6b.jsonl
+*deleting   -home-arthur-old/3f4a5b6c-7d8e-4f9a-0b1c-2d3e4f5a6b7c.jsonl
+*deleting   -home-arthur-old/
+";
+        let changes = SyncChanges::from_drift(&parse_itemized_changes(output));
+        assert_eq!(
+            changes,
+            SyncChanges {
+                new: 1,
+                updated: 2,
+                deleted: 1
+            }
+        );
+        assert_eq!(changes.describe(), "1 new session, 2 updated, 1 deleted");
+        assert_eq!(SyncChanges::default().describe(), "no session changes");
+    }
+
+    #[test]
+    fn parse_itemized_changes_reports_file_drift_only() {
+        let output = "\
+cd+++++++++ -home-arthur-new/
+>f+++++++++ -home-arthur-new/aaaa.jsonl
+>fcs....... -home-arthur-grail/bbbb.jsonl
+>fc.t...... -home-arthur-grail/cccc.jsonl
+.f..t...... -home-arthur-grail/dddd.jsonl
+*deleting   -home-arthur-grail/eeee.jsonl
+*deleting   -home-arthur-old/
+";
+        let drift = parse_itemized_changes(output);
+        let summary: Vec<(DriftKind, &str)> =
+            drift.iter().map(|d| (d.kind, d.path.as_str())).collect();
+        assert_eq!(
+            summary,
+            [
+                (DriftKind::Missing, "-home-arthur-new/aaaa.jsonl"),
+                (DriftKind::Changed, "-home-arthur-grail/bbbb.jsonl"),
+                (DriftKind::Changed, "-home-arthur-grail/cccc.jsonl"),
+                (DriftKind::Extra, "-home-arthur-grail/eeee.jsonl"),
+            ]
+        );
+    }
+
+    #[test]
+    fn groups_select_their_members() {
+        let config: Config = toml::from_str(
+            r#"
+[remotes.devbox]
+host = "devbox"
+group = "work"
+
+[remotes.buildbox]
+host = "buildbox"
+group = "work"
+
+[remotes.homelab]
+host = "homelab"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.group_members("work").unwrap(),
+            ["buildbox", "devbox"]
+        );
+        assert!(config.group_members("play").is_err());
+        assert!(config.check_remote_filter(Some("@play")).is_err());
+        assert!(config.check_remote_filter(Some("homelab")).is_ok());
+
+        assert!(config.source_matches(Some("@work"), "devbox"));
+        assert!(!config.source_matches(Some("@work"), "homelab"));
+        assert!(!config.source_matches(Some("@work"), "local"));
+        assert!(config.source_matches(Some("homelab"), "homelab"));
+        assert!(config.source_matches(None, "local"));
+    }
+
+    #[test]
+    fn sync_interval_parses_units_and_rejects_junk() {
+        assert_eq!(parse_interval("15m").unwrap(), Duration::from_secs(900));
+        assert_eq!(parse_interval("6h").unwrap(), Duration::from_secs(21_600));
+        assert_eq!(parse_interval("1d").unwrap(), Duration::from_secs(86_400));
+        let huge = format!("{}w", u64::MAX / 7 + 1);
+        let err = parse_interval(&huge).unwrap_err();
+        assert!(err.to_string().contains("out of range"), "{err}");
+        for bad in ["", "15", "m", "0h", "1.5h", "15 minutes"] {
+            assert!(parse_interval(bad).is_err(), "{bad}");
+        }
+
+        let config: Config =
+            toml::from_str("[remotes.devbox]\nhost = \"devbox\"\nsync_interval = \"2h\"\n")
+                .unwrap();
+        assert_eq!(
+            config.remotes["devbox"].sync_interval,
+            Some(Duration::from_secs(7200))
+        );
+        assert!(
+            toml::from_str::<Config>("[remotes.x]\nhost = \"x\"\nsync_interval = \"soon\"\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn scheduled_sync_waits_for_the_interval() {
+        let tmp = tempfile::tempdir().unwrap();
+        let settings = Settings {
+            cache_dir: tmp.path().display().to_string(),
+            ..Settings::default()
+        };
+        let mut remote: RemoteConfig =
+            toml::from_str("host = \"devbox\"\nsync_interval = \"1h\"").unwrap();
+        let due = |remote: &RemoteConfig| SyncPolicy::Scheduled.is_due("devbox", remote, &settings);
+        assert!(due(&remote), "never synced");
+
+        let cache_dir = tmp.path().join("devbox");
+        fs::create_dir_all(&cache_dir).unwrap();
+        update_last_sync(&cache_dir, SystemTime::now() - Duration::from_secs(600)).unwrap();
+        assert!(!due(&remote));
+        update_last_sync(&cache_dir, SystemTime::now() - Duration::from_secs(7200)).unwrap();
+        assert!(due(&remote));
+
+        update_last_sync(&cache_dir, SystemTime::now()).unwrap();
+        remote.sync_interval = None;
+        assert!(due(&remote), "no interval: every run");
+    }
+
+    #[test]
+    fn auto_sync_skips_remotes_attempted_within_the_cooldown() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut settings = Settings {
+            cache_dir: tmp.path().display().to_string(),
+            ..Settings::default()
+        };
+        let remote: RemoteConfig = toml::from_str("host = \"devbox\"").unwrap();
+        let due = |settings: &Settings| SyncPolicy::Stale.is_due("devbox", &remote, settings);
+        assert!(due(&settings), "never synced");
+
+        // A failed attempt leaves .last_sync alone but still counts
+        record_sync_attempt(&settings, "devbox").unwrap();
+        assert!(!due(&settings));
+        assert!(SyncPolicy::All.is_due("devbox", &remote, &settings));
+
+        let cache_dir = tmp.path().join("devbox");
+        stamp(
+            &cache_dir.join(LAST_ATTEMPT_FILE),
+            SystemTime::now() - Duration::from_secs(120),
+        )
+        .unwrap();
+        assert!(due(&settings));
+
+        record_sync_attempt(&settings, "devbox").unwrap();
+        settings.sync_cooldown = 0;
+        assert!(due(&settings), "0 disables the cooldown");
+    }
+
+    #[test]
+    fn failed_syncs_are_counted_until_one_succeeds() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config: Config = toml::from_str(&format!(
+            "[remotes.devbox]\nhost = \"devbox\"\n\n[remotes.homelab]\nhost = \"homelab\"\n\n[settings]\ncache_dir = \"{}\"\n",
+            tmp.path().display()
+        ))
+        .unwrap();
+        let devbox = tmp.path().join("devbox");
+        assert!(sync_warnings(&config, None).is_empty());
+
+        record_sync_outcome(&devbox, false).unwrap();
+        record_sync_outcome(&devbox, false).unwrap();
+        let warnings = sync_warnings(&config, None);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            (warnings[0].remote_name.as_str(), warnings[0].failures),
+            ("devbox", 2)
+        );
+        assert_eq!(warnings[0].last_sync, None);
+        assert!(sync_warnings(&config, Some("homelab")).is_empty());
+        let retried =
+            |name: &str| SyncPolicy::Failed.is_due(name, &config.remotes[name], &config.settings);
+        assert!(retried("devbox") && !retried("homelab"));
+
+        record_sync_outcome(&devbox, true).unwrap();
+        assert!(sync_warnings(&config, None).is_empty());
+        assert!(!retried("devbox"));
+    }
+
+    #[test]
+    fn project_rules_match_encoded_dirs_in_any_case() {
+        assert_eq!(
+            project_filter_rules("My.api2"),
+            [
+                "--include=/*[mM][yY]?[aA][pP][iI]2*/",
+                "--include=/*[mM][yY]?[aA][pP][iI]2*/**",
+                "--exclude=/*",
+            ]
+        );
+    }
+
+    #[test]
+    fn du_output_splits_into_projects_and_sessions() {
+        let output = "\
+4\t./.last_sync
+120\t./-home-arthur-grail/aaaa.jsonl
+8\t./-home-arthur-grail/aaaa/subagents/agent-1.jsonl
+8\t./-home-arthur-grail/aaaa/subagents
+8\t./-home-arthur-grail/aaaa
+130\t./-home-arthur-grail
+300\t./-home-arthur-castle/bbbb.jsonl
+300\t./-home-arthur-castle
+434\t.
+";
+        let usage = parse_du_output(output);
+        assert_eq!(usage.total, 434 * 1024);
+        assert_eq!(
+            usage.projects,
+            [
+                ("-home-arthur-castle".to_string(), 300 * 1024),
+                ("-home-arthur-grail".to_string(), 130 * 1024),
+            ]
+        );
+        assert_eq!(
+            usage.sessions,
+            [
+                ("-home-arthur-castle/bbbb.jsonl".to_string(), 300 * 1024),
+                ("-home-arthur-grail/aaaa.jsonl".to_string(), 120 * 1024),
+            ]
+        );
+        assert_eq!(shell_path("~/.claude/projects"), "~/'.claude/projects'");
+        assert_eq!(shell_path("/srv/it's"), "'/srv/it'\\''s'");
+    }
+
+    #[test]
+    fn remote_listing_drops_paths_outside_the_cache() {
+        let listing = "  12 ./../../.bashrc\n\
+                       5 /etc/passwd\n\
+                       4 ./-p/sub/../../x\n\
+                       7 ./-p/ok.jsonl\n\
+                       28 total\n";
+        let parsed = parse_file_sizes(listing);
+        assert_eq!(parsed, BTreeMap::from([("-p/ok.jsonl".to_string(), 7)]));
+        assert!(is_plain_relative("-p/ok.jsonl"));
+        for bad in ["", "../x", "/abs", "-p/../../x", "./x"] {
+            assert!(!is_plain_relative(bad), "{bad}");
+        }
+    }
+
+    #[test]
+    fn sftp_sync_compares_listed_sizes_with_the_cache() {
+        let listing = "  120 ./-home-arthur-grail/aaaa.jsonl\n\
+                       \t 40 ./-home-arthur-grail/bbbb.jsonl\n\
+                       9 ./.last_sync\n\
+                       169 total\n\
+                       7 ./-srv-shrubbery/cccc.jsonl\n";
+        let mut remote = parse_file_sizes(listing);
+        assert_eq!(remote.len(), 4);
+        remote.retain(|path, _| is_synced_file(path));
+        let cached = BTreeMap::from([
+            ("-home-arthur-grail/aaaa.jsonl".to_string(), 100),
+            ("-srv-shrubbery/cccc.jsonl".to_string(), 7),
+            ("-srv-shrubbery/gone.jsonl".to_string(), 3),
+        ]);
+        let drift = |kind, path: &str| Drift {
+            kind,
+            path: path.to_string(),
+        };
+        assert_eq!(
+            size_drift(&remote, &cached),
+            [
+                drift(DriftKind::Changed, "-home-arthur-grail/aaaa.jsonl"),
+                drift(DriftKind::Missing, "-home-arthur-grail/bbbb.jsonl"),
+                drift(DriftKind::Extra, "-srv-shrubbery/gone.jsonl"),
+            ]
+        );
+    }
+
+    #[test]
+    fn sftp_helpers_match_rsync_behavior() {
+        assert!(project_dir_matches("Grail", "-home-arthur-grail"));
+        assert!(project_dir_matches("holy_grail", "-home-arthur-holy-grail"));
+        assert!(!project_dir_matches("grail", "-srv-shrubbery"));
+        assert!(remote_rsync_missing(
+            "bash: line 1: rsync: command not found"
+        ));
+        assert!(remote_rsync_missing("sh: rsync: not found"));
+        assert!(!remote_rsync_missing(
+            "rsync: connection unexpectedly closed"
+        ));
+        assert_eq!(sftp_path("~/.claude/projects/"), ".claude/projects");
+        assert_eq!(sftp_path("/srv/claude"), "/srv/claude");
+        assert_eq!(sftp_quote("a \"b\""), "\"a \\\"b\\\"\"");
+    }
+
+    #[test]
+    fn health_output_reports_each_check() {
+        let health = parse_health_output("Welcome to devbox!\nconnected\nrsync\nclaude\n");
+        assert_eq!(
+            health,
+            RemoteHealth {
+                transport: Transport::Ssh,
+                ssh_error: None,
+                rsync: true,
+                projects_dir: false,
+                claude: Some(true),
+            }
+        );
+        assert!(!health.is_healthy());
+        assert!(parse_health_output("").ssh_error.is_some());
     }
 
     #[test]
@@ -396,6 +2351,8 @@ mod tests {
         let toml = r#"
 [remotes.devbox]
 host = "devbox"
+label = "dev"
+color = "green"
 
 [remotes.workstation]
 host = "192.168.1.100"
@@ -411,6 +2368,12 @@ stale_threshold = 7200
         assert_eq!(config.remotes.len(), 2);
         assert_eq!(config.remotes["devbox"].host, "devbox");
         assert!(config.remotes["devbox"].user.is_none());
+        assert_eq!(config.remotes["devbox"].label.as_deref(), Some("dev"));
+        assert_eq!(
+            config.remotes["devbox"].color,
+            Some(crate::session::SourceColor::Green)
+        );
+        assert!(config.remotes["workstation"].color.is_none());
 
         assert_eq!(config.remotes["workstation"].host, "192.168.1.100");
         assert_eq!(
@@ -428,6 +2391,7 @@ stale_threshold = 7200
             successes: vec![SyncResult {
                 remote_name: "devbox".to_string(),
                 duration: Duration::from_secs(1),
+                changes: SyncChanges::default(),
             }],
             failures: vec![SyncFailure {
                 remote_name: "workstation".to_string(),
@@ -439,4 +2403,22 @@ stale_threshold = 7200
         assert_eq!(summary.failure_count(), 1);
         assert_eq!(summary.failures.len(), 1);
     }
+
+    #[test]
+    fn run_in_slots_bounds_concurrency_and_keeps_order() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let items: Vec<usize> = (0..8).collect();
+        let out = run_in_slots(&items, 3, |slot, &i| {
+            assert!(slot < 3);
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(5));
+            running.fetch_sub(1, Ordering::SeqCst);
+            i * 10
+        });
+        assert_eq!(out, (0..8).map(|i| i * 10).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        assert!(run_in_slots(&[] as &[u8], 0, |_, &b| b).is_empty());
+    }
 }
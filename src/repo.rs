@@ -0,0 +1,46 @@
+//! Git repository discovery from the filesystem alone (no `git` process):
+//! a directory is a repo root if it holds a `.git` directory, or a `.git`
+//! file as in worktrees and submodules.
+
+use std::path::{Path, PathBuf};
+
+/// Nearest ancestor of `dir` (inclusive) that is a git repository root.
+pub fn repo_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|d| d.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Whether `path` is `root` or lies beneath it (component-wise, so
+/// `/src/api-v2` is not inside `/src/api`).
+pub fn is_within(path: &Path, root: &Path) -> bool {
+    path.starts_with(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn repo_root_finds_nearest_git_dir_or_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("src/deep")).unwrap();
+        let worktree = repo.join("src/wt");
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(worktree.join(".git"), "gitdir: ../../.git/worktrees/wt").unwrap();
+
+        assert_eq!(repo_root(&repo.join("src/deep")), Some(repo.clone()));
+        assert_eq!(repo_root(&worktree), Some(worktree.clone()));
+        assert_eq!(repo_root(tmp.path()), None);
+    }
+
+    #[test]
+    fn is_within_compares_components() {
+        assert!(is_within(Path::new("/src/api/web"), Path::new("/src/api")));
+        assert!(is_within(Path::new("/src/api"), Path::new("/src/api")));
+        assert!(!is_within(Path::new("/src/api-v2"), Path::new("/src/api")));
+    }
+}
@@ -0,0 +1,135 @@
+//! `cc-sessions resume <query>`: find a session by what it is called instead
+//! of picking it from the full list.
+//!
+//! The query is matched against each session's name, tag, summary, and first
+//! message. Matches are ranked by how strong the best one is (an exact name,
+//! then a plain substring, then a fuzzy subsequence), and by skim's fuzzy
+//! score within a tier. A session that is alone in the top tier is a
+//! confident match; anything else goes to a picker of the matches.
+
+use crate::session::Session;
+use skim::fuzzy_matcher::FuzzyMatcher;
+use skim::fuzzy_matcher::skim::SkimMatcherV2;
+
+/// How a session matched the query, weakest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Strength {
+    /// The query's characters appear in order in some field.
+    Fuzzy,
+    /// Some field contains the query (case-insensitive).
+    Substring,
+    /// The session's name is the query (case-insensitive).
+    ExactName,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    /// Position in the `sessions` slice given to `rank`.
+    pub index: usize,
+    pub strength: Strength,
+    pub score: i64,
+}
+
+/// Sessions matching `query`, best first. Ties keep the input order, so the
+/// caller's sort (most recent first by default) breaks them.
+pub fn rank(sessions: &[Session], query: &str) -> Vec<Match> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let lower = query.to_lowercase();
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let mut matches: Vec<Match> = sessions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, session)| {
+            let fields = [
+                session.name.as_deref(),
+                session.tag.as_deref(),
+                session.summary.as_deref(),
+                session.first_message.as_deref(),
+            ];
+            let score = fields
+                .iter()
+                .flatten()
+                .filter_map(|field| matcher.fuzzy_match(field, query))
+                .max()?;
+            let strength = if session
+                .name
+                .as_deref()
+                .is_some_and(|name| name.to_lowercase() == lower)
+            {
+                Strength::ExactName
+            } else if fields
+                .iter()
+                .flatten()
+                .any(|field| field.to_lowercase().contains(&lower))
+            {
+                Strength::Substring
+            } else {
+                Strength::Fuzzy
+            };
+            Some(Match {
+                index,
+                strength,
+                score,
+            })
+        })
+        .collect();
+    matches.sort_by_key(|m| std::cmp::Reverse((m.strength, m.score)));
+    matches
+}
+
+/// The session to resume without asking: the only match, or the only one
+/// in the strongest tier.
+pub fn confident(matches: &[Match]) -> Option<usize> {
+    match matches {
+        [only] => Some(only.index),
+        [best, next, ..] if best.strength > next.strength => Some(best.index),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str, name: Option<&str>, summary: &str) -> Session {
+        let mut s = Session::fixture(id);
+        s.name = name.map(str::to_string);
+        s.summary = Some(summary.to_string());
+        s
+    }
+
+    #[test]
+    fn exact_name_beats_substring_beats_fuzzy() {
+        let sessions = vec![
+            session("a", None, "Auth refactor follow-up"),
+            session("b", Some("auth refactor"), "Move login to middleware"),
+            session("c", None, "Add a unit test for refresh tokens"),
+            session("d", None, "Fix CI cache"),
+        ];
+        let ranked = rank(&sessions, "Auth Refactor");
+        let order: Vec<(usize, Strength)> = ranked.iter().map(|m| (m.index, m.strength)).collect();
+        assert_eq!(
+            order[..2],
+            [(1, Strength::ExactName), (0, Strength::Substring)]
+        );
+        assert!(ranked.iter().all(|m| m.index != 3));
+        assert_eq!(confident(&ranked), Some(1));
+    }
+
+    #[test]
+    fn several_equally_strong_matches_are_not_confident() {
+        let sessions = vec![
+            session("a", None, "auth middleware"),
+            session("b", None, "auth tokens"),
+        ];
+        let ranked = rank(&sessions, "auth");
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(confident(&ranked), None);
+        assert_eq!(confident(&rank(&sessions, "tokens")), Some(1));
+        assert!(rank(&sessions, "  ").is_empty());
+        assert!(rank(&sessions, "zzz").is_empty());
+    }
+}
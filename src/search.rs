@@ -0,0 +1,300 @@
+//! Transcript search query parsing and matching.
+//!
+//! Queries may carry inline modifiers ahead of the pattern, so the interactive
+//! prompt can switch modes per search without restarting:
+//!
+//! ```text
+//! cs:Arc        case-sensitive
+//! w:arc         whole-word
+//! cs:w:Arc      both (modifiers combine in any order)
+//! in:tools make  also search tool output (command results, file dumps)
+//! -in:tools make don't, even with `search_tool_output` set
+//! ```
+//!
+//! CLI flags (`--case-sensitive`, `--whole-word`) and the `search_tool_output`
+//! config setting set the defaults. `cs:` and `w:` only turn a mode on;
+//! `in:tools` and `-in:tools` are whole words, so `in:toolsfoo` is a pattern.
+
+use std::ops::Range;
+
+/// Matching modes for a transcript search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// Also match `tool_result` output, not just user/assistant text.
+    pub include_tools: bool,
+}
+
+/// A parsed search: the literal pattern plus the modes it runs with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub pattern: String,
+    pub options: SearchOptions,
+}
+
+impl SearchQuery {
+    /// Parse a raw prompt query, peeling leading `cs:` / `w:` / `in:tools` /
+    /// `-in:tools` modifiers.
+    pub fn parse(raw: &str, defaults: SearchOptions) -> Self {
+        let mut options = defaults;
+        let mut rest = raw.trim();
+        loop {
+            rest = rest.trim_start();
+            if let Some(r) = rest.strip_prefix("cs:") {
+                options.case_sensitive = true;
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix("w:") {
+                options.whole_word = true;
+                rest = r;
+            } else if let Some(r) = strip_word(rest, "in:tools") {
+                options.include_tools = true;
+                rest = r;
+            } else if let Some(r) = strip_word(rest, "-in:tools") {
+                options.include_tools = false;
+                rest = r;
+            } else {
+                break;
+            }
+        }
+        Self {
+            pattern: rest.to_string(),
+            options,
+        }
+    }
+
+    /// Whether the query can be answered from the ASCII-lowercased search
+    /// index alone. Case-sensitive queries need the original transcript text.
+    pub fn index_only(&self) -> bool {
+        !self.options.case_sensitive
+    }
+
+    /// Whether a hit in an index built with/without tool output settles the
+    /// query: `index_only`, and the index covers exactly the text searched.
+    /// A `-in:tools` query against an index with tool output may have hit
+    /// only tool output, so it needs confirming.
+    pub fn settled_by_index(&self, index_includes_tools: bool) -> bool {
+        self.index_only() && index_includes_tools == self.options.include_tools
+    }
+
+    /// Whether an index built with/without tool output covers this query.
+    /// An index without tool output can't even prefilter an `in:tools`
+    /// query, since the match may live entirely in tool output.
+    pub fn covered_by_index(&self, index_includes_tools: bool) -> bool {
+        index_includes_tools || !self.options.include_tools
+    }
+
+    /// Match against text that has already been ASCII-lowercased (the search
+    /// index). Only meaningful for queries where `index_only()` holds, or as a
+    /// prefilter: a case-sensitive hit always implies a lowercased hit.
+    pub fn matches_lowercased(&self, text_lower: &str) -> bool {
+        let pattern_lower = self.pattern.to_ascii_lowercase();
+        if !self.options.whole_word {
+            return text_lower.contains(&pattern_lower);
+        }
+        text_lower
+            .match_indices(&pattern_lower)
+            .any(|(i, m)| is_word_bounded(text_lower, i..i + m.len()))
+    }
+
+    /// Match against original-case text, honoring all options.
+    pub fn matches(&self, text: &str) -> bool {
+        !match_ranges(text, &self.pattern, self.options).is_empty()
+    }
+}
+
+/// `text` after a leading `word` that is followed by whitespace or nothing.
+fn strip_word<'a>(text: &'a str, word: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix(word)?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then_some(rest)
+}
+
+/// Byte ranges in `text` where `pattern` matches under `options`.
+/// Ranges are non-overlapping, ascending, and always on char boundaries.
+pub fn match_ranges(text: &str, pattern: &str, options: SearchOptions) -> Vec<Range<usize>> {
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+
+    let ranges = if options.case_sensitive {
+        text.match_indices(pattern)
+            .map(|(i, m)| i..i + m.len())
+            .collect()
+    } else {
+        case_insensitive_ranges(text, pattern)
+    };
+
+    if !options.whole_word {
+        return ranges;
+    }
+    ranges
+        .into_iter()
+        .filter(|r| is_word_bounded(text, r.clone()))
+        .collect()
+}
+
+fn case_insensitive_ranges(text: &str, pattern: &str) -> Vec<Range<usize>> {
+    // Fast path: ASCII-only text and pattern. Lowercasing preserves byte
+    // positions, so we lower once and match_indices gives us offsets directly.
+    // This is O(n) vs. the generic path's per-position re-lowering.
+    if text.is_ascii() && pattern.is_ascii() {
+        let text_lower = text.to_ascii_lowercase();
+        let pattern_lower = pattern.to_ascii_lowercase();
+        return text_lower
+            .match_indices(&pattern_lower)
+            .map(|(i, _)| i..i + pattern.len())
+            .collect();
+    }
+
+    // Generic path: handles case-fold expansion (ß → ss, İ → i̇). Walk the
+    // original by char, lower only the pattern-sized window at each position.
+    let pattern_lower = pattern.to_lowercase();
+    let pattern_char_count = pattern.chars().count();
+    let indices: Vec<usize> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect();
+
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i + pattern_char_count < indices.len() {
+        let start = indices[i];
+        let end = indices[i + pattern_char_count];
+        if text[start..end].to_lowercase() == pattern_lower {
+            ranges.push(start..end);
+            i += pattern_char_count;
+        } else {
+            i += 1;
+        }
+    }
+    ranges
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A match is whole-word when neither neighbour extends the word.
+fn is_word_bounded(text: &str, range: Range<usize>) -> bool {
+    let before = text[..range.start].chars().next_back();
+    let after = text[range.end..].chars().next();
+    !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CS: SearchOptions = SearchOptions {
+        case_sensitive: true,
+        whole_word: false,
+        include_tools: false,
+    };
+    const WORD: SearchOptions = SearchOptions {
+        case_sensitive: false,
+        whole_word: true,
+        include_tools: false,
+    };
+
+    #[test]
+    fn parse_plain_query_uses_defaults() {
+        let q = SearchQuery::parse("  api  ", SearchOptions::default());
+        assert_eq!(q.pattern, "api");
+        assert_eq!(q.options, SearchOptions::default());
+
+        let q = SearchQuery::parse("api", CS);
+        assert!(q.options.case_sensitive);
+    }
+
+    #[test]
+    fn parse_modifiers_combine_in_any_order() {
+        for raw in ["cs:w:Arc", "w:cs:Arc", "cs: w:Arc"] {
+            let q = SearchQuery::parse(raw, SearchOptions::default());
+            assert_eq!(q.pattern, "Arc", "raw: {raw}");
+            assert!(q.options.case_sensitive, "raw: {raw}");
+            assert!(q.options.whole_word, "raw: {raw}");
+        }
+    }
+
+    #[test]
+    fn parse_in_tools_modifier() {
+        let q = SearchQuery::parse("in:tools cs:FAILED", SearchOptions::default());
+        assert_eq!(q.pattern, "FAILED");
+        assert!(q.options.include_tools);
+        assert!(q.options.case_sensitive);
+        assert!(!q.covered_by_index(false));
+        assert!(q.covered_by_index(true));
+
+        let q = SearchQuery::parse("make", SearchOptions::default());
+        assert!(!q.options.include_tools);
+        assert!(q.covered_by_index(false));
+
+        // Only as a whole word
+        let q = SearchQuery::parse("in:toolsfoo", SearchOptions::default());
+        assert_eq!(q.pattern, "in:toolsfoo");
+        assert!(!q.options.include_tools);
+    }
+
+    #[test]
+    fn parse_negated_in_tools_overrides_the_default() {
+        let tools_on = SearchOptions {
+            include_tools: true,
+            ..SearchOptions::default()
+        };
+        let q = SearchQuery::parse("-in:tools make", tools_on);
+        assert_eq!(q.pattern, "make");
+        assert!(!q.options.include_tools);
+        // An index with tool output still prefilters, but can't settle it
+        assert!(q.covered_by_index(true));
+        assert!(!q.settled_by_index(true));
+        assert!(q.settled_by_index(false));
+
+        let q = SearchQuery::parse("in:tools make", tools_on);
+        assert!(q.options.include_tools && q.settled_by_index(true));
+        let q = SearchQuery::parse("-in:toolsy", tools_on);
+        assert_eq!(q.pattern, "-in:toolsy");
+        assert!(q.options.include_tools);
+    }
+
+    #[test]
+    fn parse_modifier_only_yields_empty_pattern() {
+        let q = SearchQuery::parse("cs:", SearchOptions::default());
+        assert!(q.pattern.is_empty());
+    }
+
+    #[test]
+    fn case_sensitive_ranges_respect_case() {
+        assert_eq!(match_ranges("Arc arc ARC", "Arc", CS), vec![0..3]);
+        assert_eq!(
+            match_ranges("Arc arc ARC", "Arc", SearchOptions::default()).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn whole_word_skips_embedded_matches() {
+        let text = "Arc<Mutex> search archive arc_x arc.";
+        let ranges = match_ranges(text, "arc", WORD);
+        let hits: Vec<&str> = ranges.iter().map(|r| &text[r.clone()]).collect();
+        assert_eq!(hits, ["Arc", "arc"]);
+        assert_eq!(ranges[1].start, text.rfind("arc.").unwrap());
+    }
+
+    #[test]
+    fn whole_word_handles_unicode_neighbours() {
+        // é is alphanumeric, so "caf" inside "café" is not a whole word
+        assert!(match_ranges("café", "caf", WORD).is_empty());
+        assert_eq!(match_ranges("🌍 api 🌍", "api", WORD).len(), 1);
+    }
+
+    #[test]
+    fn matches_lowercased_agrees_with_matches() {
+        let text = "Use Arc for sharing; searching archives";
+        let lower = text.to_ascii_lowercase();
+        for raw in ["arc", "w:arc", "w:search", "search"] {
+            let q = SearchQuery::parse(raw, SearchOptions::default());
+            assert_eq!(q.matches_lowercased(&lower), q.matches(text), "raw: {raw}");
+        }
+    }
+}
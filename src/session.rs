@@ -1,5 +1,8 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::AddAssign;
 use std::path::PathBuf;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Where a session originated from.
 #[derive(Debug, Clone)]
@@ -14,7 +17,63 @@ pub enum SessionSource {
         host: String,
         /// Only needed for raw hosts without SSH config
         user: Option<String>,
+        /// Short display label (defaults to `name`)
+        label: Option<String>,
+        /// Color for `label` in listings and preview headers
+        color: Option<SourceColor>,
+        /// How the session is resumed
+        resume: RemoteResume,
     },
+    /// Session a teammate published to a shared team location (browse only)
+    Team {
+        /// Config key (e.g., "platform")
+        name: String,
+        /// Who published it
+        owner: String,
+        /// Short display label (defaults to `name`)
+        label: Option<String>,
+        color: Option<SourceColor>,
+    },
+}
+
+/// How a remote session is resumed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RemoteResume {
+    /// `ssh -t` into the remote and run claude there
+    #[default]
+    Ssh,
+    /// The remote's `resume_command`, run locally
+    Command(String),
+    /// Not at all: an rsync daemon remote without a `resume_command`
+    Unavailable,
+}
+
+/// Terminal color for a remote's label (`color = "green"` in config).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl SourceColor {
+    /// ANSI foreground escape.
+    pub fn ansi(self) -> &'static str {
+        match self {
+            SourceColor::Red => "\x1b[31m",
+            SourceColor::Green => "\x1b[32m",
+            SourceColor::Yellow => "\x1b[33m",
+            SourceColor::Blue => "\x1b[34m",
+            SourceColor::Magenta => "\x1b[35m",
+            SourceColor::Cyan => "\x1b[36m",
+            SourceColor::White => "\x1b[37m",
+        }
+    }
 }
 
 impl SessionSource {
@@ -22,17 +81,182 @@ impl SessionSource {
     pub fn display_name(&self) -> &str {
         match self {
             SessionSource::Local => "local",
-            SessionSource::Remote { name, .. } => name,
+            SessionSource::Remote { name, .. } | SessionSource::Team { name, .. } => name,
+        }
+    }
+
+    /// What the SOURCE column shows: the remote's configured label, or its
+    /// name. Machine-readable output keeps `display_name`.
+    pub fn label(&self) -> &str {
+        match self {
+            SessionSource::Remote {
+                label: Some(label), ..
+            }
+            | SessionSource::Team {
+                label: Some(label), ..
+            } => label,
+            _ => self.display_name(),
+        }
+    }
+
+    pub fn color(&self) -> Option<SourceColor> {
+        match self {
+            SessionSource::Local => None,
+            SessionSource::Remote { color, .. } | SessionSource::Team { color, .. } => *color,
+        }
+    }
+
+    /// `text` (normally the label) in this source's color, if it has one.
+    pub fn paint(&self, text: &str) -> String {
+        match self.color() {
+            Some(color) => format!("{}{}\x1b[0m", color.ansi(), text),
+            None => text.to_string(),
         }
     }
 
-    #[cfg(test)]
     pub fn is_local(&self) -> bool {
         matches!(self, SessionSource::Local)
     }
+
+    /// The teammate who published a team session.
+    pub fn owner(&self) -> Option<&str> {
+        match self {
+            SessionSource::Team { owner, .. } => Some(owner),
+            _ => None,
+        }
+    }
+}
+
+/// API token usage summed over a session's assistant responses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+    pub cache_creation: u64,
+    pub cache_read: u64,
+}
+
+impl TokenUsage {
+    /// All tokens processed, cached or not.
+    pub fn total(&self) -> u64 {
+        self.input + self.output + self.cache_creation + self.cache_read
+    }
 }
 
-#[derive(Debug)]
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, rhs: Self) {
+        self.input += rhs.input;
+        self.output += rhs.output;
+        self.cache_creation += rhs.cache_creation;
+        self.cache_read += rhs.cache_read;
+    }
+}
+
+/// Order for the picker and `--list` (`--sort`). Newest/largest first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    /// Last modified
+    #[default]
+    Modified,
+    /// Creation time
+    Created,
+    /// Conversation turns
+    Turns,
+    /// Last resumed through cc-sessions; never-resumed sessions come last
+    Resumed,
+    /// Blend of the current repo, recency, and resumes (default inside a git repo)
+    Relevance,
+}
+
+impl SortKey {
+    /// Sort in place; ties keep the most recently modified first.
+    pub fn sort(self, sessions: &mut [Session]) {
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.modified));
+        match self {
+            SortKey::Modified => {}
+            SortKey::Created => sessions.sort_by_key(|s| std::cmp::Reverse(s.created)),
+            SortKey::Turns => sessions.sort_by_key(|s| std::cmp::Reverse(s.turn_count)),
+            SortKey::Resumed => sessions.sort_by_key(|s| std::cmp::Reverse(s.resumed)),
+            SortKey::Relevance => sessions.sort_by(|a, b| b.relevance.total_cmp(&a.relevance)),
+        }
+    }
+}
+
+/// `--sort`, optionally with named (★) sessions ahead of the rest
+/// (`--named-first`), each group keeping the key's order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub named_first: bool,
+}
+
+impl SortOrder {
+    pub fn sort(self, sessions: &mut [Session]) {
+        self.key.sort(sessions);
+        if self.named_first {
+            sessions.sort_by_key(|s| s.name.is_none());
+        }
+    }
+}
+
+/// Server and tool of an MCP tool name (`mcp__<server>__<tool>`); `None`
+/// for built-in tools.
+pub fn mcp_tool(name: &str) -> Option<(&str, &str)> {
+    name.strip_prefix("mcp__")?.split_once("__")
+}
+
+/// Author of a transcript message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+/// One transcript message in a stable shape (`cc-sessions export`),
+/// independent of how Claude Code happens to write it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Message {
+    pub role: Role,
+    /// RFC 3339 UTC, as recorded.
+    pub timestamp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub text: Vec<String>,
+    pub tool_calls: Vec<ToolCall>,
+    pub tool_results: Vec<ToolResult>,
+    /// Assistant messages only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+    /// The summary that replaced earlier messages when the context was compacted.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub compact_summary: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub input: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolResult {
+    pub tool_use_id: String,
+    pub is_error: bool,
+    /// Text parts joined by newlines; images are left out.
+    pub text: String,
+}
+
+/// An unfinished item of the last TodoWrite list in a session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenTodo {
+    pub content: String,
+    pub in_progress: bool,
+}
+
+#[derive(Debug, Clone)]
 pub struct Session {
     pub id: String,
     pub project: String,
@@ -41,10 +265,114 @@ pub struct Session {
     pub created: SystemTime,
     pub modified: SystemTime,
     pub first_message: Option<String>,
-    pub summary: Option<String>, // Session summary generated by Claude
-    pub name: Option<String>,    // customTitle from /rename - indicates important session
-    pub tag: Option<String>,     // searchable label from /tag
-    pub turn_count: usize,       // Number of user messages (conversation turns)
-    pub source: SessionSource,   // Where this session came from
+    pub summary: Option<String>,    // Session summary generated by Claude
+    pub name: Option<String>,       // customTitle from /rename - indicates important session
+    pub tag: Option<String>,        // searchable label from /tag
+    pub git_branch: Option<String>, // `gitBranch` of the latest entry that recorded one
+    pub turn_count: usize,          // Number of user messages (conversation turns)
+    pub source: SessionSource,      // Where this session came from
     pub forked_from: Option<String>, // Parent session ID if this is a fork
+    pub redundant_fork: bool,       // Fork with nothing written after forking
+    pub repo_root: Option<String>,  // Enclosing git repo (project identity)
+    pub tokens: TokenUsage,         // Summed `usage` from assistant responses
+    pub model_usage: BTreeMap<String, TokenUsage>, // `tokens`, split by model
+    pub size: u64,                  // Transcript file size in bytes
+    pub first_activity: Option<SystemTime>, // Earliest entry `timestamp`
+    pub last_activity: Option<SystemTime>, // Latest entry `timestamp`
+    pub active_hours: BTreeMap<u32, u32>, // User/assistant entries per hour since the epoch
+    pub open_todos: Vec<OpenTodo>,  // Unfinished items of the last TodoWrite list
+    pub plan_offset: Option<u64>,   // Line with the last approved plan (ExitPlanMode)
+    pub mcp_calls: BTreeMap<String, u32>, // MCP tool calls by tool name (`mcp__server__tool`)
+    pub permission_requests: u32,   // Calls to tools that ask for permission by default
+    pub denied_tools: BTreeMap<String, u32>, // Tool calls refused permission, by tool name
+    pub partial: bool,              // Too large to scan fully: counts/usage are lower bounds
+    pub archived: bool,             // `filepath` is a compressed copy in the archive
+    pub resumed: Option<SystemTime>, // Last resumed via cc-sessions (state::ResumeHistory)
+    pub resume_count: u32,          // Times resumed via cc-sessions (state::ResumeHistory)
+    pub relevance: f64,             // `--sort relevance` score (ranking::Ranking)
+    pub clock_skew: Option<Duration>, // Source clock ahead by this much; times already corrected
+    pub local_path: Option<PathBuf>, // Local checkout of a remote session's directory (`path_map`)
+}
+
+impl Session {
+    /// Wall-clock span between the first and last recorded entry. Unlike
+    /// created/modified this survives rsync, so it's meaningful for remotes.
+    pub fn duration(&self) -> Option<Duration> {
+        self.last_activity?
+            .duration_since(self.first_activity?)
+            .ok()
+    }
+
+    /// Move times stamped by the source's clock back by `skew` (how far that
+    /// clock runs ahead), then clamp any still in the future to `now`.
+    pub fn correct_clock(&mut self, skew: Option<Duration>, now: SystemTime) {
+        let fix = |t: SystemTime| (t - skew.unwrap_or_default()).min(now);
+        self.created = fix(self.created);
+        self.modified = fix(self.modified);
+        self.first_activity = self.first_activity.map(fix);
+        self.last_activity = self.last_activity.map(fix);
+        let shift = skew.map_or(0, |s| ((s.as_secs() + 1800) / 3600) as u32);
+        let last_hour = now
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| (d.as_secs() / 3600) as u32);
+        if shift > 0 || self.active_hours.keys().next_back() > Some(&last_hour) {
+            let mut hours = BTreeMap::new();
+            for (hour, n) in std::mem::take(&mut self.active_hours) {
+                *hours
+                    .entry(hour.saturating_sub(shift).min(last_hour))
+                    .or_default() += n;
+            }
+            self.active_hours = hours;
+        }
+        self.clock_skew = skew;
+    }
+
+    /// Tool calls that were refused permission (rejected at the prompt or
+    /// denied by a rule).
+    pub fn permission_denials(&self) -> u32 {
+        self.denied_tools.values().sum()
+    }
+}
+
+#[cfg(test)]
+impl Session {
+    /// Minimal local session for unit tests; override fields as needed.
+    pub fn fixture(id: &str) -> Self {
+        Session {
+            id: id.to_string(),
+            project: "test-project".to_string(),
+            project_path: "/tmp/test-project".to_string(),
+            filepath: PathBuf::from(format!("/tmp/{}.jsonl", id)),
+            created: SystemTime::now(),
+            modified: SystemTime::now(),
+            first_message: None,
+            summary: Some("test summary".to_string()),
+            name: None,
+            tag: None,
+            git_branch: None,
+            turn_count: 1,
+            source: SessionSource::Local,
+            forked_from: None,
+            redundant_fork: false,
+            repo_root: None,
+            tokens: TokenUsage::default(),
+            model_usage: BTreeMap::new(),
+            size: 0,
+            first_activity: None,
+            last_activity: None,
+            active_hours: BTreeMap::new(),
+            open_todos: Vec::new(),
+            plan_offset: None,
+            mcp_calls: BTreeMap::new(),
+            permission_requests: 0,
+            denied_tools: BTreeMap::new(),
+            partial: false,
+            archived: false,
+            resumed: None,
+            resume_count: 0,
+            relevance: 0.0,
+            clock_skew: None,
+            local_path: None,
+        }
+    }
 }
@@ -0,0 +1,313 @@
+//! `cc-sessions share`: publish a session excerpt as Markdown and print its
+//! URL.
+//!
+//! The excerpt is `export::render_markdown` over the chosen messages, run
+//! through `redact` unless `--no-redact`. Uploading shells out, as syncing
+//! does: `gh gist create` for the `gist` backend (a secret gist unless
+//! `public = true`), `curl` for `post`, which sends the Markdown as the
+//! request body to `url`.
+//!
+//! ```toml
+//! [share]
+//! backend = "post"
+//! url = "https://paste.example.com/api/pastes"
+//! headers = { Authorization = "Bearer $PASTE_TOKEN" }  # $VARs from the environment
+//! url_field = "url"  # JSON field of the response with the link (default: the body)
+//! redact = ["corp.internal"]  # Extra strings to hide
+//! ```
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// GitHub gist via the `gh` CLI
+    #[default]
+    Gist,
+    /// The Markdown as the body of a POST request via `curl`
+    Post,
+}
+
+/// `[share]` config section.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShareConfig {
+    #[serde(default)]
+    pub backend: Backend,
+    /// gist: list the gist publicly instead of as a secret one
+    #[serde(default)]
+    pub public: bool,
+    /// post: the endpoint
+    pub url: Option<String>,
+    /// post: extra request headers; `$VAR`s are expanded from the environment
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// post: JSON field of the response holding the link (default: the
+    /// whole response body)
+    pub url_field: Option<String>,
+    /// Strings to redact besides the built-in patterns
+    #[serde(default)]
+    pub redact: Vec<String>,
+}
+
+/// `--range`: 1-based, inclusive message numbers: `10-30`, `10-`, `-30`,
+/// or a single `12`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl FromStr for Range {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let number = |part: &str| {
+            part.trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("'{}' isn't a message number (they start at 1)", part))
+        };
+        let (start, end) = match s.split_once('-') {
+            Some((start, end)) => (
+                if start.trim().is_empty() {
+                    1
+                } else {
+                    number(start)?
+                },
+                if end.trim().is_empty() {
+                    None
+                } else {
+                    Some(number(end)?)
+                },
+            ),
+            None => (number(s)?, Some(number(s)?)),
+        };
+        if end.is_some_and(|end| end < start) {
+            return Err(format!("Range {} ends before it starts", s));
+        }
+        Ok(Range { start, end })
+    }
+}
+
+impl Range {
+    /// The index range of `total` messages this selects.
+    pub fn select(self, total: usize) -> Result<std::ops::Range<usize>> {
+        if self.start > total {
+            bail!(
+                "The session has {} message(s); --range starts at {}",
+                total,
+                self.start
+            );
+        }
+        Ok(self.start - 1..self.end.unwrap_or(total).min(total))
+    }
+}
+
+/// Upload `markdown` as `filename` and return its URL.
+pub fn upload(
+    config: &ShareConfig,
+    filename: &str,
+    description: &str,
+    markdown: &str,
+) -> Result<String> {
+    match config.backend {
+        Backend::Gist => upload_gist(config, filename, description, markdown),
+        Backend::Post => upload_post(config, markdown),
+    }
+}
+
+fn upload_gist(
+    config: &ShareConfig,
+    filename: &str,
+    description: &str,
+    markdown: &str,
+) -> Result<String> {
+    let mut command = Command::new("gh");
+    command.args([
+        "gist",
+        "create",
+        "--filename",
+        filename,
+        "--desc",
+        description,
+    ]);
+    if config.public {
+        command.arg("--public");
+    }
+    command.arg("-");
+    let output = run_with_stdin(&mut command, markdown.as_bytes()).context(
+        "Failed to run `gh` (install the GitHub CLI, or set backend = \"post\" under [share])",
+    )?;
+    if !output.status.success() {
+        bail!(
+            "gh gist create failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    link_in(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The body goes over stdin; the headers, which may hold tokens and
+/// shouldn't show up in the process list, go through a private temp file.
+fn upload_post(config: &ShareConfig, markdown: &str) -> Result<String> {
+    let Some(url) = &config.url else {
+        bail!("Set url under [share] for the post backend");
+    };
+    let mut headers = String::from("Content-Type: text/markdown; charset=utf-8\n");
+    for (name, value) in &config.headers {
+        let value =
+            shellexpand::env(value).with_context(|| format!("Failed to expand header {}", name))?;
+        headers.push_str(&format!("{}: {}\n", name, value));
+    }
+
+    let header_file = PrivateFile::create("headers", headers.as_bytes())?;
+    let output = run_with_stdin(
+        Command::new("curl").args([
+            "-sS",
+            "--fail-with-body",
+            "-H",
+            &format!("@{}", header_file.path.display()),
+            "--data-binary",
+            "@-",
+            url,
+        ]),
+        markdown.as_bytes(),
+    )
+    .context("Failed to run curl")?;
+    drop(header_file);
+    let response = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        bail!(
+            "Upload to {} failed: {} {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim(),
+            response.trim()
+        );
+    }
+    match &config.url_field {
+        Some(field) => serde_json::from_str::<serde_json::Value>(&response)
+            .ok()
+            .and_then(|json| json.get(field)?.as_str().map(str::to_owned))
+            .with_context(|| {
+                format!(
+                    "The response has no \"{}\" field: {}",
+                    field,
+                    response.trim()
+                )
+            }),
+        None => link_in(&response),
+    }
+}
+
+/// A file only this user can read, in a fresh directory of its own so a
+/// file or symlink planted in the shared temp dir can't be written through.
+/// Removed with its directory on drop.
+struct PrivateFile {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl PrivateFile {
+    fn create(name: &str, contents: &[u8]) -> Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let dir = std::env::temp_dir().join(format!(
+            "cc-sessions-share-{}-{}",
+            std::process::id(),
+            nanos
+        ));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let file = Self {
+            path: dir.join(name),
+            dir,
+        };
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(&file.path)
+            .and_then(|mut f| f.write_all(contents))
+            .with_context(|| format!("Failed to write {}", file.path.display()))?;
+        Ok(file)
+    }
+}
+
+impl Drop for PrivateFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn run_with_stdin(command: &mut Command, input: &[u8]) -> std::io::Result<std::process::Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input)?;
+    }
+    child.wait_with_output()
+}
+
+/// The first URL-looking line of a response.
+fn link_in(response: &str) -> Result<String> {
+    response
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with("http://") || line.starts_with("https://"))
+        .map(str::to_owned)
+        .with_context(|| format!("No link in the response: {}", response.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_parses_open_ends_and_clamps_to_the_session() {
+        let range = |s: &str| s.parse::<Range>();
+        assert_eq!(range("10-30").unwrap().select(100).unwrap(), 9..30);
+        assert_eq!(range("10-").unwrap().select(12).unwrap(), 9..12);
+        assert_eq!(range("-3").unwrap().select(12).unwrap(), 0..3);
+        assert_eq!(range("5").unwrap().select(12).unwrap(), 4..5);
+        assert_eq!(range("5-99").unwrap().select(12).unwrap(), 4..12);
+        assert!(range("13-").unwrap().select(12).is_err());
+        assert!(range("30-10").is_err());
+        assert!(range("0-3").is_err());
+        assert!(range("a-b").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_file_is_owner_only_and_removed_on_drop() {
+        use std::os::unix::fs::PermissionsExt;
+        let file = PrivateFile::create("headers", b"Authorization: x\n").unwrap();
+        let mode = |p: &std::path::Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&file.dir), 0o700);
+        assert_eq!(mode(&file.path), 0o600);
+        assert_eq!(fs::read(&file.path).unwrap(), b"Authorization: x\n");
+        let dir = file.dir.clone();
+        drop(file);
+        assert!(!dir.exists());
+    }
+}
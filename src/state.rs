@@ -0,0 +1,634 @@
+//! Local state persisted between runs.
+//!
+//! Unlike the config (user-edited) and the remote cache (rebuildable from
+//! remotes), state is written by cc-sessions itself and lives under
+//! `~/.local/state/cc-sessions/`. Each feature owns one small JSON file so
+//! a corrupt or outdated file only ever resets that feature.
+//!
+//! Files holding user decisions (fork overrides) can be shared between
+//! machines: every entry carries the time it was last changed, so two copies
+//! merge entry by entry without losing either side's edits.
+
+use crate::session::Session;
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory holding all state files.
+pub fn state_dir() -> Result<PathBuf> {
+    Ok(crate::profile::scoped(crate::paths::STATE.dir()?))
+}
+
+/// Load a state file, falling back to the default when missing or unreadable.
+/// State is best-effort: a bad file must never block listing sessions.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    state_dir()
+        .map(|dir| load_from(&dir.join(name)))
+        .unwrap_or_default()
+}
+
+/// Persist a state file, creating the state directory if needed.
+pub fn save<T: Serialize>(name: &str, value: &T) -> Result<()> {
+    save_to(&state_dir()?.join(name), value)
+}
+
+fn load_from<T: DeserializeOwned + Default>(path: &Path) -> T {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Write via a temp file + rename so a crash mid-write can't truncate state.
+fn save_to<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create state dir: {}", parent.display()))?;
+    }
+    let content = serde_json::to_string_pretty(value).context("Failed to serialize state")?;
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content)
+        .with_context(|| format!("Failed to write state file: {}", tmp.display()))?;
+    fs::rename(&tmp, path)
+        .with_context(|| format!("Failed to replace state file: {}", path.display()))?;
+    Ok(())
+}
+
+// =============================================================================
+// Search History
+// =============================================================================
+
+const SEARCH_HISTORY_FILE: &str = "search_history.json";
+
+/// Number of recent transcript searches kept for recall.
+const MAX_SEARCH_HISTORY: usize = 50;
+
+/// Recent transcript search queries, most recent first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SearchHistory {
+    pub queries: Vec<String>,
+}
+
+impl SearchHistory {
+    pub fn load() -> Self {
+        load(SEARCH_HISTORY_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        save(SEARCH_HISTORY_FILE, self)
+    }
+
+    /// Record a query at the front, dropping an older duplicate and
+    /// trimming to `MAX_SEARCH_HISTORY`.
+    pub fn record(&mut self, query: &str) {
+        self.queries.retain(|q| q != query);
+        self.queries.insert(0, query.to_string());
+        self.queries.truncate(MAX_SEARCH_HISTORY);
+    }
+}
+
+// =============================================================================
+// Resume History
+// =============================================================================
+
+const RESUME_HISTORY_FILE: &str = "resume_history.json";
+
+/// When each session was last resumed through cc-sessions (Unix ms). Unlike
+/// the transcript's mtime, this doesn't move when Claude Code compacts or
+/// rewrites a session on its own.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ResumeHistory {
+    pub resumed: BTreeMap<String, u64>,
+    /// How many times each session was resumed, for `--sort relevance`.
+    /// Older files have none, so earlier resumes count once.
+    #[serde(default)]
+    pub counts: BTreeMap<String, u32>,
+}
+
+impl ResumeHistory {
+    pub fn load() -> Self {
+        load(RESUME_HISTORY_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        save(RESUME_HISTORY_FILE, self)
+    }
+
+    pub fn record(&mut self, session_id: &str) {
+        self.resumed.insert(session_id.to_string(), now_ms());
+        *self.counts.entry(session_id.to_string()).or_insert(0) += 1;
+    }
+
+    /// The session resumed before the most recent one.
+    pub fn previous(&self) -> Option<&str> {
+        let mut recent: Vec<(&u64, &String)> = self.resumed.iter().map(|(id, t)| (t, id)).collect();
+        recent.sort_unstable_by(|a, b| b.cmp(a));
+        recent.get(1).map(|(_, id)| id.as_str())
+    }
+
+    /// Set `Session::resumed` and `resume_count` on every session.
+    pub fn apply(&self, sessions: &mut [Session]) {
+        for session in sessions {
+            session.resumed = self
+                .resumed
+                .get(&session.id)
+                .map(|&ms| UNIX_EPOCH + std::time::Duration::from_millis(ms));
+            session.resume_count = match self.counts.get(&session.id) {
+                Some(&count) => count,
+                None => u32::from(session.resumed.is_some()),
+            };
+        }
+    }
+}
+
+// =============================================================================
+// Workspaces
+// =============================================================================
+
+const WORKSPACES_FILE: &str = "workspaces.json";
+
+/// Named sets of session IDs (`ws save`, alt-w in the picker), reopened
+/// together with `--workspace NAME`. IDs keep the order they were added in.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Workspaces {
+    pub workspaces: BTreeMap<String, Vec<String>>,
+}
+
+impl Workspaces {
+    pub fn load() -> Self {
+        load(WORKSPACES_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        save(WORKSPACES_FILE, self)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.workspaces.get(name).map(Vec::as_slice)
+    }
+
+    /// Add sessions to `name`, creating it if needed. Returns how many
+    /// weren't in it yet.
+    pub fn add<'a>(&mut self, name: &str, ids: impl IntoIterator<Item = &'a str>) -> usize {
+        let members = self.workspaces.entry(name.to_string()).or_default();
+        let before = members.len();
+        for id in ids {
+            if !members.iter().any(|m| m == id) {
+                members.push(id.to_string());
+            }
+        }
+        members.len() - before
+    }
+
+    /// Drop the sessions whose IDs start with any of `prefixes` from `name`,
+    /// or the whole workspace if `prefixes` is empty. Returns how many
+    /// sessions went, or `None` if there is no such workspace.
+    pub fn remove(&mut self, name: &str, prefixes: &[String]) -> Option<usize> {
+        if prefixes.is_empty() {
+            return self.workspaces.remove(name).map(|ids| ids.len());
+        }
+        let members = self.workspaces.get_mut(name)?;
+        let before = members.len();
+        members.retain(|id| !prefixes.iter().any(|p| id.starts_with(p.as_str())));
+        Some(before - members.len())
+    }
+}
+
+// =============================================================================
+// Seen Sessions
+// =============================================================================
+
+const SEEN_FILE: &str = "seen.json";
+
+/// How a session differs from when it was last shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    New,
+    Updated,
+}
+
+/// Transcript size of each session when it was last shown in the picker or
+/// the `--list` table, for `--new-only` and the per-remote "N new" notice.
+/// Size rather than mtime: remote times move with clock-skew correction,
+/// while a transcript only changes by growing.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SeenSessions {
+    pub seen: BTreeMap<String, u64>,
+}
+
+impl SeenSessions {
+    pub fn load() -> Self {
+        load(SEEN_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        save(SEEN_FILE, self)
+    }
+
+    /// With nothing recorded yet (first run), take every session as seen,
+    /// so the first report isn't "everything is new".
+    pub fn start(&mut self, sessions: &[Session]) {
+        if self.seen.is_empty() {
+            self.record(sessions);
+        }
+    }
+
+    pub fn record<'a>(&mut self, sessions: impl IntoIterator<Item = &'a Session>) {
+        for session in sessions {
+            self.seen.insert(session.id.clone(), session.size);
+        }
+    }
+
+    /// `None` if `session` looks as it did when last shown. Archiving
+    /// changes the size without changing the session, so it doesn't count.
+    pub fn change(&self, session: &Session) -> Option<Change> {
+        match self.seen.get(&session.id) {
+            None => Some(Change::New),
+            Some(&size) if size != session.size && !session.archived => Some(Change::Updated),
+            Some(_) => None,
+        }
+    }
+}
+
+// =============================================================================
+// Fork Overrides
+// =============================================================================
+
+pub const FORK_OVERRIDES_FILE: &str = "fork_overrides.json";
+
+/// What the user decided for a fork whose recorded parent doesn't fit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForkDecision {
+    /// Nest under a different session.
+    Reparent { parent: String },
+    /// Treat as a root session.
+    Flatten,
+}
+
+/// Per-fork decisions keyed by fork session ID, applied over the
+/// `forkedFrom` parent read from transcripts.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForkOverrides {
+    pub forks: BTreeMap<String, ForkDecision>,
+    /// When each fork's decision was last set or reset (Unix ms). Kept after
+    /// a reset, so a merge can tell a removed decision from one this copy
+    /// never saw. Files from before syncing have none (treated as 0).
+    #[serde(default)]
+    pub changed: BTreeMap<String, u64>,
+}
+
+impl ForkOverrides {
+    pub fn load() -> Self {
+        load(FORK_OVERRIDES_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        save(FORK_OVERRIDES_FILE, self)
+    }
+
+    pub fn set(&mut self, fork: &str, decision: ForkDecision) {
+        self.forks.insert(fork.to_string(), decision);
+        self.changed.insert(fork.to_string(), now_ms());
+    }
+
+    /// Forget the decision for `fork`; false if there was none.
+    pub fn reset(&mut self, fork: &str) -> bool {
+        let removed = self.forks.remove(fork).is_some();
+        if removed {
+            self.changed.insert(fork.to_string(), now_ms());
+        }
+        removed
+    }
+
+    /// Take every entry `other` changed more recently; returns whether
+    /// anything changed. Ties (e.g. two untimestamped files) go to the
+    /// greater decision, so merging in either direction agrees.
+    pub fn merge(&mut self, other: &ForkOverrides) -> bool {
+        let mut updated = false;
+        for id in other.forks.keys().chain(other.changed.keys()) {
+            let key = |side: &ForkOverrides| {
+                let decision = side.forks.get(id);
+                let order = decision.map(|d| serde_json::to_string(d).unwrap_or_default());
+                (side.changed.get(id).copied().unwrap_or(0), order)
+            };
+            if key(other) <= key(self) {
+                continue;
+            }
+            match other.forks.get(id) {
+                Some(decision) => self.forks.insert(id.clone(), decision.clone()),
+                None => self.forks.remove(id),
+            };
+            if let Some(&at) = other.changed.get(id) {
+                self.changed.insert(id.clone(), at);
+            }
+            updated = true;
+        }
+        updated
+    }
+
+    /// Rewrite `forked_from` for every session with a recorded decision.
+    /// Flattening goes first; a reparent that would make a session its own
+    /// ancestor (possible when decisions arrive from another machine) is
+    /// skipped with a warning.
+    pub fn apply(&self, sessions: &mut [Session]) {
+        for session in sessions.iter_mut() {
+            if self.forks.get(&session.id) == Some(&ForkDecision::Flatten) {
+                session.forked_from = None;
+            }
+        }
+        let index: std::collections::HashMap<String, usize> = sessions
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.id.clone(), i))
+            .collect();
+        for (fork, decision) in &self.forks {
+            let ForkDecision::Reparent { parent } = decision else {
+                continue;
+            };
+            let Some(&i) = index.get(fork) else {
+                continue;
+            };
+            if descends_from(sessions, &index, parent, fork) {
+                eprintln!(
+                    "Warning: ignoring the recorded parent {} of {}: it would create a cycle",
+                    parent, fork
+                );
+                continue;
+            }
+            sessions[i].forked_from = Some(parent.clone());
+        }
+    }
+}
+
+/// Whether `ancestor` is `id` or appears on its parent chain.
+fn descends_from(
+    sessions: &[Session],
+    index: &std::collections::HashMap<String, usize>,
+    id: &str,
+    ancestor: &str,
+) -> bool {
+    let mut current = Some(id);
+    // Bounded, in case the transcripts themselves already hold a cycle
+    for _ in 0..=sessions.len() {
+        let Some(id) = current else {
+            return false;
+        };
+        if id == ancestor {
+            return true;
+        }
+        current = index
+            .get(id)
+            .and_then(|&i| sessions[i].forked_from.as_deref());
+    }
+    false
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// =============================================================================
+// Shared State (settings.sync_state / settings.state_sync_dir)
+// =============================================================================
+
+/// Merge this machine's fork decisions with another copy of the file (on a
+/// remote or in `state_sync_dir`), given as its text if it exists. Local
+/// state is saved if it gained anything. Returns the merged text when the
+/// other copy lacks something and should be replaced with it.
+pub fn merge_fork_overrides(other: Option<&str>) -> Result<Option<String>> {
+    let mut theirs: ForkOverrides = other
+        .and_then(|text| serde_json::from_str(text).ok())
+        .unwrap_or_default();
+    let mut local = ForkOverrides::load();
+    if local.merge(&theirs) {
+        local.save()?;
+    }
+    if !theirs.merge(&local) && other.is_some() {
+        return Ok(None);
+    }
+    let text = serde_json::to_string_pretty(&local).context("Failed to serialize state")?;
+    Ok(Some(text))
+}
+
+/// Two-way merge of the fork decisions with the copy in `dir` (a folder
+/// synced by other means, e.g. Syncthing or a git checkout).
+pub fn sync_with_dir(dir: &Path) -> Result<()> {
+    let path = dir.join(FORK_OVERRIDES_FILE);
+    let text = fs::read_to_string(&path).ok();
+    if let Some(merged) = merge_fork_overrides(text.as_deref())? {
+        let value: serde_json::Value =
+            serde_json::from_str(&merged).context("Failed to serialize state")?;
+        save_to(&path, &value)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seen_sessions_report_new_and_grown_sessions() {
+        let mut old = Session::fixture("old");
+        let mut seen = SeenSessions::default();
+        seen.start(std::slice::from_ref(&old));
+        assert_eq!(seen.change(&old), None);
+
+        let fresh = Session::fixture("fresh");
+        seen.start(std::slice::from_ref(&fresh));
+        assert_eq!(seen.change(&fresh), Some(Change::New));
+
+        old.size += 100;
+        assert_eq!(seen.change(&old), Some(Change::Updated));
+        old.archived = true;
+        assert_eq!(seen.change(&old), None);
+
+        seen.record([&old, &fresh]);
+        old.archived = false;
+        assert_eq!((seen.change(&old), seen.change(&fresh)), (None, None));
+    }
+
+    #[test]
+    fn search_history_dedupes_and_caps() {
+        let mut history = SearchHistory::default();
+        history.record("api");
+        history.record("auth");
+        history.record("api");
+        assert_eq!(history.queries, ["api", "auth"]);
+
+        for i in 0..MAX_SEARCH_HISTORY + 5 {
+            history.record(&format!("q{i}"));
+        }
+        assert_eq!(history.queries.len(), MAX_SEARCH_HISTORY);
+        assert_eq!(history.queries[0], format!("q{}", MAX_SEARCH_HISTORY + 4));
+    }
+
+    #[test]
+    fn resume_history_sets_resumed_times() {
+        let mut sessions = vec![Session::fixture("a"), Session::fixture("b")];
+        let mut history = ResumeHistory {
+            resumed: BTreeMap::from([("b".to_string(), 1_500)]),
+            counts: BTreeMap::new(),
+        };
+        history.apply(&mut sessions);
+        assert_eq!(sessions[0].resumed, None);
+        assert_eq!(
+            sessions[1].resumed,
+            Some(UNIX_EPOCH + std::time::Duration::from_millis(1_500))
+        );
+        assert_eq!((sessions[0].resume_count, sessions[1].resume_count), (0, 1));
+        history.record("b");
+        history.record("b");
+        history.apply(&mut sessions);
+        assert_eq!(sessions[1].resume_count, 2);
+    }
+
+    #[test]
+    fn previous_is_the_second_most_recent_resume() {
+        let mut history = ResumeHistory::default();
+        assert_eq!(history.previous(), None);
+        history.resumed = BTreeMap::from([
+            ("a".to_string(), 3_000),
+            ("b".to_string(), 1_000),
+            ("c".to_string(), 2_000),
+        ]);
+        assert_eq!(history.previous(), Some("c"));
+        history.resumed.insert("c".to_string(), 4_000);
+        assert_eq!(history.previous(), Some("a"));
+    }
+
+    #[test]
+    fn load_missing_or_corrupt_file_yields_default() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("search_history.json");
+        let history: SearchHistory = load_from(&path);
+        assert!(history.queries.is_empty());
+
+        fs::write(&path, "not json").unwrap();
+        let history: SearchHistory = load_from(&path);
+        assert!(history.queries.is_empty());
+    }
+
+    #[test]
+    fn fork_overrides_rewrite_parents() {
+        let mut sessions = vec![
+            Session::fixture("a"),
+            Session::fixture("b"),
+            Session::fixture("c"),
+        ];
+        sessions[0].forked_from = Some("gone".to_string());
+        sessions[1].forked_from = Some("gone".to_string());
+        sessions[2].forked_from = Some("gone".to_string());
+
+        let overrides = ForkOverrides {
+            changed: BTreeMap::new(),
+            forks: BTreeMap::from([
+                (
+                    "a".to_string(),
+                    ForkDecision::Reparent {
+                        parent: "c".to_string(),
+                    },
+                ),
+                ("b".to_string(), ForkDecision::Flatten),
+            ]),
+        };
+        overrides.apply(&mut sessions);
+        assert_eq!(sessions[0].forked_from.as_deref(), Some("c"));
+        assert_eq!(sessions[1].forked_from, None);
+        assert_eq!(sessions[2].forked_from.as_deref(), Some("gone"));
+    }
+
+    #[test]
+    fn fork_overrides_skip_reparents_that_close_a_cycle() {
+        let mut sessions = vec![
+            Session::fixture("a"),
+            Session::fixture("b"),
+            Session::fixture("c"),
+        ];
+        sessions[1].forked_from = Some("a".to_string());
+        sessions[2].forked_from = Some("b".to_string());
+        let reparent = |parent: &str| ForkDecision::Reparent {
+            parent: parent.to_string(),
+        };
+
+        let overrides = ForkOverrides {
+            changed: BTreeMap::new(),
+            forks: BTreeMap::from([
+                ("a".to_string(), reparent("c")),
+                ("b".to_string(), reparent("b")),
+            ]),
+        };
+        overrides.apply(&mut sessions);
+        assert_eq!(sessions[0].forked_from, None);
+        assert_eq!(sessions[1].forked_from.as_deref(), Some("a"));
+        assert_eq!(sessions[2].forked_from.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn fork_overrides_merge_newest_change_wins() {
+        let flatten = |at| ForkOverrides {
+            forks: BTreeMap::from([("a".to_string(), ForkDecision::Flatten)]),
+            changed: BTreeMap::from([("a".to_string(), at)]),
+        };
+        let mut laptop = flatten(10);
+        laptop.reset("a");
+        let mut desktop = flatten(20);
+        desktop.set(
+            "b",
+            ForkDecision::Reparent {
+                parent: "c".to_string(),
+            },
+        );
+
+        // The reset on the laptop is newer than the desktop's decision.
+        let mut merged = desktop.clone();
+        assert!(merged.merge(&laptop));
+        assert!(!merged.forks.contains_key("a"));
+        assert!(merged.forks.contains_key("b"));
+        assert!(laptop.merge(&desktop));
+        assert_eq!(laptop, merged);
+        assert!(!laptop.merge(&merged));
+
+        // Untimestamped (pre-sync) files still converge.
+        let legacy: ForkOverrides =
+            serde_json::from_str(r#"{"forks":{"x":{"reparent":{"parent":"y"}}}}"#).unwrap();
+        let mut other = ForkOverrides::default();
+        assert!(other.merge(&legacy));
+        assert_eq!(other.forks, legacy.forks);
+    }
+
+    #[test]
+    fn workspaces_add_without_duplicates_and_remove() {
+        let mut ws = Workspaces::default();
+        assert_eq!(ws.add("infra", ["aaa-1", "bbb-2"]), 2);
+        assert_eq!(ws.add("infra", ["bbb-2", "ccc-3"]), 1);
+        assert_eq!(ws.get("infra").unwrap(), ["aaa-1", "bbb-2", "ccc-3"]);
+
+        assert_eq!(ws.remove("infra", &["bbb".to_string()]), Some(1));
+        assert_eq!(ws.get("infra").unwrap(), ["aaa-1", "ccc-3"]);
+        assert_eq!(ws.remove("nope", &[]), None);
+        assert_eq!(ws.remove("infra", &[]), Some(2));
+        assert!(ws.get("infra").is_none());
+    }
+
+    #[test]
+    fn save_then_load_round_trips() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("nested/search_history.json");
+        let mut history = SearchHistory::default();
+        history.record("cs:Arc");
+        save_to(&path, &history).unwrap();
+
+        let loaded: SearchHistory = load_from(&path);
+        assert_eq!(loaded.queries, ["cs:Arc"]);
+        assert!(!path.with_extension("tmp").exists());
+    }
+}
@@ -0,0 +1,1388 @@
+//! Aggregate views over discovered sessions (`cc-sessions stats`).
+//!
+//! Aggregation is pure over `&[Session]` so every view works on whatever
+//! discovery and the usual filters (`--project`, `--remote`, `--min-turns`)
+//! produced. Rendering returns strings; `main.rs` decides where they go.
+//! Every view also has a JSON form (`--json`) with a versioned schema.
+
+use crate::dates::{day_of, format_day};
+use crate::pricing::{Cost, PriceTable};
+use crate::session::{Session, TokenUsage};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::time::{Duration, SystemTime};
+
+// =============================================================================
+// Aggregation
+// =============================================================================
+
+/// Session count, turns, and tokens for some group of sessions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Totals {
+    pub sessions: usize,
+    pub turns: usize,
+    pub tokens: TokenUsage,
+    /// Calls to tools that ask for permission, and how many were refused.
+    pub permission_requests: u64,
+    pub permission_denials: u64,
+    /// Sessions forked from another one.
+    pub forks: usize,
+    /// Earliest and latest activity of any session in the group.
+    pub first_activity: Option<SystemTime>,
+    pub last_activity: Option<SystemTime>,
+}
+
+impl Totals {
+    fn add(&mut self, session: &Session) {
+        self.sessions += 1;
+        self.turns += session.turn_count;
+        self.tokens += session.tokens;
+        self.permission_requests += u64::from(session.permission_requests);
+        self.permission_denials += u64::from(session.permission_denials());
+        self.forks += usize::from(session.forked_from.is_some());
+        let first = session.first_activity.unwrap_or(session.created);
+        let last = session.last_activity.unwrap_or(session.modified);
+        self.merge_activity(Some(first), Some(last));
+    }
+
+    fn merge_activity(&mut self, first: Option<SystemTime>, last: Option<SystemTime>) {
+        self.first_activity = match (self.first_activity, first) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.last_activity = self.last_activity.max(last);
+    }
+}
+
+/// Shape of a project's per-session turn counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TurnDistribution {
+    pub mean: f64,
+    pub median: usize,
+    pub p90: usize,
+}
+
+impl TurnDistribution {
+    /// Summarize turn counts; empty input yields all zeros.
+    fn of(mut turns: Vec<usize>) -> Self {
+        if turns.is_empty() {
+            return Self::default();
+        }
+        turns.sort_unstable();
+        Self {
+            mean: turns.iter().sum::<usize>() as f64 / turns.len() as f64,
+            median: percentile(&turns, 50),
+            p90: percentile(&turns, 90),
+        }
+    }
+}
+
+/// Nearest-rank percentile of an ascending, non-empty slice.
+fn percentile(sorted: &[usize], p: usize) -> usize {
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// One project's totals, overall and split by source.
+#[derive(Debug)]
+pub struct ProjectStats {
+    pub project: String,
+    pub total: Totals,
+    pub turns: TurnDistribution,
+    /// Keyed by source display name ("local", remote names).
+    pub by_source: BTreeMap<String, Totals>,
+}
+
+impl ProjectStats {
+    /// Sources in display order: local first, then remotes alphabetically.
+    pub fn sources(&self) -> impl Iterator<Item = (&str, &Totals)> {
+        sources_in(&self.by_source)
+    }
+}
+
+/// Entries of a per-source map with "local" first, then remotes alphabetically.
+fn sources_in<T>(by_source: &BTreeMap<String, T>) -> impl Iterator<Item = (&str, &T)> {
+    let local = by_source.get_key_value("local");
+    let remotes = by_source.iter().filter(|(name, _)| *name != "local");
+    local
+        .into_iter()
+        .chain(remotes)
+        .map(|(name, totals)| (name.as_str(), totals))
+}
+
+/// Roll sessions up by project, busiest (most sessions) first.
+pub fn by_project(sessions: &[&Session]) -> Vec<ProjectStats> {
+    let mut projects: BTreeMap<&str, ProjectStats> = BTreeMap::new();
+    let mut turn_counts: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for session in sessions {
+        let entry = projects
+            .entry(session.project.as_str())
+            .or_insert_with(|| ProjectStats {
+                project: session.project.clone(),
+                total: Totals::default(),
+                turns: TurnDistribution::default(),
+                by_source: BTreeMap::new(),
+            });
+        entry.total.add(session);
+        turn_counts
+            .entry(session.project.as_str())
+            .or_default()
+            .push(session.turn_count);
+        entry
+            .by_source
+            .entry(session.source.display_name().to_string())
+            .or_default()
+            .add(session);
+    }
+
+    for (project, turns) in turn_counts {
+        if let Some(entry) = projects.get_mut(project) {
+            entry.turns = TurnDistribution::of(turns);
+        }
+    }
+
+    let mut rows: Vec<ProjectStats> = projects.into_values().collect();
+    rows.sort_by(|a, b| {
+        b.total
+            .sessions
+            .cmp(&a.total.sessions)
+            .then_with(|| a.project.cmp(&b.project))
+    });
+    rows
+}
+
+/// Sum of all project totals.
+fn grand_total(rows: &[ProjectStats]) -> Totals {
+    let mut grand = Totals::default();
+    for row in rows {
+        add_totals(&mut grand, &row.total);
+    }
+    grand
+}
+
+/// Totals per source across all projects, local first.
+fn source_totals(rows: &[ProjectStats]) -> Vec<(&str, Totals)> {
+    let mut by_source: BTreeMap<&str, Totals> = BTreeMap::new();
+    for row in rows {
+        for (source, totals) in row.sources() {
+            add_totals(by_source.entry(source).or_default(), totals);
+        }
+    }
+    let local = by_source.remove("local").map(|t| ("local", t));
+    local.into_iter().chain(by_source).collect()
+}
+
+/// Ranking metric for the `--top` leaderboard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RankBy {
+    #[default]
+    Turns,
+    Duration,
+    Size,
+}
+
+/// The `n` biggest sessions by `by`, largest first. Sessions without a
+/// recorded activity span rank as zero duration.
+pub fn top_sessions<'a>(sessions: &[&'a Session], by: RankBy, n: usize) -> Vec<&'a Session> {
+    let key = |s: &Session| match by {
+        RankBy::Turns => s.turn_count as u64,
+        RankBy::Duration => s.duration().map_or(0, |d| d.as_secs()),
+        RankBy::Size => s.size,
+    };
+    let mut ranked: Vec<&Session> = sessions.to_vec();
+    ranked.sort_by_key(|s| std::cmp::Reverse(key(s)));
+    ranked.truncate(n);
+    ranked
+}
+
+/// Token totals for one calendar week (Monday 00:00 UTC onwards).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WeekTokens {
+    /// Days since the Unix epoch of the week's Monday.
+    pub start_day: u64,
+    pub sessions: usize,
+    /// Prompt-side tokens: fresh input plus cache writes and reads.
+    pub input: u64,
+    pub output: u64,
+}
+
+/// Days since the epoch of the Monday on or before `day`. 1970-01-01 was a
+/// Thursday, hence the offset of 3; its first days (mtimes reset to the
+/// epoch) count in a week starting on day 0.
+fn week_start(day: u64) -> u64 {
+    day.saturating_sub((day + 3) % 7)
+}
+
+/// Bucket token usage into the `weeks` most recent calendar weeks ending
+/// with the week containing `now`, oldest first. Each session counts in the
+/// week of its last activity (falling back to mtime); sessions older than
+/// the window are dropped. Empty weeks are kept so gaps show in the chart.
+pub fn weekly_tokens(sessions: &[&Session], weeks: usize, now: SystemTime) -> Vec<WeekTokens> {
+    let current = week_start(day_of(now));
+    let first = current.saturating_sub(7 * weeks.saturating_sub(1) as u64);
+
+    let mut buckets: Vec<WeekTokens> = (first..=current)
+        .step_by(7)
+        .map(|start_day| WeekTokens {
+            start_day,
+            ..Default::default()
+        })
+        .collect();
+
+    for session in sessions {
+        let start = week_start(day_of(session.last_activity.unwrap_or(session.modified)));
+        if start < first || start > current {
+            continue;
+        }
+        let bucket = &mut buckets[((start - first) / 7) as usize];
+        bucket.sessions += 1;
+        bucket.input +=
+            session.tokens.input + session.tokens.cache_creation + session.tokens.cache_read;
+        bucket.output += session.tokens.output;
+    }
+    buckets
+}
+
+/// How much one MCP server was used.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct McpServerStats {
+    pub server: String,
+    /// Sessions that called any of its tools.
+    pub sessions: usize,
+    pub calls: u64,
+    /// Latest activity of a session that used it.
+    pub last_used: Option<SystemTime>,
+    /// Calls per tool, most used first.
+    pub tools: Vec<(String, u64)>,
+}
+
+/// Usage per MCP server, most called first. Servers in `configured` that
+/// no session called are listed last with zero calls, since those are the
+/// ones worth reconsidering.
+pub fn mcp_usage(sessions: &[&Session], configured: &BTreeSet<String>) -> Vec<McpServerStats> {
+    let mut servers: BTreeMap<&str, McpServerStats> = BTreeMap::new();
+    let mut tools: BTreeMap<(&str, &str), u64> = BTreeMap::new();
+    for session in sessions {
+        let mut used = BTreeSet::new();
+        for (name, &count) in &session.mcp_calls {
+            let Some((server, tool)) = crate::session::mcp_tool(name) else {
+                continue;
+            };
+            let stats = servers.entry(server).or_default();
+            stats.calls += u64::from(count);
+            *tools.entry((server, tool)).or_default() += u64::from(count);
+            if used.insert(server) {
+                stats.sessions += 1;
+                let active = session.last_activity.unwrap_or(session.modified);
+                stats.last_used = stats.last_used.max(Some(active));
+            }
+        }
+    }
+    for ((server, tool), calls) in tools {
+        if let Some(stats) = servers.get_mut(server) {
+            stats.tools.push((tool.to_string(), calls));
+        }
+    }
+    let mut rows: Vec<McpServerStats> = servers
+        .into_iter()
+        .map(|(server, mut stats)| {
+            stats.server = server.to_string();
+            stats
+                .tools
+                .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            stats
+        })
+        .collect();
+    for server in configured {
+        if !rows.iter().any(|r| &r.server == server) {
+            rows.push(McpServerStats {
+                server: server.clone(),
+                ..Default::default()
+            });
+        }
+    }
+    rows.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.server.cmp(&b.server)));
+    rows
+}
+
+/// Estimated spend for a group of sessions.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CostTotals {
+    pub sessions: usize,
+    pub tokens: TokenUsage,
+    pub cost: Cost,
+}
+
+impl CostTotals {
+    fn add(&mut self, other: &CostTotals) {
+        self.sessions += other.sessions;
+        self.tokens += other.tokens;
+        self.cost += other.cost;
+    }
+}
+
+/// One project's estimated spend, overall and split by source.
+#[derive(Debug)]
+pub struct ProjectCosts {
+    pub project: String,
+    pub total: CostTotals,
+    pub by_source: BTreeMap<String, CostTotals>,
+}
+
+impl ProjectCosts {
+    /// Sources in display order: local first, then remotes alphabetically.
+    fn sources(&self) -> impl Iterator<Item = (&str, &CostTotals)> {
+        sources_in(&self.by_source)
+    }
+}
+
+/// Price every session and roll up by project and source, priciest first.
+pub fn costs_by_project(sessions: &[&Session], prices: &PriceTable) -> Vec<ProjectCosts> {
+    let mut projects: BTreeMap<&str, ProjectCosts> = BTreeMap::new();
+    for session in sessions {
+        let one = CostTotals {
+            sessions: 1,
+            tokens: session.tokens,
+            cost: prices.cost(&session.model_usage),
+        };
+        let entry = projects
+            .entry(session.project.as_str())
+            .or_insert_with(|| ProjectCosts {
+                project: session.project.clone(),
+                total: CostTotals::default(),
+                by_source: BTreeMap::new(),
+            });
+        entry.total.add(&one);
+        entry
+            .by_source
+            .entry(session.source.display_name().to_string())
+            .or_default()
+            .add(&one);
+    }
+
+    let mut rows: Vec<ProjectCosts> = projects.into_values().collect();
+    rows.sort_by(|a, b| {
+        b.total
+            .cost
+            .usd
+            .total_cmp(&a.total.cost.usd)
+            .then_with(|| a.project.cmp(&b.project))
+    });
+    rows
+}
+
+/// Spend per source across all projects, local first.
+fn cost_source_totals(rows: &[ProjectCosts]) -> Vec<(&str, CostTotals)> {
+    let mut by_source: BTreeMap<&str, CostTotals> = BTreeMap::new();
+    for row in rows {
+        for (source, totals) in row.sources() {
+            by_source.entry(source).or_default().add(totals);
+        }
+    }
+    let local = by_source.remove("local").map(|t| ("local", t));
+    local.into_iter().chain(by_source).collect()
+}
+
+// =============================================================================
+// Rendering
+// =============================================================================
+
+const PROJECT_WIDTH: usize = 24;
+const RULE_WIDTH: usize = 70;
+const MATRIX_RULE_WIDTH: usize = 80;
+const PROJECTS_RULE_WIDTH: usize = 108;
+
+/// One row per project, with the turn distribution (mean / median / p90
+/// turns per session) to tell long interactive projects from one-shots.
+pub fn render_projects(rows: &[ProjectStats]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<PROJECT_WIDTH$} {:>8} {:>6} {:>8} {:>6} {:>6} {:>6} {:>10} {:>9} {:>10}",
+        "PROJECT",
+        "SESSIONS",
+        "FORKS",
+        "TURNS",
+        "MEAN",
+        "MEDIAN",
+        "P90",
+        "TOKENS",
+        "DENIED",
+        "LAST"
+    );
+    let _ = writeln!(out, "{}", "─".repeat(PROJECTS_RULE_WIDTH));
+    for row in rows {
+        let _ = writeln!(
+            out,
+            "{:<PROJECT_WIDTH$} {:>8} {:>6} {:>8} {:>6.1} {:>6} {:>6} {:>10} {:>9} {:>10}",
+            fit(&row.project),
+            row.total.sessions,
+            row.total.forks,
+            row.total.turns,
+            row.turns.mean,
+            row.turns.median,
+            row.turns.p90,
+            format_tokens(row.total.tokens.total()),
+            totals_permissions(&row.total),
+            last_day(&row.total)
+        );
+    }
+    let grand = grand_total(rows);
+    let _ = writeln!(out, "{}", "─".repeat(PROJECTS_RULE_WIDTH));
+    let _ = writeln!(
+        out,
+        "{:<PROJECT_WIDTH$} {:>8} {:>6} {:>8} {:>6} {:>6} {:>6} {:>10} {:>9} {:>10}",
+        "Total",
+        grand.sessions,
+        grand.forks,
+        grand.turns,
+        "",
+        "",
+        "",
+        format_tokens(grand.tokens.total()),
+        totals_permissions(&grand),
+        last_day(&grand)
+    );
+    out
+}
+
+/// Project × source matrix: each project lists its per-source split, with a
+/// subtotal line when the project spans more than one machine.
+pub fn render_source_matrix(rows: &[ProjectStats]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<PROJECT_WIDTH$} {:<10} {:>8} {:>8} {:>10} {:>9}",
+        "PROJECT", "SOURCE", "SESSIONS", "TURNS", "TOKENS", "DENIED"
+    );
+    let _ = writeln!(out, "{}", "─".repeat(MATRIX_RULE_WIDTH));
+
+    for row in rows {
+        let mut project = fit(&row.project);
+        for (source, totals) in row.sources() {
+            let _ = writeln!(out, "{}", matrix_line(&project, source, totals));
+            project.clear();
+        }
+        if row.by_source.len() > 1 {
+            let _ = writeln!(out, "{}", matrix_line("", "all", &row.total));
+        }
+    }
+
+    let _ = writeln!(out, "{}", "─".repeat(MATRIX_RULE_WIDTH));
+    for (source, totals) in source_totals(rows) {
+        let _ = writeln!(out, "{}", matrix_line("Total", source, &totals));
+    }
+    out
+}
+
+/// Leaderboard of individual sessions, all metrics shown, ranked by one.
+pub fn render_top(sessions: &[&Session]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:>3} {:>6} {:>8} {:>8} {:<8} {:<16} SUMMARY",
+        "#", "TURNS", "DURATION", "SIZE", "SOURCE", "PROJECT"
+    );
+    let _ = writeln!(out, "{}", "─".repeat(100));
+    for (i, session) in sessions.iter().enumerate() {
+        let duration = session.duration().map_or("-".to_string(), format_duration);
+        let _ = writeln!(
+            out,
+            "{:>3} {:>6} {:>8} {:>8} {:<8} {:<16} {}",
+            i + 1,
+            session.turn_count,
+            duration,
+            format_size(session.size),
+            session.source.display_name(),
+            session.project,
+            crate::format_session_desc(session, 50)
+        );
+    }
+    out
+}
+
+const TREND_BAR_WIDTH: usize = 40;
+
+/// Weekly token bar chart. Each bar is scaled to the busiest week; the
+/// output share is drawn solid (`█`) after the input share (`▒`).
+pub fn render_trend(weeks: &[WeekTokens]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<10} {:>8} {:>10} {:>10}  ▒ input  █ output",
+        "WEEK", "SESSIONS", "INPUT", "OUTPUT"
+    );
+    let _ = writeln!(out, "{}", "─".repeat(42 + TREND_BAR_WIDTH));
+
+    let max = weeks.iter().map(|w| w.input + w.output).max().unwrap_or(0);
+    for week in weeks {
+        let total = week.input + week.output;
+        let (input_cells, output_cells) = if max == 0 {
+            (0, 0)
+        } else {
+            let cells = |n: u64| (n as f64 / max as f64 * TREND_BAR_WIDTH as f64).round() as usize;
+            // Keep a visible sliver for any nonzero output, which is usually
+            // dwarfed by cached input.
+            let output = cells(week.output).max(usize::from(week.output > 0));
+            (cells(total).saturating_sub(output), output)
+        };
+        let row = format!(
+            "{:<10} {:>8} {:>10} {:>10}  {}{}",
+            format_day(week.start_day),
+            week.sessions,
+            format_tokens(week.input),
+            format_tokens(week.output),
+            "▒".repeat(input_cells),
+            "█".repeat(output_cells)
+        );
+        let _ = writeln!(out, "{}", row.trim_end());
+    }
+    out
+}
+
+/// MCP usage per server, with its three most used tools.
+pub fn render_mcp(rows: &[McpServerStats]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<20} {:>8} {:>8} {:<10}  TOP TOOLS",
+        "SERVER", "SESSIONS", "CALLS", "LAST USED"
+    );
+    let _ = writeln!(out, "{}", "─".repeat(80));
+    for row in rows {
+        let last_used = row
+            .last_used
+            .map_or("never".to_string(), |t| format_day(day_of(t)));
+        let tools: Vec<String> = row
+            .tools
+            .iter()
+            .take(3)
+            .map(|(tool, calls)| format!("{} ({})", tool, calls))
+            .collect();
+        let line = format!(
+            "{:<20} {:>8} {:>8} {:<10}  {}",
+            row.server,
+            row.sessions,
+            row.calls,
+            last_used,
+            tools.join(", ")
+        );
+        let _ = writeln!(out, "{}", line.trim_end());
+    }
+    if rows.is_empty() {
+        let _ = writeln!(out, "No MCP tool calls or configured servers found");
+    }
+    out
+}
+
+/// Estimated spend per project × source, with per-source totals.
+pub fn render_costs(rows: &[ProjectCosts]) -> String {
+    let line = |project: &str, source: &str, t: &CostTotals| {
+        format!(
+            "{:<PROJECT_WIDTH$} {:<10} {:>8} {:>10} {:>10}",
+            project,
+            source,
+            t.sessions,
+            format_tokens(t.tokens.total()),
+            format_usd(t.cost.usd)
+        )
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<PROJECT_WIDTH$} {:<10} {:>8} {:>10} {:>10}",
+        "PROJECT", "SOURCE", "SESSIONS", "TOKENS", "EST. COST"
+    );
+    let _ = writeln!(out, "{}", "─".repeat(RULE_WIDTH));
+    for row in rows {
+        let mut project = fit(&row.project);
+        for (source, totals) in row.sources() {
+            let _ = writeln!(out, "{}", line(&project, source, totals));
+            project.clear();
+        }
+        if row.by_source.len() > 1 {
+            let _ = writeln!(out, "{}", line("", "all", &row.total));
+        }
+    }
+
+    let _ = writeln!(out, "{}", "─".repeat(RULE_WIDTH));
+    let mut grand = CostTotals::default();
+    for (source, totals) in cost_source_totals(rows) {
+        let _ = writeln!(out, "{}", line("Total", source, &totals));
+        grand.add(&totals);
+    }
+    let _ = writeln!(out, "{}", line("Total", "all", &grand));
+
+    if grand.cost.unpriced_tokens > 0 {
+        let _ = writeln!(
+            out,
+            "\nNote: {} tokens from models without a known price are excluded. \
+             Add [pricing.<model-prefix>] entries to the config to include them.",
+            format_tokens(grand.cost.unpriced_tokens)
+        );
+    }
+    out
+}
+
+/// Quote a CSV field if it contains a delimiter, quote, or newline.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// One CSV row per project × source, for expense reports.
+pub fn render_costs_csv(rows: &[ProjectCosts]) -> String {
+    let mut out = String::from(
+        "project,source,sessions,input_tokens,output_tokens,cache_creation_tokens,\
+         cache_read_tokens,unpriced_tokens,cost_usd\n",
+    );
+    for row in rows {
+        for (source, t) in row.sources() {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{:.4}",
+                csv_field(&row.project),
+                csv_field(source),
+                t.sessions,
+                t.tokens.input,
+                t.tokens.output,
+                t.tokens.cache_creation,
+                t.tokens.cache_read,
+                t.cost.unpriced_tokens,
+                t.cost.usd
+            );
+        }
+    }
+    out
+}
+
+fn add_totals(acc: &mut Totals, other: &Totals) {
+    acc.sessions += other.sessions;
+    acc.turns += other.turns;
+    acc.tokens += other.tokens;
+    acc.permission_requests += other.permission_requests;
+    acc.permission_denials += other.permission_denials;
+    acc.forks += other.forks;
+    acc.merge_activity(other.first_activity, other.last_activity);
+}
+
+fn matrix_line(project: &str, source: &str, totals: &Totals) -> String {
+    format!(
+        "{:<PROJECT_WIDTH$} {:<10} {:>8} {:>8} {:>10} {:>9}",
+        project,
+        source,
+        totals.sessions,
+        totals.turns,
+        format_tokens(totals.tokens.total()),
+        totals_permissions(totals)
+    )
+}
+
+/// Day of the group's latest activity, `-` for an empty group.
+fn last_day(totals: &Totals) -> String {
+    totals
+        .last_activity
+        .map_or_else(|| "-".to_string(), |t| format_day(day_of(t)))
+}
+
+fn totals_permissions(totals: &Totals) -> String {
+    format_permissions(totals.permission_denials, totals.permission_requests)
+}
+
+/// Refused out of permission-gated tool calls (`2/31`), `-` if there were
+/// none of either.
+pub fn format_permissions(denied: impl Into<u64>, requests: impl Into<u64>) -> String {
+    match (denied.into(), requests.into()) {
+        (0, 0) => "-".to_string(),
+        (denied, requests) => format!("{}/{}", denied, requests),
+    }
+}
+
+// =============================================================================
+// JSON Output
+// =============================================================================
+
+/// Version of the `stats --json` schema. Adding fields is backwards
+/// compatible; bump this when a field is removed, renamed, or changes meaning.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct JsonTokens {
+    input: u64,
+    output: u64,
+    cache_creation: u64,
+    cache_read: u64,
+    total: u64,
+}
+
+#[derive(Serialize)]
+struct JsonTotals {
+    sessions: usize,
+    turns: usize,
+    tokens: JsonTokens,
+    permission_requests: u64,
+    permission_denials: u64,
+    forks: usize,
+    first_activity: Option<String>,
+    last_activity: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonSourceTotals {
+    source: String,
+    #[serde(flatten)]
+    totals: JsonTotals,
+}
+
+#[derive(Serialize)]
+struct JsonTurnDistribution {
+    mean: f64,
+    median: usize,
+    p90: usize,
+}
+
+#[derive(Serialize)]
+struct JsonProject {
+    project: String,
+    #[serde(flatten)]
+    totals: JsonTotals,
+    turn_distribution: JsonTurnDistribution,
+    by_source: Vec<JsonSourceTotals>,
+}
+
+#[derive(Serialize)]
+struct JsonReport {
+    schema_version: u32,
+    projects: Vec<JsonProject>,
+    totals: JsonTotals,
+    by_source: Vec<JsonSourceTotals>,
+}
+
+impl From<&TokenUsage> for JsonTokens {
+    fn from(t: &TokenUsage) -> Self {
+        Self {
+            input: t.input,
+            output: t.output,
+            cache_creation: t.cache_creation,
+            cache_read: t.cache_read,
+            total: t.total(),
+        }
+    }
+}
+
+impl From<&Totals> for JsonTotals {
+    fn from(t: &Totals) -> Self {
+        Self {
+            sessions: t.sessions,
+            turns: t.turns,
+            tokens: JsonTokens::from(&t.tokens),
+            permission_requests: t.permission_requests,
+            permission_denials: t.permission_denials,
+            forks: t.forks,
+            first_activity: t.first_activity.map(crate::dates::format_timestamp),
+            last_activity: t.last_activity.map(crate::dates::format_timestamp),
+        }
+    }
+}
+
+fn json_source(source: &str, totals: &Totals) -> JsonSourceTotals {
+    JsonSourceTotals {
+        source: source.to_string(),
+        totals: totals.into(),
+    }
+}
+
+/// The per-project report as pretty JSON. Carries everything the table
+/// views show (including the per-source split), so one schema serves both.
+pub fn render_json(rows: &[ProjectStats]) -> Result<String> {
+    let report = JsonReport {
+        schema_version: JSON_SCHEMA_VERSION,
+        projects: rows
+            .iter()
+            .map(|row| JsonProject {
+                project: row.project.clone(),
+                totals: (&row.total).into(),
+                turn_distribution: JsonTurnDistribution {
+                    mean: row.turns.mean,
+                    median: row.turns.median,
+                    p90: row.turns.p90,
+                },
+                by_source: row.sources().map(|(s, t)| json_source(s, t)).collect(),
+            })
+            .collect(),
+        totals: (&grand_total(rows)).into(),
+        by_source: source_totals(rows)
+            .iter()
+            .map(|(s, t)| json_source(s, t))
+            .collect(),
+    };
+    serde_json::to_string_pretty(&report).context("Failed to serialize stats")
+}
+
+#[derive(Serialize)]
+struct JsonRankedSession {
+    rank: usize,
+    id: String,
+    project: String,
+    source: String,
+    turns: usize,
+    duration_secs: Option<u64>,
+    size_bytes: u64,
+    summary: Option<String>,
+    path: String,
+}
+
+#[derive(Serialize)]
+struct JsonLeaderboard {
+    schema_version: u32,
+    by: RankBy,
+    sessions: Vec<JsonRankedSession>,
+}
+
+/// The `--top` leaderboard as pretty JSON.
+pub fn render_top_json(sessions: &[&Session], by: RankBy) -> Result<String> {
+    let report = JsonLeaderboard {
+        schema_version: JSON_SCHEMA_VERSION,
+        by,
+        sessions: sessions
+            .iter()
+            .enumerate()
+            .map(|(i, s)| JsonRankedSession {
+                rank: i + 1,
+                id: s.id.clone(),
+                project: s.project.clone(),
+                source: s.source.display_name().to_string(),
+                turns: s.turn_count,
+                duration_secs: s.duration().map(|d| d.as_secs()),
+                size_bytes: s.size,
+                summary: s.name.clone().or(s.summary.clone()),
+                path: s.filepath.display().to_string(),
+            })
+            .collect(),
+    };
+    serde_json::to_string_pretty(&report).context("Failed to serialize stats")
+}
+
+#[derive(Serialize)]
+struct JsonWeek {
+    week_start: String,
+    sessions: usize,
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+#[derive(Serialize)]
+struct JsonTrend {
+    schema_version: u32,
+    weeks: Vec<JsonWeek>,
+}
+
+/// The weekly token trend as pretty JSON.
+pub fn render_trend_json(weeks: &[WeekTokens]) -> Result<String> {
+    let report = JsonTrend {
+        schema_version: JSON_SCHEMA_VERSION,
+        weeks: weeks
+            .iter()
+            .map(|w| JsonWeek {
+                week_start: format_day(w.start_day),
+                sessions: w.sessions,
+                input_tokens: w.input,
+                output_tokens: w.output,
+            })
+            .collect(),
+    };
+    serde_json::to_string_pretty(&report).context("Failed to serialize stats")
+}
+
+#[derive(Serialize)]
+struct JsonMcpTool {
+    tool: String,
+    calls: u64,
+}
+
+#[derive(Serialize)]
+struct JsonMcpServer {
+    server: String,
+    sessions: usize,
+    calls: u64,
+    last_used: Option<String>,
+    tools: Vec<JsonMcpTool>,
+}
+
+#[derive(Serialize)]
+struct JsonMcpReport {
+    schema_version: u32,
+    servers: Vec<JsonMcpServer>,
+}
+
+/// MCP usage per server as pretty JSON.
+pub fn render_mcp_json(rows: &[McpServerStats]) -> Result<String> {
+    let report = JsonMcpReport {
+        schema_version: JSON_SCHEMA_VERSION,
+        servers: rows
+            .iter()
+            .map(|row| JsonMcpServer {
+                server: row.server.clone(),
+                sessions: row.sessions,
+                calls: row.calls,
+                last_used: row.last_used.map(|t| format_day(day_of(t))),
+                tools: row
+                    .tools
+                    .iter()
+                    .map(|(tool, calls)| JsonMcpTool {
+                        tool: tool.clone(),
+                        calls: *calls,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    };
+    serde_json::to_string_pretty(&report).context("Failed to serialize stats")
+}
+
+#[derive(Serialize)]
+struct JsonCostTotals {
+    sessions: usize,
+    tokens: JsonTokens,
+    cost_usd: f64,
+    unpriced_tokens: u64,
+}
+
+#[derive(Serialize)]
+struct JsonSourceCost {
+    source: String,
+    #[serde(flatten)]
+    totals: JsonCostTotals,
+}
+
+#[derive(Serialize)]
+struct JsonProjectCost {
+    project: String,
+    #[serde(flatten)]
+    totals: JsonCostTotals,
+    by_source: Vec<JsonSourceCost>,
+}
+
+#[derive(Serialize)]
+struct JsonCostReport {
+    schema_version: u32,
+    projects: Vec<JsonProjectCost>,
+    by_source: Vec<JsonSourceCost>,
+}
+
+impl From<&CostTotals> for JsonCostTotals {
+    fn from(t: &CostTotals) -> Self {
+        Self {
+            sessions: t.sessions,
+            tokens: JsonTokens::from(&t.tokens),
+            cost_usd: t.cost.usd,
+            unpriced_tokens: t.cost.unpriced_tokens,
+        }
+    }
+}
+
+fn json_source_cost(source: &str, totals: &CostTotals) -> JsonSourceCost {
+    JsonSourceCost {
+        source: source.to_string(),
+        totals: totals.into(),
+    }
+}
+
+/// The cost report as pretty JSON.
+pub fn render_costs_json(rows: &[ProjectCosts]) -> Result<String> {
+    let report = JsonCostReport {
+        schema_version: JSON_SCHEMA_VERSION,
+        projects: rows
+            .iter()
+            .map(|row| JsonProjectCost {
+                project: row.project.clone(),
+                totals: (&row.total).into(),
+                by_source: row.sources().map(|(s, t)| json_source_cost(s, t)).collect(),
+            })
+            .collect(),
+        by_source: cost_source_totals(rows)
+            .iter()
+            .map(|(s, t)| json_source_cost(s, t))
+            .collect(),
+    };
+    serde_json::to_string_pretty(&report).context("Failed to serialize stats")
+}
+
+/// "$12.34"; sub-cent spend shows as "<$0.01" rather than a misleading $0.00.
+fn format_usd(usd: f64) -> String {
+    if usd > 0.0 && usd < 0.01 {
+        "<$0.01".to_string()
+    } else {
+        format!("${:.2}", usd)
+    }
+}
+
+/// Truncate a project name to the column width.
+fn fit(project: &str) -> String {
+    if project.chars().count() <= PROJECT_WIDTH {
+        return project.to_string();
+    }
+    let kept: String = project.chars().take(PROJECT_WIDTH - 1).collect();
+    format!("{}…", kept)
+}
+
+/// Compact token count: 950 → "950", 80_000 → "80.0K", 2_500_000 → "2.5M".
+pub fn format_tokens(n: u64) -> String {
+    match n {
+        0..1_000 => n.to_string(),
+        1_000..1_000_000 => format!("{:.1}K", n as f64 / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.1}M", n as f64 / 1e6),
+        _ => format!("{:.1}B", n as f64 / 1e9),
+    }
+}
+
+/// Coarse duration: "42m", "3h 05m", "2d 4h".
+pub fn format_duration(d: Duration) -> String {
+    let mins = d.as_secs() / 60;
+    match mins {
+        0..60 => format!("{}m", mins),
+        60..1440 => format!("{}h {:02}m", mins / 60, mins % 60),
+        _ => format!("{}d {}h", mins / 1440, (mins % 1440) / 60),
+    }
+}
+
+/// Human-readable byte size: "812 B", "12.3 KB", "4.5 MB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{RemoteResume, SessionSource};
+
+    fn refs(sessions: &[Session]) -> Vec<&Session> {
+        sessions.iter().collect()
+    }
+
+    fn session(project: &str, source: &str, turns: usize, tokens: u64) -> Session {
+        let mut s = Session::fixture(&format!("{project}-{source}-{turns}"));
+        s.project = project.to_string();
+        s.turn_count = turns;
+        s.tokens.output = tokens;
+        if source != "local" {
+            s.source = SessionSource::Remote {
+                name: source.to_string(),
+                host: source.to_string(),
+                user: None,
+                label: None,
+                color: None,
+                resume: RemoteResume::Ssh,
+            };
+        }
+        s
+    }
+
+    #[test]
+    fn by_project_splits_by_source() {
+        let sessions = vec![
+            session("grail", "local", 3, 100),
+            session("grail", "devbox", 5, 200),
+            session("grail", "devbox", 1, 50),
+            session("shrubbery", "local", 2, 10),
+        ];
+        let rows = by_project(&refs(&sessions));
+        assert_eq!(rows[0].project, "grail");
+        assert_eq!(rows[0].total.sessions, 3);
+        assert_eq!(rows[0].total.turns, 9);
+        assert_eq!(rows[0].total.tokens.total(), 350);
+
+        let sources: Vec<(&str, usize)> = rows[0]
+            .sources()
+            .map(|(name, t)| (name, t.sessions))
+            .collect();
+        assert_eq!(sources, [("local", 1), ("devbox", 2)]);
+        assert_eq!(rows[1].project, "shrubbery");
+    }
+
+    #[test]
+    fn sources_list_local_before_remotes() {
+        let sessions = vec![
+            session("grail", "zeta", 1, 0),
+            session("grail", "alpha", 1, 0),
+            session("grail", "local", 1, 0),
+        ];
+        let rows = by_project(&refs(&sessions));
+        let names: Vec<&str> = rows[0].sources().map(|(name, _)| name).collect();
+        assert_eq!(names, ["local", "alpha", "zeta"]);
+    }
+
+    #[test]
+    fn source_matrix_adds_subtotal_for_multi_source_projects() {
+        let sessions = vec![
+            session("grail", "local", 3, 100),
+            session("grail", "devbox", 5, 200),
+            session("shrubbery", "local", 2, 10),
+        ];
+        let out = render_source_matrix(&by_project(&refs(&sessions)));
+        let all_rows = out.lines().filter(|l| l.contains(" all ")).count();
+        assert_eq!(all_rows, 1);
+        assert!(
+            out.lines()
+                .all(|l| !l.starts_with("grail") || l.ends_with(" -"))
+        );
+        assert!(
+            out.lines()
+                .any(|l| l.starts_with("Total") && l.contains("devbox"))
+        );
+    }
+
+    #[test]
+    fn turn_distribution_uses_nearest_rank() {
+        let d = TurnDistribution::of(vec![1, 1, 2, 3, 4, 5, 6, 8, 10, 40]);
+        assert_eq!(d.mean, 8.0);
+        assert_eq!(d.median, 4);
+        assert_eq!(d.p90, 10);
+
+        let single = TurnDistribution::of(vec![7]);
+        assert_eq!((single.median, single.p90), (7, 7));
+        assert_eq!(
+            TurnDistribution::of(Vec::new()),
+            TurnDistribution::default()
+        );
+    }
+
+    #[test]
+    fn by_project_computes_turn_distribution() {
+        let sessions = vec![
+            session("grail", "local", 1, 0),
+            session("grail", "devbox", 3, 0),
+            session("grail", "local", 20, 0),
+        ];
+        let rows = by_project(&refs(&sessions));
+        assert_eq!(rows[0].turns.median, 3);
+        assert_eq!(rows[0].turns.p90, 20);
+        assert_eq!(rows[0].turns.mean, 8.0);
+    }
+
+    #[test]
+    fn json_report_has_stable_shape() {
+        use std::time::UNIX_EPOCH;
+
+        let mut sessions = vec![
+            session("grail", "local", 3, 100),
+            session("grail", "devbox", 5, 200),
+        ];
+        sessions[1].permission_requests = 4;
+        sessions[1].denied_tools = BTreeMap::from([("Bash".to_string(), 1)]);
+        sessions[1].forked_from = Some("grail-local-3".to_string());
+        for (s, day) in sessions.iter_mut().zip(0..) {
+            s.first_activity = Some(UNIX_EPOCH + Duration::from_secs(day * 86_400));
+            s.last_activity = s.first_activity;
+        }
+        let rows = by_project(&refs(&sessions));
+        assert!(
+            render_projects(&rows)
+                .lines()
+                .any(|l| l.starts_with("grail") && l.ends_with(" 1/4 1970-01-02"))
+        );
+        let json = render_json(&rows).unwrap();
+        let v: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(v["schema_version"], JSON_SCHEMA_VERSION);
+        let project = &v["projects"][0];
+        assert_eq!(project["project"], "grail");
+        assert_eq!(project["sessions"], 2);
+        assert_eq!(project["turns"], 8);
+        assert_eq!(project["tokens"]["output"], 300);
+        assert_eq!(project["tokens"]["total"], 300);
+        assert_eq!(project["turn_distribution"]["median"], 3);
+        assert_eq!(project["by_source"][0]["source"], "local");
+        assert_eq!(project["by_source"][1]["source"], "devbox");
+        assert_eq!(v["totals"]["sessions"], 2);
+        assert_eq!(v["by_source"][1]["turns"], 5);
+        assert_eq!(v["by_source"][1]["permission_denials"], 1);
+        assert_eq!(v["totals"]["permission_requests"], 4);
+        assert_eq!(project["forks"], 1);
+        assert_eq!(project["first_activity"], "1970-01-01T00:00:00Z");
+        assert_eq!(project["last_activity"], "1970-01-02T00:00:00Z");
+        assert_eq!(v["by_source"][0]["forks"], 0);
+    }
+
+    #[test]
+    fn top_sessions_ranks_by_metric() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let mut long = session("grail", "local", 2, 0);
+        long.first_activity = Some(UNIX_EPOCH);
+        long.last_activity = Some(UNIX_EPOCH + Duration::from_secs(7200));
+        let mut chatty = session("shrubbery", "local", 40, 0);
+        chatty.size = 10;
+        let mut big = session("parrot", "devbox", 5, 0);
+        big.size = 5_000_000;
+        let sessions = vec![long, chatty, big];
+
+        let project_of = |ranked: Vec<&Session>| -> Vec<String> {
+            ranked.iter().map(|s| s.project.clone()).collect()
+        };
+        assert_eq!(
+            project_of(top_sessions(&refs(&sessions), RankBy::Turns, 2)),
+            ["shrubbery", "parrot"]
+        );
+        assert_eq!(
+            project_of(top_sessions(&refs(&sessions), RankBy::Duration, 1)),
+            ["grail"]
+        );
+        assert_eq!(
+            project_of(top_sessions(&refs(&sessions), RankBy::Size, 1)),
+            ["parrot"]
+        );
+    }
+
+    #[test]
+    fn format_duration_and_size() {
+        assert_eq!(format_duration(Duration::from_secs(42 * 60)), "42m");
+        assert_eq!(
+            format_duration(Duration::from_secs(3 * 3600 + 300)),
+            "3h 05m"
+        );
+        assert_eq!(format_duration(Duration::from_secs(52 * 3600)), "2d 4h");
+        assert_eq!(format_size(812), "812 B");
+        assert_eq!(format_size(12_600), "12.3 KB");
+        assert_eq!(format_size(4_718_592), "4.5 MB");
+    }
+
+    #[test]
+    fn week_start_is_monday() {
+        // 2025-01-15 (Wednesday) is day 20103; its Monday is 2025-01-13
+        assert_eq!(format_day(week_start(20_103)), "2025-01-13");
+        assert_eq!(format_day(week_start(20_101)), "2025-01-13");
+        assert_eq!(format_day(week_start(20_100)), "2025-01-06");
+        // The epoch's Thursday to Saturday have no Monday before them
+        for day in 0..=2 {
+            assert_eq!(week_start(day), 0);
+        }
+        assert_eq!(format_day(week_start(4)), "1970-01-05");
+    }
+
+    #[test]
+    fn weekly_tokens_buckets_by_last_activity() {
+        let day = crate::dates::start_of_day;
+        let now = day(20_103); // Wed 2025-01-15
+
+        let mut this_week = session("grail", "local", 1, 100);
+        this_week.tokens.cache_read = 1000;
+        this_week.last_activity = Some(day(20_101)); // Mon
+        let mut last_week = session("grail", "local", 1, 50);
+        last_week.last_activity = Some(day(20_100)); // Sun before
+        let mut ancient = session("grail", "local", 1, 999);
+        ancient.last_activity = Some(day(19_000));
+
+        let sessions = [this_week, last_week, ancient];
+        let weeks = weekly_tokens(&refs(&sessions), 3, now);
+        assert_eq!(weeks.len(), 3);
+        assert_eq!(format_day(weeks[2].start_day), "2025-01-13");
+        assert_eq!((weeks[2].input, weeks[2].output), (1000, 100));
+        assert_eq!(weeks[1].output, 50);
+        assert_eq!(weeks[0].sessions, 0);
+    }
+
+    fn priced(project: &str, source: &str, sonnet_output: u64) -> Session {
+        let mut s = session(project, source, 1, sonnet_output);
+        s.model_usage.insert(
+            "claude-sonnet-4-5".to_string(),
+            TokenUsage {
+                output: sonnet_output,
+                ..Default::default()
+            },
+        );
+        s
+    }
+
+    #[test]
+    fn costs_by_project_sorts_by_spend() {
+        let prices = PriceTable::new(&Default::default());
+        let sessions = vec![
+            priced("grail", "local", 100_000),
+            priced("parrot", "local", 1_000_000),
+            priced("parrot", "devbox", 1_000_000),
+        ];
+        let rows = costs_by_project(&refs(&sessions), &prices);
+        assert_eq!(rows[0].project, "parrot");
+        assert!((rows[0].total.cost.usd - 30.0).abs() < 1e-9);
+        assert!((rows[0].by_source["devbox"].cost.usd - 15.0).abs() < 1e-9);
+        assert!((rows[1].total.cost.usd - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mcp_usage_aggregates_servers_and_lists_unused_ones() {
+        let mut a = session("grail", "local", 3, 0);
+        a.mcp_calls = BTreeMap::from([
+            ("mcp__github__create_issue".to_string(), 2),
+            ("mcp__github__search".to_string(), 5),
+        ]);
+        let mut b = session("grail", "devbox", 1, 0);
+        b.mcp_calls = BTreeMap::from([
+            ("mcp__github__search".to_string(), 1),
+            ("mcp__slack__post".to_string(), 1),
+        ]);
+        let configured = BTreeSet::from(["github".to_string(), "sentry".to_string()]);
+        let rows = mcp_usage(&refs(&[a, b]), &configured);
+
+        let summary: Vec<(&str, usize, u64)> = rows
+            .iter()
+            .map(|r| (r.server.as_str(), r.sessions, r.calls))
+            .collect();
+        assert_eq!(
+            summary,
+            [("github", 2, 8), ("slack", 1, 1), ("sentry", 0, 0)]
+        );
+        assert_eq!(
+            rows[0].tools,
+            [("search".to_string(), 6), ("create_issue".to_string(), 2)]
+        );
+        assert!(rows[2].last_used.is_none());
+        let table = render_mcp(&rows);
+        assert!(table.contains("search (6), create_issue (2)"));
+        assert!(table.lines().last().unwrap().starts_with("sentry"));
+        assert!(table.lines().last().unwrap().contains("never"));
+    }
+
+    #[test]
+    fn costs_csv_quotes_awkward_names() {
+        let prices = PriceTable::new(&Default::default());
+        let sessions = vec![priced("spam, eggs", "local", 1_000_000)];
+        let csv = render_costs_csv(&costs_by_project(&refs(&sessions), &prices));
+        let mut lines = csv.lines();
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .starts_with("project,source,sessions,")
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "\"spam, eggs\",local,1,0,1000000,0,0,0,15.0000"
+        );
+    }
+
+    #[test]
+    fn format_tokens_scales_units() {
+        assert_eq!(format_tokens(950), "950");
+        assert_eq!(format_tokens(80_000), "80.0K");
+        assert_eq!(format_tokens(2_500_000), "2.5M");
+        assert_eq!(format_tokens(3_000_000_000), "3.0B");
+    }
+}
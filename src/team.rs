@@ -0,0 +1,504 @@
+//! Team sources: sessions teammates publish to a shared location, listed
+//! next to your own so you can learn from each other's agent sessions.
+//!
+//! A team is a directory every member can read and write: a network drive,
+//! or a bucket mounted with its FUSE driver. `cc-sessions team publish`
+//! writes the member's own corner of it:
+//!
+//! ```text
+//! <path>/<owner>/index.json   session metadata (always)
+//! <path>/<owner>/<id>.jsonl   transcripts (with --transcripts)
+//! ```
+//!
+//! Each publish replaces the previous one, so the index is the member's
+//! whole contribution. Everything is redacted (`redact.rs`) on the way out.
+//! Discovery reads every other member's index as `SessionSource::Team`
+//! sessions: listed with an OWNER column, previewed and searched from the
+//! published transcript when there is one, never resumed.
+//!
+//! ```toml
+//! [teams.platform]
+//! path = "/mnt/shared/cc-sessions"
+//! label = "team"       # Optional: shown in the SOURCE column
+//! color = "magenta"    # Optional: label color
+//! owner = "alice"      # Optional: name to publish as (default: $USER)
+//! ```
+
+use crate::dates;
+use crate::redact::Redactor;
+use crate::session::{Session, SessionSource, SourceColor, TokenUsage};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Bumped on incompatible changes to `index.json`; newer indexes are
+/// skipped with a warning rather than misread.
+pub const INDEX_SCHEMA_VERSION: u32 = 1;
+
+const INDEX_FILE: &str = "index.json";
+
+/// `[teams.<name>]` config section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TeamConfig {
+    /// Shared directory (`~` is expanded)
+    pub path: String,
+    /// Short display label (defaults to the team name)
+    pub label: Option<String>,
+    pub color: Option<SourceColor>,
+    /// Name to publish as (default: `$USER`)
+    pub owner: Option<String>,
+}
+
+impl TeamConfig {
+    pub fn dir(&self) -> PathBuf {
+        PathBuf::from(shellexpand::tilde(&self.path).as_ref())
+    }
+
+    /// Who this machine publishes as.
+    pub fn owner(&self) -> Result<String> {
+        let owner = self
+            .owner
+            .clone()
+            .or_else(|| std::env::var("USER").ok())
+            .or_else(|| std::env::var("USERNAME").ok())
+            .filter(|owner| !owner.is_empty())
+            .context("Set owner under [teams.<name>] ($USER isn't set)")?;
+        if owner.starts_with('.') || owner.contains(['/', '\\']) {
+            bail!("'{}' can't be used as an owner name", owner);
+        }
+        Ok(owner)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Index {
+    schema_version: u32,
+    owner: String,
+    published: String,
+    sessions: Vec<Entry>,
+}
+
+/// One published session. Times are RFC 3339 UTC.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    id: String,
+    project: String,
+    project_path: String,
+    created: String,
+    modified: String,
+    first_message: Option<String>,
+    summary: Option<String>,
+    name: Option<String>,
+    tag: Option<String>,
+    git_branch: Option<String>,
+    turn_count: usize,
+    #[serde(default)]
+    tokens: TokenUsage,
+    forked_from: Option<String>,
+    /// `<id>.jsonl` sits next to the index
+    #[serde(default)]
+    transcript: bool,
+}
+
+// =============================================================================
+// Discovery
+// =============================================================================
+
+/// A member whose index couldn't be read, and why.
+pub type MemberFailure = (String, String);
+
+/// Sessions other members of team `name` published, and the members whose
+/// index couldn't be read.
+pub fn find_sessions(name: &str, team: &TeamConfig) -> Result<(Vec<Session>, Vec<MemberFailure>)> {
+    let dir = team.dir();
+    let members = fs::read_dir(&dir).with_context(|| format!("can't read {}", dir.display()))?;
+    let me = team.owner().ok();
+    let mut sessions = Vec::new();
+    let mut failures = Vec::new();
+    for member in members.filter_map(|e| e.ok()) {
+        let member_dir = member.path();
+        let index_path = member_dir.join(INDEX_FILE);
+        let owner = member.file_name().to_string_lossy().into_owned();
+        // Your own sessions are already listed from where they live
+        if owner.starts_with('.') || Some(&owner) == me.as_ref() || !index_path.is_file() {
+            continue;
+        }
+        match read_index(&index_path) {
+            Ok(index) => sessions.extend(index.sessions.into_iter().map(|entry| {
+                let source = SessionSource::Team {
+                    name: name.to_string(),
+                    owner: owner.clone(),
+                    label: team.label.clone(),
+                    color: team.color,
+                };
+                entry.into_session(&member_dir, source)
+            })),
+            Err(e) => failures.push((owner, format!("{:#}", e))),
+        }
+    }
+    Ok((sessions, failures))
+}
+
+fn read_index(path: &Path) -> Result<Index> {
+    let content = fs::read_to_string(path)?;
+    let index: Index = serde_json::from_str(&content).context("invalid index.json")?;
+    if index.schema_version > INDEX_SCHEMA_VERSION {
+        bail!(
+            "published by a newer cc-sessions (index schema {}); upgrade to read it",
+            index.schema_version
+        );
+    }
+    Ok(index)
+}
+
+impl Entry {
+    fn from_session(session: &Session, transcript: bool) -> Self {
+        Entry {
+            id: session.id.clone(),
+            project: session.project.clone(),
+            project_path: session.project_path.clone(),
+            created: dates::format_timestamp(session.created),
+            modified: dates::format_timestamp(session.modified),
+            first_message: session.first_message.clone(),
+            summary: session.summary.clone(),
+            name: session.name.clone(),
+            tag: session.tag.clone(),
+            git_branch: session.git_branch.clone(),
+            turn_count: session.turn_count,
+            tokens: session.tokens,
+            forked_from: session.forked_from.clone(),
+            transcript,
+        }
+    }
+
+    /// A session whose `filepath` is the published transcript, which
+    /// doesn't exist for metadata-only entries.
+    fn into_session(self, member_dir: &Path, source: SessionSource) -> Session {
+        let time = |s: &str| {
+            dates::parse_timestamp_ms(s)
+                .map_or(UNIX_EPOCH, |ms| UNIX_EPOCH + Duration::from_millis(ms))
+        };
+        let filepath = member_dir.join(format!("{}.jsonl", self.id));
+        let size = if self.transcript {
+            fs::metadata(&filepath).map_or(0, |m| m.len())
+        } else {
+            0
+        };
+        Session {
+            filepath,
+            created: time(&self.created),
+            modified: time(&self.modified),
+            id: self.id,
+            project: self.project,
+            project_path: self.project_path,
+            first_message: self.first_message,
+            summary: self.summary,
+            name: self.name,
+            tag: self.tag,
+            git_branch: self.git_branch,
+            turn_count: self.turn_count,
+            source,
+            forked_from: self.forked_from,
+            redundant_fork: false,
+            repo_root: None,
+            tokens: self.tokens,
+            model_usage: BTreeMap::new(),
+            size,
+            first_activity: None,
+            last_activity: None,
+            active_hours: BTreeMap::new(),
+            open_todos: Vec::new(),
+            plan_offset: None,
+            mcp_calls: BTreeMap::new(),
+            permission_requests: 0,
+            denied_tools: BTreeMap::new(),
+            partial: false,
+            archived: false,
+            resumed: None,
+            resume_count: 0,
+            relevance: 0.0,
+            clock_skew: None,
+            local_path: None,
+        }
+    }
+}
+
+// =============================================================================
+// Publishing
+// =============================================================================
+
+/// What a publish wrote (or, for a dry run, would write).
+#[derive(Debug, Default)]
+pub struct Published {
+    pub owner: String,
+    pub sessions: usize,
+    pub transcripts: usize,
+    /// Transcripts from the previous publish that were withdrawn
+    pub withdrawn: usize,
+    /// Values hidden by redaction
+    pub redacted: usize,
+}
+
+pub struct PublishOptions<'a> {
+    pub transcripts: bool,
+    /// `None` publishes as-is (`--no-redact`)
+    pub redactor: Option<&'a Redactor>,
+    pub dry_run: bool,
+}
+
+/// Replace this machine's publish to `team` with `sessions`.
+pub fn publish(
+    team: &TeamConfig,
+    sessions: &[&Session],
+    options: &PublishOptions,
+) -> Result<Published> {
+    let owner = team.owner()?;
+    let team_dir = team.dir();
+    if !team_dir.is_dir() {
+        bail!(
+            "Team directory {} doesn't exist (is the share mounted?)",
+            team_dir.display()
+        );
+    }
+    let dir = team_dir.join(&owner);
+    let mut published = Published {
+        owner: owner.clone(),
+        sessions: sessions.len(),
+        ..Published::default()
+    };
+    if !options.dry_run {
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    let redact = |text: &mut String, count: &mut usize| {
+        if let Some(redactor) = options.redactor {
+            let redacted = redactor.redact(text);
+            *count += redacted.count;
+            *text = redacted.text;
+        }
+    };
+    let mut entries = Vec::with_capacity(sessions.len());
+    for session in sessions {
+        let mut entry = Entry::from_session(session, options.transcripts);
+        for text in [
+            &mut entry.first_message,
+            &mut entry.summary,
+            &mut entry.name,
+            &mut entry.tag,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            redact(text, &mut published.redacted);
+        }
+        redact(&mut entry.project_path, &mut published.redacted);
+        if options.transcripts {
+            let dest = dir.join(format!("{}.jsonl", session.id));
+            published.redacted +=
+                copy_transcript(&session.filepath, &dest, options.redactor, options.dry_run)
+                    .with_context(|| format!("Failed to publish {}", session.id))?;
+            published.transcripts += 1;
+        }
+        entries.push(entry);
+    }
+
+    // Transcripts no longer in the index are withdrawn
+    let kept: HashSet<String> = entries
+        .iter()
+        .filter(|e| e.transcript)
+        .map(|e| format!("{}.jsonl", e.id))
+        .collect();
+    for old in fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+    {
+        let file_name = old.file_name().to_string_lossy().into_owned();
+        if file_name.ends_with(".jsonl") && !kept.contains(&file_name) {
+            if !options.dry_run {
+                fs::remove_file(old.path())
+                    .with_context(|| format!("Failed to withdraw {}", old.path().display()))?;
+            }
+            published.withdrawn += 1;
+        }
+    }
+
+    if !options.dry_run {
+        let index = Index {
+            schema_version: INDEX_SCHEMA_VERSION,
+            owner,
+            published: dates::format_timestamp(SystemTime::now()),
+            sessions: entries,
+        };
+        let content = serde_json::to_string_pretty(&index).context("Failed to serialize index")?;
+        write_replacing(&dir.join(INDEX_FILE), content.as_bytes())?;
+    }
+    Ok(published)
+}
+
+/// Remove this machine's publish from `team`. Returns whether there was one.
+pub fn unpublish(team: &TeamConfig) -> Result<bool> {
+    let dir = team.dir().join(team.owner()?);
+    if !dir.exists() {
+        return Ok(false);
+    }
+    fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+    Ok(true)
+}
+
+/// Copy a (possibly archived) transcript with every JSON string redacted,
+/// so the result is still valid JSONL. Returns the values hidden.
+fn copy_transcript(
+    src: &Path,
+    dest: &Path,
+    redactor: Option<&Redactor>,
+    dry_run: bool,
+) -> Result<usize> {
+    let reader = crate::archive::open(src)?;
+    let mut out = Vec::new();
+    let mut count = 0;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match (redactor, serde_json::from_str::<serde_json::Value>(&line)) {
+            (None, _) => out.extend_from_slice(line.as_bytes()),
+            (Some(redactor), Ok(mut entry)) => {
+                redact_strings(&mut entry, redactor, &mut count);
+                serde_json::to_writer(&mut out, &entry)?;
+            }
+            // Not JSON: redact it as text; readers skip it either way
+            (Some(redactor), Err(_)) => {
+                let redacted = redactor.redact(&line);
+                count += redacted.count;
+                out.extend_from_slice(redacted.text.as_bytes());
+            }
+        }
+        out.push(b'\n');
+    }
+    if !dry_run {
+        write_replacing(dest, &out)?;
+    }
+    Ok(count)
+}
+
+fn redact_strings(value: &mut serde_json::Value, redactor: &Redactor, count: &mut usize) {
+    use serde_json::Value;
+    match value {
+        Value::String(text) => {
+            let redacted = redactor.redact(text);
+            *count += redacted.count;
+            *text = redacted.text;
+        }
+        Value::Array(items) => {
+            for item in items {
+                redact_strings(item, redactor, count);
+            }
+        }
+        Value::Object(fields) => {
+            for field in fields.values_mut() {
+                redact_strings(field, redactor, count);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Write via a temp file + rename, so teammates never read a half-written
+/// file.
+fn write_replacing(path: &Path, content: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = BufWriter::new(
+        fs::File::create(&tmp).with_context(|| format!("Failed to write {}", tmp.display()))?,
+    );
+    file.write_all(content)
+        .and_then(|_| file.flush())
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    drop(file);
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn team(dir: &Path, owner: &str) -> TeamConfig {
+        TeamConfig {
+            path: dir.display().to_string(),
+            label: None,
+            color: None,
+            owner: Some(owner.to_string()),
+        }
+    }
+
+    #[test]
+    fn published_sessions_are_redacted_and_listed_for_teammates() {
+        let shared = tempfile::tempdir().unwrap();
+        let home = tempfile::tempdir().unwrap();
+        let transcript = home.path().join("a.jsonl");
+        fs::write(
+            &transcript,
+            "{\"type\":\"user\",\"message\":{\"content\":\"use token=hunter2hunter2 \\\"quoted\\\"\"}}\n",
+        )
+        .unwrap();
+        let mut session = Session::fixture("aaaaaaaa-0000-0000-0000-000000000000");
+        session.filepath = transcript;
+        session.first_message = Some("deploy with password=hunter2hunter2".to_string());
+        session.project_path = "/home/alice/src/api".to_string();
+        let redactor = Redactor::new(&[]);
+
+        let alice = team(shared.path(), "alice");
+        let published = publish(
+            &alice,
+            &[&session],
+            &PublishOptions {
+                transcripts: true,
+                redactor: Some(&redactor),
+                dry_run: false,
+            },
+        )
+        .unwrap();
+        assert_eq!((published.sessions, published.transcripts), (1, 1));
+        assert_eq!(published.redacted, 2);
+
+        // Alice doesn't see her own publish; Bob does.
+        assert!(find_sessions("platform", &alice).unwrap().0.is_empty());
+        let (sessions, failures) = find_sessions("platform", &team(shared.path(), "bob")).unwrap();
+        assert!(failures.is_empty());
+        let [listed] = &sessions[..] else {
+            panic!("expected one session, got {}", sessions.len());
+        };
+        assert_eq!(listed.source.owner(), Some("alice"));
+        assert_eq!(listed.project_path, "~/src/api");
+        assert_eq!(
+            listed.first_message.as_deref(),
+            Some("deploy with password=[REDACTED]")
+        );
+        let copied = fs::read_to_string(&listed.filepath).unwrap();
+        let entry: serde_json::Value = serde_json::from_str(copied.trim()).unwrap();
+        assert_eq!(
+            entry["message"]["content"],
+            "use token=[REDACTED] \"quoted\""
+        );
+
+        // Republishing without transcripts withdraws them.
+        let published = publish(
+            &alice,
+            &[&session],
+            &PublishOptions {
+                transcripts: false,
+                redactor: Some(&redactor),
+                dry_run: false,
+            },
+        )
+        .unwrap();
+        assert_eq!(published.withdrawn, 1);
+        assert!(!listed.filepath.exists());
+    }
+}
@@ -0,0 +1,90 @@
+//! `--timing`: wall-clock time per phase, printed to stderr as each phase
+//! finishes.
+//!
+//! Phases are laps: `lap` reports the time since the previous lap (or since
+//! start), so the sequential phases of `main` add up to the whole run.
+//! Sub-phases that were measured elsewhere (one rsync, one source's scan) are
+//! reported as indented detail lines with `detail`. Everything is a no-op
+//! unless enabled, so call sites don't need to check.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct Timing {
+    enabled: bool,
+    start: Instant,
+    last: Instant,
+}
+
+impl Timing {
+    pub fn new(enabled: bool) -> Self {
+        let now = Instant::now();
+        Self {
+            enabled,
+            start: now,
+            last: now,
+        }
+    }
+
+    /// Report the time since the previous lap as `phase`.
+    pub fn lap(&mut self, phase: &str) {
+        let now = Instant::now();
+        self.report(phase, now - self.last, None);
+        self.last = now;
+    }
+
+    /// Restart the lap clock without reporting, e.g. after waiting on the user.
+    pub fn reset(&mut self) {
+        self.last = Instant::now();
+    }
+
+    /// Report a sub-phase measured by the caller, with optional context.
+    pub fn detail(&self, phase: &str, elapsed: Duration, note: Option<&str>) {
+        self.report(&format!("  {}", phase), elapsed, note);
+    }
+
+    /// Report the time since `Timing::new`.
+    pub fn total(&self) {
+        self.report("total", self.start.elapsed(), None);
+    }
+
+    fn report(&self, phase: &str, elapsed: Duration, note: Option<&str>) {
+        if self.enabled {
+            eprintln!("{}", format_line(phase, elapsed, note));
+        }
+    }
+}
+
+fn format_line(phase: &str, elapsed: Duration, note: Option<&str>) -> String {
+    let mut line = format!("timing: {:<24} {:>9}", phase, format_elapsed(elapsed));
+    if let Some(note) = note {
+        line.push_str(&format!("  ({})", note));
+    }
+    line
+}
+
+/// "850µs", "12.3ms", "4.20s".
+fn format_elapsed(d: Duration) -> String {
+    let micros = d.as_micros();
+    match micros {
+        0..1_000 => format!("{}µs", micros),
+        1_000..1_000_000 => format!("{:.1}ms", micros as f64 / 1e3),
+        _ => format!("{:.2}s", d.as_secs_f64()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_align_and_scale_units() {
+        assert_eq!(format_elapsed(Duration::from_micros(850)), "850µs");
+        assert_eq!(format_elapsed(Duration::from_micros(12_345)), "12.3ms");
+        assert_eq!(format_elapsed(Duration::from_millis(4_200)), "4.20s");
+        assert_eq!(
+            format_line("  local", Duration::from_millis(5), Some("3 scanned")),
+            "timing:   local                      5.0ms  (3 scanned)"
+        );
+    }
+}
@@ -0,0 +1,589 @@
+//! `cc-sessions top`: a live view of the sessions being written right now,
+//! on this machine and on every SSH remote.
+//!
+//! Each source is polled on its own thread. Local transcripts are stat'ed;
+//! a remote is asked over one ssh connection per poll for the transcripts
+//! modified within the window, each with its size and last `TAIL_BYTES`.
+//! A session is active while its transcript was written to within the
+//! window, and shown as `writing` when it grew since the previous poll.
+//! The screen is redrawn as polls arrive until q / esc / ctrl-c; piped, a
+//! single snapshot is printed instead.
+
+use crate::claude_code;
+use crate::colors;
+use crate::follow::Keys;
+use crate::plain;
+use crate::remote::{self, Config, Transport};
+use crate::session::{Message, Role, SourceColor};
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How much of the end of an active transcript is read for its status.
+const TAIL_BYTES: u64 = 32 * 1024;
+
+/// Snippets are cut to this many characters (and to the terminal width).
+const MAX_SNIPPET_CHARS: usize = 200;
+
+pub struct Options {
+    /// Time between polls of each source
+    pub interval: Duration,
+    /// How recently a transcript must have been written to count as active
+    pub window: Duration,
+}
+
+/// A place sessions are polled from.
+struct Source {
+    name: String,
+    label: String,
+    color: Option<SourceColor>,
+    kind: Kind,
+}
+
+#[derive(Clone)]
+enum Kind {
+    Local(PathBuf),
+    Ssh {
+        target: String,
+        dir: String,
+    },
+    /// Can't be polled (an rsync daemon has no shell)
+    Unpolled(&'static str),
+}
+
+/// A recently written transcript, as one poll saw it.
+#[derive(Debug, PartialEq)]
+struct Observed {
+    /// Path relative to the projects dir: `<project dir>/<id>.jsonl`
+    path: String,
+    size: u64,
+    /// The end of the file, possibly starting mid-line
+    tail: Vec<u8>,
+}
+
+/// What the end of a transcript says about where the session is.
+#[derive(Debug, Default, PartialEq)]
+struct Status {
+    project: Option<String>,
+    /// The newest entry's timestamp
+    last: Option<SystemTime>,
+    /// The last prompt typed by the user, which started the current turn
+    prompt: Option<SystemTime>,
+    /// The newest message: role and its first line of text, tool call, or result
+    snippet: Option<(Role, String)>,
+}
+
+/// Per-source state between polls.
+#[derive(Default)]
+struct Polled {
+    sessions: Vec<(Observed, Status)>,
+    /// Sizes at the previous poll, to tell which transcripts grew since
+    sizes: HashMap<String, u64>,
+    grew: Vec<bool>,
+    error: Option<String>,
+    polled: bool,
+}
+
+pub fn run(config: &Config, remote_filter: Option<&str>, options: &Options) -> Result<()> {
+    let sources = sources(config, remote_filter)?;
+    if sources.is_empty() {
+        bail!("No sources match --remote");
+    }
+
+    let (tx, rx) = mpsc::channel();
+    for (index, source) in sources.iter().enumerate() {
+        if matches!(source.kind, Kind::Unpolled(_)) {
+            continue;
+        }
+        let (kind, tx) = (source.kind.clone(), tx.clone());
+        let (interval, window) = (options.interval, options.window);
+        std::thread::spawn(move || {
+            loop {
+                let result = poll(&kind, window).map_err(|e| format!("{:#}", e));
+                if tx.send((index, result)).is_err() {
+                    return;
+                }
+                std::thread::sleep(interval);
+            }
+        });
+    }
+    drop(tx);
+
+    let mut polled: Vec<Polled> = sources.iter().map(|_| Polled::default()).collect();
+    let pollable = sources
+        .iter()
+        .filter(|s| !matches!(s.kind, Kind::Unpolled(_)))
+        .count();
+
+    if !std::io::stdout().is_terminal() {
+        for (index, result) in rx.iter().take(pollable) {
+            update(&mut polled[index], result);
+        }
+        for line in render(&sources, &polled, options, None) {
+            println!("{}", plain::render(&line));
+        }
+        return Ok(());
+    }
+
+    let keys = Keys::open();
+    let mut out = std::io::stdout().lock();
+    crossterm::execute!(
+        out,
+        crossterm::terminal::EnterAlternateScreen,
+        crossterm::cursor::Hide
+    )?;
+    let result = (|| {
+        let mut redraw = true;
+        let mut last_draw = Instant::now();
+        loop {
+            match rx.recv_timeout(Duration::from_millis(250)) {
+                Ok((index, result)) => {
+                    update(&mut polled[index], result);
+                    redraw = true;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                // Nothing to poll (only rsync daemon remotes)
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    std::thread::sleep(Duration::from_millis(250))
+                }
+            }
+            if keys.as_ref().is_some_and(Keys::quit_pressed) {
+                break;
+            }
+            // Ages tick even when nothing was polled
+            if redraw || last_draw.elapsed() >= Duration::from_secs(1) {
+                let width = crossterm::terminal::size()
+                    .ok()
+                    .filter(|&(w, _)| w > 0)
+                    .map_or(120, |(w, _)| w as usize);
+                crossterm::queue!(
+                    out,
+                    crossterm::cursor::MoveTo(0, 0),
+                    crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
+                )?;
+                for line in render(&sources, &polled, options, Some(width)) {
+                    write!(out, "{}\r\n", plain::render(&line))?;
+                }
+                out.flush()?;
+                redraw = false;
+                last_draw = Instant::now();
+            }
+        }
+        anyhow::Ok(())
+    })();
+    let _ = crossterm::execute!(
+        out,
+        crossterm::cursor::Show,
+        crossterm::terminal::LeaveAlternateScreen
+    );
+    drop(keys);
+    result
+}
+
+/// Local (unless filtered out), then each matching remote by name.
+fn sources(config: &Config, remote_filter: Option<&str>) -> Result<Vec<Source>> {
+    let mut sources = Vec::new();
+    if config.source_matches(remote_filter, "local") {
+        for (i, root) in claude_code::local_projects_dirs()?.into_iter().enumerate() {
+            sources.push(Source {
+                name: claude_code::local_root_name(i, &root),
+                label: "local".to_string(),
+                color: None,
+                kind: Kind::Local(root),
+            });
+        }
+    }
+    let mut names: Vec<&String> = config.remotes.keys().collect();
+    names.sort();
+    for name in names {
+        if !config.source_matches(remote_filter, name) {
+            continue;
+        }
+        let remote = &config.remotes[name];
+        let kind = match remote.transport {
+            Transport::Ssh | Transport::Sftp => Kind::Ssh {
+                target: remote::ssh_target(remote),
+                dir: remote::remote_projects_dir(remote).to_string(),
+            },
+            Transport::Rsyncd => Kind::Unpolled("rsync daemon remotes can't be polled"),
+        };
+        sources.push(Source {
+            name: name.clone(),
+            label: remote.label.clone().unwrap_or_else(|| name.clone()),
+            color: remote.color,
+            kind,
+        });
+    }
+    Ok(sources)
+}
+
+fn update(state: &mut Polled, result: std::result::Result<Vec<Observed>, String>) {
+    let first = !state.polled;
+    state.polled = true;
+    match result {
+        Ok(observed) => {
+            // A transcript new to the window was just written to as well
+            state.grew = observed
+                .iter()
+                .map(|o| !first && state.sizes.get(&o.path).is_none_or(|&size| size < o.size))
+                .collect();
+            state.sizes = observed.iter().map(|o| (o.path.clone(), o.size)).collect();
+            state.sessions = observed
+                .into_iter()
+                .map(|o| {
+                    let status = status(&o.tail);
+                    (o, status)
+                })
+                .collect();
+            state.error = None;
+        }
+        Err(e) => state.error = Some(e),
+    }
+}
+
+// =============================================================================
+// Polling
+// =============================================================================
+
+fn poll(kind: &Kind, window: Duration) -> Result<Vec<Observed>> {
+    match kind {
+        Kind::Local(dir) => poll_local(dir, window),
+        Kind::Ssh { target, dir } => poll_ssh(target, dir, window),
+        Kind::Unpolled(_) => Ok(Vec::new()),
+    }
+}
+
+fn poll_local(dir: &std::path::Path, window: Duration) -> Result<Vec<Observed>> {
+    let (files, _) = claude_code::session_candidate_files(dir);
+    let mut observed = Vec::new();
+    for path in files {
+        let Ok(meta) = path.metadata() else { continue };
+        let recent = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age <= window);
+        if !recent {
+            continue;
+        }
+        let mut tail = Vec::new();
+        let mut file =
+            File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+        file.seek(SeekFrom::Start(meta.len().saturating_sub(TAIL_BYTES)))?;
+        file.take(TAIL_BYTES).read_to_end(&mut tail)?;
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        observed.push(Observed {
+            path: relative.to_string_lossy().into_owned(),
+            size: meta.len(),
+            tail,
+        });
+    }
+    Ok(observed)
+}
+
+/// One ssh round trip: each recent transcript as a `\x1e<size> <path>`
+/// line followed by its tail. JSONL can't contain a raw `\x1e`.
+fn poll_ssh(target: &str, dir: &str, window: Duration) -> Result<Vec<Observed>> {
+    let script = format!(
+        "cd {} || exit 1; find . -mindepth 2 -maxdepth 2 -name '*.jsonl' -mmin -{} | \
+         while IFS= read -r f; do printf '\\036%s %s\\n' \"$(wc -c < \"$f\")\" \"$f\"; \
+         tail -c {} \"$f\"; echo; done",
+        remote::shell_path(dir),
+        window.as_secs().div_ceil(60).max(1),
+        TAIL_BYTES
+    );
+    let output = Command::new("ssh")
+        .args([
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ConnectTimeout=5",
+            target,
+            &script,
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to execute ssh")?;
+    if !output.status.success() {
+        match String::from_utf8_lossy(&output.stderr).trim() {
+            "" => bail!("ssh failed ({})", output.status),
+            reason => bail!("{}", reason),
+        }
+    }
+    Ok(parse_listing(&output.stdout))
+}
+
+fn parse_listing(out: &[u8]) -> Vec<Observed> {
+    out.split(|&b| b == 0x1e)
+        .filter_map(|record| {
+            let newline = record.iter().position(|&b| b == b'\n')?;
+            let header = std::str::from_utf8(&record[..newline]).ok()?;
+            let (size, path) = header.trim().split_once(' ')?;
+            Some(Observed {
+                path: path.trim_start_matches("./").to_string(),
+                size: size.trim().parse().ok()?,
+                tail: record[newline + 1..].to_vec(),
+            })
+        })
+        .collect()
+}
+
+/// Read the tail of a transcript. A cut-off first line doesn't parse and
+/// is skipped.
+fn status(tail: &[u8]) -> Status {
+    let mut status = Status::default();
+    for line in tail.split(|&b| b == b'\n') {
+        let Ok(entry) = serde_json::from_slice::<serde_json::Value>(line) else {
+            continue;
+        };
+        let timestamp = entry
+            .get("timestamp")
+            .and_then(|v| v.as_str())
+            .and_then(crate::dates::parse_timestamp_ms)
+            .map(|ms| UNIX_EPOCH + Duration::from_millis(ms));
+        if let Some(cwd) = entry.get("cwd").and_then(|v| v.as_str()) {
+            status.project = std::path::Path::new(cwd)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned());
+        }
+        status.last = timestamp.or(status.last);
+        let Some((message, _)) = claude_code::entry_message(&entry) else {
+            continue;
+        };
+        if message.role == Role::User && message.tool_results.is_empty() && !message.text.is_empty()
+        {
+            status.prompt = timestamp.or(status.prompt);
+        }
+        if let Some(snippet) = snippet(&message) {
+            status.snippet = Some((message.role, snippet));
+        }
+    }
+    status
+}
+
+/// First line of text, else the tool call (`→ Bash cargo test`) or result.
+fn snippet(message: &Message) -> Option<String> {
+    let first_line = |text: &str| {
+        text.lines()
+            .find(|l| !l.trim().is_empty())
+            .map(|l| l.trim().to_string())
+    };
+    if let Some(text) = message.text.iter().rev().find_map(|t| first_line(t)) {
+        return Some(text);
+    }
+    if let Some(call) = message.tool_calls.last() {
+        let detail = ["command", "file_path", "pattern", "path", "description"]
+            .iter()
+            .find_map(|key| call.input.get(key).and_then(|v| v.as_str()))
+            .and_then(first_line)
+            .unwrap_or_default();
+        return Some(format!("→ {} {}", call.name, detail).trim_end().to_string());
+    }
+    let result = message.tool_results.last()?;
+    let mark = if result.is_error { "✗" } else { "←" };
+    Some(format!(
+        "{} {}",
+        mark,
+        first_line(&result.text).unwrap_or_default()
+    ))
+}
+
+// =============================================================================
+// Rendering
+// =============================================================================
+
+/// The screen: a title, one row per active session grouped by source, and
+/// a line per source that failed or can't be polled.
+fn render(
+    sources: &[Source],
+    polled: &[Polled],
+    options: &Options,
+    width: Option<usize>,
+) -> Vec<String> {
+    let now = SystemTime::now();
+    let mut rows = Vec::new();
+    for (source, state) in sources.iter().zip(polled) {
+        let mut sessions: Vec<(&Observed, &Status, bool)> = state
+            .sessions
+            .iter()
+            .zip(state.grew.iter().chain(std::iter::repeat(&false)))
+            .map(|((o, s), &grew)| (o, s, grew))
+            .collect();
+        sessions.sort_by_key(|(_, status, grew)| (!grew, std::cmp::Reverse(status.last)));
+        for (observed, status, grew) in sessions {
+            rows.push((source, observed, status, grew));
+        }
+    }
+
+    let mut title = format!(
+        "{}cc-sessions top{}: {} active in the last {}",
+        colors::BOLD,
+        colors::RESET,
+        rows.len(),
+        crate::stats::format_duration(options.window)
+    );
+    if width.is_some() {
+        title.push_str(&format!(
+            " (every {}s, q to quit)",
+            options.interval.as_secs()
+        ));
+    }
+    let mut lines = vec![title];
+    lines.push(String::new());
+    let label_width = sources
+        .iter()
+        .map(|s| s.label.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+    lines.push(format!(
+        "{:<label_width$} {:<16} {:<8} {:>8} {:>8}  LAST MESSAGE",
+        "SOURCE", "PROJECT", "SESSION", "ELAPSED", "LAST"
+    ));
+    let fixed = label_width + 1 + 17 + 9 + 9 + 9 + 2 + 2;
+    let snippet_width = width
+        .map_or(MAX_SNIPPET_CHARS, |w| w.saturating_sub(fixed))
+        .min(MAX_SNIPPET_CHARS);
+
+    for (source, observed, status, grew) in &rows {
+        let label = format!("{:<label_width$}", source.label);
+        let label = match source.color {
+            Some(color) => format!("{}{}{}", color.ansi(), label, colors::RESET),
+            None => label,
+        };
+        let id = observed
+            .path
+            .rsplit('/')
+            .next()
+            .unwrap_or("")
+            .trim_end_matches(".jsonl");
+        let project = status
+            .project
+            .clone()
+            .unwrap_or_else(|| observed.path.split('/').next().unwrap_or("").to_string());
+        let elapsed = status
+            .prompt
+            .and_then(|t| now.duration_since(t).ok())
+            .map_or_else(|| "-".to_string(), format_elapsed);
+        let last = if *grew {
+            format!("{}{:>8}{}", colors::GREEN, "writing", colors::RESET)
+        } else {
+            let age = status
+                .last
+                .and_then(|t| now.duration_since(t).ok())
+                .map_or_else(|| "-".to_string(), format_elapsed);
+            format!("{:>8}", age)
+        };
+        let (role, text) = match &status.snippet {
+            Some((Role::User, text)) => (format!("{}U{}", colors::CYAN, colors::RESET), text),
+            Some((Role::Assistant, text)) => {
+                (format!("{}A{}", colors::YELLOW, colors::RESET), text)
+            }
+            None => (" ".to_string(), &String::new()),
+        };
+        lines.push(format!(
+            "{} {:<16} {:<8} {:>8} {}  {} {}",
+            label,
+            clip(&project, 16),
+            id.get(..8).unwrap_or(id),
+            elapsed,
+            last,
+            role,
+            clip(text, snippet_width)
+        ));
+    }
+
+    let notes: Vec<String> = sources
+        .iter()
+        .zip(polled)
+        .filter_map(|(source, state)| match (&source.kind, &state.error) {
+            (Kind::Unpolled(why), _) => Some(format!("{}: {}", source.name, why)),
+            (_, Some(error)) => Some(format!(
+                "{}⚠ {}: {}{}",
+                colors::YELLOW,
+                source.name,
+                error.lines().next().unwrap_or(""),
+                colors::RESET
+            )),
+            _ if !state.polled => Some(format!(
+                "{}{}: polling…{}",
+                colors::DIM,
+                source.name,
+                colors::RESET
+            )),
+            _ => None,
+        })
+        .collect();
+    if !notes.is_empty() {
+        lines.push(String::new());
+        lines.extend(notes);
+    }
+    lines
+}
+
+/// "45s", "3m 12s", "1h 05m".
+fn format_elapsed(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60),
+    }
+}
+
+fn clip(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut clipped: String = text.chars().take(max.saturating_sub(1)).collect();
+    clipped.push('…');
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_reads_the_current_turn_from_a_cut_off_tail() {
+        let tail = concat!(
+            "t\":\"cut off\"}}\n",
+            r#"{"type":"user","timestamp":"2025-01-15T10:00:00Z","cwd":"/src/api","message":{"content":"fix the tests"}}"#,
+            "\n",
+            r#"{"type":"assistant","timestamp":"2025-01-15T10:00:05Z","cwd":"/src/api","message":{"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo test\nmore"}}]}}"#,
+            "\n",
+            r#"{"type":"user","timestamp":"2025-01-15T10:00:09Z","cwd":"/src/api","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"ok"}]}}"#,
+            "\n",
+            r#"{"type":"assistant","timestamp":"2025-01-15T10:00:12Z","message":{"content":[{"type":"tool_use","id":"t2","name":"Read","input":{"file_path":"src/lib.rs"}}]}}"#,
+            "\n{\"type\":\"assis"
+        );
+        let at = |s: u64| Some(UNIX_EPOCH + Duration::from_secs(1_736_935_200 + s));
+        assert_eq!(
+            status(tail.as_bytes()),
+            Status {
+                project: Some("api".to_string()),
+                last: at(12),
+                prompt: at(0),
+                snippet: Some((Role::Assistant, "→ Read src/lib.rs".to_string())),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_listing_splits_records() {
+        let out = b"\x1e120 ./-src-api/a.jsonl\n{\"x\":1}\n\n\x1e7 ./-src-web/b.jsonl\n\n";
+        let observed = parse_listing(out);
+        assert_eq!(observed.len(), 2);
+        assert_eq!(
+            (observed[0].path.as_str(), observed[0].size),
+            ("-src-api/a.jsonl", 120)
+        );
+        assert_eq!(observed[0].tail, b"{\"x\":1}\n\n");
+        assert_eq!(observed[1].path, "-src-web/b.jsonl");
+    }
+}
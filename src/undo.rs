@@ -0,0 +1,319 @@
+//! Undo journal for commands that delete or move session data
+//! (`cc-sessions undo`).
+//!
+//! `forks prune`, `fsck --fix`, `archive`, `cache vacuum`, and the picker's
+//! batch delete each record one batch. Files they delete are moved into the batch's trash instead, so
+//! every action can be reversed:
+//!
+//! ```text
+//! ~/.local/share/cc-sessions/undo/
+//!   1760000000000/           # batch, named after when it started (Unix ms)
+//!     journal.json           # command + actions, rewritten after each one
+//!     trash/0/<file name>    # a deleted file or dir, one slot per action
+//! ```
+//!
+//! `undo` reverses the newest batch's actions last-first and deletes the
+//! batch once all of them are undone; anything that couldn't be undone stays
+//! in the journal for another try. Batches older than `KEEP_DAYS` are purged
+//! when a new one starts, which is when trashed data actually frees space.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const JOURNAL_FILE: &str = "journal.json";
+const TRASH_DIR: &str = "trash";
+
+/// How long a batch can still be undone.
+pub const KEEP_DAYS: u64 = 30;
+
+pub fn undo_dir() -> Result<PathBuf> {
+    Ok(crate::profile::scoped(crate::paths::DATA.dir()?).join("undo"))
+}
+
+/// One reversible step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// `from` was moved to `to`: a rename, or a delete into the trash
+    Moved { from: PathBuf, to: PathBuf },
+    /// `original` was compressed into `archived` and deleted
+    Archived {
+        original: PathBuf,
+        archived: PathBuf,
+    },
+}
+
+impl Action {
+    /// The path the action took away, which undoing puts back.
+    pub fn original(&self) -> &Path {
+        match self {
+            Action::Moved { from, .. } => from,
+            Action::Archived { original, .. } => original,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Journal {
+    /// The command that made the batch, e.g. `forks prune`
+    pub command: String,
+    /// Unix ms
+    pub created: u64,
+    pub actions: Vec<Action>,
+}
+
+/// A batch being recorded. Nothing is written until the first action, so a
+/// command that ends up doing nothing leaves no batch behind.
+pub struct Batch {
+    dir: PathBuf,
+    journal: Journal,
+}
+
+impl Batch {
+    /// Start a batch for `command`, purging expired ones first.
+    pub fn begin(command: &str) -> Result<Self> {
+        let root = undo_dir()?;
+        purge_expired(&root, SystemTime::now());
+        Ok(Self::begin_in(&root, command))
+    }
+
+    pub fn begin_in(root: &Path, command: &str) -> Self {
+        let created = now_ms();
+        Self {
+            dir: root.join(created.to_string()),
+            journal: Journal {
+                command: command.to_string(),
+                created,
+                actions: Vec::new(),
+            },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.journal.actions.is_empty()
+    }
+
+    /// Delete `path` (a file or dir) by moving it into the trash.
+    pub fn trash(&mut self, path: &Path) -> Result<()> {
+        let name = path
+            .file_name()
+            .with_context(|| format!("Can't trash {}", path.display()))?;
+        let slot = self
+            .dir
+            .join(TRASH_DIR)
+            .join(self.journal.actions.len().to_string());
+        fs::create_dir_all(&slot)
+            .with_context(|| format!("Failed to create {}", slot.display()))?;
+        let to = slot.join(name);
+        move_path(path, &to)?;
+        self.record(Action::Moved {
+            from: path.to_path_buf(),
+            to,
+        })
+    }
+
+    /// Record an action the caller already carried out.
+    pub fn record(&mut self, action: Action) -> Result<()> {
+        self.journal.actions.push(action);
+        write_journal(&self.dir, &self.journal)
+    }
+}
+
+/// The newest batch's dir and journal, if there is one.
+pub fn latest(root: &Path) -> Result<Option<(PathBuf, Journal)>> {
+    let Some((_, dir)) = batch_dirs(root)
+        .into_iter()
+        .max_by_key(|(created, _)| *created)
+    else {
+        return Ok(None);
+    };
+    let path = dir.join(JOURNAL_FILE);
+    let text =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let journal = serde_json::from_str(&text)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some((dir, journal)))
+}
+
+/// Undo `journal`'s actions last-first with `restore_archived` for archived
+/// transcripts. Returns each action with its outcome; the batch is deleted
+/// when all succeeded, otherwise the journal keeps the ones that failed.
+pub fn undo(
+    dir: &Path,
+    mut journal: Journal,
+    restore_archived: impl Fn(&Path) -> Result<PathBuf>,
+) -> Vec<(Action, Result<()>)> {
+    let mut results = Vec::new();
+    let mut failed = Vec::new();
+    for action in journal.actions.drain(..).rev() {
+        let result = match &action {
+            Action::Moved { from, to } => {
+                if from.exists() {
+                    Err(anyhow::anyhow!("{} exists again", from.display()))
+                } else {
+                    move_path(to, from)
+                }
+            }
+            Action::Archived { archived, .. } => restore_archived(archived).map(|_| ()),
+        };
+        if result.is_err() {
+            failed.push(action.clone());
+        }
+        results.push((action, result));
+    }
+
+    if failed.is_empty() {
+        if let Err(e) = fs::remove_dir_all(dir) {
+            eprintln!("Warning: Failed to remove {}: {}", dir.display(), e);
+        }
+    } else {
+        failed.reverse();
+        journal.actions = failed;
+        if let Err(e) = write_journal(dir, &journal) {
+            eprintln!("Warning: {:#}", e);
+        }
+    }
+    results
+}
+
+fn batch_dirs(root: &Path) -> Vec<(u64, PathBuf)> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let created = entry.file_name().to_str()?.parse().ok()?;
+            let path = entry.path();
+            path.join(JOURNAL_FILE).exists().then_some((created, path))
+        })
+        .collect()
+}
+
+fn purge_expired(root: &Path, now: SystemTime) {
+    let keep = Duration::from_secs(KEEP_DAYS * 86400);
+    for (created, dir) in batch_dirs(root) {
+        if UNIX_EPOCH + Duration::from_millis(created) + keep < now
+            && let Err(e) = fs::remove_dir_all(&dir)
+        {
+            eprintln!("Warning: Failed to purge {}: {}", dir.display(), e);
+        }
+    }
+}
+
+/// Written after every action (temp file + rename), so a crash mid-command
+/// still leaves a journal of everything done so far.
+fn write_journal(dir: &Path, journal: &Journal) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let content = serde_json::to_string_pretty(journal).context("Failed to serialize journal")?;
+    let path = dir.join(JOURNAL_FILE);
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, &path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Rename `from` to `to`. Across filesystems a file is copied (keeping its
+/// mtime, which places a session in the list) and then removed; a dir
+/// isn't, rather than risk a half-copied tree.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if from.is_dir() {
+        bail!("Failed to move {} to {}", from.display(), to.display());
+    }
+    let modified = fs::metadata(from)
+        .and_then(|m| m.modified())
+        .with_context(|| format!("Failed to read {}", from.display()))?;
+    fs::copy(from, to)
+        .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+    let _ = File::options()
+        .write(true)
+        .open(to)
+        .and_then(|f| f.set_modified(modified));
+    fs::remove_file(from).with_context(|| format!("Failed to delete {}", from.display()))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_restores_trashed_and_renamed_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("undo");
+        let deleted = tmp.path().join("a.jsonl");
+        let renamed = tmp.path().join("b.jsonl");
+        fs::write(&deleted, "a").unwrap();
+        fs::write(&renamed, "b").unwrap();
+
+        let mut batch = Batch::begin_in(&root, "forks prune");
+        assert!(latest(&root).unwrap().is_none());
+        batch.trash(&deleted).unwrap();
+        let target = tmp.path().join("c.jsonl");
+        fs::rename(&renamed, &target).unwrap();
+        batch
+            .record(Action::Moved {
+                from: renamed.clone(),
+                to: target.clone(),
+            })
+            .unwrap();
+        assert!(!deleted.exists());
+
+        let (dir, journal) = latest(&root).unwrap().unwrap();
+        assert_eq!(journal.command, "forks prune");
+        let results = undo(&dir, journal, |_| unreachable!());
+        assert!(results.iter().all(|(_, r)| r.is_ok()));
+        assert_eq!(fs::read_to_string(&deleted).unwrap(), "a");
+        assert_eq!(fs::read_to_string(&renamed).unwrap(), "b");
+        assert!(!target.exists() && !dir.exists());
+    }
+
+    #[test]
+    fn failed_actions_stay_in_the_journal() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("undo");
+        let path = tmp.path().join("a.jsonl");
+        fs::write(&path, "a").unwrap();
+        let mut batch = Batch::begin_in(&root, "fsck --fix");
+        batch.trash(&path).unwrap();
+        fs::write(&path, "new").unwrap();
+
+        let (dir, journal) = latest(&root).unwrap().unwrap();
+        assert!(undo(&dir, journal, |_| unreachable!())[0].1.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        let (_, journal) = latest(&root).unwrap().unwrap();
+        assert_eq!(journal.actions.len(), 1);
+    }
+
+    #[test]
+    fn purge_drops_only_expired_batches() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut batch = Batch::begin_in(tmp.path(), "archive");
+        batch
+            .record(Action::Archived {
+                original: "/p/a.jsonl".into(),
+                archived: "/a/a.jsonl.gz".into(),
+            })
+            .unwrap();
+        purge_expired(tmp.path(), SystemTime::now());
+        assert!(latest(tmp.path()).unwrap().is_some());
+        let later = SystemTime::now() + Duration::from_secs((KEEP_DAYS + 1) * 86400);
+        purge_expired(tmp.path(), later);
+        assert!(latest(tmp.path()).unwrap().is_none());
+    }
+}